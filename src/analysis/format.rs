@@ -0,0 +1,96 @@
+use crate::lexer::{lexer, tokens};
+use crate::parser::printer;
+use crate::parser::syntax::{self, SyntaxKind};
+use crate::source::{self, Span};
+
+const INDENT: &str = "    ";
+
+/// Re-indents a program by four spaces per block, trims the whitespace at
+/// the end of its lines and ends every line with \n, leaving the rest as
+/// written. A line is indented as deep as its first token or comment is in
+/// the syntax tree, which nests the bodies of O RLY? and WTF? branches a
+/// level below the lines starting them, so programs that do not parse are
+/// formatted too. The lines a comment or token goes on to, as OBTW comments
+/// do, are left alone.
+pub fn format(text: &str) -> String {
+    let text = source::normalize_newlines(text);
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut starts = vec![0];
+    starts.extend(
+        chars
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == '\n')
+            .map(|(i, _)| i + 1),
+    );
+    let line = |offset: usize| starts.partition_point(|start| *start <= offset) - 1;
+
+    let mut depths: Vec<Option<usize>> = vec![None; starts.len()];
+    let mut verbatim = vec![false; starts.len()];
+    for (leaf, depth) in syntax::parse(&text).leaves() {
+        let span = leaf.span;
+        if leaf.kind == SyntaxKind::Trivia || chars.get(span.start) == Some(&'\n') {
+            continue;
+        }
+        let first = line(span.start);
+        depths[first].get_or_insert(depth);
+        for inside in verbatim.iter_mut().take(line(span.end) + 1).skip(first + 1) {
+            *inside = true;
+        }
+    }
+
+    let mut formatted = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if verbatim[i] {
+            formatted.push_str(line.trim_end());
+        } else if !line.trim().is_empty() {
+            formatted.push_str(&INDENT.repeat(depths[i].unwrap_or(0)));
+            formatted.push_str(line.trim());
+        }
+        formatted.push('\n');
+    }
+
+    formatted
+}
+
+/// Shrinks a program to its tokens, each printed by the printer diagnostics
+/// quote expressions with: comments, indentation and blank lines go, and
+/// tokens are separated by one space where any whitespace or comment was
+/// between them. Lines stay as they are, since most statements end at one.
+/// Err with the span and message of the first token that does not lex.
+pub fn minify(text: &str) -> Result<String, (Span, String)> {
+    let text = source::normalize_newlines(text);
+    let tokens = lexer::Lexer::init(&text).get_tokens();
+    if let Some(error) = lexer::Lexer::get_first_error(&tokens) {
+        if let tokens::Token::Illegal(e) = &error.token {
+            return Err((error.span(), e.to_string()));
+        }
+    }
+
+    let mut minified = String::new();
+    let mut previous_end = None;
+    for token in tokens.iter() {
+        match token.token {
+            tokens::Token::EOF => break,
+            tokens::Token::Newline => {
+                if !minified.is_empty() && !minified.ends_with('\n') {
+                    minified.push('\n');
+                }
+                previous_end = None;
+            }
+            _ => {
+                if previous_end.is_some_and(|end| end < token.start) {
+                    minified.push(' ');
+                }
+                // a keyword of several words is spelled with single spaces
+                minified.push_str(&printer::print_token_value(&token.token));
+                previous_end = Some(token.end);
+            }
+        }
+    }
+    if !minified.is_empty() && !minified.ends_with('\n') {
+        minified.push('\n');
+    }
+
+    Ok(minified)
+}
@@ -0,0 +1,136 @@
+use super::symbols::{self, SymbolKind, SymbolTable};
+use crate::diagnostics::{LintHit, OVERWRITTEN_IT, UNUSED_VARIABLE};
+use crate::lexer::lexer;
+use crate::parser::ast;
+use crate::source::Span;
+
+/// Runs every lint over a parsed program, whatever their levels
+pub fn check(statements: &[ast::StatementNode]) -> Vec<LintHit> {
+    let table = symbols::resolve(statements);
+    let mut hits = unused_variables(&table);
+    overwritten_it(statements, &mut hits);
+    hits
+}
+
+fn unused_variables(table: &SymbolTable) -> Vec<LintHit> {
+    table
+        .symbols
+        .iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Variable && symbol.references.is_empty())
+        .map(|symbol| LintHit {
+            lint: &UNUSED_VARIABLE,
+            span: Span::new(symbol.start, symbol.end),
+            message: format!(
+                "Variable {} is never used",
+                lexer::escape_identifier(&symbol.name)
+            ),
+        })
+        .collect()
+}
+
+/// Whether `expression` reads IT anywhere in it
+fn reads_it(expression: &ast::ExpressionNode) -> bool {
+    matches!(
+        expression.value,
+        ast::ExpressionNodeValueOption::ItReference(_)
+    ) || expression.operands().into_iter().any(reads_it)
+}
+
+/// Bare expressions, whose value goes into IT, followed by another before
+/// anything reads IT. IT is only followed through one block at a time: the
+/// statements with blocks of their own read or may write it, and the ones
+/// leaving the block end the search.
+fn overwritten_it(statements: &[ast::StatementNode], hits: &mut Vec<LintHit>) {
+    // the bare expression whose value IT holds, if nothing has read it yet
+    let mut unread: Option<&ast::ExpressionNode> = None;
+
+    for statement in statements.iter() {
+        let expressions = match &statement.value {
+            ast::StatementNodeValueOption::Expression(expression) => {
+                if let Some(previous) = unread.filter(|_| !reads_it(expression)) {
+                    if let Some(token) = previous.token() {
+                        hits.push(LintHit {
+                            lint: &OVERWRITTEN_IT,
+                            span: token.token.span(),
+                            message: "IT is given the value of this expression, which the next one replaces before it is read".to_string(),
+                        });
+                    }
+                }
+                // a function is called for what it does as much as for what
+                // it returns
+                unread = Some(expression).filter(|expression| {
+                    !matches!(
+                        expression.value,
+                        ast::ExpressionNodeValueOption::FunctionCallExpression(_)
+                    )
+                });
+                continue;
+            }
+            ast::StatementNodeValueOption::VariableAssignmentStatement(var_assign) => {
+                vec![&var_assign.expression]
+            }
+            ast::StatementNodeValueOption::BukkitSlotDeclarationStatement(slot_dec) => {
+                vec![&slot_dec.expression]
+            }
+            ast::StatementNodeValueOption::VisibleStatement(visible) => {
+                visible.expressions.iter().collect()
+            }
+            ast::StatementNodeValueOption::AssureStatement(assure) => {
+                let mut expressions = vec![&assure.expression];
+                expressions.extend(assure.message.iter());
+                expressions
+            }
+            ast::StatementNodeValueOption::DumpzStatement(dumpz) => vec![&dumpz.expression],
+            ast::StatementNodeValueOption::VariableDeclarationStatement(_)
+            | ast::StatementNodeValueOption::FLUSHZStatement(_) => vec![],
+            ast::StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                // a definition runs nothing where it is written
+                overwritten_it(&function.statements, hits);
+                continue;
+            }
+            ast::StatementNodeValueOption::IfStatement(if_stmt) => {
+                overwritten_it(&if_stmt.statements, hits);
+                for else_if in if_stmt.else_ifs.iter() {
+                    overwritten_it(&else_if.statements, hits);
+                }
+                if let Some(else_) = &if_stmt.else_ {
+                    overwritten_it(else_, hits);
+                }
+                unread = None;
+                continue;
+            }
+            ast::StatementNodeValueOption::SwitchStatement(switch) => {
+                for case in switch.cases.iter() {
+                    overwritten_it(&case.statements, hits);
+                }
+                if let Some(default) = &switch.default {
+                    overwritten_it(default, hits);
+                }
+                unread = None;
+                continue;
+            }
+            ast::StatementNodeValueOption::LoopStatement(loop_stmt) => {
+                overwritten_it(&loop_stmt.statements, hits);
+                unread = None;
+                continue;
+            }
+            ast::StatementNodeValueOption::WatchinLoopStatement(loop_stmt) => {
+                overwritten_it(&loop_stmt.statements, hits);
+                unread = None;
+                continue;
+            }
+            // GIMMEH leaves in IT whether it read anything
+            ast::StatementNodeValueOption::GimmehStatement(_)
+            | ast::StatementNodeValueOption::KTHXBYEStatement(_)
+            | ast::StatementNodeValueOption::GTFOStatement(_)
+            | ast::StatementNodeValueOption::ReturnStatement(_) => {
+                unread = None;
+                continue;
+            }
+        };
+
+        if expressions.into_iter().any(reads_it) {
+            unread = None;
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod format;
+pub mod lints;
+pub mod rename;
+pub mod symbols;
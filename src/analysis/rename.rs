@@ -0,0 +1,77 @@
+use crate::analysis::symbols;
+use crate::lexer::lexer;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RenameError {
+    IncompleteAnalysis,
+    SymbolNotFound(String),
+    InvalidIdentifier(String),
+    Collision(String),
+}
+
+impl std::error::Error for RenameError {}
+
+impl std::fmt::Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RenameError::IncompleteAnalysis => {
+                write!(f, "program has syntax errors, fix them before renaming")
+            }
+            RenameError::SymbolNotFound(name) => write!(f, "no symbol named {} found", name),
+            RenameError::InvalidIdentifier(name) => {
+                write!(f, "{} is not a valid identifier", name)
+            }
+            RenameError::Collision(name) => {
+                write!(f, "{} is already declared in the same scope", name)
+            }
+        }
+    }
+}
+
+/// Renames every declaration of and reference to `old` in `source`, returning
+/// the rewritten source and the number of occurrences that were replaced
+pub fn rename(source: &str, old: &str, new: &str) -> Result<(String, usize), RenameError> {
+    let table = symbols::analyze(source);
+    if !table.complete {
+        return Err(RenameError::IncompleteAnalysis);
+    }
+
+    if !lexer::is_identifier_text(new) {
+        return Err(RenameError::InvalidIdentifier(new.to_string()));
+    }
+
+    let targets = table.find(old);
+    if targets.is_empty() {
+        return Err(RenameError::SymbolNotFound(old.to_string()));
+    }
+
+    for target in targets.iter() {
+        let collides = table.symbols.iter().any(|s| {
+            s.name == new
+                && (s.scope == target.scope
+                    || s.kind == symbols::SymbolKind::Function
+                    || target.kind == symbols::SymbolKind::Function)
+        });
+        if collides {
+            return Err(RenameError::Collision(new.to_string()));
+        }
+    }
+
+    let mut occurrences = targets
+        .iter()
+        .flat_map(|s| s.occurrences())
+        .collect::<Vec<symbols::Reference>>();
+    occurrences.sort_by_key(|r| r.start);
+    occurrences.dedup();
+
+    // a keyword is written in backticks
+    let escaped = lexer::escape_identifier(new);
+
+    // offsets are char indices, replace from the back so earlier ones stay valid
+    let mut chars = source.chars().collect::<Vec<char>>();
+    for occurrence in occurrences.iter().rev() {
+        chars.splice(occurrence.start..occurrence.end, escaped.chars());
+    }
+
+    Ok((chars.iter().collect(), occurrences.len()))
+}
@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+
+use crate::lexer::lexer;
+use crate::lexer::tokens;
+use crate::parser::ast;
+use crate::parser::parser;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+    Parameter,
+    LoopVariable,
+}
+
+impl std::fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SymbolKind::Variable => write!(f, "variable"),
+            SymbolKind::Function => write!(f, "function"),
+            SymbolKind::Parameter => write!(f, "parameter"),
+            SymbolKind::LoopVariable => write!(f, "loop variable"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub type_: Option<String>,
+    /// function the symbol was declared in, or "main" for the program body
+    pub scope: String,
+    pub start: usize,
+    pub end: usize,
+    pub references: Vec<Reference>,
+}
+
+impl Symbol {
+    /// The declaration followed by every reference
+    pub fn occurrences(&self) -> Vec<Reference> {
+        let mut occurrences = vec![Reference {
+            start: self.start,
+            end: self.end,
+        }];
+        occurrences.extend(self.references.iter().cloned());
+        occurrences
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    /// identifiers that did not resolve to any declaration
+    pub unresolved: Vec<(String, Reference)>,
+    /// false when lexer or parser errors meant only part of the source was analyzed
+    pub complete: bool,
+}
+
+impl SymbolTable {
+    pub fn functions(&self) -> Vec<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Function)
+            .collect()
+    }
+
+    pub fn find(&self, name: &str) -> Vec<&Symbol> {
+        self.symbols.iter().filter(|s| s.name == name).collect()
+    }
+
+    /// The symbol declared or referenced at the given source offset
+    pub fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| {
+            s.occurrences()
+                .iter()
+                .any(|r| r.start <= offset && offset < r.end)
+        })
+    }
+}
+
+/// Lexes, parses and resolves every name in `source`. Lexer and parser errors
+/// are tolerated so editors still get symbols for partially valid programs.
+pub fn analyze(source: &str) -> SymbolTable {
+    let mut l = lexer::Lexer::init(source);
+    let tokens = l.get_tokens();
+    let lexed_cleanly = !lexer::Lexer::has_errors(&tokens);
+    let p = parser::Parser::parse(tokens);
+
+    let mut table = resolve(&p.ast.statements);
+    table.complete = lexed_cleanly && p.errors.is_empty();
+    table
+}
+
+/// Resolves every name in an already parsed program
+pub fn resolve(statements: &[ast::StatementNode]) -> SymbolTable {
+    let mut resolver = Resolver::new();
+    resolver.resolve_statements(statements);
+
+    SymbolTable {
+        symbols: resolver.symbols,
+        unresolved: resolver.unresolved,
+        complete: true,
+    }
+}
+
+fn identifier_name(token: &ast::TokenNode) -> Option<String> {
+    match token.value() {
+        tokens::Token::Identifier(name) => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+fn type_name(token: &ast::TokenNode) -> Option<String> {
+    match token.value() {
+        tokens::Token::Word(word) => Some(word.to_string()),
+        _ => None,
+    }
+}
+
+struct Resolver {
+    symbols: Vec<Symbol>,
+    unresolved: Vec<(String, Reference)>,
+    // each scope maps names to indices into `symbols`
+    scopes: Vec<HashMap<String, usize>>,
+    functions: HashMap<String, usize>,
+    scope_name: String,
+}
+
+impl Resolver {
+    fn new() -> Self {
+        Resolver {
+            symbols: vec![],
+            unresolved: vec![],
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            scope_name: "main".to_string(),
+        }
+    }
+
+    fn declare(&mut self, token: &ast::TokenNode, kind: SymbolKind, type_: Option<String>) {
+        let name = match identifier_name(token) {
+            Some(name) => name,
+            None => return,
+        };
+
+        self.symbols.push(Symbol {
+            name: name.clone(),
+            kind: kind.clone(),
+            type_,
+            scope: self.scope_name.clone(),
+            start: token.token.start,
+            end: token.token.end,
+            references: vec![],
+        });
+
+        let index = self.symbols.len() - 1;
+        if kind == SymbolKind::Function {
+            self.functions.insert(name, index);
+        } else {
+            self.scopes.last_mut().unwrap().insert(name, index);
+        }
+    }
+
+    fn reference(&mut self, token: &ast::TokenNode) {
+        let name = match identifier_name(token) {
+            Some(name) => name,
+            None => return,
+        };
+        let reference = Reference {
+            start: token.token.start,
+            end: token.token.end,
+        };
+
+        for scope in self.scopes.iter().rev() {
+            if let Some(index) = scope.get(&name) {
+                self.symbols[*index].references.push(reference);
+                return;
+            }
+        }
+
+        self.unresolved.push((name, reference));
+    }
+
+    fn resolve_statements(&mut self, statements: &[ast::StatementNode]) {
+        // functions can be called before their definition so declare them up front
+        for statement in statements.iter() {
+            if let ast::StatementNodeValueOption::FunctionDefinitionStatement(function) =
+                &statement.value
+            {
+                self.declare(
+                    &function.identifier,
+                    SymbolKind::Function,
+                    type_name(&function.return_type),
+                );
+            }
+        }
+
+        for statement in statements.iter() {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_block(&mut self, statements: &[ast::StatementNode]) {
+        for statement in statements.iter() {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &ast::StatementNode) {
+        match &statement.value {
+            ast::StatementNodeValueOption::Expression(expression) => {
+                self.resolve_expression(expression)
+            }
+            ast::StatementNodeValueOption::VariableDeclarationStatement(var_dec) => {
+                self.declare(
+                    &var_dec.identifier,
+                    SymbolKind::Variable,
+                    type_name(&var_dec.type_),
+                );
+            }
+            ast::StatementNodeValueOption::VariableAssignmentStatement(var_assign) => {
+                // the right hand side is evaluated before the variable is declared
+                self.resolve_expression(&var_assign.expression);
+                match &var_assign.variable {
+                    ast::VariableAssignmentNodeVariableOption::Identifier(token) => {
+                        self.reference(token)
+                    }
+                    ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(
+                        var_dec,
+                    ) => self.declare(
+                        &var_dec.identifier,
+                        SymbolKind::Variable,
+                        type_name(&var_dec.type_),
+                    ),
+                    ast::VariableAssignmentNodeVariableOption::BukkitSlot(slot) => {
+                        self.reference(&slot.bukkit)
+                    }
+                }
+            }
+            ast::StatementNodeValueOption::KTHXBYEStatement(_) => {}
+            ast::StatementNodeValueOption::VisibleStatement(visible) => {
+                for expression in visible.expressions.iter() {
+                    self.resolve_expression(expression);
+                }
+            }
+            ast::StatementNodeValueOption::GimmehStatement(gimmeh) => {
+                self.reference(&gimmeh.identifier)
+            }
+            ast::StatementNodeValueOption::IfStatement(if_statement) => {
+                self.resolve_block(&if_statement.statements);
+                for else_if in if_statement.else_ifs.iter() {
+                    self.resolve_expression(&else_if.expression);
+                    self.resolve_block(&else_if.statements);
+                }
+                if let Some(else_) = &if_statement.else_ {
+                    self.resolve_block(else_);
+                }
+            }
+            ast::StatementNodeValueOption::SwitchStatement(switch) => {
+                for case in switch.cases.iter() {
+                    self.resolve_expression(&case.expression);
+                    self.resolve_block(&case.statements);
+                }
+                if let Some(default) = &switch.default {
+                    self.resolve_block(default);
+                }
+            }
+            ast::StatementNodeValueOption::GTFOStatement(_) => {}
+            ast::StatementNodeValueOption::FLUSHZStatement(_) => {}
+            ast::StatementNodeValueOption::AssureStatement(assure) => {
+                self.resolve_expression(&assure.expression);
+                if let Some(message) = &assure.message {
+                    self.resolve_expression(message);
+                }
+            }
+            ast::StatementNodeValueOption::DumpzStatement(dumpz) => {
+                self.resolve_expression(&dumpz.expression);
+            }
+            ast::StatementNodeValueOption::BukkitSlotDeclarationStatement(slot_dec) => {
+                self.resolve_expression(&slot_dec.expression);
+                self.reference(&slot_dec.slot.bukkit);
+            }
+            ast::StatementNodeValueOption::LoopStatement(loop_statement) => {
+                self.scopes.push(HashMap::new());
+                if let Some((_, variable)) = &loop_statement.counter {
+                    self.declare(variable, SymbolKind::LoopVariable, None);
+                }
+                if let Some(condition) = &loop_statement.condition_expression {
+                    self.resolve_expression(condition);
+                }
+                self.resolve_block(&loop_statement.statements);
+                self.scopes.pop();
+            }
+            ast::StatementNodeValueOption::WatchinLoopStatement(loop_statement) => {
+                self.resolve_expression(&loop_statement.bukkit);
+                self.scopes.push(HashMap::new());
+                self.declare(&loop_statement.item, SymbolKind::LoopVariable, None);
+                self.resolve_block(&loop_statement.statements);
+                self.scopes.pop();
+            }
+            ast::StatementNodeValueOption::ReturnStatement(return_statement) => {
+                self.resolve_expression(&return_statement.expression)
+            }
+            ast::StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                // function bodies only see their own parameters and locals
+                let outer_scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+                let outer_name = std::mem::replace(
+                    &mut self.scope_name,
+                    identifier_name(&function.identifier).unwrap_or_default(),
+                );
+
+                for (identifier, type_) in function.arguments.iter() {
+                    self.declare(identifier, SymbolKind::Parameter, type_name(type_));
+                }
+                self.resolve_block(&function.statements);
+
+                self.scopes = outer_scopes;
+                self.scope_name = outer_name;
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &ast::ExpressionNode) {
+        match &expression.value {
+            ast::ExpressionNodeValueOption::NumberValue(_)
+            | ast::ExpressionNodeValueOption::NumbarValue(_)
+            | ast::ExpressionNodeValueOption::YarnValue(_)
+            | ast::ExpressionNodeValueOption::TroofValue(_)
+            | ast::ExpressionNodeValueOption::ItReference(_) => {}
+            ast::ExpressionNodeValueOption::VariableReference(var_ref) => {
+                self.reference(&var_ref.identifier)
+            }
+            ast::ExpressionNodeValueOption::BukkitSlot(slot) => self.reference(&slot.bukkit),
+            ast::ExpressionNodeValueOption::SumExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::DiffExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::ProduktExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::QuoshuntExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::ModExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::BiggrExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::SmallrExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::FurstExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::LatrExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::BothOfExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::EitherOfExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::WonOfExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::BothSaemExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::DiffrintExpression(e) => {
+                self.resolve_binary(&e.left, &e.right)
+            }
+            ast::ExpressionNodeValueOption::NotExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::MaekExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::KopyExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::LengthzExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::CodezExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::LettarExpression(e) => {
+                self.resolve_expression(&e.expression)
+            }
+            ast::ExpressionNodeValueOption::SplitzExpression(e) => {
+                self.resolve_binary(&e.yarn, &e.separator)
+            }
+            ast::ExpressionNodeValueOption::JoinzExpression(e) => {
+                self.resolve_binary(&e.bukkit, &e.separator)
+            }
+            ast::ExpressionNodeValueOption::MatchezExpression(e) => {
+                self.resolve_binary(&e.yarn, &e.pattern)
+            }
+            ast::ExpressionNodeValueOption::WunOfExpression(e) => {
+                self.resolve_expression(&e.condition);
+                self.resolve_binary(&e.then, &e.else_);
+            }
+            ast::ExpressionNodeValueOption::AllOfExpression(e) => {
+                for expression in e.expressions.iter() {
+                    self.resolve_expression(expression);
+                }
+            }
+            ast::ExpressionNodeValueOption::AnyOfExpression(e) => {
+                for expression in e.expressions.iter() {
+                    self.resolve_expression(expression);
+                }
+            }
+            ast::ExpressionNodeValueOption::SmooshExpression(e) => {
+                for expression in e.expressions.iter() {
+                    self.resolve_expression(expression);
+                }
+            }
+            ast::ExpressionNodeValueOption::FunctionCallExpression(call) => {
+                self.reference_function(&call.identifier);
+                for argument in call.arguments.iter() {
+                    self.resolve_expression(argument);
+                }
+            }
+        }
+    }
+
+    /// Functions are in scope everywhere, their own bodies included
+    fn reference_function(&mut self, token: &ast::TokenNode) {
+        let name = match identifier_name(token) {
+            Some(name) => name,
+            None => return,
+        };
+        let reference = Reference {
+            start: token.token.start,
+            end: token.token.end,
+        };
+
+        match self.functions.get(&name) {
+            Some(index) => self.symbols[*index].references.push(reference),
+            None => self.unresolved.push((name, reference)),
+        }
+    }
+
+    fn resolve_binary(&mut self, left: &ast::ExpressionNode, right: &ast::ExpressionNode) {
+        self.resolve_expression(left);
+        self.resolve_expression(right);
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::compiler::ir;
+use crate::compiler::opt::OptOptions;
+use crate::diagnostics::Diagnostic;
+
+/// FNV-1a, which unlike the std hasher is stable across compiler releases
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes.iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+fn serialize_statements(statements: &[ir::IRStatement], out: &mut String) {
+    for statement in statements.iter() {
+        let line = match statement {
+            ir::IRStatement::Push(n) => format!("push {}", n),
+            ir::IRStatement::Add => "add".to_string(),
+            ir::IRStatement::Subtract => "subtract".to_string(),
+            ir::IRStatement::Multiply => "multiply".to_string(),
+            ir::IRStatement::Divide => "divide".to_string(),
+            ir::IRStatement::Modulo => "modulo".to_string(),
+            ir::IRStatement::Sign => "sign".to_string(),
+            ir::IRStatement::Allocate => "allocate".to_string(),
+            ir::IRStatement::Free => "free".to_string(),
+            ir::IRStatement::Store(size, offset) => format!("store {} {}", size, offset),
+            ir::IRStatement::Load(size, offset) => format!("load {} {}", size, offset),
+            ir::IRStatement::StrAlloc => "str_alloc".to_string(),
+            ir::IRStatement::StrFree => "str_free".to_string(),
+            ir::IRStatement::StrStore(letters) => format!("str_store {}", letters),
+            ir::IRStatement::StrLoad(letters) => format!("str_load {}", letters),
+            ir::IRStatement::StrConcat(left, right) => format!("str_concat {} {}", left, right),
+            ir::IRStatement::StrCmp(letters) => format!("str_cmp {}", letters),
+            ir::IRStatement::StrPrint(letters) => format!("str_print {}", letters),
+            ir::IRStatement::Copy => "copy".to_string(),
+            ir::IRStatement::Mov => "mov".to_string(),
+            ir::IRStatement::BeginArena => "begin_arena".to_string(),
+            ir::IRStatement::EndArena => "end_arena".to_string(),
+            ir::IRStatement::Collect(roots) => format!("collect {}", roots),
+            ir::IRStatement::Hook(hook) => format!("hook {}", hook),
+            ir::IRStatement::RefHook(hook) => format!("ref_hook {}", hook),
+            ir::IRStatement::SetHook(hook) => format!("set_hook {}", hook),
+            ir::IRStatement::Call(name) => format!("call {}", name),
+            ir::IRStatement::CallForeign(name) => format!("call_foreign {}", name),
+            ir::IRStatement::If { then, else_ } => {
+                out.push_str("if\n");
+                serialize_statements(then, out);
+                out.push_str("else\n");
+                serialize_statements(else_, out);
+                "end".to_string()
+            }
+            ir::IRStatement::Loop { body } => {
+                out.push_str("loop\n");
+                serialize_statements(body, out);
+                "end".to_string()
+            }
+            ir::IRStatement::LoadBasePtr => "load_base_ptr".to_string(),
+            ir::IRStatement::EstablishStackFrame => "establish_stack_frame".to_string(),
+            ir::IRStatement::EndStackFrame(arg_size, local_scope_size) => {
+                format!("end_stack_frame {} {}", arg_size, local_scope_size)
+            }
+            ir::IRStatement::SetReturnRegister => "set_return_register".to_string(),
+            ir::IRStatement::AccessReturnRegister => "access_return_register".to_string(),
+            ir::IRStatement::Halt => "halt".to_string(),
+        };
+
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// Writes the IR in a line based text format
+pub fn serialize(ir: &ir::IR) -> String {
+    let mut out = format!(
+        "entry {} {} {}\n",
+        ir.entry.stack_size, ir.entry.heap_size, ir.entry.hooks
+    );
+    serialize_statements(&ir.entry.statements, &mut out);
+
+    for function in ir.functions.iter() {
+        out.push_str(format!("function {} {}", function.name, function.hooks).as_str());
+        // followed by the name the program gives it, which hand-written IR
+        // can leave out
+        if let Some(source) = ir.source_names.get(&function.name) {
+            out.push_str(format!(" {}", source).as_str());
+        }
+        out.push('\n');
+        serialize_statements(&function.statements, &mut out);
+    }
+
+    for function in ir.foreign.iter() {
+        out.push_str(
+            format!(
+                "foreign {} {}",
+                function.name,
+                function.result.map_or("NOOB", |kind| kind.name())
+            )
+            .as_str(),
+        );
+        for kind in function.args.iter() {
+            out.push(' ');
+            out.push_str(kind.name());
+        }
+        out.push('\n');
+    }
+
+    // the variable's name goes last, as it may have spaces
+    for hook in ir.hook_names.iter() {
+        out.push_str(
+            format!(
+                "name {} {} {} {}\n",
+                hook.function, hook.hook, hook.type_, hook.name
+            )
+            .as_str(),
+        );
+    }
+
+    out
+}
+
+/// Reads statements until a line that isn't one, which is returned with them
+fn deserialize_statements<'a>(
+    lines: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Option<Vec<ir::IRStatement>> {
+    let mut statements = vec![];
+
+    while let Some(line) = lines.peek() {
+        let (op, operand) = line.split_once(' ').unwrap_or((line, ""));
+        let statement = match op {
+            "push" => ir::IRStatement::Push(operand.parse().ok()?),
+            "add" => ir::IRStatement::Add,
+            "subtract" => ir::IRStatement::Subtract,
+            "multiply" => ir::IRStatement::Multiply,
+            "divide" => ir::IRStatement::Divide,
+            "modulo" => ir::IRStatement::Modulo,
+            "sign" => ir::IRStatement::Sign,
+            "allocate" => ir::IRStatement::Allocate,
+            "free" => ir::IRStatement::Free,
+            "store" => {
+                let (size, offset) = operand.split_once(' ')?;
+                ir::IRStatement::Store(size.parse().ok()?, offset.parse().ok()?)
+            }
+            "load" => {
+                let (size, offset) = operand.split_once(' ')?;
+                ir::IRStatement::Load(size.parse().ok()?, offset.parse().ok()?)
+            }
+            "str_alloc" => ir::IRStatement::StrAlloc,
+            "str_free" => ir::IRStatement::StrFree,
+            "str_store" => ir::IRStatement::StrStore(operand.parse().ok()?),
+            "str_load" => ir::IRStatement::StrLoad(operand.parse().ok()?),
+            "str_concat" => {
+                let (left, right) = operand.split_once(' ')?;
+                ir::IRStatement::StrConcat(left.parse().ok()?, right.parse().ok()?)
+            }
+            "str_cmp" => ir::IRStatement::StrCmp(operand.parse().ok()?),
+            "str_print" => ir::IRStatement::StrPrint(operand.parse().ok()?),
+            "copy" => ir::IRStatement::Copy,
+            "mov" => ir::IRStatement::Mov,
+            "begin_arena" => ir::IRStatement::BeginArena,
+            "end_arena" => ir::IRStatement::EndArena,
+            "collect" => ir::IRStatement::Collect(operand.parse().ok()?),
+            "hook" => ir::IRStatement::Hook(operand.parse().ok()?),
+            "ref_hook" => ir::IRStatement::RefHook(operand.parse().ok()?),
+            "set_hook" => ir::IRStatement::SetHook(operand.parse().ok()?),
+            "call" => ir::IRStatement::Call(operand.to_string()),
+            "call_foreign" => ir::IRStatement::CallForeign(operand.to_string()),
+            "if" => {
+                lines.next();
+                let then = deserialize_statements(lines)?;
+                if lines.next()? != "else" {
+                    return None;
+                }
+                let else_ = deserialize_statements(lines)?;
+                if *lines.peek()? != "end" {
+                    return None;
+                }
+                ir::IRStatement::If { then, else_ }
+            }
+            "loop" => {
+                lines.next();
+                let body = deserialize_statements(lines)?;
+                if *lines.peek()? != "end" {
+                    return None;
+                }
+                ir::IRStatement::Loop { body }
+            }
+            "load_base_ptr" => ir::IRStatement::LoadBasePtr,
+            "establish_stack_frame" => ir::IRStatement::EstablishStackFrame,
+            "end_stack_frame" => {
+                let (arg_size, local_scope_size) = operand.split_once(' ')?;
+                ir::IRStatement::EndStackFrame(
+                    arg_size.parse().ok()?,
+                    local_scope_size.parse().ok()?,
+                )
+            }
+            "set_return_register" => ir::IRStatement::SetReturnRegister,
+            "access_return_register" => ir::IRStatement::AccessReturnRegister,
+            "halt" => ir::IRStatement::Halt,
+            _ => return Some(statements),
+        };
+
+        lines.next();
+        statements.push(statement);
+    }
+
+    Some(statements)
+}
+
+/// Reads back what `serialize` wrote, or None if it is malformed
+pub fn deserialize(text: &str) -> Option<ir::IR> {
+    let mut lines = text.lines().peekable();
+
+    let mut sizes = lines.next()?.strip_prefix("entry ")?.split(' ');
+    let (stack_size, heap_size, hooks) = (sizes.next()?, sizes.next()?, sizes.next()?);
+    let statements = deserialize_statements(&mut lines)?;
+    let entry = ir::IRFunctionEntry::new(
+        stack_size.parse().ok()?,
+        heap_size.parse().ok()?,
+        hooks.parse().ok()?,
+        statements,
+    );
+
+    let mut functions = vec![];
+    let mut source_names = BTreeMap::new();
+    while let Some(line) = lines.next_if(|line| line.starts_with("function ")) {
+        let mut fields = line.strip_prefix("function ")?.splitn(3, ' ');
+        let (name, hooks) = (fields.next()?, fields.next()?);
+        if let Some(source) = fields.next() {
+            source_names.insert(name.to_string(), source.to_string());
+        }
+        functions.push(ir::IRFunction::new(
+            name.to_string(),
+            hooks.parse().ok()?,
+            deserialize_statements(&mut lines)?,
+        ));
+    }
+
+    let mut ir = ir::IR::new(functions, entry);
+    ir.source_names = source_names;
+    while let Some(line) = lines.next_if(|line| line.starts_with("foreign ")) {
+        let mut fields = line.strip_prefix("foreign ")?.split(' ');
+        let name = fields.next()?.to_string();
+        let result = match fields.next()? {
+            "NOOB" => None,
+            kind => Some(ir::ForeignKind::from_name(kind)?),
+        };
+        let args = fields
+            .map(ir::ForeignKind::from_name)
+            .collect::<Option<Vec<ir::ForeignKind>>>()?;
+        ir.foreign.push(ir::ForeignFn { name, args, result });
+    }
+    for line in lines {
+        let mut fields = line.strip_prefix("name ")?.splitn(4, ' ');
+        ir.hook_names.push(ir::HookName {
+            function: fields.next()?.to_string(),
+            hook: fields.next()?.parse().ok()?,
+            type_: fields.next()?.to_string(),
+            name: fields.next()?.to_string(),
+        });
+    }
+
+    Some(ir)
+}
+
+/// A warning on one line, with its newlines and backslashes escaped
+fn escape_line(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape_line(line: &str) -> String {
+    let mut text = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some(c) => text.push(c),
+            None => {}
+        }
+    }
+
+    text
+}
+
+/// Optimized IR stored on disk: whole programs with their warnings, keyed
+/// by a hash of the program's name and source and everything else that
+/// affects the result, and each function on its own, so that after an edit
+/// only the functions whose IR changed are optimized again
+pub struct IrCache {
+    dir: PathBuf,
+}
+
+impl IrCache {
+    pub fn new(dir: PathBuf) -> Self {
+        IrCache { dir }
+    }
+
+    /// The compiler's version and the options, which any IR stored depends
+    /// on
+    fn salt(options: &OptOptions) -> String {
+        format!(
+            "{} {} {} {} {} {:?} {:?}\n",
+            env!("CARGO_PKG_VERSION"),
+            options.level,
+            options.inline_threshold,
+            options.release,
+            options.gc,
+            options.passes,
+            options.disabled,
+        )
+    }
+
+    /// The key of a whole program. The name is part of it as the warnings
+    /// point into the file.
+    pub fn key(name: &str, source: &str, options: &OptOptions) -> String {
+        let salt = format!("{}{}\n", Self::salt(options), name);
+        format!("{:016x}", hash((salt + source).as_bytes()))
+    }
+
+    /// The key of an IR holding one function, or just an entry point, as
+    /// the passes that look across functions left it. Its callers are not
+    /// part of it, as the passes after those see one function at a time.
+    pub fn function_key(piece: &ir::IR, options: &OptOptions) -> String {
+        let text = Self::salt(options) + &serialize(piece);
+        format!("fn-{:016x}", hash(text.as_bytes()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.ir", key))
+    }
+
+    /// The optimized IR of the function stored under `key`
+    pub fn load_function(&self, key: &str) -> Option<ir::IR> {
+        deserialize(&fs::read_to_string(self.path(key)).ok()?)
+    }
+
+    pub fn store_function(&self, key: &str, piece: &ir::IR) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), serialize(piece))
+    }
+
+    /// The IR and warnings stored under `key`, which come after the IR one
+    /// a line
+    pub fn load(&self, key: &str) -> Option<(ir::IR, Vec<Diagnostic>)> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        let (ir, warnings) = text.split_once("\nwarnings\n")?;
+        let warnings = warnings
+            .lines()
+            .map(|line| Diagnostic::warning(unescape_line(line)))
+            .collect();
+        Some((deserialize(ir)?, warnings))
+    }
+
+    pub fn store(&self, key: &str, ir: &ir::IR, warnings: &[Diagnostic]) -> std::io::Result<()> {
+        let mut text = serialize(ir);
+        text.push_str("warnings\n");
+        for warning in warnings.iter() {
+            text.push_str(&escape_line(&warning.text));
+            text.push('\n');
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::opt;
+    use crate::driver;
+
+    /// A program of three functions, too long to be inlined, the second
+    /// adding `step`
+    fn program(step: i32) -> String {
+        let mut text = String::from("HAI 1.2\n");
+        for (name, add) in [("f", 1), ("g", step), ("h", 3)] {
+            text.push_str(&format!("HOW IZ I {} ITZ NUMBER YR x ITZ NUMBER\n", name));
+            text.push_str(&format!("  I HAS A y ITZ NUMBER R SUM OF x AN {}\n", add));
+            text.push_str("  y R PRODUKT OF y AN SUM OF y AN 2\n  FOUND YR y\nIF U SAY SO\n");
+        }
+        text.push_str("I HAS A n ITZ NUMBER R I IZ f YR 1 MKAY\n");
+        text.push_str("n R SUM OF I IZ g YR n MKAY AN I IZ h YR n MKAY\n");
+        text.push_str("VISIBLE MAEK n A YARN\nKTHXBYE\n");
+        text
+    }
+
+    #[test]
+    fn an_edit_to_one_function_only_has_it_optimized_again() {
+        let options = OptOptions {
+            level: 2,
+            inline_threshold: 0,
+            release: false,
+            gc: false,
+            passes: None,
+            disabled: vec![],
+            print_ir_after: None,
+        };
+        let dir = std::env::temp_dir().join(format!("lolcat-cache-{}", std::process::id()));
+        let cache = IrCache::new(dir.clone());
+
+        // the functions each pass seeing one at a time is run on
+        let optimize = |text: &str| {
+            let mut seen = vec![];
+            let ir = opt::optimize_with(
+                driver::lower_text(text),
+                &options,
+                Some(&cache),
+                &mut |run| {
+                    if run.name != "inline" {
+                        seen.push((run.ir.functions.len(), run.ir.entry.statements.is_empty()));
+                    }
+                },
+            );
+            (ir, seen)
+        };
+
+        let (_, seen) = optimize(&program(2));
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|seen| *seen == (3, false)), "{:?}", seen);
+        let (ir, seen) = optimize(&program(5));
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|seen| *seen == (1, true)), "{:?}", seen);
+        let _ = fs::remove_dir_all(&dir);
+
+        // the same IR as optimizing it all
+        let uncached = opt::optimize(driver::lower_text(&program(5)), &options);
+        assert_eq!(serialize(&ir), serialize(&uncached));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use crate::compiler::ir;
+
+pub const ENTRY_NAME: &str = "main";
+
+pub struct CallGraph {
+    /// function names in IR order, starting with the entry point
+    pub functions: Vec<String>,
+    /// (caller, callee) pairs in the order the calls appear
+    pub edges: Vec<(String, String)>,
+    pub reachable: HashSet<String>,
+}
+
+fn callees(statements: &[ir::IRStatement]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    ir::walk(statements, &mut |statement| {
+        if let ir::IRStatement::Call(name) = statement {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    });
+
+    names
+}
+
+impl CallGraph {
+    pub fn new(ir: &ir::IR) -> Self {
+        let mut functions = vec![ENTRY_NAME.to_string()];
+        let mut edges = vec![];
+
+        for callee in callees(&ir.entry.statements) {
+            edges.push((ENTRY_NAME.to_string(), callee));
+        }
+
+        for function in ir.functions.iter() {
+            functions.push(function.name.clone());
+            for callee in callees(&function.statements) {
+                edges.push((function.name.clone(), callee));
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        let mut pending = vec![ENTRY_NAME.to_string()];
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+
+            for (caller, callee) in edges.iter() {
+                if *caller == name {
+                    pending.push(callee.clone());
+                }
+            }
+        }
+
+        CallGraph {
+            functions,
+            edges,
+            reachable,
+        }
+    }
+
+    pub fn unreachable(&self) -> Vec<String> {
+        self.functions
+            .iter()
+            .filter(|name| !self.reachable.contains(*name))
+            .cloned()
+            .collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for name in self.functions.iter() {
+            text.push_str(name);
+            if !self.reachable.contains(name) {
+                text.push_str(" (unreachable)");
+            }
+            text.push('\n');
+
+            for (caller, callee) in self.edges.iter() {
+                if caller == name {
+                    text.push_str(format!("  -> {}\n", callee).as_str());
+                }
+            }
+        }
+
+        text
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph callgraph {\n");
+
+        for name in self.functions.iter() {
+            if self.reachable.contains(name) {
+                dot.push_str(format!("  \"{}\";\n", name).as_str());
+            } else {
+                dot.push_str(format!("  \"{}\" [style=dashed, color=gray];\n", name).as_str());
+            }
+        }
+
+        for (caller, callee) in self.edges.iter() {
+            dot.push_str(format!("  \"{}\" -> \"{}\";\n", caller, callee).as_str());
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
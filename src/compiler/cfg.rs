@@ -0,0 +1,204 @@
+use crate::compiler::ir;
+
+pub type BlockId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// Pops a condition, an If whose arms both end by jumping to `join`
+    Branch {
+        then: BlockId,
+        else_: BlockId,
+        join: BlockId,
+    },
+    /// Pops a condition on entry to a Loop
+    Loop {
+        body: BlockId,
+        exit: BlockId,
+    },
+    /// Pops a condition at the end of the body of the Loop starting at `body`
+    Continue {
+        body: BlockId,
+        exit: BlockId,
+    },
+    Return,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then, else_, .. } => vec![*then, *else_],
+            Terminator::Loop { body, exit } | Terminator::Continue { body, exit } => {
+                vec![*body, *exit]
+            }
+            Terminator::Return => vec![],
+        }
+    }
+
+    /// Whether the terminator pops a condition off the stack
+    pub fn pops_condition(&self) -> bool {
+        !matches!(self, Terminator::Jump(_) | Terminator::Return)
+    }
+}
+
+/// Straight-line statements followed by a terminator
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub statements: Vec<ir::IRStatement>,
+    pub terminator: Terminator,
+}
+
+impl Block {
+    fn new() -> Self {
+        Block {
+            statements: vec![],
+            terminator: Terminator::Return,
+        }
+    }
+}
+
+/// A statement list as basic blocks with explicit branches. Blocks remember
+/// which If or Loop they came from so the graph can be turned back into
+/// structured statements for assembly.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+    pub entry: BlockId,
+}
+
+impl Cfg {
+    pub fn new(statements: &[ir::IRStatement]) -> Self {
+        let mut cfg = Cfg {
+            blocks: vec![Block::new()],
+            entry: 0,
+        };
+        cfg.build(statements, 0);
+
+        cfg
+    }
+
+    fn add_block(&mut self) -> BlockId {
+        self.blocks.push(Block::new());
+        self.blocks.len() - 1
+    }
+
+    /// Appends `statements` to `current`, returning the block control ends up in
+    fn build(&mut self, statements: &[ir::IRStatement], mut current: BlockId) -> BlockId {
+        for statement in statements.iter() {
+            match statement {
+                ir::IRStatement::If { then, else_ } => {
+                    let then_block = self.add_block();
+                    let else_block = self.add_block();
+                    let join = self.add_block();
+                    self.blocks[current].terminator = Terminator::Branch {
+                        then: then_block,
+                        else_: else_block,
+                        join,
+                    };
+
+                    let then_end = self.build(then, then_block);
+                    self.blocks[then_end].terminator = Terminator::Jump(join);
+                    let else_end = self.build(else_, else_block);
+                    self.blocks[else_end].terminator = Terminator::Jump(join);
+
+                    current = join;
+                }
+                ir::IRStatement::Loop { body } => {
+                    let body_block = self.add_block();
+                    let exit = self.add_block();
+                    self.blocks[current].terminator = Terminator::Loop {
+                        body: body_block,
+                        exit,
+                    };
+
+                    let body_end = self.build(body, body_block);
+                    self.blocks[body_end].terminator = Terminator::Continue {
+                        body: body_block,
+                        exit,
+                    };
+
+                    current = exit;
+                }
+                _ => self.blocks[current].statements.push(statement.clone()),
+            }
+        }
+
+        current
+    }
+
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![self.entry];
+
+        while let Some(id) = pending.pop() {
+            if reachable[id] {
+                continue;
+            }
+            reachable[id] = true;
+            pending.extend(self.blocks[id].terminator.successors());
+        }
+
+        reachable
+    }
+
+    /// Empties every block that can't be reached from the entry, returning how many there were
+    pub fn remove_unreachable(&mut self) -> usize {
+        let mut removed = 0;
+
+        for (id, reachable) in self.reachable().into_iter().enumerate() {
+            if !reachable {
+                self.blocks[id] = Block::new();
+                removed += 1;
+            }
+        }
+
+        removed
+    }
+
+    /// Converts the graph back into structured statements
+    pub fn to_statements(&self) -> Vec<ir::IRStatement> {
+        self.emit(self.entry, None, None)
+    }
+
+    /// Emits blocks from `start` until control reaches `stop` or the end of
+    /// the body of the loop starting at `loop_body`
+    fn emit(
+        &self,
+        start: BlockId,
+        stop: Option<BlockId>,
+        loop_body: Option<BlockId>,
+    ) -> Vec<ir::IRStatement> {
+        let mut statements = vec![];
+        let mut current = start;
+
+        while Some(current) != stop {
+            let block = &self.blocks[current];
+            statements.extend(block.statements.iter().cloned());
+
+            match &block.terminator {
+                Terminator::Jump(target) => current = *target,
+                Terminator::Branch { then, else_, join } => {
+                    statements.push(ir::IRStatement::If {
+                        then: self.emit(*then, Some(*join), loop_body),
+                        else_: self.emit(*else_, Some(*join), loop_body),
+                    });
+                    current = *join;
+                }
+                Terminator::Loop { body, exit } => {
+                    statements.push(ir::IRStatement::Loop {
+                        body: self.emit(*body, None, Some(*body)),
+                    });
+                    current = *exit;
+                }
+                Terminator::Continue { body, .. } if Some(*body) == loop_body => break,
+                Terminator::Continue { .. } => {
+                    panic!("Loop body ended outside of its loop")
+                }
+                Terminator::Return => break,
+            }
+        }
+
+        statements
+    }
+}
@@ -1,8 +1,64 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use crate::compiler::target::Target;
 
+/// A number the IR pushes. NUMBERs, TROOFs, sizes and addresses are Ints
+/// so a target with native integers can keep them in its words, and only
+/// NUMBARs are Floats. The VM holds both as floats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+}
+
+impl Value {
+    pub fn as_f32(self) -> f32 {
+        match self {
+            Value::Int(n) => n as f32,
+            Value::Float(n) => n,
+        }
+    }
+
+    pub fn is_int(self) -> bool {
+        matches!(self, Value::Int(_))
+    }
+
+    /// `result` of an operation computed as floats, an Int when every
+    /// operand was and it is still a whole number that fits
+    pub fn folded(result: f32, ints: bool) -> Value {
+        if ints && result.fract() == 0.0 && result >= i32::MIN as f32 && result < i32::MAX as f32 {
+            Value::Int(result as i32)
+        } else {
+            Value::Float(result)
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Floats always keep a point, so the two read back apart
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{:?}", n),
+        }
+    }
+}
+
+impl std::str::FromStr for Value {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(n) => Ok(Value::Int(n)),
+            Err(_) => s.parse().map(Value::Float),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum IRStatement {
-    Push(f32),
+    Push(Value),
     Add,
     Subtract,
     Multiply,
@@ -11,16 +67,61 @@ pub enum IRStatement {
     Sign,
     Allocate,
     Free,
-    Store(i32),
-    Load(i32),
+    /// Pops an address and then that many values, storing them from the
+    /// element the offset gives on: the value pushed first goes lowest. An
+    /// element is one value however many bytes the target gives it, and the
+    /// address is one Allocate pushed, so a target scales the offset itself
+    /// and a debug build may check the elements lie in the allocation.
+    Store(i32, i32),
+    /// Pops an address and pushes that many values from the element the
+    /// offset gives on, the lowest first, so a Load undoes a Store
+    Load(i32, i32),
+    /// Pops a number of letters and pushes the address of a new YARN that
+    /// long, laid out however the target packs letters
+    StrAlloc,
+    /// Pops the address and then the number of letters of a YARN, like Free
+    StrFree,
+    /// Pops an address, then that many letters, storing them into a YARN
+    StrStore(i32),
+    /// Pops the address of a YARN and pushes that many of its letters
+    StrLoad(i32),
+    /// Pops the address of a YARN of the second size and then of a YARN
+    /// holding the first size of letters, appending the letters of the one
+    /// to the other like strcat. The YARN appended to has to have room.
+    StrConcat(i32, i32),
+    /// Pops the addresses of two YARNs of that many letters, pushing -1, 0
+    /// or 1 as the first letter they differ in is smaller in the first, they
+    /// do not differ, or it is larger
+    StrCmp(i32),
+    /// Pops the address of a YARN and prints that many of its letters
+    StrPrint(i32),
     Copy,
     Mov,
+    /// Starts a region whose heap allocations are all freed by its end, so
+    /// a target may hand them out from an arena it drops at EndArena
+    BeginArena,
+    EndArena,
+    /// Pops the addresses of that many YARNs and bukkits the program still
+    /// holds, after which a target may free every allocation none of them
+    /// reaches. Only emitted between statements with --gc, which leaves out
+    /// every free.
+    Collect(i32),
     Hook(i32),
     RefHook(i32),
+    /// Pops a value into a hook, which RefHook then pushes back, for values
+    /// kept across a loop rather than positions
+    SetHook(i32),
     Call(String),
     CallForeign(String),
-    BeginWhile,
-    EndWhile,
+    /// Pops a condition and runs `then` if it is nonzero, `else_` otherwise
+    If {
+        then: Vec<IRStatement>,
+        else_: Vec<IRStatement>,
+    },
+    /// Pops a condition and runs `body` while it is nonzero, `body` pushes the next condition
+    Loop {
+        body: Vec<IRStatement>,
+    },
     LoadBasePtr,
     EstablishStackFrame,
     EndStackFrame(i32, i32),
@@ -32,7 +133,7 @@ pub enum IRStatement {
 impl IRStatement {
     pub fn assemble(&self, target: &impl Target) -> String {
         match self {
-            IRStatement::Push(n) => target.push(*n),
+            IRStatement::Push(value) => target.push(*value),
             IRStatement::Add => target.add(),
             IRStatement::Subtract => target.subtract(),
             IRStatement::Multiply => target.multiply(),
@@ -41,16 +142,41 @@ impl IRStatement {
             IRStatement::Sign => target.sign(),
             IRStatement::Allocate => target.allocate(),
             IRStatement::Free => target.free(),
-            IRStatement::Store(floats) => target.store(*floats),
-            IRStatement::Load(floats) => target.load(*floats),
+            IRStatement::Store(size, offset) => target.store(*size, *offset),
+            IRStatement::Load(size, offset) => target.load(*size, *offset),
+            IRStatement::StrAlloc => target.str_alloc(),
+            IRStatement::StrFree => target.str_free(),
+            IRStatement::StrStore(letters) => target.str_store(*letters),
+            IRStatement::StrLoad(letters) => target.str_load(*letters),
+            IRStatement::StrConcat(left, right) => target.str_concat(*left, *right),
+            IRStatement::StrCmp(letters) => target.str_cmp(*letters),
+            IRStatement::StrPrint(letters) => target.str_print(*letters),
             IRStatement::Copy => target.f_copy(),
             IRStatement::Mov => target.mov(),
+            IRStatement::BeginArena => target.begin_arena(),
+            IRStatement::EndArena => target.end_arena(),
+            IRStatement::Collect(roots) => target.collect(*roots),
             IRStatement::Hook(index) => target.hook(*index),
             IRStatement::RefHook(index) => target.ref_hook(*index),
+            IRStatement::SetHook(index) => target.set_hook(*index),
             IRStatement::Call(name) => target.call_fn(name.clone()),
             IRStatement::CallForeign(name) => target.call_foreign_fn(name.clone()),
-            IRStatement::BeginWhile => target.begin_while(),
-            IRStatement::EndWhile => target.end_while(),
+            IRStatement::If { then, else_ } => {
+                let mut code = target.begin_if();
+                code.push_str(&assemble_block(then, target));
+                if !else_.is_empty() {
+                    code.push_str(&target.begin_else());
+                    code.push_str(&assemble_block(else_, target));
+                }
+                code.push_str(&target.end_if());
+                code
+            }
+            IRStatement::Loop { body } => {
+                let mut code = target.begin_while();
+                code.push_str(&assemble_block(body, target));
+                code.push_str(&target.end_while());
+                code
+            }
             IRStatement::LoadBasePtr => target.load_base_ptr(),
             IRStatement::EstablishStackFrame => target.establish_stack_frame(),
             IRStatement::EndStackFrame(arg_size, local_scope_size) => {
@@ -61,28 +187,105 @@ impl IRStatement {
             IRStatement::Halt => target.halt(),
         }
     }
+
+    /// Discards the value on top of the stack
+    pub fn pop() -> Self {
+        IRStatement::If {
+            then: vec![],
+            else_: vec![],
+        }
+    }
+
+    /// The blocks nested in an If or Loop
+    pub fn blocks(&self) -> Vec<&Vec<IRStatement>> {
+        match self {
+            IRStatement::If { then, else_ } => vec![then, else_],
+            IRStatement::Loop { body } => vec![body],
+            _ => vec![],
+        }
+    }
+
+    /// Rebuilds an If or Loop with every nested block passed through `f`
+    pub fn map_blocks(&self, mut f: impl FnMut(&[IRStatement]) -> Vec<IRStatement>) -> Self {
+        match self {
+            IRStatement::If { then, else_ } => IRStatement::If {
+                then: f(then),
+                else_: f(else_),
+            },
+            IRStatement::Loop { body } => IRStatement::Loop { body: f(body) },
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Assembles statements in order, handing runs of pushes to the target at
+/// once
+fn assemble_block(statements: &[IRStatement], target: &impl Target) -> String {
+    let mut code = String::new();
+    let mut pushes = vec![];
+
+    for statement in statements.iter() {
+        if let IRStatement::Push(value) = statement {
+            pushes.push(*value);
+            continue;
+        }
+        if !pushes.is_empty() {
+            code.push_str(&target.push_many(&pushes));
+            pushes.clear();
+        }
+        code.push_str(&statement.assemble(target));
+    }
+    if !pushes.is_empty() {
+        code.push_str(&target.push_many(&pushes));
+    }
+
+    code
+}
+
+/// Calls `f` on every statement in order, including those in nested blocks
+pub fn walk<'a>(statements: &'a [IRStatement], f: &mut impl FnMut(&'a IRStatement)) {
+    for statement in statements.iter() {
+        f(statement);
+        for block in statement.blocks() {
+            walk(block, f);
+        }
+    }
+}
+
+/// The hook slots a frame running `statements` needs, one more than the
+/// highest hook they use
+pub fn hook_slots(statements: &[IRStatement]) -> i32 {
+    let mut slots = 0;
+    walk(statements, &mut |statement| match statement {
+        IRStatement::Hook(hook) | IRStatement::RefHook(hook) | IRStatement::SetHook(hook) => {
+            slots = slots.max(hook + 1)
+        }
+        _ => {}
+    });
+    slots
 }
 
 #[derive(Debug, Clone)]
 pub struct IRFunction {
     pub name: String,
+    /// hook slots its frame reserves, its hooks being numbered from 0
+    pub hooks: i32,
     pub statements: Vec<IRStatement>,
 }
 
 impl IRFunction {
-    pub fn new(name: String, statements: Vec<IRStatement>) -> Self {
-        IRFunction { name, statements }
+    pub fn new(name: String, hooks: i32, statements: Vec<IRStatement>) -> Self {
+        IRFunction {
+            name,
+            hooks,
+            statements,
+        }
     }
 
     pub fn assemble(&self, target: &impl Target) -> String {
         let mut code = String::new();
-        let mut body = String::new();
-
-        for statement in self.statements.iter() {
-            let assembly = statement.assemble(target);
-
-            body.push_str(&assembly);
-        }
+        let mut body = target.reserve_hooks(self.hooks);
+        body.push_str(&assemble_block(&self.statements, target));
 
         code.push_str(&target.fn_definition(self.name.clone(), body));
 
@@ -94,33 +297,28 @@ impl IRFunction {
 pub struct IRFunctionEntry {
     pub stack_size: i32,
     pub heap_size: i32,
+    /// hook slots its frame reserves, see IRFunction::hooks
+    pub hooks: i32,
     pub statements: Vec<IRStatement>,
 }
 
 impl IRFunctionEntry {
-    pub fn new(stack_size: i32, heap_size: i32, statements: Vec<IRStatement>) -> Self {
+    pub fn new(stack_size: i32, heap_size: i32, hooks: i32, statements: Vec<IRStatement>) -> Self {
         IRFunctionEntry {
             stack_size,
             heap_size,
+            hooks,
             statements,
         }
     }
 
-    pub fn assemble(&self, target: &impl Target, hooks: i32) -> String {
+    pub fn assemble(&self, target: &impl Target) -> String {
         let mut code = String::new();
-        let mut body = String::new();
-
-        for statement in self.statements.iter() {
-            let assembly = statement.assemble(target);
-
-            body.push_str(&assembly);
-        }
+        let body = assemble_block(&self.statements, target);
 
         code.push_str(&target.begin_entry_point(self.stack_size, self.heap_size));
+        code.push_str(&target.reserve_hooks(self.hooks));
         // we don't need a return address as end_stack_frame is never called in entry
-        for _ in 0..hooks {
-            code.push_str(&target.push(0.0));
-        }
         code.push_str(&target.establish_stack_frame());
         code.push_str(&body);
         code.push_str(&target.end_entry_point());
@@ -129,35 +327,219 @@ impl IRFunctionEntry {
     }
 }
 
+/// The variable a hook of a function holds, for showing variables by name
+/// while the program runs
+#[derive(Debug, Clone)]
+pub struct HookName {
+    /// the function the hook is in, main for the entry point
+    pub function: String,
+    pub hook: i32,
+    pub name: String,
+    /// the type the variable was declared with, as DUMPZ names it
+    pub type_: String,
+}
+
+/// A type a foreign function takes or returns, as C has it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignKind {
+    Number,
+    Numbar,
+    Troof,
+}
+
+impl ForeignKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForeignKind::Number => "NUMBER",
+            ForeignKind::Numbar => "NUMBAR",
+            ForeignKind::Troof => "TROOF",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "NUMBER" => Some(ForeignKind::Number),
+            "NUMBAR" => Some(ForeignKind::Numbar),
+            "TROOF" => Some(ForeignKind::Troof),
+            _ => None,
+        }
+    }
+}
+
+/// A foreign function from outside the target's std, linked in with the
+/// program. It pops its arguments, the last pushed being the last argument,
+/// and pushes its result if it has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignFn {
+    pub name: String,
+    pub args: Vec<ForeignKind>,
+    pub result: Option<ForeignKind>,
+}
+
 #[derive(Debug, Clone)]
 pub struct IR {
     pub functions: Vec<IRFunction>,
     pub entry: IRFunctionEntry,
+    /// the variables of every function by hook, left empty in release builds
+    pub hook_names: Vec<HookName>,
+    /// the foreign functions called besides the target's std
+    pub foreign: Vec<ForeignFn>,
+    /// the name the program gives each function, by its IR name, for
+    /// showing calls while the program runs
+    pub source_names: BTreeMap<String, String>,
+}
+
+/// The code every program `target` assembles starts with: its core and, for
+/// a standard target, its std
+pub fn prelude(target: &impl Target) -> String {
+    let mut code = target.core_prelude();
+    if target.is_standard() {
+        code.push_str(&target.std());
+    }
+    code
 }
 
 impl IR {
     pub fn new(functions: Vec<IRFunction>, entry: IRFunctionEntry) -> Self {
-        IR { functions, entry }
+        IR {
+            functions,
+            entry,
+            hook_names: vec![],
+            foreign: vec![],
+            source_names: BTreeMap::new(),
+        }
     }
 
-    pub fn assemble(&self, target: &impl Target, hooks: i32) -> String {
-        let mut code = String::new();
-        code.push_str(&target.core_prelude());
-        if target.is_standard() {
-            code.push_str(&target.std());
+    /// The name the program gives IR function `name`, main for the entry
+    /// point
+    pub fn source_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.source_names
+            .get(name)
+            .map_or(name, |source| source.as_str())
+    }
+
+    /// The names of the foreign functions called anywhere, each once
+    fn foreign_calls<'a>(&'a self) -> Vec<&'a str> {
+        let mut calls: Vec<&str> = vec![];
+        let mut visit = |statement: &'a IRStatement| {
+            if let IRStatement::CallForeign(name) = statement {
+                if !calls.contains(&name.as_str()) {
+                    calls.push(name);
+                }
+            }
+        };
+
+        walk(&self.entry.statements, &mut visit);
+        for function in self.functions.iter() {
+            walk(&function.statements, &mut visit);
+        }
+        calls
+    }
+
+    /// Checks that every foreign function called is in `target`'s std or
+    /// declared in `foreign`, and that the declarations neither repeat nor
+    /// replace one of the std, returning a message for each that does not
+    pub fn check_foreign(&self, target: &impl Target) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        for (i, function) in self.foreign.iter().enumerate() {
+            if target.has_foreign(&function.name) {
+                errors.push(format!(
+                    "Error: foreign function {} is already in the std",
+                    function.name
+                ));
+            } else if self.foreign[..i]
+                .iter()
+                .any(|other| other.name == function.name)
+            {
+                errors.push(format!(
+                    "Error: foreign function {} is declared twice",
+                    function.name
+                ));
+            }
+        }
+
+        for name in self.foreign_calls() {
+            if !target.has_foreign(name) && !self.foreign.iter().any(|f| f.name == name) {
+                errors.push(format!("Error: foreign function {} is not declared", name));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Number of statements across the entry point and every function
+    pub fn statement_count(&self) -> usize {
+        let mut count = 0;
+        walk(&self.entry.statements, &mut |_| count += 1);
+        for function in self.functions.iter() {
+            walk(&function.statements, &mut |_| count += 1);
         }
 
+        count
+    }
+
+    /// Shrinks the hook slots every frame reserves to those its statements
+    /// use, which passes that drop or rename hooks leave too many of
+    pub fn fit_hooks(&mut self) {
+        self.entry.hooks = hook_slots(&self.entry.statements);
+        for function in self.functions.iter_mut() {
+            function.hooks = hook_slots(&function.statements);
+        }
+    }
+
+    /// Hook slots across the entry point and every function
+    pub fn hook_count(&self) -> i32 {
+        self.entry.hooks + self.functions.iter().map(|f| f.hooks).sum::<i32>()
+    }
+
+    pub fn assemble(&self, target: &impl Target) -> String {
+        self.assemble_after(&prelude(target), target)
+    }
+
+    /// assemble with the code every program starts with, as prelude gives it
+    pub fn assemble_after(&self, prelude: &str, target: &impl Target) -> String {
+        let mut code = String::from(prelude);
+        // declared functions that are never called need not be linked in
+        let calls = self.foreign_calls();
+        let foreign = self
+            .foreign
+            .iter()
+            .filter(|function| calls.contains(&function.name.as_str()))
+            .cloned()
+            .collect::<Vec<ForeignFn>>();
+        code.push_str(&target.foreign_prototypes(&foreign));
+
+        let names = self
+            .functions
+            .iter()
+            .map(|function| function.name.clone())
+            .collect::<Vec<String>>();
+        let shown = names
+            .iter()
+            .map(|name| self.source_name(name).to_string())
+            .collect::<Vec<String>>();
+        code.push_str(&target.function_table(&names, &shown));
+        code.push_str(&target.hook_table(&names, &self.hook_names));
+
+        // functions can call each other whatever order they come in
+        for name in names.iter() {
+            code.push_str(&target.fn_header(name.clone()));
+        }
         for function in self.functions.iter() {
             let assembly = function.assemble(target);
 
             code.push_str(&assembly);
         }
 
-        let entry = self.entry.assemble(target, hooks);
+        let entry = self.entry.assemble(target);
 
         code.push_str(&entry);
         code.push_str(&target.core_postlude());
 
-        code
+        target.finish(code, &names)
     }
 }
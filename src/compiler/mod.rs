@@ -1,3 +1,8 @@
+pub mod cache;
+pub mod callgraph;
+pub mod cfg;
 pub mod ir;
+pub mod opt;
+pub mod sandbox;
 pub mod target;
 pub mod visit;
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+
+use crate::compiler::ir;
+
+/// Abstract state of the straight-line region being simplified. Stack
+/// positions are relative to the stack pointer at the start of the region.
+#[derive(Clone, Default, PartialEq)]
+pub struct Region {
+    stack: Vec<Option<ir::Value>>,
+    hooks: HashMap<i32, usize>,
+}
+
+impl Region {
+    pub fn new() -> Self {
+        Region {
+            stack: vec![],
+            hooks: HashMap::new(),
+        }
+    }
+
+    /// What is known on every path, given states reaching the same point
+    pub fn meet(&self, other: &Region) -> Region {
+        if self.stack.len() != other.stack.len() {
+            return Region::new();
+        }
+
+        Region {
+            stack: self
+                .stack
+                .iter()
+                .zip(other.stack.iter())
+                .map(|(a, b)| if a == b { *a } else { None })
+                .collect(),
+            hooks: self
+                .hooks
+                .iter()
+                .filter(|(hook, position)| other.hooks.get(hook) == Some(position))
+                .map(|(hook, position)| (*hook, *position))
+                .collect(),
+        }
+    }
+
+    /// The value on top of the stack, if it is known
+    pub fn top(&self) -> Option<ir::Value> {
+        self.stack.last().copied().flatten()
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.hooks.clear();
+    }
+
+    fn push(&mut self, value: Option<ir::Value>) {
+        self.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<ir::Value> {
+        match self.stack.pop() {
+            Some(value) => {
+                let len = self.stack.len();
+                self.hooks.retain(|_, position| *position < len);
+                value
+            }
+            None => {
+                // popped past the start of the region, nothing tracked is valid
+                self.reset();
+                None
+            }
+        }
+    }
+
+    fn pop_many(&mut self, amount: i32) {
+        for _ in 0..amount {
+            self.pop();
+        }
+    }
+
+    fn hook_value(&self, hook: i32) -> Option<ir::Value> {
+        self.hooks
+            .get(&hook)
+            .and_then(|position| self.stack[*position])
+    }
+}
+
+fn fold(statement: &ir::IRStatement, a: ir::Value, b: ir::Value) -> Option<ir::Value> {
+    let ints = a.is_int() && b.is_int();
+    let (a, b) = (a.as_f32(), b.as_f32());
+    let result = match statement {
+        ir::IRStatement::Add => Some(a + b),
+        ir::IRStatement::Subtract => Some(a - b),
+        ir::IRStatement::Multiply => Some(a * b),
+        ir::IRStatement::Divide if b != 0.0 => Some(a / b),
+        ir::IRStatement::Modulo if b as i32 != 0 => Some((a as i32 % b as i32) as f32),
+        _ => None,
+    };
+
+    // infinities and NaN have no literal form in the emitted C
+    result
+        .filter(|n| n.is_finite())
+        .map(|n| ir::Value::folded(n, ints))
+}
+
+/// Whether `x op identity` is always `x`, letting the push and the op both go
+fn is_identity(statement: &ir::IRStatement, operand: ir::Value) -> bool {
+    let operand = operand.as_f32();
+    match statement {
+        ir::IRStatement::Add | ir::IRStatement::Subtract => operand == 0.0,
+        ir::IRStatement::Multiply | ir::IRStatement::Divide => operand == 1.0,
+        _ => false,
+    }
+}
+
+fn last_push(output: &[ir::IRStatement], from_end: usize) -> Option<ir::Value> {
+    if output.len() < from_end {
+        return None;
+    }
+    match output[output.len() - from_end] {
+        ir::IRStatement::Push(n) => Some(n),
+        _ => None,
+    }
+}
+
+fn simplify_statements(statements: &[ir::IRStatement]) -> Vec<ir::IRStatement> {
+    simplify(statements, &mut Region::new())
+}
+
+/// Simplifies `statements` starting from what `region` knows, leaving it
+/// describing the state after them
+pub fn simplify(statements: &[ir::IRStatement], region: &mut Region) -> Vec<ir::IRStatement> {
+    let mut output: Vec<ir::IRStatement> = vec![];
+
+    for statement in statements.iter() {
+        match statement {
+            ir::IRStatement::Push(n) => {
+                region.push(Some(*n));
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Add
+            | ir::IRStatement::Subtract
+            | ir::IRStatement::Multiply
+            | ir::IRStatement::Divide
+            | ir::IRStatement::Modulo => {
+                if let (Some(a), Some(b)) = (last_push(&output, 2), last_push(&output, 1)) {
+                    if let Some(result) = fold(statement, a, b) {
+                        output.truncate(output.len() - 2);
+                        output.push(ir::IRStatement::Push(result));
+                        region.pop_many(2);
+                        region.push(Some(result));
+                        continue;
+                    }
+                }
+
+                if let Some(b) = last_push(&output, 1) {
+                    if is_identity(statement, b) {
+                        output.pop();
+                        region.pop();
+                        continue;
+                    }
+                }
+
+                let b = region.pop();
+                let a = region.pop();
+                region.push(match (a, b) {
+                    (Some(a), Some(b)) => fold(statement, a, b),
+                    _ => None,
+                });
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Sign => {
+                if let Some(x) = last_push(&output, 1) {
+                    let sign = ir::Value::Int(if x.as_f32() >= 0.0 { 1 } else { -1 });
+                    output.pop();
+                    output.push(ir::IRStatement::Push(sign));
+                    region.pop();
+                    region.push(Some(sign));
+                    continue;
+                }
+
+                region.pop();
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Hook(hook) => {
+                match region.stack.len() {
+                    0 => region.hooks.remove(hook),
+                    len => region.hooks.insert(*hook, len - 1),
+                };
+                output.push(statement.clone());
+            }
+            ir::IRStatement::RefHook(_) => {
+                // the slot's address, which is never known at compile time
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Copy => {
+                if let Some(ir::IRStatement::RefHook(hook)) = output.last() {
+                    if let Some(value) = region.hook_value(*hook) {
+                        output.pop();
+                        output.push(ir::IRStatement::Push(value));
+                        region.pop();
+                        region.push(Some(value));
+                        continue;
+                    }
+                }
+
+                region.pop();
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Mov => {
+                let target = match output.last() {
+                    Some(ir::IRStatement::RefHook(hook)) => Some(region.hooks.get(hook).copied()),
+                    _ => None,
+                };
+
+                region.pop();
+                let value = region.pop();
+                match target {
+                    Some(Some(position)) => region.stack[position] = value,
+                    // a hook set before this region points below every tracked slot
+                    Some(None) => {}
+                    None => region.stack.iter_mut().for_each(|v| *v = None),
+                }
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Allocate | ir::IRStatement::StrAlloc => {
+                region.pop();
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Free | ir::IRStatement::StrFree => {
+                region.pop_many(2);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::StrCmp(_) => {
+                region.pop_many(2);
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::StrConcat(..) => {
+                region.pop_many(2);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::StrPrint(_) => {
+                region.pop();
+                output.push(statement.clone());
+            }
+            ir::IRStatement::BeginArena | ir::IRStatement::EndArena => {
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Collect(roots) => {
+                region.pop_many(*roots);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Store(floats, _) | ir::IRStatement::StrStore(floats) => {
+                region.pop_many(floats + 1);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::Load(floats, _) | ir::IRStatement::StrLoad(floats) => {
+                region.pop();
+                for _ in 0..*floats {
+                    region.push(None);
+                }
+                output.push(statement.clone());
+            }
+            ir::IRStatement::SetReturnRegister => {
+                region.pop();
+                output.push(statement.clone());
+            }
+            ir::IRStatement::AccessReturnRegister => {
+                region.push(None);
+                output.push(statement.clone());
+            }
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => {
+                // nested blocks start regions of their own, and whatever they
+                // write is unknown once control joins back here
+                output.push(statement.map_blocks(simplify_statements));
+                region.reset();
+            }
+            // calls and frame changes end the region
+            _ => {
+                region.reset();
+                output.push(statement.clone());
+            }
+        }
+    }
+
+    output
+}
+
+/// Propagates constants stored in hooks through straight-line code, folds
+/// arithmetic on known operands and drops operations with an identity operand
+pub fn propagate_constants(ir: &ir::IR) -> ir::IR {
+    let entry = ir::IRFunctionEntry::new(
+        ir.entry.stack_size,
+        ir.entry.heap_size,
+        ir.entry.hooks,
+        simplify_statements(&ir.entry.statements),
+    );
+    let functions = ir
+        .functions
+        .iter()
+        .map(|f| ir::IRFunction::new(f.name.clone(), f.hooks, simplify_statements(&f.statements)))
+        .collect();
+
+    ir::IR::new(functions, entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::ir::{IRStatement as S, Value};
+    use crate::driver;
+
+    fn pushes(statements: &[S]) -> Vec<Value> {
+        statements
+            .iter()
+            .filter_map(|statement| match statement {
+                S::Push(n) => Some(*n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn literal_arithmetic_folds_into_one_push() {
+        let before = vec![
+            S::Push(Value::Int(2)),
+            S::Push(Value::Int(3)),
+            S::Multiply,
+            S::Push(Value::Int(4)),
+            S::Add,
+        ];
+        let after = simplify_statements(&before);
+        assert_eq!((before.len(), after.len()), (5, 1));
+        assert_eq!(pushes(&after), vec![Value::Int(10)]);
+    }
+
+    #[test]
+    fn a_hook_holding_a_constant_is_read_as_the_constant() {
+        let before = vec![
+            S::Push(Value::Int(4)),
+            S::Hook(0),
+            S::RefHook(0),
+            S::Copy,
+            S::Push(Value::Int(1)),
+            S::Add,
+        ];
+        let after = simplify_statements(&before);
+        assert_eq!((before.len(), after.len()), (6, 3));
+        assert!(matches!(after[1], S::Hook(0)));
+        assert_eq!(pushes(&after), vec![Value::Int(4), Value::Int(5)]);
+    }
+
+    #[test]
+    fn operations_with_an_identity_operand_are_dropped() {
+        let before = vec![
+            S::RefHook(0),
+            S::Copy,
+            S::Push(Value::Int(0)),
+            S::Add,
+            S::Push(Value::Int(1)),
+            S::Multiply,
+        ];
+        let after = simplify_statements(&before);
+        assert_eq!((before.len(), after.len()), (6, 2));
+        assert!(matches!(after[..], [S::RefHook(0), S::Copy]));
+    }
+
+    #[test]
+    fn a_call_forgets_what_hooks_held() {
+        let before = vec![
+            S::Push(Value::Int(4)),
+            S::Hook(0),
+            S::Call("lol_f".to_string()),
+            S::RefHook(0),
+            S::Copy,
+            S::Push(Value::Int(1)),
+            S::Add,
+        ];
+        assert_eq!(simplify_statements(&before).len(), before.len());
+    }
+
+    #[test]
+    fn division_by_zero_is_left_to_panic_at_runtime() {
+        let before = vec![S::Push(Value::Int(1)), S::Push(Value::Int(0)), S::Divide];
+        assert_eq!(simplify_statements(&before).len(), before.len());
+    }
+
+    #[test]
+    fn a_literal_heavy_program_loses_statements() {
+        let text = "HAI 1.2\n\
+            I HAS A x ITZ NUMBER R SUM OF 2 AN 3\n\
+            x R SUM OF x AN PRODUKT OF 2 AN 4\n\
+            KTHXBYE\n";
+        let ir = driver::lower_text(text);
+        let before = ir.statement_count();
+        let after = propagate_constants(&ir).statement_count();
+        assert!(
+            after < before,
+            "{} statements before, {} after",
+            before,
+            after
+        );
+    }
+}
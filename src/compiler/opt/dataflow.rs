@@ -0,0 +1,113 @@
+use crate::compiler::cfg::{Cfg, Terminator};
+use crate::compiler::ir;
+use crate::compiler::opt::constprop::{self, Region};
+
+/// The jump a block's terminator becomes when its simplified statements end
+/// by pushing a known condition
+fn folded_terminator(
+    statements: &[ir::IRStatement],
+    terminator: &Terminator,
+) -> Option<Terminator> {
+    let condition = match statements.last() {
+        Some(ir::IRStatement::Push(n)) => n.as_f32(),
+        _ => return None,
+    };
+
+    match terminator {
+        Terminator::Branch { then, else_, .. } => Some(Terminator::Jump(if condition != 0.0 {
+            *then
+        } else {
+            *else_
+        })),
+        // a loop that does run still tests a condition after every iteration
+        Terminator::Loop { exit, .. } if condition == 0.0 => Some(Terminator::Jump(*exit)),
+        _ => None,
+    }
+}
+
+/// Runs the block's statements and terminator over `region`, returning the
+/// simplified statements and the terminator to use for them
+fn transfer(
+    statements: &[ir::IRStatement],
+    terminator: &Terminator,
+    region: &mut Region,
+) -> (Vec<ir::IRStatement>, Terminator) {
+    let mut statements = constprop::simplify(statements, region);
+
+    match folded_terminator(&statements, terminator) {
+        Some(folded) => {
+            statements.pop();
+            region.pop();
+            (statements, folded)
+        }
+        None => {
+            if terminator.pops_condition() {
+                region.pop();
+            }
+            (statements, terminator.clone())
+        }
+    }
+}
+
+/// What is known on entry to every block, None for blocks that never run
+fn entry_states(cfg: &Cfg) -> Vec<Option<Region>> {
+    let mut states: Vec<Option<Region>> = vec![None; cfg.blocks.len()];
+    states[cfg.entry] = Some(Region::new());
+    let mut pending = vec![cfg.entry];
+
+    while let Some(id) = pending.pop() {
+        let block = &cfg.blocks[id];
+        let mut region = states[id].clone().unwrap();
+        let (_, terminator) = transfer(&block.statements, &block.terminator, &mut region);
+
+        for successor in terminator.successors() {
+            // meeting with the old state keeps every state shrinking, so loops settle
+            let state = match &states[successor] {
+                Some(old) => old.meet(&region),
+                None => region.clone(),
+            };
+
+            if states[successor].as_ref() != Some(&state) {
+                states[successor] = Some(state);
+                pending.push(successor);
+            }
+        }
+    }
+
+    states
+}
+
+fn optimize_statements(statements: &[ir::IRStatement]) -> Vec<ir::IRStatement> {
+    let mut cfg = Cfg::new(statements);
+
+    for (id, state) in entry_states(&cfg).into_iter().enumerate() {
+        if let Some(mut region) = state {
+            let block = &cfg.blocks[id];
+            let (statements, terminator) =
+                transfer(&block.statements, &block.terminator, &mut region);
+            cfg.blocks[id].statements = statements;
+            cfg.blocks[id].terminator = terminator;
+        }
+    }
+
+    cfg.remove_unreachable();
+    cfg.to_statements()
+}
+
+/// Propagates constants across basic blocks, folds branches on known
+/// conditions and drops the blocks that can no longer run
+pub fn optimize_blocks(ir: &ir::IR) -> ir::IR {
+    let entry = ir::IRFunctionEntry::new(
+        ir.entry.stack_size,
+        ir.entry.heap_size,
+        ir.entry.hooks,
+        optimize_statements(&ir.entry.statements),
+    );
+    let functions = ir
+        .functions
+        .iter()
+        .map(|f| ir::IRFunction::new(f.name.clone(), f.hooks, optimize_statements(&f.statements)))
+        .collect();
+
+    ir::IR::new(functions, entry)
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::compiler::ir;
+
+fn calls(statements: &[ir::IRStatement], callee: Option<&String>) -> bool {
+    let mut found = false;
+    ir::walk(statements, &mut |s| {
+        if let ir::IRStatement::Call(name) = s {
+            found |= callee.is_none_or(|callee| callee == name);
+        }
+    });
+
+    found
+}
+
+fn rename_hook(hook: i32, renames: &mut HashMap<i32, i32>, next_hook: &mut i32) -> i32 {
+    *renames.entry(hook).or_insert_with(|| {
+        *next_hook += 1;
+        *next_hook - 1
+    })
+}
+
+fn rename_hooks(
+    statements: &[ir::IRStatement],
+    renames: &mut HashMap<i32, i32>,
+    next_hook: &mut i32,
+) -> Vec<ir::IRStatement> {
+    let mut result = vec![];
+    for statement in statements.iter() {
+        result.push(match statement {
+            ir::IRStatement::Hook(hook) => {
+                ir::IRStatement::Hook(rename_hook(*hook, renames, next_hook))
+            }
+            ir::IRStatement::RefHook(hook) => {
+                ir::IRStatement::RefHook(rename_hook(*hook, renames, next_hook))
+            }
+            ir::IRStatement::SetHook(hook) => {
+                ir::IRStatement::SetHook(rename_hook(*hook, renames, next_hook))
+            }
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => {
+                statement.map_blocks(|block| rename_hooks(block, renames, next_hook))
+            }
+            _ => statement.clone(),
+        });
+    }
+
+    result
+}
+
+/// Inlines the calls in `statements` to the functions in `bodies`, giving
+/// the hooks of each body fresh indices from `hooks` the first time it is
+/// inlined, kept in `renamed` for the caller's later calls
+fn inline_calls(
+    statements: &[ir::IRStatement],
+    bodies: &HashMap<String, &Vec<ir::IRStatement>>,
+    renamed: &mut HashMap<String, Vec<ir::IRStatement>>,
+    hooks: &mut i32,
+) -> Vec<ir::IRStatement> {
+    let mut result = vec![];
+
+    for statement in statements.iter() {
+        match statement {
+            ir::IRStatement::Call(name) if bodies.contains_key(name) => {
+                let body = renamed
+                    .entry(name.clone())
+                    .or_insert_with(|| rename_hooks(bodies[name], &mut HashMap::new(), hooks));
+                // the callee's stack frame still expects the return address slot
+                // that the target pushes for a real call
+                result.push(ir::IRStatement::Push(ir::Value::Int(1)));
+                result.extend(body.iter().cloned());
+            }
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => result
+                .push(statement.map_blocks(|block| inline_calls(block, bodies, renamed, hooks))),
+            _ => result.push(statement.clone()),
+        }
+    }
+
+    result
+}
+
+/// Replaces calls to leaf functions of at most `threshold` statements with
+/// their bodies. Hooks are numbered per function, so the hooks of an inlined
+/// body are renamed to fresh indices of the caller's, which reserves that
+/// many more. Functions that are no longer called afterwards are removed.
+pub fn inline_functions(ir: &ir::IR, threshold: usize) -> ir::IR {
+    let mut bodies = HashMap::new();
+
+    for function in ir.functions.iter() {
+        if function.statements.len() <= threshold && !calls(&function.statements, None) {
+            bodies.insert(function.name.clone(), &function.statements);
+        }
+    }
+
+    if bodies.is_empty() {
+        return ir.clone();
+    }
+
+    let mut hooks = ir.entry.hooks;
+    let statements = inline_calls(
+        &ir.entry.statements,
+        &bodies,
+        &mut HashMap::new(),
+        &mut hooks,
+    );
+    let entry =
+        ir::IRFunctionEntry::new(ir.entry.stack_size, ir.entry.heap_size, hooks, statements);
+    let functions = ir
+        .functions
+        .iter()
+        .map(|f| {
+            let mut hooks = f.hooks;
+            let statements = inline_calls(&f.statements, &bodies, &mut HashMap::new(), &mut hooks);
+            ir::IRFunction::new(f.name.clone(), hooks, statements)
+        })
+        .collect::<Vec<ir::IRFunction>>();
+
+    let still_called = |name: &String| {
+        calls(&entry.statements, Some(name))
+            || functions.iter().any(|f| calls(&f.statements, Some(name)))
+    };
+    let functions = functions
+        .iter()
+        .filter(|f| !bodies.contains_key(&f.name) || still_called(&f.name))
+        .cloned()
+        .collect();
+
+    ir::IR::new(functions, entry)
+}
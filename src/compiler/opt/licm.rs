@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use crate::compiler::ir;
+
+// shorter sequences cost as much to re-read from a slot as to recompute
+const MIN_HOIST_LENGTH: usize = 3;
+
+struct LoopEffects {
+    /// hooks re-pointed by a Hook or set by a SetHook inside the loop
+    rehooked: HashSet<i32>,
+    /// slots written through RefHook(hook) Mov
+    written_hooks: HashSet<i32>,
+}
+
+/// Collects what the loop body writes, or None if it may write anything
+fn loop_effects(body: &[ir::IRStatement]) -> Option<LoopEffects> {
+    let mut effects = LoopEffects {
+        rehooked: HashSet::new(),
+        written_hooks: HashSet::new(),
+    };
+
+    collect_effects(body, &mut effects)?;
+    Some(effects)
+}
+
+fn collect_effects(body: &[ir::IRStatement], effects: &mut LoopEffects) -> Option<()> {
+    for (i, statement) in body.iter().enumerate() {
+        match statement {
+            ir::IRStatement::Hook(hook) | ir::IRStatement::SetHook(hook) => {
+                effects.rehooked.insert(*hook);
+            }
+            ir::IRStatement::Mov => match i.checked_sub(1).map(|j| &body[j]) {
+                Some(ir::IRStatement::RefHook(hook)) => {
+                    effects.written_hooks.insert(*hook);
+                }
+                _ => return None,
+            },
+            // user functions could write any slot of the stack
+            ir::IRStatement::Call(_) => return None,
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => {
+                for block in statement.blocks() {
+                    collect_effects(block, effects)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(())
+}
+
+/// Length of the longest pure, loop-invariant sequence starting at `start`
+/// that leaves exactly one value on the stack, or 0 if there is none
+fn invariant_length(body: &[ir::IRStatement], start: usize, effects: &LoopEffects) -> usize {
+    let mut depth = 0;
+    let mut longest = 0;
+
+    for i in start..body.len() {
+        let effect = match &body[i] {
+            ir::IRStatement::Push(_) => 1,
+            ir::IRStatement::RefHook(hook) => {
+                if effects.rehooked.contains(hook) {
+                    break;
+                }
+                1
+            }
+            ir::IRStatement::Copy => {
+                // only reads of slots named by an unchanged hook are known
+                match i.checked_sub(1).map(|j| &body[j]) {
+                    Some(ir::IRStatement::RefHook(hook))
+                        if i > start && !effects.written_hooks.contains(hook) => {}
+                    _ => break,
+                }
+                0
+            }
+            ir::IRStatement::Add
+            | ir::IRStatement::Subtract
+            | ir::IRStatement::Multiply
+            | ir::IRStatement::Divide => -1,
+            ir::IRStatement::Sign => 0,
+            // modulo is left alone because hoisting could raise a division by
+            // zero in a loop that would never have run
+            _ => break,
+        };
+
+        depth += effect;
+        if depth <= 0 {
+            break;
+        }
+        if depth == 1 {
+            longest = i - start + 1;
+        }
+    }
+
+    longest
+}
+
+/// Splits a loop body into the statements to run once before the loop and
+/// the body that remains, or None if nothing can be hoisted
+fn hoist_loop(
+    body: &[ir::IRStatement],
+    next_hook: &mut i32,
+) -> Option<(Vec<ir::IRStatement>, Vec<ir::IRStatement>)> {
+    let effects = loop_effects(body)?;
+
+    let mut hoisted = vec![];
+    let mut new_body = vec![];
+    let mut i = 0;
+    while i < body.len() {
+        let length = invariant_length(body, i, &effects);
+        if length >= MIN_HOIST_LENGTH {
+            // hoisted values live in extra hooks so the loop condition stays
+            // on top of the stack
+            let hook = *next_hook;
+            *next_hook += 1;
+
+            hoisted.extend(body[i..i + length].iter().cloned());
+            hoisted.push(ir::IRStatement::SetHook(hook));
+
+            new_body.push(ir::IRStatement::RefHook(hook));
+            i += length;
+        } else {
+            new_body.push(body[i].clone());
+            i += 1;
+        }
+    }
+
+    if hoisted.is_empty() {
+        return None;
+    }
+
+    Some((hoisted, new_body))
+}
+
+fn hoist_statements(statements: &[ir::IRStatement], next_hook: &mut i32) -> Vec<ir::IRStatement> {
+    let mut result = vec![];
+
+    for statement in statements.iter() {
+        match statement {
+            ir::IRStatement::Loop { body } => {
+                // inner loops go first so what they hoist can move further out
+                let body = hoist_statements(body, next_hook);
+                match hoist_loop(&body, next_hook) {
+                    Some((hoisted, body)) => {
+                        result.extend(hoisted);
+                        result.push(ir::IRStatement::Loop { body });
+                    }
+                    None => result.push(ir::IRStatement::Loop { body }),
+                }
+            }
+            ir::IRStatement::If { .. } => {
+                result.push(statement.map_blocks(|block| hoist_statements(block, next_hook)))
+            }
+            _ => result.push(statement.clone()),
+        }
+    }
+
+    result
+}
+
+/// Moves loop-invariant arithmetic out of Loop bodies. This
+/// relies on the visitor's guarantee that every hook names a distinct live
+/// slot, so a write through one hook never changes a read through another.
+pub fn hoist_invariants(ir: &ir::IR) -> ir::IR {
+    let mut hooks = ir.entry.hooks;
+    let statements = hoist_statements(&ir.entry.statements, &mut hooks);
+    let entry =
+        ir::IRFunctionEntry::new(ir.entry.stack_size, ir.entry.heap_size, hooks, statements);
+    let functions = ir
+        .functions
+        .iter()
+        .map(|f| {
+            let mut hooks = f.hooks;
+            let statements = hoist_statements(&f.statements, &mut hooks);
+            ir::IRFunction::new(f.name.clone(), hooks, statements)
+        })
+        .collect();
+
+    ir::IR::new(functions, entry)
+}
@@ -0,0 +1,237 @@
+pub mod constprop;
+pub mod dataflow;
+pub mod inline;
+pub mod licm;
+pub mod ssa;
+
+use crate::compiler::cache::{self, IrCache};
+use crate::compiler::ir;
+
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+pub struct OptOptions {
+    /// 0 disables every pass, 2 and above add the basic block and hook renaming passes
+    pub level: u8,
+    /// largest function, in IR statements, that the inliner will copy into callers
+    pub inline_threshold: usize,
+    /// ASSURE statements were left out by the front end, which changes the IR
+    /// it hands the passes
+    pub release: bool,
+    /// the front end left every free out for the runtime's collector
+    pub gc: bool,
+    /// the passes to run whatever the level, None for the ones it enables
+    pub passes: Option<Vec<String>>,
+    /// passes not to run even when enabled
+    pub disabled: Vec<String>,
+    /// the pass, or all, after which to print the IR to stderr
+    pub print_ir_after: Option<String>,
+}
+
+/// A pass of the optimizer
+pub struct Pass {
+    pub name: &'static str,
+    /// the -O levels that run it
+    pub levels: RangeInclusive<u8>,
+    /// whether it looks across functions rather than at one at a time
+    pub whole_program: bool,
+    run: fn(&ir::IR, &OptOptions) -> ir::IR,
+}
+
+impl Pass {
+    pub fn enabled(&self, options: &OptOptions) -> bool {
+        let selected = match &options.passes {
+            Some(passes) => passes.iter().any(|pass| pass == self.name),
+            None => self.levels.contains(&options.level),
+        };
+        selected && !options.disabled.iter().any(|pass| pass == self.name)
+    }
+}
+
+/// Every pass, in the order they run, those looking across functions first.
+/// From -O2 the block-level dataflow pass takes over from constprop.
+pub const PASSES: &[Pass] = &[
+    Pass {
+        name: "inline",
+        levels: 1..=u8::MAX,
+        whole_program: true,
+        run: |ir, options| inline::inline_functions(ir, options.inline_threshold),
+    },
+    Pass {
+        name: "constprop",
+        levels: 1..=1,
+        whole_program: false,
+        run: |ir, _| constprop::propagate_constants(ir),
+    },
+    Pass {
+        name: "dataflow",
+        levels: 2..=u8::MAX,
+        whole_program: false,
+        run: |ir, _| dataflow::optimize_blocks(ir),
+    },
+    Pass {
+        name: "ssa",
+        levels: 2..=u8::MAX,
+        whole_program: false,
+        run: |ir, _| ssa::eliminate_redundancy(ir),
+    },
+    Pass {
+        name: "licm",
+        levels: 1..=u8::MAX,
+        whole_program: false,
+        run: |ir, _| licm::hoist_invariants(ir),
+    },
+];
+
+/// What running a pass did, for statistics
+pub struct PassRun<'a> {
+    pub name: &'static str,
+    /// IR statements before the pass, ir.statement_count() being those after
+    pub before: usize,
+    pub ir: &'a ir::IR,
+    pub time: Duration,
+}
+
+/// Runs the passes enabled by `options`, returning the optimized IR, whose
+/// frames reserve just the hooks they now use
+pub fn optimize(ir: ir::IR, options: &OptOptions) -> ir::IR {
+    optimize_with(ir, options, None, &mut |_| {})
+}
+
+/// optimize, calling `after` once each pass has run. With a cache, the
+/// passes that see one function at a time only run on the functions it does
+/// not have, `after` seeing just those, and store what they make of them.
+pub fn optimize_with(
+    ir: ir::IR,
+    options: &OptOptions,
+    cache: Option<&IrCache>,
+    after: &mut dyn FnMut(&PassRun),
+) -> ir::IR {
+    // the passes build IR of their own, so each result is given back the
+    // hooks and functions the visitor named and the foreign functions
+    // declared
+    let hook_names = ir.hook_names.clone();
+    let foreign = ir.foreign.clone();
+    let source_names = ir.source_names.clone();
+
+    let passes = PASSES
+        .iter()
+        .filter(|pass| pass.enabled(options))
+        .collect::<Vec<&Pass>>();
+    let split = passes
+        .iter()
+        .position(|pass| !pass.whole_program)
+        .unwrap_or(passes.len());
+
+    let ir = run_passes(ir, &passes[..split], options, after);
+    let mut ir = match cache {
+        Some(cache) => run_cached(ir, &passes[split..], options, cache, after),
+        None => run_passes(ir, &passes[split..], options, after),
+    };
+    ir.hook_names = hook_names;
+    ir.foreign = foreign;
+    ir.source_names = source_names;
+    ir.fit_hooks();
+    ir
+}
+
+/// Runs the functions of `ir` the cache does not have through `passes`,
+/// which see one function at a time, storing each result
+fn run_cached(
+    ir: ir::IR,
+    passes: &[&Pass],
+    options: &OptOptions,
+    cache: &IrCache,
+    after: &mut dyn FnMut(&PassRun),
+) -> ir::IR {
+    let entry = ir::IR::new(vec![], ir.entry.clone());
+    let mut pieces = vec![(IrCache::function_key(&entry, options), entry)];
+    for function in ir.functions.iter() {
+        let piece = ir::IR::new(
+            vec![function.clone()],
+            ir::IRFunctionEntry::new(0, 0, 0, vec![]),
+        );
+        pieces.push((IrCache::function_key(&piece, options), piece));
+    }
+    let mut found = pieces
+        .iter()
+        .map(|(key, _)| cache.load_function(key))
+        .collect::<Vec<Option<ir::IR>>>();
+
+    // the entry point and functions the cache does not have, optimized
+    // together, with the entry point left empty if it has it
+    let missing = ir::IR::new(
+        ir.functions
+            .iter()
+            .zip(&found[1..])
+            .filter(|(_, found)| found.is_none())
+            .map(|(function, _)| function.clone())
+            .collect(),
+        match found[0] {
+            Some(_) => ir::IRFunctionEntry::new(ir.entry.stack_size, ir.entry.heap_size, 0, vec![]),
+            None => ir.entry.clone(),
+        },
+    );
+    let missing = run_passes(missing, passes, options, after);
+    let mut optimized = missing.functions.into_iter();
+    for (i, (key, _)) in pieces.iter().enumerate() {
+        if found[i].is_some() {
+            continue;
+        }
+        let piece = match i {
+            0 => ir::IR::new(vec![], missing.entry.clone()),
+            _ => ir::IR::new(
+                vec![optimized.next().unwrap()],
+                ir::IRFunctionEntry::new(0, 0, 0, vec![]),
+            ),
+        };
+        // a function that could not be stored is optimized again next time
+        let _ = cache.store_function(key, &piece);
+        found[i] = Some(piece);
+    }
+
+    let mut found = found.into_iter().map(Option::unwrap);
+    let entry = found.next().unwrap().entry;
+    let functions = found.map(|piece| piece.functions[0].clone()).collect();
+    ir::IR::new(functions, entry)
+}
+
+/// Runs `passes` over `ir` in order
+fn run_passes(
+    ir: ir::IR,
+    passes: &[&Pass],
+    options: &OptOptions,
+    after: &mut dyn FnMut(&PassRun),
+) -> ir::IR {
+    let hook_names = ir.hook_names.clone();
+    let foreign = ir.foreign.clone();
+    let source_names = ir.source_names.clone();
+
+    let mut ir = ir;
+    for pass in passes.iter() {
+        let before = ir.statement_count();
+        let start = Instant::now();
+        ir = (pass.run)(&ir, options);
+        let time = start.elapsed();
+        ir.hook_names = hook_names.clone();
+        ir.foreign = foreign.clone();
+        ir.source_names = source_names.clone();
+
+        if options
+            .print_ir_after
+            .as_ref()
+            .is_some_and(|print| print == pass.name || print == "all")
+        {
+            eprint!("; IR after {}\n{}", pass.name, cache::serialize(&ir));
+        }
+        after(&PassRun {
+            name: pass.name,
+            before,
+            ir: &ir,
+            time,
+        });
+    }
+    ir
+}
@@ -0,0 +1,535 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::ir;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+/// What a value number stands for, values with equal expressions are equal
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Expression {
+    Int(i32),
+    Float(u32),
+    Binary(Operator, usize, usize),
+    Sign(usize),
+}
+
+#[derive(Clone)]
+struct Entry {
+    value: usize,
+    /// stack position the value points at when it came from a RefHook
+    address_of: Option<usize>,
+    /// index of the first output statement computing the value, while every
+    /// statement since then can be dropped without changing anything else
+    start: Option<usize>,
+}
+
+/// Value numbers for a straight-line region. Stack positions are relative to
+/// the stack pointer at the start of the region, like in constprop.
+struct Numbering {
+    stack: Vec<Entry>,
+    hooks: HashMap<i32, usize>,
+    expressions: HashMap<Expression, usize>,
+    next_value: usize,
+}
+
+impl Numbering {
+    fn new() -> Self {
+        Numbering {
+            stack: vec![],
+            hooks: HashMap::new(),
+            expressions: HashMap::new(),
+            next_value: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.hooks.clear();
+    }
+
+    fn fresh(&mut self) -> usize {
+        self.next_value += 1;
+        self.next_value - 1
+    }
+
+    fn number(&mut self, expression: Expression) -> usize {
+        if let Some(value) = self.expressions.get(&expression) {
+            return *value;
+        }
+
+        let value = self.fresh();
+        self.expressions.insert(expression, value);
+        value
+    }
+
+    fn push(&mut self, value: usize, start: Option<usize>) {
+        self.stack.push(Entry {
+            value,
+            address_of: None,
+            start,
+        });
+    }
+
+    fn pop(&mut self) -> Option<Entry> {
+        match self.stack.pop() {
+            Some(entry) => {
+                let len = self.stack.len();
+                self.hooks.retain(|_, position| *position < len);
+                Some(entry)
+            }
+            None => {
+                self.reset();
+                None
+            }
+        }
+    }
+
+    fn pop_many(&mut self, amount: i32) {
+        for _ in 0..amount {
+            self.pop();
+        }
+    }
+
+    /// Called after a statement with side effects is emitted, which no
+    /// computation spanning it may drop
+    fn barrier(&mut self) {
+        for entry in self.stack.iter_mut() {
+            entry.start = None;
+        }
+    }
+
+    /// A hook naming a slot below the top of the stack that holds `value`
+    fn hook_holding(&self, value: usize) -> Option<i32> {
+        let top = self.stack.len().checked_sub(1)?;
+        self.hooks
+            .iter()
+            .filter(|(_, position)| **position < top && self.stack[**position].value == value)
+            .map(|(hook, _)| *hook)
+            .min()
+    }
+}
+
+fn operator(statement: &ir::IRStatement) -> Option<Operator> {
+    match statement {
+        ir::IRStatement::Add => Some(Operator::Add),
+        ir::IRStatement::Subtract => Some(Operator::Subtract),
+        ir::IRStatement::Multiply => Some(Operator::Multiply),
+        ir::IRStatement::Divide => Some(Operator::Divide),
+        ir::IRStatement::Modulo => Some(Operator::Modulo),
+        _ => None,
+    }
+}
+
+/// Replaces the statements computing the top of the stack with a read of a
+/// hook already holding the same value, when that is shorter
+fn reuse(output: &mut Vec<ir::IRStatement>, numbering: &mut Numbering) {
+    let top = match numbering.stack.last() {
+        Some(top) => top.clone(),
+        None => return,
+    };
+    let start = match top.start {
+        Some(start) if output.len() - start > 2 => start,
+        _ => return,
+    };
+
+    if let Some(hook) = numbering.hook_holding(top.value) {
+        output.truncate(start);
+        output.push(ir::IRStatement::RefHook(hook));
+        output.push(ir::IRStatement::Copy);
+    }
+}
+
+fn reads_hook(statement: &ir::IRStatement, hook: i32) -> bool {
+    let mut found = false;
+    ir::walk(std::slice::from_ref(statement), &mut |s| {
+        if let ir::IRStatement::RefHook(h) = s {
+            found |= *h == hook;
+        }
+    });
+
+    found
+}
+
+/// Indices of the Hook statements whose assignment is never read, which can
+/// be dropped along with the computation around them
+fn dead_hooks(statements: &[ir::IRStatement]) -> HashSet<usize> {
+    let mut dead = HashSet::new();
+
+    for (i, statement) in statements.iter().enumerate() {
+        let hook = match statement {
+            ir::IRStatement::Hook(hook) => *hook,
+            _ => continue,
+        };
+
+        let read = statements[i + 1..]
+            .iter()
+            .take_while(|s| !matches!(s, ir::IRStatement::Hook(h) if *h == hook))
+            .any(|s| reads_hook(s, hook) || hooks_in(s, hook));
+        if !read {
+            dead.insert(i);
+        }
+    }
+
+    dead
+}
+
+/// Whether `hook` is hooked anywhere inside the blocks of `statement`
+fn hooks_in(statement: &ir::IRStatement, hook: i32) -> bool {
+    let mut found = false;
+    for block in statement.blocks() {
+        ir::walk(block, &mut |s| {
+            if let ir::IRStatement::Hook(h) = s {
+                found |= *h == hook;
+            }
+        });
+    }
+
+    found
+}
+
+fn eliminate_common(statements: &[ir::IRStatement], dead: &HashSet<usize>) -> Vec<ir::IRStatement> {
+    let mut output: Vec<ir::IRStatement> = vec![];
+    let mut numbering = Numbering::new();
+
+    for (i, statement) in statements.iter().enumerate() {
+        let start = output.len();
+
+        match statement {
+            ir::IRStatement::Push(n) => {
+                let value = numbering.number(match n {
+                    ir::Value::Int(n) => Expression::Int(*n),
+                    ir::Value::Float(n) => Expression::Float(n.to_bits()),
+                });
+                numbering.push(value, Some(start));
+            }
+            ir::IRStatement::RefHook(hook) => {
+                let value = numbering.fresh();
+                let address_of = numbering.hooks.get(hook).copied();
+                numbering.stack.push(Entry {
+                    value,
+                    address_of,
+                    start: Some(start),
+                });
+            }
+            ir::IRStatement::Copy => {
+                let address = numbering.pop();
+                let value = match address.as_ref().and_then(|a| a.address_of) {
+                    Some(position) if position < numbering.stack.len() => {
+                        numbering.stack[position].value
+                    }
+                    _ => numbering.fresh(),
+                };
+                numbering.push(value, address.and_then(|a| a.start));
+            }
+            ir::IRStatement::Add
+            | ir::IRStatement::Subtract
+            | ir::IRStatement::Multiply
+            | ir::IRStatement::Divide
+            | ir::IRStatement::Modulo => {
+                let b = numbering.pop();
+                let a = numbering.pop();
+                match (a, b) {
+                    (Some(a), Some(b)) => {
+                        let operator = operator(statement).unwrap();
+                        let (left, right) = match operator {
+                            Operator::Add | Operator::Multiply => {
+                                (a.value.min(b.value), a.value.max(b.value))
+                            }
+                            _ => (a.value, b.value),
+                        };
+                        let value = numbering.number(Expression::Binary(operator, left, right));
+                        let start = match (a.start, b.start) {
+                            (Some(start), Some(_)) => Some(start),
+                            _ => None,
+                        };
+                        numbering.push(value, start);
+                    }
+                    _ => {
+                        let value = numbering.fresh();
+                        numbering.push(value, None);
+                    }
+                }
+            }
+            ir::IRStatement::Sign => match numbering.pop() {
+                Some(x) => {
+                    let value = numbering.number(Expression::Sign(x.value));
+                    numbering.push(value, x.start);
+                }
+                None => {
+                    let value = numbering.fresh();
+                    numbering.push(value, None);
+                }
+            },
+            ir::IRStatement::Hook(hook) if dead.contains(&i) => {
+                // whether or not it is dropped, the hook no longer names a known slot
+                numbering.hooks.remove(hook);
+            }
+            ir::IRStatement::Hook(hook) => {
+                match numbering.stack.len() {
+                    0 => numbering.hooks.remove(hook),
+                    len => numbering.hooks.insert(*hook, len - 1),
+                };
+                numbering.barrier();
+            }
+            ir::IRStatement::Mov => {
+                let address = numbering.pop();
+                let value = numbering.pop();
+                match (address.and_then(|a| a.address_of), value) {
+                    (Some(position), Some(value)) if position < numbering.stack.len() => {
+                        numbering.stack[position].value = value.value
+                    }
+                    _ => {
+                        // the write could have landed anywhere
+                        for i in 0..numbering.stack.len() {
+                            numbering.stack[i].value = numbering.fresh();
+                        }
+                        numbering.hooks.clear();
+                    }
+                }
+                numbering.barrier();
+            }
+            ir::IRStatement::Allocate | ir::IRStatement::StrAlloc => {
+                numbering.pop();
+                numbering.barrier();
+                let value = numbering.fresh();
+                numbering.push(value, None);
+            }
+            ir::IRStatement::Free | ir::IRStatement::StrFree => {
+                numbering.pop_many(2);
+                numbering.barrier();
+            }
+            ir::IRStatement::StrCmp(_) => {
+                numbering.pop_many(2);
+                numbering.barrier();
+                let value = numbering.fresh();
+                numbering.push(value, None);
+            }
+            ir::IRStatement::StrConcat(..) => {
+                numbering.pop_many(2);
+                numbering.barrier();
+            }
+            ir::IRStatement::StrPrint(_) => {
+                numbering.pop();
+                numbering.barrier();
+            }
+            ir::IRStatement::BeginArena | ir::IRStatement::EndArena => {}
+            ir::IRStatement::Collect(roots) => {
+                numbering.pop_many(*roots);
+                numbering.barrier();
+            }
+            ir::IRStatement::Store(floats, _) | ir::IRStatement::StrStore(floats) => {
+                numbering.pop_many(floats + 1);
+                numbering.barrier();
+            }
+            ir::IRStatement::Load(floats, _) | ir::IRStatement::StrLoad(floats) => {
+                numbering.pop();
+                numbering.barrier();
+                for _ in 0..*floats {
+                    let value = numbering.fresh();
+                    numbering.push(value, None);
+                }
+            }
+            ir::IRStatement::SetReturnRegister => {
+                numbering.pop();
+                numbering.barrier();
+            }
+            ir::IRStatement::AccessReturnRegister => {
+                numbering.barrier();
+                let value = numbering.fresh();
+                numbering.push(value, None);
+            }
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => {
+                // a hook set inside a block may be read after it, so none are dead
+                output.push(statement.map_blocks(|block| eliminate_common(block, &HashSet::new())));
+                numbering.reset();
+                continue;
+            }
+            _ => numbering.reset(),
+        }
+
+        output.push(statement.clone());
+        if matches!(
+            statement,
+            ir::IRStatement::Copy
+                | ir::IRStatement::Add
+                | ir::IRStatement::Subtract
+                | ir::IRStatement::Multiply
+                | ir::IRStatement::Divide
+                | ir::IRStatement::Modulo
+                | ir::IRStatement::Sign
+        ) {
+            reuse(&mut output, &mut numbering);
+        }
+    }
+
+    output
+}
+
+/// One assignment of a hook, live from its Hook to its last use. Positions
+/// are indices into the body's top level, so uses inside an If or Loop count
+/// as uses at the statement containing them.
+struct Version {
+    start: usize,
+    end: usize,
+}
+
+/// Hooks of the body that have to keep their index: the ones hooked inside
+/// a nested block or read before they are first hooked
+fn pinned_hooks(body: &[ir::IRStatement]) -> HashSet<i32> {
+    let mut pinned = HashSet::new();
+    let mut hooked = HashSet::new();
+
+    for statement in body.iter() {
+        if let ir::IRStatement::Hook(hook) = statement {
+            hooked.insert(*hook);
+        }
+
+        for block in statement.blocks() {
+            ir::walk(block, &mut |s| {
+                if let ir::IRStatement::Hook(hook) = s {
+                    pinned.insert(*hook);
+                }
+            });
+        }
+
+        ir::walk(std::slice::from_ref(statement), &mut |s| {
+            if let ir::IRStatement::Hook(hook) | ir::IRStatement::RefHook(hook) = s {
+                if !hooked.contains(hook) {
+                    pinned.insert(*hook);
+                }
+            }
+        });
+    }
+
+    pinned
+}
+
+fn versions(body: &[ir::IRStatement], pinned: &HashSet<i32>) -> Vec<Version> {
+    let mut versions: Vec<Version> = vec![];
+    let mut current: HashMap<i32, usize> = HashMap::new();
+
+    for (i, statement) in body.iter().enumerate() {
+        if let ir::IRStatement::Hook(hook) = statement {
+            if !pinned.contains(hook) {
+                current.insert(*hook, versions.len());
+                versions.push(Version { start: i, end: i });
+            }
+            continue;
+        }
+
+        ir::walk(std::slice::from_ref(statement), &mut |s| {
+            if let ir::IRStatement::RefHook(hook) = s {
+                if let Some(version) = current.get(hook) {
+                    versions[*version].end = i;
+                }
+            }
+        });
+    }
+
+    versions
+}
+
+fn rewrite(statements: &[ir::IRStatement], renames: &HashMap<i32, i32>) -> Vec<ir::IRStatement> {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            ir::IRStatement::RefHook(hook) => {
+                ir::IRStatement::RefHook(*renames.get(hook).unwrap_or(hook))
+            }
+            ir::IRStatement::If { .. } | ir::IRStatement::Loop { .. } => {
+                statement.map_blocks(|block| rewrite(block, renames))
+            }
+            _ => statement.clone(),
+        })
+        .collect()
+}
+
+/// Gives every version of the body's hooks an index, sharing indices between
+/// versions that are never live at the same time
+fn renumber_body(
+    body: &[ir::IRStatement],
+    pinned: &HashSet<i32>,
+    next_hook: &mut i32,
+) -> Vec<ir::IRStatement> {
+    let versions = versions(body, pinned);
+    let mut indices = vec![0; versions.len()];
+    let mut free: Vec<i32> = vec![];
+    let mut active: Vec<usize> = vec![];
+
+    for (v, version) in versions.iter().enumerate() {
+        active.retain(|a| {
+            if versions[*a].end < version.start {
+                free.push(indices[*a]);
+                false
+            } else {
+                true
+            }
+        });
+
+        free.sort_unstable_by(|a, b| b.cmp(a));
+        indices[v] = match free.pop() {
+            Some(index) => index,
+            None => {
+                while pinned.contains(next_hook) {
+                    *next_hook += 1;
+                }
+                *next_hook += 1;
+                *next_hook - 1
+            }
+        };
+        active.push(v);
+    }
+
+    let mut result = vec![];
+    let mut renames: HashMap<i32, i32> = HashMap::new();
+    let mut v = 0;
+    for (i, statement) in body.iter().enumerate() {
+        match statement {
+            ir::IRStatement::Hook(hook) if v < versions.len() && versions[v].start == i => {
+                renames.insert(*hook, indices[v]);
+                result.push(ir::IRStatement::Hook(indices[v]));
+                v += 1;
+            }
+            _ => result.extend(rewrite(std::slice::from_ref(statement), &renames)),
+        }
+    }
+
+    result
+}
+
+/// Numbers values through hooks to replace recomputed expressions with reads
+/// of a hook already holding them, then renames every assignment of a hook
+/// into its own version and packs the versions into as few hook indices as
+/// possible. Every body has hooks of its own, so each is packed separately.
+pub fn eliminate_redundancy(ir: &ir::IR) -> ir::IR {
+    // returns the statements and the hooks they use
+    let optimize = |statements: &Vec<ir::IRStatement>| {
+        let statements = eliminate_common(statements, &dead_hooks(statements));
+        let pinned = pinned_hooks(&statements);
+        let mut next_hook = 0;
+        let statements = renumber_body(&statements, &pinned, &mut next_hook);
+        let hooks = pinned.iter().map(|hook| hook + 1).fold(next_hook, i32::max);
+        (statements, hooks)
+    };
+
+    let (statements, hooks) = optimize(&ir.entry.statements);
+    let entry =
+        ir::IRFunctionEntry::new(ir.entry.stack_size, ir.entry.heap_size, hooks, statements);
+    let functions = ir
+        .functions
+        .iter()
+        .map(|f| {
+            let (statements, hooks) = optimize(&f.statements);
+            ir::IRFunction::new(f.name.clone(), hooks, statements)
+        })
+        .collect();
+
+    ir::IR::new(functions, entry)
+}
@@ -0,0 +1,94 @@
+use crate::compiler::ir;
+
+/// Foreign functions of the std that only touch the machine, stdin and
+/// stdout. Anything else could reach files, the environment or arbitrary C
+/// functions, so sandboxed programs may not call it.
+pub const ALLOWED_FOREIGN: &[&str] = &[
+    "prn",
+    "prs",
+    "prh",
+    "prc",
+    "flush",
+    "prend",
+    "getch",
+    "float_to_int",
+    "string_to_int",
+    "int_to_float",
+    "string_to_float",
+    "int_to_string",
+    "float_to_string",
+    "troof_to_string",
+    "yarn_length",
+    "yarn_compare",
+    "letter_code",
+    "code_letter",
+    "yarn_split",
+    "yarn_join",
+    "yarn_matchez",
+    "print_string",
+    "read_succeeded",
+    "read_char",
+    "read_letter",
+    "read_string",
+    "bukkit_new",
+    "bukkit_retain",
+    "bukkit_release",
+    "bukkit_kopy",
+    "bukkit_put",
+    "bukkit_get",
+    "bukkit_length",
+    "bukkit_has_index",
+    "bukkit_value",
+    "bukkit_copy_yarn",
+    "stack_unwind",
+    "trace",
+    "assure_failed",
+    "assure_failed_sayin",
+    "dump_number",
+    "dump_numbar",
+    "dump_yarn",
+    "dump_troof",
+    "dump_noob",
+    "dump_bukkit",
+    "dump_name",
+];
+
+/// Largest stack, in floats, a sandboxed program may use
+pub const MAX_STACK_SIZE: i32 = 1000;
+/// Largest heap, in bytes, a sandboxed program may use
+pub const MAX_HEAP_SIZE: i32 = 4000;
+/// Loop iterations and calls a sandboxed program may run before it panics
+pub const MAX_STEPS: u64 = 10_000_000;
+
+/// Checks that `ir` only calls allowed foreign functions, returning a message
+/// for every other one it calls
+pub fn check(ir: &ir::IR) -> Result<(), Vec<String>> {
+    let mut rejected: Vec<String> = vec![];
+    let mut visit = |statement: &ir::IRStatement| {
+        if let ir::IRStatement::CallForeign(name) = statement {
+            if !ALLOWED_FOREIGN.contains(&name.as_str()) && !rejected.contains(name) {
+                rejected.push(name.clone());
+            }
+        }
+    };
+
+    ir::walk(&ir.entry.statements, &mut visit);
+    for function in ir.functions.iter() {
+        ir::walk(&function.statements, &mut visit);
+    }
+
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    Err(rejected
+        .iter()
+        .map(|name| format!("Error: {} is not allowed in sandbox mode", name))
+        .collect())
+}
+
+/// Shrinks the memory `ir` asks for to the sandbox limits
+pub fn limit(ir: &mut ir::IR) {
+    ir.entry.stack_size = ir.entry.stack_size.min(MAX_STACK_SIZE);
+    ir.entry.heap_size = ir.entry.heap_size.min(MAX_HEAP_SIZE);
+}
@@ -0,0 +1,887 @@
+use crate::compiler::cache;
+use crate::compiler::ir;
+use crate::compiler::opt::{self, OptOptions};
+use crate::compiler::target::Target;
+use crate::interpreter;
+
+use std::{
+    env, fs,
+    io::{Result, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// How a case's program is given
+pub enum Program {
+    /// IR as the cache writes it, for the operations of the Target trait on
+    /// their own
+    Ir(&'static str),
+    /// LOLCODE, put through the front end, for what programs actually do
+    Lolcode(&'static str),
+}
+
+/// Turns LOLCODE into IR, or into its diagnostics
+pub type FrontEnd<'f> = &'f dyn Fn(&str) -> std::result::Result<ir::IR, String>;
+/// Runs an executable on an input, returning its exit code and stdout
+pub type Execute<'f> = &'f dyn Fn(&Path, &str) -> Result<(i32, Vec<u8>)>;
+
+/// A program every target has to run the same way
+pub struct Case {
+    pub name: &'static str,
+    pub program: Program,
+    /// what the program reads from stdin
+    pub input: &'static str,
+    /// what it has to print, with \n ending lines
+    pub output: &'static str,
+    pub code: i32,
+}
+
+/// Every case, the IR ones first going through one operation or two of the
+/// Target trait each
+pub const CASES: &[Case] = &[
+    Case {
+        name: "ir/arithmetic",
+        program: Program::Ir(
+            "entry 1000 4000 0
+push 7
+push 2
+subtract
+call_foreign prn
+call_foreign prend
+push 7
+push 2
+divide
+call_foreign prn
+call_foreign prend
+push 7
+push 2
+multiply
+push 1
+add
+call_foreign prn
+call_foreign prend
+push 7
+push 2
+modulo
+call_foreign prn
+call_foreign prend
+push -3
+sign
+call_foreign prn
+call_foreign prend
+push 0
+sign
+call_foreign prn
+call_foreign prend
+halt
+",
+        ),
+        input: "",
+        output: "5.000000\n3.500000\n15.000000\n1.000000\n-1.000000\n1.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/modulo-by-zero",
+        program: Program::Ir(
+            "entry 1000 4000 0
+push 1
+push 0
+modulo
+halt
+",
+        ),
+        input: "",
+        output: "panic: modulo by zero\n    at main\n\n",
+        code: 1,
+    },
+    Case {
+        name: "ir/heap",
+        program: Program::Ir(
+            "entry 1000 4000 1
+push 2
+allocate
+hook 0
+push 11
+push 22
+ref_hook 0
+copy
+store 2 0
+ref_hook 0
+copy
+load 2 0
+subtract
+call_foreign prn
+call_foreign prend
+push 2
+ref_hook 0
+copy
+free
+halt
+",
+        ),
+        input: "",
+        output: "-11.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/offsets",
+        program: Program::Ir(
+            "entry 1000 4000 1
+push 3
+allocate
+hook 0
+push 7
+ref_hook 0
+copy
+store 1 2
+push 5
+ref_hook 0
+copy
+store 1 0
+ref_hook 0
+copy
+load 1 2
+ref_hook 0
+copy
+load 1 0
+subtract
+call_foreign prn
+call_foreign prend
+push 3
+ref_hook 0
+copy
+free
+halt
+",
+        ),
+        input: "",
+        output: "2.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/store-out-of-bounds",
+        program: Program::Ir(
+            "entry 1000 4000 1
+push 2
+allocate
+hook 0
+push 1
+ref_hook 0
+copy
+store 1 2
+halt
+",
+        ),
+        input: "",
+        output: "panic: invalid heap address\n    at main\n\n",
+        code: 3,
+    },
+    Case {
+        name: "ir/yarns",
+        program: Program::Ir(
+            "entry 1000 4000 2
+push 5
+str_alloc
+hook 0
+push 2
+str_alloc
+hook 1
+push 104
+push 105
+ref_hook 0
+copy
+str_store 2
+push 33
+push 33
+ref_hook 1
+copy
+str_store 2
+ref_hook 0
+copy
+ref_hook 1
+copy
+str_concat 2 2
+ref_hook 0
+copy
+str_print 4
+call_foreign prend
+ref_hook 0
+copy
+ref_hook 1
+copy
+str_cmp 2
+call_foreign prn
+call_foreign prend
+ref_hook 0
+copy
+str_load 2
+add
+call_foreign prn
+call_foreign prend
+push 2
+ref_hook 1
+copy
+str_free
+push 5
+ref_hook 0
+copy
+str_free
+halt
+",
+        ),
+        input: "",
+        output: "hi!!\n1.000000\n209.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/stack",
+        program: Program::Ir(
+            "entry 1000 4000 2
+push 1
+hook 0
+push 2
+ref_hook 0
+mov
+ref_hook 0
+copy
+call_foreign prn
+call_foreign prend
+push 9
+set_hook 1
+ref_hook 1
+call_foreign prn
+call_foreign prend
+halt
+",
+        ),
+        input: "",
+        output: "2.000000\n9.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/control-flow",
+        program: Program::Ir(
+            "entry 1000 4000 1
+push 0
+hook 0
+push 1
+if
+push 1
+call_foreign prn
+else
+push 2
+call_foreign prn
+end
+push 0
+if
+push 3
+call_foreign prn
+else
+push 4
+call_foreign prn
+end
+call_foreign prend
+push 1
+loop
+ref_hook 0
+copy
+call_foreign prn
+ref_hook 0
+copy
+push 1
+add
+ref_hook 0
+mov
+push 3
+ref_hook 0
+copy
+subtract
+end
+call_foreign prend
+halt
+",
+        ),
+        input: "",
+        output: "1.0000004.000000\n0.0000001.0000002.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/calls",
+        program: Program::Ir(
+            "entry 1000 4000 0
+push 5
+call fact
+access_return_register
+call_foreign prn
+call_foreign prend
+halt
+function fact 1
+establish_stack_frame
+load_base_ptr
+push 2
+subtract
+copy
+hook 0
+ref_hook 0
+copy
+push 1
+subtract
+sign
+push 1
+add
+if
+ref_hook 0
+copy
+ref_hook 0
+copy
+push 1
+subtract
+call fact
+access_return_register
+multiply
+set_return_register
+else
+push 1
+set_return_register
+end
+end_stack_frame 1 1
+",
+        ),
+        input: "",
+        output: "120.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "ir/input",
+        program: Program::Ir(
+            "entry 1000 4000 0
+call_foreign read_char
+call_foreign prc
+call_foreign read_char
+call_foreign prc
+call_foreign read_char
+call_foreign read_succeeded
+call_foreign prn
+call_foreign prend
+halt
+",
+        ),
+        input: "ok",
+        output: "ok0.000000\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/visible",
+        program: Program::Lolcode(
+            "HAI 1.2
+VISIBLE \"HAI WORLD\"
+VISIBLE \"a\" AN \"b\"!
+VISIBLE SMOOSH \"c\" AN \"d\" MKAY
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "HAI WORLD\nabcd\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/math",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A x ITZ NUMBER R 7
+VISIBLE MAEK SUM OF x AN 5 A YARN
+VISIBLE MAEK QUOSHUNT OF x AN 2 A YARN
+VISIBLE MAEK MOD OF x AN 4 A YARN
+VISIBLE MAEK BIGGR OF x AN 9 A YARN
+VISIBLE MAEK QUOSHUNT OF 7.0 AN 2.0 A YARN
+VISIBLE MAEK BOTH SAEM x AN 7 A YARN
+VISIBLE MAEK NOT BOTH SAEM x AN 7 A YARN
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "12\n3\n3\n9\n3.500000\nWIN\nFAIL\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/yarns",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A s ITZ YARN R \"a,b,c\"
+I HAS A parts ITZ BUKKIT R SPLITZ s ON \",\"
+VISIBLE MAEK LENGTHZ OF parts A YARN
+VISIBLE JOINZ parts WIF \"-\"
+VISIBLE MAEK FURST OF \"a\" AN \"b\" A YARN
+VISIBLE MAEK FURST OF \"ab\" AN \"abc\" A YARN \" \" MAEK LATR OF \"ab\" AN \"abc\" A YARN
+VISIBLE MAEK FURST OF \"ab\" AN \"ab\" A YARN \" \" MAEK LATR OF \"\" AN \"\" A YARN
+VISIBLE MAEK CODEZ OF \"A\" A YARN
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "3\na-b-c\nWIN\nWIN FAIL\nFAIL FAIL\n65\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/bukkits",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A cat ITZ BUKKIT
+cat HAS A name ITZ \"Tom\"
+cat HAS A age ITZ \"3\"
+cat'Z name R \"Tim\"
+IM IN YR loop WATCHIN YR value IN cat
+  VISIBLE value
+IM OUTTA YR loop
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "Tim\n3\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/scopes",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A b ITZ BUKKIT
+b HAS A x ITZ \"1\"
+b HAS A y ITZ \"2\"
+I HAS A s ITZ YARN R \"a\"
+I HAS A t ITZ YARN R \"bb\"
+I HAS A u ITZ YARN R \"ccc\"
+IM IN YR loop WATCHIN YR item IN b
+  I HAS A v ITZ YARN
+  v R SMOOSH item AN s MKAY
+  I HAS A w ITZ YARN
+  w R SMOOSH t AN u MKAY
+  VISIBLE v AN w
+IM OUTTA YR loop
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "1abbccc\n2abbccc\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/gimmeh",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A line ITZ YARN
+GIMMEH LINE line
+VISIBLE \"HAI \" AN line
+KTHXBYE
+",
+        ),
+        input: "CEILING CAT\n",
+        output: "HAI CEILING CAT\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/maek-overflow",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A line ITZ YARN
+GIMMEH LINE line
+I HAS A n ITZ NUMBER R MAEK line A NUMBER
+VISIBLE MAEK n A YARN
+KTHXBYE
+",
+        ),
+        input: "99999999999\n",
+        output: "1215752192\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/assure",
+        program: Program::Lolcode(
+            "HAI 1.2
+VISIBLE \"before\"
+ASSURE BOTH SAEM 1 AN 2 MKAY SAYIN \"nope\"
+VISIBLE \"after\"
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "before\npanic: ASSURE failed at line 3: nope\n    at main\n\n",
+        code: 1,
+    },
+    Case {
+        name: "lolcode/mkay",
+        program: Program::Lolcode(
+            "HAI 1.2
+VISIBLE SMOOSH \"a\" AN SMOOSH \"b\" AN \"c\"
+VISIBLE SMOOSH SMOOSH \"d\" AN \"e\" MKAY AN \"f\"
+I HAS A t ITZ TROOF R ALL OF WIN AN ANY OF FAIL AN WIN
+VISIBLE WUN OF t DEN \"g\" ELSS \"h\"
+VISIBLE WUN OF ALL OF ANY OF FAIL AN FAIL MKAY AN WIN MKAY DEN \"i\" ELSS \"j\", VISIBLE SMOOSH \"k\" AN \"l\"
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "abc\ndef\ng\nj\nkl\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/if",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A s ITZ YARN R \"a\"
+I HAS A n ITZ NUMBER R 2
+BOTH SAEM n AN 1
+O RLY?
+  YA RLY
+    s R \"one\"
+  MEBBE BOTH SAEM n AN 2
+    I HAS A t ITZ YARN
+    t R \"two\"
+    VISIBLE t
+    s R SMOOSH s AN \"bc\" MKAY
+  NO WAI
+    s R \"d\"
+OIC
+VISIBLE s
+\"\"
+O RLY?, YA RLY, VISIBLE \"full\", NO WAI, VISIBLE \"empty\", OIC
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "two\nabc\nempty\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/loop",
+        program: Program::Lolcode(
+            "HAI 1.2
+I HAS A total ITZ NUMBER R 0
+IM IN YR outer UPPIN YR a TIL BOTH SAEM a AN 3
+  IM IN YR inner NERFIN YR b WILE DIFFRINT b AN -3
+    total R SUM OF total AN PRODUKT OF a AN b
+  IM OUTTA YR inner
+IM OUTTA YR outer
+VISIBLE MAEK total A YARN
+I HAS A s ITZ YARN R \"a\"
+IM IN YR grow UPPIN YR i TIL BOTH SAEM i AN 3
+  BOTH SAEM i AN 1, O RLY?, YA RLY, s R \"bcd\", OIC
+  VISIBLE s
+IM OUTTA YR grow
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "-9\na\nbcd\nbcd\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/switch",
+        program: Program::Lolcode(
+            "HAI 1.2
+IM IN YR l UPPIN YR i TIL BOTH SAEM i AN 4
+  i
+  WTF?
+    OMG 0
+      VISIBLE \"zero\"
+    OMG 1
+    OMG 2
+      VISIBLE \"small\"
+      BOTH SAEM i AN 2, O RLY?, YA RLY, GTFO, OIC
+      VISIBLE \"one\"
+    OMGWTF
+      VISIBLE \"big\"
+  OIC
+IM OUTTA YR l
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "zero\nsmall\none\nbig\nsmall\none\nbig\nsmall\nbig\n",
+        code: 0,
+    },
+    Case {
+        name: "lolcode/function",
+        program: Program::Lolcode(
+            "HAI 1.2
+HOW IZ I fact ITZ NUMBER YR n ITZ NUMBER
+  BOTH SAEM n AN 0, O RLY?, YA RLY, FOUND YR 1, OIC
+  FOUND YR PRODUKT OF n AN I IZ fact YR DIFF OF n AN 1 MKAY
+IF U SAY SO
+HOW IZ I greet ITZ YARN YR name ITZ YARN
+  SMOOSH \"hai \" AN name MKAY
+IF U SAY SO
+HOW IZ I count ITZ NOOB YR upto ITZ NUMBER
+  IM IN YR l UPPIN YR i
+    BOTH SAEM i AN upto, O RLY?, YA RLY, GTFO, OIC
+    VISIBLE MAEK i A YARN
+  IM OUTTA YR l
+IF U SAY SO
+VISIBLE MAEK I IZ fact YR 5 MKAY A YARN
+VISIBLE I IZ greet YR \"bob\" MKAY
+VISIBLE I IZ greet YR \"alice\" MKAY
+I IZ count YR 2 MKAY
+KTHXBYE
+",
+        ),
+        input: "",
+        output: "120\nhai bob\nhai alice\n0\n1\n",
+        code: 0,
+    },
+];
+
+/// Runs an executable built by `target` on `input`, returning its exit
+/// code and what it printed to stdout
+pub fn run_native(path: &Path, input: &str) -> Result<(i32, Vec<u8>)> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    Ok((output.status.code().unwrap_or(-1), output.stdout))
+}
+
+/// The IR of `source`, checking that compiling it again gives the same. The
+/// front end's maps are ordered, so nothing should depend on where a hash
+/// map puts things, which differs from one map to the next.
+fn deterministic(source: &str, front_end: FrontEnd) -> std::result::Result<ir::IR, String> {
+    let compile = || front_end(source).map_err(|e| format!("it does not compile: {}", e));
+    let ir = compile()?;
+    if cache::serialize(&compile()?) != cache::serialize(&ir) {
+        return Err("compiling it twice gives different IR".to_string());
+    }
+    Ok(ir)
+}
+
+/// What the interpreter, which defines what IR means, makes of `ir` run on
+/// `input`: its exit code and output, with \n ending lines and no NULs
+pub fn reference(ir: &ir::IR, input: &str) -> (i32, String) {
+    interpreter::run_captured(ir, input.as_bytes().to_vec(), |machine| {
+        machine.newline = "\n"
+    })
+}
+
+/// Assembles every case with `target`, compiles it and runs the result with
+/// `execute`, which has to do what the reference interpreter does with the
+/// case, returning each case's name and why it failed if it did.
+/// LOLCODE cases are put through `front_end`, which returns their IR or
+/// their diagnostics.
+pub fn run<T: Target>(
+    target: &T,
+    front_end: FrontEnd,
+    execute: Execute,
+) -> Vec<(&'static str, Option<String>)> {
+    CASES
+        .iter()
+        .map(|case| (case.name, check(target, case, front_end, execute).err()))
+        .collect()
+}
+
+fn check<T: Target>(
+    target: &T,
+    case: &Case,
+    front_end: FrontEnd,
+    execute: Execute,
+) -> std::result::Result<(), String> {
+    let ir = match case.program {
+        Program::Ir(text) => cache::deserialize(text).ok_or("the IR is malformed")?,
+        Program::Lolcode(source) => deterministic(source, front_end)?,
+    };
+
+    // the catalog checks the reference as well, so a case that it gets wrong
+    // is not taken as what every target has to do
+    let (code, expected) = reference(&ir, case.input);
+    if expected != case.output || code != case.code {
+        return Err(format!(
+            "the reference interpreter printed {:?} and exited with {} instead of {:?} and {}",
+            expected, code, case.output, case.code
+        ));
+    }
+
+    let path = env::temp_dir().join(format!(
+        "lolcat-conformance-{}-{}",
+        std::process::id(),
+        case.name.replace('/', "-")
+    ));
+    let out_file = path.to_string_lossy().to_string();
+    target
+        .compile(ir.assemble(target), Some(out_file))
+        .map_err(|e| format!("could not compile: {}", e))?;
+    let ran = execute(&path, case.input);
+    let _ = fs::remove_file(&path);
+    let (code, output) = ran.map_err(|e| format!("could not run: {}", e))?;
+
+    // the platform's newline is \n wherever it is not \r\n, and a YARN's
+    // letters after its first NUL, which may be printed or not, are not part
+    // of it
+    let output = String::from_utf8_lossy(&output)
+        .replace("\r\n", "\n")
+        .replace('\0', "");
+    if output != expected {
+        return Err(format!("printed {:?} instead of {:?}", output, expected));
+    }
+    if code != case.code {
+        return Err(format!("exited with {} instead of {}", code, case.code));
+    }
+    Ok(())
+}
+
+/// A xorshift generator, so a seed always makes the same programs
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves 0
+        Rng(seed.max(1))
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+const VARIABLES: &[&str] = &["a", "b", "c"];
+
+/// A NUMBER expression of at most `depth` nested operations
+fn random_expression(rng: &mut Rng, depth: u32) -> String {
+    if depth == 0 || rng.below(3) == 0 {
+        return match rng.below(2) {
+            0 => rng.below(20).to_string(),
+            _ => VARIABLES[rng.below(VARIABLES.len() as u64) as usize].to_string(),
+        };
+    }
+
+    let left = random_expression(rng, depth - 1);
+    // dividing only by literals keeps the programs from panicking
+    let divisor = (rng.below(9) + 1).to_string();
+    match rng.below(7) {
+        0 => format!("SUM OF {} AN {}", left, random_expression(rng, depth - 1)),
+        1 => format!("DIFF OF {} AN {}", left, random_expression(rng, depth - 1)),
+        2 => format!(
+            "PRODUKT OF {} AN {}",
+            left,
+            random_expression(rng, depth - 1)
+        ),
+        3 => format!("BIGGR OF {} AN {}", left, random_expression(rng, depth - 1)),
+        4 => format!(
+            "SMALLR OF {} AN {}",
+            left,
+            random_expression(rng, depth - 1)
+        ),
+        5 => format!("MOD OF {} AN {}", left, divisor),
+        _ => format!("QUOSHUNT OF {} AN {}", left, divisor),
+    }
+}
+
+/// A program of NUMBER variables, assignments of arithmetic on them, a loop
+/// over a bukkit of them and VISIBLEs of what they hold, as the passes see
+/// in most programs
+pub fn random_program(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut program = String::from("HAI 1.2\n");
+    for name in VARIABLES.iter() {
+        program.push_str(&format!(
+            "I HAS A {} ITZ NUMBER R {}\n",
+            name,
+            rng.below(20)
+        ));
+    }
+    // a bukkit has to have a slot before it is watched
+    program.push_str("I HAS A bukkit ITZ BUKKIT\n");
+    program.push_str(&format!("bukkit HAS A first ITZ {}\n", rng.below(20)));
+
+    for i in 0..rng.below(12) + 4 {
+        let variable = VARIABLES[rng.below(VARIABLES.len() as u64) as usize];
+        let line = match rng.below(5) {
+            0 | 1 => format!("{} R {}\n", variable, random_expression(&mut rng, 3)),
+            2 => format!(
+                "VISIBLE MAEK {} A YARN\n",
+                random_expression(&mut rng, 3)
+            ),
+            3 => format!(
+                "bukkit HAS A slot{} ITZ {}\n",
+                i,
+                random_expression(&mut rng, 2)
+            ),
+            _ => format!(
+                "IM IN YR loop WATCHIN YR item IN bukkit\n{} R SUM OF {} AN item\nVISIBLE MAEK {} A YARN\nIM OUTTA YR loop\n",
+                variable,
+                random_expression(&mut rng, 2),
+                random_expression(&mut rng, 2)
+            ),
+        };
+        program.push_str(&line);
+    }
+
+    for name in VARIABLES.iter() {
+        program.push_str(&format!("VISIBLE MAEK {} A YARN\n", name));
+    }
+    program.push_str("KTHXBYE\n");
+    program
+}
+
+/// Runs the passes `options` enables on `ir`, failing with the first that
+/// makes the reference interpreter run the IR differently than before it
+pub fn check_passes(ir: &ir::IR, options: &OptOptions) -> std::result::Result<(), String> {
+    let before = reference(ir, "");
+    let mut failure = None;
+    opt::optimize_with(ir.clone(), options, None, &mut |run| {
+        if failure.is_none() {
+            let after = reference(run.ir, "");
+            if after != before {
+                failure = Some(format!(
+                    "after {} at -O{} it printed {:?} and exited with {} instead of {:?} and {}",
+                    run.name, options.level, after.1, after.0, before.1, before.0
+                ));
+            }
+        }
+    });
+
+    match failure {
+        Some(failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+/// Checks `count` random programs, made from `seed` on, with check_passes
+/// at -O1 and -O2 and the rest of `options`, returning each that failed with
+/// its text and why
+pub fn fuzz(
+    seed: u64,
+    count: u64,
+    options: &OptOptions,
+    front_end: FrontEnd,
+) -> Vec<(String, String)> {
+    let mut failures = vec![];
+    for seed in seed..seed + count {
+        let program = random_program(seed);
+        let ir = match deterministic(&program, front_end) {
+            Ok(ir) => ir,
+            Err(failure) => {
+                failures.push((program, failure));
+                continue;
+            }
+        };
+
+        for level in [1, 2] {
+            let options = OptOptions {
+                level,
+                inline_threshold: options.inline_threshold,
+                release: options.release,
+                gc: options.gc,
+                passes: None,
+                disabled: options.disabled.clone(),
+                print_ir_after: None,
+            };
+            if let Err(failure) = check_passes(&ir, &options) {
+                failures.push((program.clone(), failure));
+                break;
+            }
+        }
+    }
+    failures
+}
@@ -0,0 +1,128 @@
+#[cfg(feature = "conformance")]
+pub mod conformance;
+pub mod vm;
+
+use crate::compiler::ir::{ForeignFn, HookName, Value};
+
+pub trait Target {
+    fn get_name(&self) -> char;
+    fn is_standard(&self) -> bool;
+
+    fn std(&self) -> String;
+    /// Whether `name` is a function of the std, which call_foreign_fn can
+    /// call without it being declared
+    fn has_foreign(&self, name: &str) -> bool;
+    /// Declares foreign functions from outside the std so call_foreign_fn
+    /// can call them, given the ones the program calls. Emitted after the
+    /// std.
+    fn foreign_prototypes(&self, functions: &[ForeignFn]) -> String;
+    fn core_prelude(&self) -> String;
+    fn core_postlude(&self) -> String;
+    /// Names every function for the stack traces printed when a program
+    /// panics, the entry point being number 0 and `names[i]` number i + 1,
+    /// shown as `shown[i]`, the name the program gives it. Emitted after the
+    /// std and before any function.
+    fn function_table(&self, names: &[String], shown: &[String]) -> String;
+    /// Names the variables held by hooks, for the target's runtime to show.
+    /// `names` are the functions as given to function_table, and the hooks
+    /// of a function not among them are left out. Emitted right after it.
+    fn hook_table(&self, names: &[String], hooks: &[HookName]) -> String;
+
+    fn begin_entry_point(&self, stack_size: i32, heap_size: i32) -> String;
+    fn end_entry_point(&self) -> String;
+
+    fn establish_stack_frame(&self) -> String;
+    fn end_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String;
+    fn set_return_register(&self) -> String;
+    fn access_return_register(&self) -> String;
+    fn load_base_ptr(&self) -> String;
+
+    fn push_int(&self, n: i32) -> String;
+    fn push_float(&self, n: f32) -> String;
+    fn push(&self, value: Value) -> String {
+        match value {
+            Value::Int(n) => self.push_int(n),
+            Value::Float(n) => self.push_float(n),
+        }
+    }
+    /// Pushes `values` in order, for runs of pushes in the IR
+    fn push_many(&self, values: &[Value]) -> String {
+        values.iter().map(|n| self.push(*n)).collect()
+    }
+
+    fn add(&self) -> String;
+    fn subtract(&self) -> String;
+    fn multiply(&self) -> String;
+    fn divide(&self) -> String;
+    fn modulo(&self) -> String;
+    fn sign(&self) -> String;
+
+    fn allocate(&self) -> String;
+    fn free(&self) -> String;
+    /// Store and Load of IRStatement, the offset counted in values
+    fn store(&self, size: i32, offset: i32) -> String;
+    fn load(&self, size: i32, offset: i32) -> String;
+    /// YARNs get operations of their own so a target can keep their letters
+    /// in less room than a float each and work on whole YARNs at once
+    fn str_alloc(&self) -> String;
+    fn str_free(&self) -> String;
+    fn str_store(&self, letters: i32) -> String;
+    fn str_load(&self, letters: i32) -> String;
+    fn str_concat(&self, left: i32, right: i32) -> String;
+    fn str_cmp(&self, letters: i32) -> String;
+    fn str_print(&self, letters: i32) -> String;
+    fn f_copy(&self) -> String;
+    fn mov(&self) -> String;
+
+    /// Marks the start of a region none of whose allocations outlive it,
+    /// which a target may ignore
+    fn begin_arena(&self) -> String;
+    fn end_arena(&self) -> String;
+    /// Pops the addresses of `roots` values still in use, then frees every
+    /// allocation none of them reaches, following the addresses stored in
+    /// anything but a YARN
+    fn collect(&self, roots: i32) -> String;
+
+    /// Starts a frame of `hooks` hook slots for the function being entered,
+    /// emitted first in the entry point and in every function. Hooks are
+    /// numbered from 0 in each frame, so a recursive call has hooks of its
+    /// own, and the frame ends when its function returns.
+    fn reserve_hooks(&self, hooks: i32) -> String;
+    /// Stores the position of the value on top of the stack in hook `index`
+    /// of the current frame
+    fn hook(&self, index: i32) -> String;
+    /// Pushes what hook `index` of the current frame holds
+    fn ref_hook(&self, index: i32) -> String;
+    /// Pops the value on top of the stack into hook `index` of the current
+    /// frame
+    fn set_hook(&self, index: i32) -> String;
+
+    fn fn_header(&self, name: String) -> String;
+    fn fn_definition(&self, name: String, body: String) -> String;
+    /// Calls a function of the program, ending the hook frame it reserved
+    /// once it returns
+    fn call_fn(&self, name: String) -> String;
+    /// Calls a function from the target's std or one declared to
+    /// foreign_prototypes. Output functions such as
+    /// print_string and prend may buffer, but everything written so far must
+    /// be visible after flush, before any read_* function waits for input and
+    /// when the program halts.
+    fn call_foreign_fn(&self, name: String) -> String;
+
+    fn begin_if(&self) -> String;
+    fn begin_else(&self) -> String;
+    fn end_if(&self) -> String;
+
+    fn begin_while(&self) -> String;
+    fn end_while(&self) -> String;
+
+    fn halt(&self) -> String;
+
+    /// A last pass over the whole assembled program, given the names of its
+    /// functions as they were passed to function_table
+    fn finish(&self, code: String, _functions: &[String]) -> String {
+        code
+    }
+
+    fn compile(&self, code: String, out_file: Option<String>) -> std::io::Result<()>;
+}
@@ -0,0 +1,725 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+#include<string.h>
+#include <locale.h>
+#ifdef __wasi__
+// WASI has no signals, a playground stops a program by dropping it
+typedef int sig_atomic_t;
+#else
+#include <signal.h>
+#endif
+#ifdef _WIN32
+#include <io.h>
+#include <fcntl.h>
+#endif
+
+// bytes a value takes on the heap. Sizes and offsets the IR counts in
+// values are scaled by it here and nowhere else.
+#define VALUE_BYTES 4
+
+typedef struct machine {
+    float* stack;
+    char*  heap;
+    bool*  allocated;
+    // hook slots of the running calls, each call's frame starting right
+    // after a slot holding the base of the frame before it
+    float* hooks;
+    int    hook_base;
+    int    hook_top;
+    int    hook_capacity;
+    int    stack_size;
+    int    heap_size;
+    int    stack_pointer;
+    int    base_ptr;
+    float  return_register;
+    // ids of the HOW IZ I calls being run, the outermost first
+    int*   calls;
+    int    call_depth;
+    int    call_capacity;
+    // loop iterations and calls run so far, and how many are allowed (0 for
+    // no limit)
+    long   steps;
+    long   max_steps;
+    // what VISIBLE ends lines with
+    const char *newline;
+    // bytes of heap a YARN letter takes: 4 keeps every letter a float like
+    // any other value, 1 packs a letter to a byte
+    int    yarn_width;
+    // one past the last byte allocated outside an arena
+    int    heap_high;
+    // while arena_depth > 0 allocations are bumped up from arena_base and
+    // all dropped at once when the outermost arena ends
+    int    arena_depth;
+    int    arena_base;
+    int    arena_top;
+    // bytes of the allocation starting at each byte of the heap (0 where
+    // none starts), which of them are YARNs and the marks of a collection
+    int*   blocks;
+    bool*  yarns;
+    bool*  marked;
+    // bytes allocated outside an arena, and how many there have to be for
+    // machine_collect to free anything
+    int    heap_in_use;
+    int    next_collection;
+} machine;
+
+// names of the entry point and every HOW IZ I function, indexed by the ids
+// given to machine_enter, emitted after the runtime by the backend
+extern const char *FUNCTION_NAMES[];
+
+// the variable hook `hook` of the function FUNCTION_NAMES[function] holds,
+// with the type DUMPZ would give it
+typedef struct hook_name {
+    int         function;
+    int         hook;
+    const char *name;
+    const char *type;
+} hook_name;
+
+// every variable of a debug build, emitted after FUNCTION_NAMES
+extern const hook_name HOOK_NAMES[];
+extern const int HOOK_NAME_COUNT;
+
+// shows the variables of the running calls on stderr, from the std
+void dump_variables(machine *vm);
+
+const int NO_FREE_MEMORY       = 1;
+const int STACK_UNDERFLOW      = 2;
+const int INVALID_HEAP_ADDRESS = 3;
+
+// ends the program once a panic message has been printed, listing the
+// calls being run, the innermost first
+void machine_abort(machine *vm, int code) {
+    for (int i = vm->call_depth - 1; i >= 0; i--) {
+        printf("    at %s\n", FUNCTION_NAMES[vm->calls[i]]);
+    }
+    printf("    at %s\n", FUNCTION_NAMES[0]);
+    printf("\n");
+    dump_variables(vm);
+    exit(code);
+}
+
+void panic(machine *vm, int code) {
+    printf("panic: ");
+    switch (code) {
+        case 1:
+            printf("no free memory\n");
+            break;
+        case 2:
+            printf("stack underflow\n");
+            break;
+        case 3:
+            printf("invalid heap address\n");
+            break;
+        default:
+            printf("unknown error\n");
+    }
+    machine_abort(vm, code);
+}
+
+// set by the SIGINT handler, which does nothing else so that it stays
+// async-signal-safe. Reads, loop iterations and calls check it.
+volatile sig_atomic_t interrupted = 0;
+
+void on_interrupt(int sig) {
+    interrupted = 1;
+}
+
+// without SA_RESTART a read blocked on input returns once interrupted
+void machine_handle_interrupts() {
+#if defined(__wasi__)
+#elif defined(_WIN32)
+    signal(SIGINT, on_interrupt);
+#else
+    struct sigaction action;
+    memset(&action, 0, sizeof(action));
+    action.sa_handler = on_interrupt;
+    sigemptyset(&action.sa_mask);
+    sigaction(SIGINT, &action, NULL);
+#endif
+}
+
+void machine_drop(machine *vm);
+
+// exits with the shell's code for SIGINT after Ctrl-C, keeping the output
+// printed so far
+void machine_check_interrupt(machine *vm) {
+    if (interrupted) {
+        fflush(stdout);
+        machine_drop(vm);
+        exit(130);
+    }
+}
+
+// runs before every loop iteration and call, the only ways a program can
+// keep running
+void machine_step(machine *vm) {
+    machine_check_interrupt(vm);
+    if (vm->max_steps && ++vm->steps > vm->max_steps) {
+        printf("panic: ");
+        printf("program exceeded step limit\n");
+        machine_abort(vm, 1);
+    }
+}
+
+void machine_push(machine *vm, float n) {
+    if (vm->stack_pointer >= vm->stack_size) {
+        panic(vm, NO_FREE_MEMORY);
+    }
+    vm->stack[vm->stack_pointer++] = n;
+}
+
+// pushes several values with one call, which --minify uses for runs of
+// pushes
+void machine_push_many(machine *vm, int count, const float *values) {
+    for (int i = 0; i < count; i++) {
+        machine_push(vm, values[i]);
+    }
+}
+
+float machine_pop(machine *vm) {
+    if (vm->stack_pointer <= 0) {
+        panic(vm, STACK_UNDERFLOW);
+    }
+    float result = vm->stack[--vm->stack_pointer];
+    vm->stack[vm->stack_pointer] = 0;
+    return result;
+}
+
+machine *machine_new(int stack_size, int heap_size) {
+    machine *result    = malloc(sizeof(machine));
+    result->stack_size = stack_size;
+    result->heap_size  = heap_size;
+    result->stack      = malloc(sizeof(float) * stack_size);
+    result->heap       = malloc(sizeof(char)  * heap_size);
+    result->allocated  = malloc(sizeof(bool)  * heap_size);
+    result->blocks     = malloc(sizeof(int)   * heap_size);
+    result->yarns      = malloc(sizeof(bool)  * heap_size);
+    result->marked     = malloc(sizeof(bool)  * heap_size);
+    result->return_register = 0;
+    result->stack_pointer = 0;
+    result->hook_capacity = 16;
+    result->hook_base = 0;
+    result->hook_top = 0;
+    result->hooks = malloc(sizeof(float) * result->hook_capacity);
+    result->call_capacity = 16;
+    result->call_depth = 0;
+    result->calls = malloc(sizeof(int) * result->call_capacity);
+    result->steps = 0;
+    result->max_steps = 0;
+    result->newline = "\n";
+    result->yarn_width = VALUE_BYTES;
+    result->heap_high = 0;
+    result->arena_depth = 0;
+    result->arena_base = 0;
+    result->arena_top = 0;
+    result->heap_in_use = 0;
+    result->next_collection = heap_size / 4;
+
+    for (int i = 0; i < stack_size; i++) {
+        result->stack[i] = 0;
+    }
+
+    for (int i = 0; i < heap_size; i++) {
+        result->heap[i] = 0;
+        result->allocated[i] = false;
+        result->blocks[i] = 0;
+        result->yarns[i] = false;
+        result->marked[i] = false;
+    }
+
+    result->base_ptr      = 0;
+
+    machine_handle_interrupts();
+
+    return result;
+}
+
+// makes VISIBLE end lines with exactly `newline`, which on Windows means
+// keeping stdout's text mode from turning every \n into \r\n
+void machine_set_newline(machine *vm, const char *newline) {
+    vm->newline = newline;
+#ifdef _WIN32
+    fflush(stdout);
+    _setmode(_fileno(stdout), _O_BINARY);
+#endif
+}
+
+// makes YARNs take `width` bytes a letter, 1 or 4
+void machine_set_yarn_width(machine *vm, int width) {
+    vm->yarn_width = width;
+}
+
+void machine_drop(machine *vm) {
+    free(vm->stack);
+    free(vm->heap);
+    free(vm->allocated);
+    free(vm->blocks);
+    free(vm->yarns);
+    free(vm->marked);
+    free(vm->hooks);
+    free(vm->calls);
+    free(vm);
+}
+
+void machine_load_base_ptr(machine *vm) {
+    machine_push(vm, vm->base_ptr);
+}
+
+void machine_establish_stack_frame(machine *vm) {
+    machine_load_base_ptr(vm);
+    vm->base_ptr = vm->stack_pointer - 1;
+}
+
+void machine_end_stack_frame(machine *vm, int arg_size, int local_scope_size) {
+    for (int i = 0; i < local_scope_size; i++) {
+        machine_pop(vm); // free local scope
+    }
+
+    vm->base_ptr = machine_pop(vm); // restore base pointer
+
+    machine_pop(vm); // free return address (not used in a vm as c will handle this)
+
+    for (int i = 0; i < arg_size; i++) {
+        machine_pop(vm); // free arguments
+    }
+}
+
+void machine_set_return_register(machine *vm) {
+    vm->return_register = machine_pop(vm);
+}
+
+void machine_access_return_register(machine *vm) {
+    machine_push(vm, vm->return_register);
+}
+
+// marks `size` free bytes of the heap as allocated, returning where they
+// start. A YARN's letters are never followed by machine_collect.
+int machine_reserve(machine *vm, int size, bool yarn) {
+    if (vm->arena_depth > 0) {
+        if (vm->arena_top + size > vm->heap_size) {
+            panic(vm, NO_FREE_MEMORY);
+        }
+        vm->arena_top += size;
+        return vm->arena_top - size;
+    }
+
+    int addr = -1, consecutive_free_calls = 0;
+
+    for (int i = 0; i < vm->heap_size; i++) {
+        if (!vm->allocated[i]) consecutive_free_calls++;
+        else consecutive_free_calls = 0;
+
+        if (consecutive_free_calls == size) {
+            addr = i - size + 1;
+            break;
+        }
+    }
+
+    if (addr == -1) {
+        panic(vm, NO_FREE_MEMORY);
+    }
+
+    for (int i = 0; i < size; i++) {
+        vm->allocated[addr + i] = true;
+    }
+    vm->blocks[addr] = size;
+    vm->yarns[addr] = yarn;
+    vm->heap_in_use += size;
+    if (addr + size > vm->heap_high) {
+        vm->heap_high = addr + size;
+    }
+
+    return addr;
+}
+
+void machine_release(machine *vm, int addr, int size) {
+    // an arena's allocations go when it ends
+    if (vm->arena_depth > 0 && addr >= vm->arena_base) {
+        return;
+    }
+
+    for (int i = 0; i < size; i++) {
+        vm->allocated[addr + i] = false;
+        vm->heap[addr + i] = 0;
+    }
+    vm->blocks[addr] = 0;
+    vm->yarns[addr] = false;
+    vm->heap_in_use -= size;
+    if (addr + size == vm->heap_high) {
+        while (vm->heap_high > 0 && !vm->allocated[vm->heap_high - 1]) {
+            vm->heap_high--;
+        }
+    }
+}
+
+// starts a region none of whose allocations outlive it, handed out from
+// above everything allocated so far without searching for room
+void machine_begin_arena(machine *vm) {
+    if (vm->arena_depth++ == 0) {
+        vm->arena_base = vm->heap_high;
+        vm->arena_top = vm->heap_high;
+    }
+}
+
+void machine_end_arena(machine *vm) {
+    if (--vm->arena_depth == 0) {
+        memset(&vm->heap[vm->arena_base], 0, vm->arena_top - vm->arena_base);
+    }
+}
+
+int machine_allocate(machine *vm) {
+    int addr = machine_reserve(vm, machine_pop(vm) * VALUE_BYTES, false);
+    machine_push(vm, addr);
+    return addr;
+}
+
+void machine_free(machine *vm) {
+    int addr = machine_pop(vm), size = machine_pop(vm) * VALUE_BYTES;
+    machine_release(vm, addr, size);
+}
+
+void float2Bytes(unsigned char bytes_temp[VALUE_BYTES], float float_variable) {
+    union {
+        float a;
+        unsigned char bytes[VALUE_BYTES];
+    } thing;
+    thing.a = float_variable;
+    memcpy(bytes_temp, thing.bytes, VALUE_BYTES);
+}
+
+float bytes2Float(unsigned char bytes_temp[VALUE_BYTES]) {
+    union {
+        float a;
+        unsigned char bytes[VALUE_BYTES];
+    } thing;
+    memcpy(thing.bytes, bytes_temp, VALUE_BYTES);
+    return thing.a;
+}
+
+// the `index`th value from `addr` on, combined from its bytes
+float machine_read_value(machine *vm, int addr, int index) {
+    unsigned char bytes[VALUE_BYTES];
+    memcpy(bytes, &vm->heap[addr + index * VALUE_BYTES], VALUE_BYTES);
+    return bytes2Float(bytes);
+}
+
+// writes the `index`th value from `addr` on, broken into its bytes
+void machine_write_value(machine *vm, int addr, int index, float value) {
+    unsigned char bytes[VALUE_BYTES];
+    float2Bytes(bytes, value);
+    memcpy(&vm->heap[addr + index * VALUE_BYTES], bytes, VALUE_BYTES);
+}
+
+// stores `floats` popped values from the `offset`th float at `addr` on,
+// the value pushed first going lowest
+void machine_store_at(machine *vm, int addr, int floats, int offset) {
+    for (int i = floats - 1; i >= 0; i--) {
+        machine_write_value(vm, addr, offset + i, machine_pop(vm));
+    }
+}
+
+void machine_load_at(machine *vm, int addr, int floats, int offset) {
+    for (int i = 0; i < floats; i++) {
+        machine_push(vm, machine_read_value(vm, addr, offset + i));
+    }
+}
+
+void machine_store(machine *vm, int floats, int offset) {
+    machine_store_at(vm, machine_pop(vm), floats, offset);
+}
+
+void machine_load(machine *vm, int floats, int offset) {
+    machine_load_at(vm, machine_pop(vm), floats, offset);
+}
+
+// panics unless every byte of the floats a store or load touches is
+// allocated, on the heap or in the running arena
+void machine_check_access(machine *vm, int addr, int floats, int offset) {
+    int start = addr + offset * VALUE_BYTES, end = start + floats * VALUE_BYTES;
+    if (offset < 0 || start < 0 || end > vm->heap_size) {
+        panic(vm, INVALID_HEAP_ADDRESS);
+    }
+    for (int i = start; i < end; i++) {
+        bool in_arena = vm->arena_depth > 0 && i >= vm->arena_base && i < vm->arena_top;
+        if (!vm->allocated[i] && !in_arena) {
+            panic(vm, INVALID_HEAP_ADDRESS);
+        }
+    }
+}
+
+// machine_store and machine_load of debug builds
+void machine_store_checked(machine *vm, int floats, int offset) {
+    int addr = machine_pop(vm);
+    machine_check_access(vm, addr, floats, offset);
+    machine_store_at(vm, addr, floats, offset);
+}
+
+void machine_load_checked(machine *vm, int floats, int offset) {
+    int addr = machine_pop(vm);
+    machine_check_access(vm, addr, floats, offset);
+    machine_load_at(vm, addr, floats, offset);
+}
+
+// marks the allocation starting at `value`, if one does, to be scanned
+void machine_mark(machine *vm, int *pending, int *count, float value) {
+    if (!(value >= 0 && value < vm->heap_size)) {
+        return;
+    }
+    int addr = value;
+    if (addr != value || !vm->blocks[addr] || vm->marked[addr]) {
+        return;
+    }
+    vm->marked[addr] = true;
+    pending[(*count)++] = addr;
+}
+
+// pops the addresses of `roots` values still in use and, once enough of the
+// heap is allocated, frees every allocation none of them reaches. Anything
+// but a YARN is scanned float by float for addresses, so a number that
+// happens to equal one only keeps that allocation a while longer.
+void machine_collect(machine *vm, int roots) {
+    if (vm->heap_in_use < vm->next_collection) {
+        for (int i = 0; i < roots; i++) {
+            machine_pop(vm);
+        }
+        return;
+    }
+
+    int *pending = malloc(sizeof(int) * vm->heap_size), count = 0;
+    for (int i = 0; i < roots; i++) {
+        machine_mark(vm, pending, &count, machine_pop(vm));
+    }
+    while (count > 0) {
+        int addr = pending[--count];
+        if (vm->yarns[addr]) {
+            continue;
+        }
+        for (int i = 0; (i + 1) * VALUE_BYTES <= vm->blocks[addr]; i++) {
+            machine_mark(vm, pending, &count, machine_read_value(vm, addr, i));
+        }
+    }
+    free(pending);
+
+    for (int addr = 0; addr < vm->heap_size; addr++) {
+        if (vm->blocks[addr] && !vm->marked[addr]) {
+            machine_release(vm, addr, vm->blocks[addr]);
+        }
+        vm->marked[addr] = false;
+    }
+    vm->next_collection = vm->heap_in_use + vm->heap_size / 4;
+}
+
+float yarn_get(machine *vm, int addr, int index) {
+    if (vm->yarn_width == 1) {
+        return (unsigned char)vm->heap[addr + index];
+    }
+
+    return machine_read_value(vm, addr, index);
+}
+
+// a letter packed into a byte has to fit in one, as a byte of input does
+// whether char is signed or not
+void yarn_set(machine *vm, int addr, int index, float letter) {
+    if (vm->yarn_width == 1) {
+        if (letter < -128 || letter > 255) {
+            printf("panic: ");
+            printf("letter %d does not fit in a byte\n", (int)letter);
+            machine_abort(vm, 1);
+        }
+        vm->heap[addr + index] = (unsigned char)(int)letter;
+        return;
+    }
+
+    machine_write_value(vm, addr, index, letter);
+}
+
+// pops a number of letters, pushing the address of a new YARN that long
+int machine_str_alloc(machine *vm) {
+    int addr = machine_reserve(vm, machine_pop(vm) * vm->yarn_width, true);
+    machine_push(vm, addr);
+    return addr;
+}
+
+// pops the address and then the number of letters of a YARN
+void machine_str_free(machine *vm) {
+    int addr = machine_pop(vm), size = machine_pop(vm) * vm->yarn_width;
+    machine_release(vm, addr, size);
+}
+
+void machine_str_store(machine *vm, int letters) {
+    int addr = machine_pop(vm);
+    for (int i = letters - 1; i >= 0; i--) {
+        yarn_set(vm, addr, i, machine_pop(vm));
+    }
+}
+
+void machine_str_load(machine *vm, int letters) {
+    int addr = machine_pop(vm);
+    for (int i = 0; i < letters; i++) {
+        machine_push(vm, yarn_get(vm, addr, i));
+    }
+}
+
+// pops the address of a YARN of `right` letters and of one holding `left`,
+// appending the letters of the first to the second
+void machine_str_concat(machine *vm, int left, int right) {
+    int right_addr = machine_pop(vm);
+    int left_addr = machine_pop(vm);
+    memcpy(&vm->heap[left_addr + left * vm->yarn_width], &vm->heap[right_addr], right * vm->yarn_width);
+}
+
+// pops the addresses of two YARNs of `letters` letters, pushing the order of
+// the first letter they differ in. Packed letters are unsigned bytes, which
+// memcmp orders the same way.
+void machine_str_cmp(machine *vm, int letters) {
+    int right = machine_pop(vm);
+    int left = machine_pop(vm);
+    int order = 0;
+    if (vm->yarn_width == 1) {
+        order = memcmp(&vm->heap[left], &vm->heap[right], letters);
+        order = order < 0 ? -1 : order > 0;
+    } else {
+        for (int i = 0; i < letters; i++) {
+            float a = yarn_get(vm, left, i);
+            float b = yarn_get(vm, right, i);
+            if (a != b) {
+                order = a < b ? -1 : 1;
+                break;
+            }
+        }
+    }
+    machine_push(vm, order);
+}
+
+void machine_str_print(machine *vm, int letters) {
+    int addr = machine_pop(vm);
+    if (vm->yarn_width == 1) {
+        fwrite(&vm->heap[addr], 1, letters, stdout);
+        return;
+    }
+    for (int i = 0; i < letters; i++) {
+        printf("%c", (char)yarn_get(vm, addr, i));
+    }
+}
+
+void machine_copy(machine *vm) {
+    int offset = machine_pop(vm);
+
+    machine_push(vm, vm->stack[offset]);
+}
+
+void machine_mov(machine *vm) {
+    int offset = machine_pop(vm);
+    float value = machine_pop(vm);
+
+    vm->stack[offset] = value;
+}
+
+// starts the hook frame of the function being entered, its hooks being
+// numbered from 0
+void machine_reserve_hooks(machine *vm, int count) {
+    if (vm->hook_top + count + 1 > vm->hook_capacity) {
+        while (vm->hook_top + count + 1 > vm->hook_capacity) {
+            vm->hook_capacity *= 2;
+        }
+        vm->hooks = realloc(vm->hooks, sizeof(float) * vm->hook_capacity);
+    }
+
+    vm->hooks[vm->hook_top] = vm->hook_base;
+    vm->hook_base = vm->hook_top + 1;
+    vm->hook_top = vm->hook_base + count;
+    for (int i = vm->hook_base; i < vm->hook_top; i++) {
+        vm->hooks[i] = 0;
+    }
+}
+
+// what hook `hook` of the function with id `function` is named, NULL for a
+// hook holding no variable
+const hook_name *machine_hook_name(int function, int hook) {
+    for (int i = 0; i < HOOK_NAME_COUNT; i++) {
+        if (HOOK_NAMES[i].function == function && HOOK_NAMES[i].hook == hook) {
+            return &HOOK_NAMES[i];
+        }
+    }
+    return NULL;
+}
+
+// ends the hook frame of the function returning, going back to its caller's
+void machine_release_hooks(machine *vm) {
+    vm->hook_top = vm->hook_base - 1;
+    vm->hook_base = vm->hooks[vm->hook_top];
+}
+
+void machine_hook(machine *vm, int hook) {
+    vm->hooks[vm->hook_base + hook] = vm->stack_pointer - 1;
+}
+
+void machine_ref_hook(machine *vm, int hook) {
+    machine_push(vm, vm->hooks[vm->hook_base + hook]);
+}
+
+void machine_set_hook(machine *vm, int hook) {
+    vm->hooks[vm->hook_base + hook] = machine_pop(vm);
+}
+
+void machine_add(machine *vm) {
+    machine_push(vm, machine_pop(vm) + machine_pop(vm));
+}
+
+void machine_subtract(machine *vm) {
+    float b = machine_pop(vm);
+    float a = machine_pop(vm);
+    machine_push(vm, a - b);
+}
+
+void machine_multiply(machine *vm) {
+    machine_push(vm, machine_pop(vm) * machine_pop(vm));
+}
+
+void machine_divide(machine *vm) {
+    float b = machine_pop(vm);
+    float a = machine_pop(vm);
+    machine_push(vm, a/b);
+}
+
+void machine_modulo(machine *vm) {
+    float b = machine_pop(vm);
+    float a = machine_pop(vm);
+    if ((int)b == 0) {
+        printf("panic: ");
+        printf("modulo by zero\n");
+        machine_abort(vm, 1);
+    }
+    machine_push(vm, (float)((int)a % (int)b));
+}
+
+void machine_sign(machine *vm) {
+    float x = machine_pop(vm);
+    if (x >= 0) {
+        machine_push(vm, 1);
+    } else {
+        machine_push(vm, -1);
+    }
+}
+
+// records a call to the function FUNCTION_NAMES[id] for stack traces
+void machine_enter(machine *vm, int id) {
+    machine_step(vm);
+    if (vm->call_depth == vm->call_capacity) {
+        vm->call_capacity *= 2;
+        vm->calls = realloc(vm->calls, sizeof(int) * vm->call_capacity);
+    }
+    vm->calls[vm->call_depth++] = id;
+}
+
+void machine_leave(machine *vm) {
+    machine_release_hooks(vm);
+    vm->call_depth--;
+}
+
+void machine_halt(machine *vm) {
+    machine_drop(vm);
+    exit(0);
+}
\ No newline at end of file
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+/// Punctuation that could run together into another token (`- -` into
+/// `--`, `/ *` into a comment), so the space between two of them is kept
+const JOINING: &str = "+-*/%&|<>=!^.:#";
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether `previous` and `next` need a space between them to stay apart
+fn needs_space(previous: Option<char>, next: char) -> bool {
+    match previous {
+        Some(previous) => {
+            (is_word(previous) && is_word(next))
+                || (JOINING.contains(previous) && JOINING.contains(next))
+        }
+        None => false,
+    }
+}
+
+/// Shrinks assembled C: comments go, whitespace is only kept where tokens
+/// would otherwise run together (preprocessor lines keep their own line and
+/// single spaces), and the functions in `functions` are renamed to F and the
+/// id function_table gave them, with their FN_ ids replaced by the ids.
+/// String and character literals are left as they are.
+pub fn minify(code: &str, functions: &[String]) -> String {
+    let mut renames = HashMap::new();
+    for (i, name) in functions.iter().enumerate() {
+        renames.insert(name.clone(), format!("F{}", i + 1));
+        renames.insert(format!("FN_{}", name), (i + 1).to_string());
+    }
+
+    let chars = code.chars().collect::<Vec<char>>();
+    let mut minified = String::new();
+    let mut space = false;
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\n' {
+            line_start = true;
+            space = true;
+            i += 1;
+        } else if c.is_whitespace() {
+            space = true;
+            i += 1;
+        } else if c == '#' && line_start {
+            let start = i;
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            let line = chars[start..i].iter().collect::<String>();
+            if !minified.is_empty() && !minified.ends_with('\n') {
+                minified.push('\n');
+            }
+            minified.push_str(&line.split_whitespace().collect::<Vec<&str>>().join(" "));
+            minified.push('\n');
+            space = false;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            space = true;
+        } else {
+            line_start = false;
+            if space && needs_space(minified.chars().last(), c) {
+                minified.push(' ');
+            }
+            space = false;
+
+            let start = i;
+            if c == '"' || c == '\'' {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            } else if is_word(c) {
+                while i < chars.len() && is_word(chars[i]) {
+                    i += 1;
+                }
+            } else {
+                i += 1;
+            }
+
+            let token = chars[start..i.min(chars.len())].iter().collect::<String>();
+            match renames.get(&token) {
+                Some(renamed) => minified.push_str(renamed),
+                None => minified.push_str(&token),
+            }
+        }
+    }
+
+    if !minified.ends_with('\n') {
+        minified.push('\n');
+    }
+    minified
+}
@@ -0,0 +1,745 @@
+mod minify;
+
+use super::Target;
+use crate::compiler::ir;
+
+use std::{
+    env::{self, consts::EXE_SUFFIX, current_exe},
+    fs,
+    io::{Error, ErrorKind, IsTerminal, Read, Result},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub struct VM {
+    /// loop iterations and calls a program may run before it panics
+    pub max_steps: Option<u64>,
+    /// what VISIBLE ends lines with, None for the platform's own (stdout's
+    /// text mode makes \n into \r\n on Windows)
+    pub newline: Option<&'static str>,
+    /// bytes of heap a YARN letter takes, 4 (a float) or 1
+    pub yarn_width: i32,
+    /// allocate from an arena dropped at the end of each region the
+    /// compiler marks rather than searching the heap every time
+    pub arena: bool,
+    /// panic on a Store or Load outside the allocation it is given, which
+    /// debug builds do
+    pub checked: bool,
+    /// C compiler to use instead of looking for one
+    pub cc: Option<String>,
+    /// keep the generated C next to the output as <output>.c
+    pub save_temps: bool,
+    /// how long the C compiler may run before it is killed
+    pub timeout: Option<Duration>,
+    /// show that the C compiler is still running
+    pub progress: bool,
+    /// generate the smallest C possible rather than readable C
+    pub minify: bool,
+    /// text the program prints when run with --lolcat-info, kept in a
+    /// section of its own so it can also be read without running it
+    pub info: Option<String>,
+}
+
+/// A target building programs as the command line does by default
+impl Default for VM {
+    fn default() -> Self {
+        VM {
+            max_steps: None,
+            newline: None,
+            yarn_width: 4,
+            arena: false,
+            checked: true,
+            cc: None,
+            save_temps: false,
+            timeout: Some(Duration::from_secs(120)),
+            progress: false,
+            minify: false,
+            info: None,
+        }
+    }
+}
+
+/// The C type of a foreign function's argument or result
+fn c_type(kind: ir::ForeignKind) -> &'static str {
+    match kind {
+        ir::ForeignKind::Number => "int",
+        ir::ForeignKind::Numbar => "float",
+        ir::ForeignKind::Troof => "bool",
+    }
+}
+
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+/// Numbers the temp C files of one run of the compiler
+static NEXT_SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+/// C compilers tried, in order, when neither --cc, $LOLCAT_CC nor a bundled
+/// tcc is there
+const SYSTEM_CCS: &[&str] = &["cc", "gcc", "clang"];
+
+/// `text` as a C string literal, a line to a literal so it stays readable
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'\n' => literal.push_str("\\n\"\n\""),
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            // ? is escaped so that ?? never starts a trigraph
+            b'?' => literal.push_str("\\?"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(format!("{}{}", name, EXE_SUFFIX)))
+        .find(|path| path.is_file())
+}
+
+impl VM {
+    /// The C compiler to build programs with: the --cc option, then
+    /// $LOLCAT_CC, then tcc/tcc inside $LOLCAT_DEP or the dep directory next to
+    /// this executable, then the first of cc, gcc and clang on the PATH
+    pub fn find_cc(&self) -> Result<String> {
+        if let Some(cc) = &self.cc {
+            return Ok(cc.clone());
+        }
+        if let Ok(cc) = env::var("LOLCAT_CC") {
+            return Ok(cc);
+        }
+
+        let deps_path = match env::var_os("LOLCAT_DEP") {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.join("dep"))),
+        };
+        if let Some(deps_path) = deps_path {
+            let tcc_path = deps_path.join("tcc").join(format!("tcc{}", EXE_SUFFIX));
+            if tcc_path.exists() {
+                return Ok(tcc_path.to_string_lossy().to_string());
+            }
+        }
+
+        for cc in SYSTEM_CCS.iter() {
+            if find_on_path(cc).is_some() {
+                return Ok(cc.to_string());
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::NotFound,
+            "no C compiler found: there is no dep/tcc next to the compiler (or in $LOLCAT_DEP) and none of cc, gcc or clang is on the PATH; install one, or name one with --cc or $LOLCAT_CC",
+        ))
+    }
+
+    /// Compiles assembled code to a wasm32-wasi module with clang (or
+    /// $WASI_CC), passing $WASI_SYSROOT as the sysroot if it is set
+    pub fn compile_wasm(&self, code: String, out_file: &str) -> Result<()> {
+        let cc = env::var("WASI_CC").unwrap_or_else(|_| "clang".to_string());
+        let mut command = Command::new(&cc);
+        command.args(["--target=wasm32-wasi", "-O2", "-o", out_file]);
+        if let Ok(sysroot) = env::var("WASI_SYSROOT") {
+            command.arg(format!("--sysroot={}", sysroot));
+        }
+
+        self.run_cc(command, code, out_file)
+    }
+
+    /// Where the C for `out_file` is written: next to it when the temps are
+    /// kept, in the temp directory otherwise
+    pub fn source_path(&self, out_file: &str) -> PathBuf {
+        if self.save_temps {
+            return PathBuf::from(format!("{}.c", out_file));
+        }
+
+        let id = NEXT_SOURCE.fetch_add(1, Ordering::Relaxed);
+        env::temp_dir().join(format!("lolcat-{}-{}.c", std::process::id(), id))
+    }
+
+    /// Waits for the C compiler, killing it once it runs past the timeout (None
+    /// is returned then). With progress on it shows how long it has taken: a
+    /// spinner on a terminal, a log line every few seconds otherwise.
+    fn wait(&self, mut child: Child, cc: &str) -> Result<Option<ExitStatus>> {
+        let start = Instant::now();
+        let spinner = self.progress && std::io::stderr().is_terminal();
+        let mut ticks = 0;
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                if spinner && ticks > 0 {
+                    eprint!("\r\x1b[K");
+                }
+                return Ok(Some(status));
+            }
+
+            let elapsed = start.elapsed();
+            if self.timeout.is_some_and(|timeout| elapsed >= timeout) {
+                let _ = child.kill();
+                let _ = child.wait();
+                if spinner && ticks > 0 {
+                    eprintln!();
+                }
+                return Ok(None);
+            }
+
+            if spinner {
+                let frame = SPINNER[ticks % SPINNER.len()];
+                eprint!("\r{} waiting for {} ({}s)", frame, cc, elapsed.as_secs());
+            } else if self.progress && ticks > 0 && ticks % 50 == 0 {
+                eprintln!(
+                    "level=info phase=cc msg=\"still waiting for {} after {}s\"",
+                    cc,
+                    elapsed.as_secs()
+                );
+            }
+
+            ticks += 1;
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Writes `code` to a file and compiles it with `command`, keeping the
+    /// file if the compiler fails so there is something to look at
+    fn run_cc(&self, mut command: Command, code: String, out_file: &str) -> Result<()> {
+        let source = self.source_path(out_file);
+        fs::write(&source, code)?;
+
+        let cc = command.get_program().to_string_lossy().to_string();
+        let mut child = command
+            .args(["-x", "c"])
+            .arg(&source)
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::new(e.kind(), format!("unable to run {}: {}", cc, e)))?;
+
+        // the compiler's messages are collected so that several compilers
+        // running at once do not interleave them
+        let mut stderr = child.stderr.take().unwrap();
+        let messages = thread::spawn(move || {
+            let mut messages = String::new();
+            let _ = stderr.read_to_string(&mut messages);
+            messages
+        });
+
+        let status = match self.wait(child, &cc)? {
+            Some(status) => status,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "{} was stopped after {} seconds (see --cc-timeout), the generated C was kept in {}",
+                        cc,
+                        self.timeout.map_or(0, |timeout| timeout.as_secs()),
+                        source.display()
+                    ),
+                ))
+            }
+        };
+        let messages = messages.join().unwrap_or_default();
+        if !status.success() {
+            return Err(Error::other(format!(
+                "{}{} failed ({}), the generated C was kept in {}",
+                messages,
+                cc,
+                status,
+                source.display()
+            )));
+        }
+        eprint!("{}", messages);
+
+        if self.save_temps {
+            eprintln!("Saved the generated C to {}", source.display());
+        } else {
+            let _ = fs::remove_file(&source);
+        }
+        Ok(())
+    }
+}
+
+impl Target for VM {
+    fn get_name(&self) -> char {
+        'c'
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> String {
+        String::from(include_str!("std.c"))
+    }
+
+    /// The std's functions are those of std.c taking only the machine
+    fn has_foreign(&self, name: &str) -> bool {
+        include_str!("std.c").contains(format!("\nvoid {}(machine *vm) {{", name).as_str())
+    }
+
+    /// A declared function is a plain C function, such as `int clamp(int,
+    /// int)` for one taking two NUMBERs and returning one, called through a
+    /// wrapper named `ffi_<name>` that moves its arguments and result between
+    /// it and the stack
+    fn foreign_prototypes(&self, functions: &[ir::ForeignFn]) -> String {
+        let mut code = String::new();
+        for function in functions.iter() {
+            let result = function.result.map_or("void", c_type);
+            let params = match function.args.is_empty() {
+                true => "void".to_string(),
+                false => function
+                    .args
+                    .iter()
+                    .map(|kind| c_type(*kind))
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            };
+            code.push_str(&format!(
+                "{} {}({});
+",
+                result, function.name, params
+            ));
+
+            code.push_str(&format!(
+                "void ffi_{}(machine *vm) {{
+",
+                function.name
+            ));
+            for (i, kind) in function.args.iter().enumerate().rev() {
+                code.push_str(&format!(
+                    "    {} a{} = ({})machine_pop(vm);
+",
+                    c_type(*kind),
+                    i,
+                    c_type(*kind)
+                ));
+            }
+            let args = (0..function.args.len())
+                .map(|i| format!("a{}", i))
+                .collect::<Vec<String>>()
+                .join(", ");
+            match function.result {
+                Some(_) => code.push_str(&format!(
+                    "    machine_push(vm, {}({}));
+",
+                    function.name, args
+                )),
+                None => code.push_str(&format!(
+                    "    {}({});
+",
+                    function.name, args
+                )),
+            }
+            code.push_str(
+                "}
+",
+            );
+        }
+        code
+    }
+
+    fn core_prelude(&self) -> String {
+        String::from(include_str!("core.c"))
+    }
+
+    fn core_postlude(&self) -> String {
+        String::new()
+    }
+
+    fn function_table(&self, names: &[String], shown: &[String]) -> String {
+        let mut code = String::from("const char *FUNCTION_NAMES[] = {\"main\"");
+        for name in shown.iter() {
+            code.push_str(format!(", \"{}\"", name).as_str());
+        }
+        code.push_str("};\n");
+        if self.minify {
+            // minify puts the ids in place of their names
+            return code;
+        }
+
+        for (i, name) in names.iter().enumerate() {
+            code.push_str(format!("#define FN_{} {}\n", name, i + 1).as_str());
+        }
+
+        code
+    }
+
+    fn hook_table(&self, names: &[String], hooks: &[ir::HookName]) -> String {
+        let mut entries = vec![];
+        for hook in hooks.iter() {
+            let id = if hook.function == "main" {
+                0
+            } else {
+                match names.iter().position(|name| *name == hook.function) {
+                    Some(i) => i + 1,
+                    None => continue,
+                }
+            };
+            entries.push(format!(
+                "{{{}, {}, {}, \"{}\"}}",
+                id,
+                hook.hook,
+                c_string(&hook.name),
+                hook.type_
+            ));
+        }
+
+        // C has no empty arrays, so the table always ends with a blank entry
+        entries.push("{-1, -1, \"\", \"\"}".to_string());
+        format!(
+            "const hook_name HOOK_NAMES[] = {{\n{}\n}};\nconst int HOOK_NAME_COUNT = {};\n",
+            entries.join(",\n"),
+            entries.len() - 1
+        )
+    }
+
+    fn begin_entry_point(&self, stack_size: i32, heap_size: i32) -> String {
+        let mut code = match &self.info {
+            Some(info) => format!(
+                concat!(
+                    "#if defined(__ELF__)\n",
+                    "__attribute__((used, section(\".lolcat_info\")))\n",
+                    "#elif defined(__APPLE__)\n",
+                    "__attribute__((used, section(\"__DATA,__lolcat_info\")))\n",
+                    "#endif\n",
+                    "const char LOLCAT_INFO[] = {};\n",
+                    "int main(int argc, char **argv) {{\n",
+                    "if (argc > 1 && strcmp(argv[1], \"--lolcat-info\") == 0) {{\n",
+                    "fputs(LOLCAT_INFO, stdout);\nreturn 0;\n}}\n",
+                ),
+                c_string(info)
+            ),
+            None => String::from("int main() {\n"),
+        };
+        code.push_str(
+            format!(
+                "machine *vm = machine_new({}, {});\n",
+                stack_size, heap_size
+            )
+            .as_str(),
+        );
+        if let Some(max_steps) = self.max_steps {
+            code.push_str(format!("vm->max_steps = {};\n", max_steps).as_str());
+        }
+        if let Some(newline) = self.newline {
+            code.push_str(
+                format!(
+                    "machine_set_newline(vm, \"{}\");\n",
+                    newline.escape_default()
+                )
+                .as_str(),
+            );
+        }
+        if self.yarn_width != 4 {
+            code.push_str(format!("machine_set_yarn_width(vm, {});\n", self.yarn_width).as_str());
+        }
+
+        code
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("\nmachine_drop(vm);\nreturn 0;\n}")
+    }
+
+    fn establish_stack_frame(&self) -> String {
+        String::from("machine_establish_stack_frame(vm);\n")
+    }
+
+    fn end_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "machine_end_stack_frame(vm, {}, {});\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn set_return_register(&self) -> String {
+        String::from("machine_set_return_register(vm);\n")
+    }
+
+    fn access_return_register(&self) -> String {
+        String::from("machine_access_return_register(vm);\n")
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("machine_load_base_ptr(vm);\n")
+    }
+
+    fn push_int(&self, n: i32) -> String {
+        format!("machine_push(vm, {});\n", n)
+    }
+
+    fn push_float(&self, n: f32) -> String {
+        format!("machine_push(vm, {});\n", n)
+    }
+
+    fn push_many(&self, values: &[ir::Value]) -> String {
+        if !self.minify {
+            return values.iter().map(|value| self.push(*value)).collect();
+        }
+
+        // whichever is shorter once minified, every value a float either way
+        let pushes = values
+            .iter()
+            .map(|value| format!("machine_push(vm,{});", value.as_f32()))
+            .collect::<String>();
+        let merged = format!(
+            "machine_push_many(vm,{},(float[]){{{}}});",
+            values.len(),
+            values
+                .iter()
+                .map(|value| value.as_f32().to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        );
+        if merged.len() < pushes.len() {
+            merged
+        } else {
+            pushes
+        }
+    }
+
+    fn add(&self) -> String {
+        String::from("machine_add(vm);\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("machine_subtract(vm);\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("machine_multiply(vm);\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("machine_divide(vm);\n")
+    }
+
+    fn modulo(&self) -> String {
+        String::from("machine_modulo(vm);\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("machine_sign(vm);\n")
+    }
+
+    fn allocate(&self) -> String {
+        String::from("machine_allocate(vm);\n")
+    }
+
+    fn free(&self) -> String {
+        String::from("machine_free(vm);\n")
+    }
+
+    fn store(&self, size: i32, offset: i32) -> String {
+        let check = if self.checked { "_checked" } else { "" };
+        format!("machine_store{}(vm, {}, {});\n", check, size, offset)
+    }
+
+    fn load(&self, size: i32, offset: i32) -> String {
+        let check = if self.checked { "_checked" } else { "" };
+        format!("machine_load{}(vm, {}, {});\n", check, size, offset)
+    }
+
+    fn str_alloc(&self) -> String {
+        String::from("machine_str_alloc(vm);\n")
+    }
+
+    fn str_free(&self) -> String {
+        String::from("machine_str_free(vm);\n")
+    }
+
+    fn str_store(&self, letters: i32) -> String {
+        format!("machine_str_store(vm, {});\n", letters)
+    }
+
+    fn str_load(&self, letters: i32) -> String {
+        format!("machine_str_load(vm, {});\n", letters)
+    }
+
+    fn str_concat(&self, left: i32, right: i32) -> String {
+        format!("machine_str_concat(vm, {}, {});\n", left, right)
+    }
+
+    fn str_cmp(&self, letters: i32) -> String {
+        format!("machine_str_cmp(vm, {});\n", letters)
+    }
+
+    fn str_print(&self, letters: i32) -> String {
+        format!("machine_str_print(vm, {});\n", letters)
+    }
+
+    fn f_copy(&self) -> String {
+        String::from("machine_copy(vm);\n")
+    }
+
+    fn mov(&self) -> String {
+        String::from("machine_mov(vm);\n")
+    }
+
+    fn begin_arena(&self) -> String {
+        if self.arena {
+            String::from("machine_begin_arena(vm);\n")
+        } else {
+            String::new()
+        }
+    }
+
+    fn end_arena(&self) -> String {
+        if self.arena {
+            String::from("machine_end_arena(vm);\n")
+        } else {
+            String::new()
+        }
+    }
+
+    fn collect(&self, roots: i32) -> String {
+        format!("machine_collect(vm, {});\n", roots)
+    }
+
+    fn reserve_hooks(&self, hooks: i32) -> String {
+        format!("machine_reserve_hooks(vm, {});\n", hooks)
+    }
+
+    fn hook(&self, index: i32) -> String {
+        format!("machine_hook(vm, {});\n", index)
+    }
+
+    fn ref_hook(&self, index: i32) -> String {
+        format!("machine_ref_hook(vm, {});\n", index)
+    }
+
+    fn set_hook(&self, index: i32) -> String {
+        format!("machine_set_hook(vm, {});\n", index)
+    }
+
+    fn fn_header(&self, name: String) -> String {
+        format!("void {}(machine* vm);\n", name)
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        format!("void {}(machine* vm) {{ {}}}\n", name, body)
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        // we push 1 as a temp value for a return pointer
+        format!(
+            "machine_enter(vm, FN_{});\nmachine_push(vm, 1);\n{}(vm);\nmachine_leave(vm);\n",
+            name, name
+        )
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        match self.has_foreign(&name) {
+            true => format!("{}(vm);\n", name),
+            false => format!("ffi_{}(vm);\n", name),
+        }
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if (machine_pop(vm)) {\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("} else {\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn begin_while(&self) -> String {
+        String::from("while (machine_pop(vm)) {\nmachine_step(vm);\n")
+    }
+
+    fn end_while(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn halt(&self) -> String {
+        String::from("machine_halt(vm);\n")
+    }
+
+    fn finish(&self, code: String, functions: &[String]) -> String {
+        if self.minify {
+            minify::minify(&code, functions)
+        } else {
+            code
+        }
+    }
+
+    fn compile(&self, code: String, out_file: Option<String>) -> Result<()> {
+        let cc = self.find_cc()?;
+        let out_path = match out_file {
+            Some(path) => path,
+            None => format!("main{}", EXE_SUFFIX)[..].to_string(),
+        };
+
+        let mut command = Command::new(&cc);
+        command.arg("-O2").args(["-o", out_path.as_str()]);
+        self.run_cc(command, code, &out_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver;
+
+    #[test]
+    fn numbars_are_written_and_read_with_a_point_in_a_comma_locale() {
+        // few systems have a locale besides C installed, so one that writes
+        // 3,25 is built from its source
+        let dir = env::temp_dir().join(format!("lolcat-locale-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let _ = Command::new("localedef")
+            .args(["-i", "de_DE", "-f", "UTF-8"])
+            .arg(dir.join("de_DE.UTF-8"))
+            .stderr(Stdio::null())
+            .status();
+        if !dir.join("de_DE.UTF-8").exists() {
+            eprintln!("skipped: localedef could not build de_DE.UTF-8");
+            let _ = fs::remove_dir_all(&dir);
+            return;
+        }
+
+        let text = "HAI 1.2
+I HAS A x ITZ NUMBAR R 3.25
+VISIBLE MAEK x A YARN
+I HAS A y ITZ YARN R \"-0.5\"
+VISIBLE MAEK MAEK y A NUMBAR A YARN
+KTHXBYE
+";
+        let ir = driver::lower_text(text);
+
+        // as if the program were linked into code that sets the locale
+        let target = VM {
+            newline: Some("\n"),
+            ..VM::default()
+        };
+        let code = ir.assemble(&target).replacen(
+            "int main() {\n",
+            "int main() {\nif (setlocale(LC_NUMERIC, \"de_DE.UTF-8\") == NULL) return 2;\n",
+            1,
+        );
+        let out_file = dir.join("program").to_string_lossy().to_string();
+        target.compile(code, Some(out_file.clone())).unwrap();
+        let output = Command::new(&out_file)
+            .env("LOCPATH", &dir)
+            .output()
+            .unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).replace('\0', ""),
+            "3.250000\n-0.500000\n"
+        );
+    }
+}
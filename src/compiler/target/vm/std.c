@@ -0,0 +1,906 @@
+
+// formats a NUMBAR the same way in any C locale: six decimals after a '.',
+// and "nan" for every NaN, as format_float in the interpreter does
+void format_float(char *buffer, int size, float n) {
+    if (n != n) {
+        snprintf(buffer, size, "nan");
+        return;
+    }
+    snprintf(buffer, size, "%f", n);
+
+    // a locale set by whatever the program is linked into may use another
+    // decimal point, which can be longer than one byte
+    const char *point = localeconv()->decimal_point;
+    char *found = strstr(buffer, point);
+    if (strcmp(point, ".") != 0 && found != NULL) {
+        *found = '.';
+        memmove(found + 1, found + strlen(point), strlen(found + strlen(point)) + 1);
+    }
+}
+
+void prn(machine *vm) {
+    float n = machine_pop(vm);
+    char buffer[64];
+    format_float(buffer, sizeof(buffer), n);
+    printf("%s", buffer);
+}
+
+void prs(machine *vm) {
+    float addr = machine_pop(vm);
+    int i;
+    for (i=addr; vm->stack[i]; i++) {
+        printf("%c", (char)vm->stack[i]);
+    }
+}
+
+void prh(machine *vm) {
+    float addr = machine_pop(vm);
+    printf("%c", vm->heap[(int)addr]);
+}
+
+void prc(machine *vm) {
+    float n = machine_pop(vm);
+    printf("%c", (char)n);
+}
+
+// output goes through stdio's buffer, which is flushed by flush, before
+// every read and when the program exits
+void flush(machine *vm) {
+    fflush(stdout);
+}
+
+void prend(machine *vm) {
+    printf("%s", vm->newline);
+}
+
+void getch(machine *vm) {
+    char ch = getchar();
+    if (ch == '\r') {
+        ch = getchar();
+    }
+    machine_check_interrupt(vm);
+    machine_push(vm, ch);
+}
+
+void float_to_int(machine *vm) {
+    float n = machine_pop(vm);
+    machine_push(vm, (int)n);
+}
+
+// reads the letters of a YARN loaded on the stack as MAEK does, up to its
+// first 0: -?[0-9]+, or for a NUMBAR also one . among the digits. Anything
+// else panics. utils.rs's parse_number reads numbers the same way, and wraps
+// the whole part around as it does, which unsigned arithmetic defines.
+float parse_loaded_number(machine *vm, int size, bool numbar) {
+    float *letters = &vm->stack[vm->stack_pointer - size];
+    int length = 0;
+    while (length < size && letters[length] != 0) {
+        length++;
+    }
+
+    int i = 0;
+    bool is_negative = length > 0 && letters[0] == 45;
+    if (is_negative) {
+        i++;
+    }
+
+    unsigned int integer_part = 0;
+    float fraction_part = 0.0;
+    bool found_decimal_point = false;
+    bool found_digit = false;
+    float divisor_for_fraction = 1.0;
+    bool valid = true;
+    for (; i < length; i++) {
+        int code = letters[i];
+
+        if (code == 46 && numbar && !found_decimal_point) {
+            found_decimal_point = true;
+        } else if (code >= 48 && code <= 57) {
+            unsigned int digit = code - 48;
+            found_digit = true;
+            if (!found_decimal_point) {
+                integer_part = integer_part * 10 + digit;
+            } else {
+                divisor_for_fraction *= 10.0;
+                fraction_part += digit / divisor_for_fraction;
+            }
+        } else {
+            valid = false;
+            break;
+        }
+    }
+
+    if (!valid || !found_digit) {
+        printf("panic: ");
+        printf("cannot convert \"");
+        for (int j = 0; j < length; j++) {
+            printf("%c", (char)letters[j]);
+        }
+        printf("\" to %s\n", numbar ? "NUMBAR" : "NUMBER");
+        machine_abort(vm, 1);
+    }
+
+    for (int j = 0; j < size; j++) {
+        machine_pop(vm);
+    }
+
+    float result = (int)integer_part + fraction_part;
+    if (is_negative) {
+        result = -result;
+    }
+    return result;
+}
+
+void string_to_int(machine *vm) {
+    int size = machine_pop(vm);
+    machine_str_load(vm, size);
+    machine_push(vm, parse_loaded_number(vm, size, false));
+}
+
+void int_to_float(machine *vm) {
+    int n = machine_pop(vm);
+    machine_push(vm, (float)n);
+}
+
+void string_to_float(machine *vm) {
+    int size = machine_pop(vm);
+    machine_str_load(vm, size);
+    machine_push(vm, parse_loaded_number(vm, size, true));
+}
+
+void int_to_string(machine *vm) {
+    int n = machine_pop(vm);
+    char buffer[32];
+    for (int i = 0; i < 32; i++) {
+        buffer[i] = 0;
+    }
+    sprintf(buffer, "%d", n);
+    machine_push(vm, 32);
+    int addr = machine_str_alloc(vm);
+    for (int i = 0; i < 32; i++) {
+        machine_push(vm, buffer[i]);
+    }
+    machine_push(vm, (float)addr);
+    machine_str_store(vm, 32);
+}
+
+void float_to_string(machine *vm) {
+    float n = machine_pop(vm);
+    char buffer[32];
+    for (int i = 0; i < 32; i++) {
+        buffer[i] = 0;
+    }
+    format_float(buffer, sizeof(buffer), n);
+    machine_push(vm, 32);
+    int addr = machine_str_alloc(vm);
+    for (int i = 0; i < 32; i++) {
+        machine_push(vm, buffer[i]);
+    }
+    machine_push(vm, (float)addr);
+    machine_str_store(vm, 32);
+}
+
+// pops a TROOF, pushing the address of a new YARN of 5 letters holding WIN
+// or FAIL
+void troof_to_string(machine *vm) {
+    float troof = machine_pop(vm);
+    char buffer[5] = {0};
+    strcpy(buffer, troof ? "WIN" : "FAIL");
+    machine_push(vm, 5);
+    int addr = machine_str_alloc(vm);
+    for (int i = 0; i < 5; i++) {
+        machine_push(vm, buffer[i]);
+    }
+    machine_push(vm, (float)addr);
+    machine_str_store(vm, 5);
+}
+
+// pops the size and the address of a YARN, pushing how many letters come
+// before the zeros padding it
+void yarn_length(machine *vm) {
+    int size = machine_pop(vm);
+    int addr = machine_pop(vm);
+
+    machine_push(vm, addr);
+    machine_str_load(vm, size);
+    int length = 0;
+    for (int i = 0; i < size; i++) {
+        if (vm->stack[vm->stack_pointer - size + i] == 0) {
+            break;
+        }
+        length++;
+    }
+    for (int i = 0; i < size; i++) {
+        machine_pop(vm);
+    }
+
+    machine_push(vm, length);
+}
+
+void print_string(machine *vm) {
+    machine_str_print(vm, machine_pop(vm));
+}
+
+// set by every read, GIMMEH copies it into IT
+bool input_eof = false;
+
+void read_succeeded(machine *vm) {
+    machine_push(vm, !input_eof);
+}
+
+void read_char(machine *vm) {
+    fflush(stdout); // show any prompt before waiting for input
+    int ch = getchar();
+    if (ch == '\r') {
+        ch = getchar();
+    }
+    machine_check_interrupt(vm);
+    input_eof = ch == EOF;
+    machine_push(vm, ch);
+}
+
+void read_letter(machine *vm) {
+    machine_push(vm, 1);
+    int addr = machine_str_alloc(vm);
+    read_char(vm);
+    if (input_eof) {
+        // an empty letter rather than the EOF code
+        machine_pop(vm);
+        machine_push(vm, 0);
+    }
+    machine_push(vm, (float)addr);
+    machine_str_store(vm, 1);
+}
+
+void read_string(machine *vm) {
+    char buffer[256];
+    for (int i = 0; i < 256; i++) {
+        buffer[i] = 0;
+    }
+    fflush(stdout); // show any prompt before waiting for input
+
+    // at the end of input the buffer stays empty
+    input_eof = fgets(buffer, sizeof(buffer), stdin) == NULL;
+    machine_check_interrupt(vm);
+
+    machine_push(vm, 256);
+    int addr = machine_str_alloc(vm);
+    for (int i = 0; i < 256; i++) {
+        if (buffer[i] == '\n') {
+            machine_push(vm, 0);
+            continue;
+        }
+        machine_push(vm, (float)buffer[i]);
+    }
+    machine_push(vm, (float)addr);
+    machine_str_store(vm, 256);
+}
+
+// a bukkit is a 5 float header [length, capacity, entries, slots, references]
+// on the heap. entries holds [key, value, size] triples in insertion order,
+// where size is the length of a YARN the bukkit owns, -1 for a reference to
+// another bukkit and 0 for any other value. slots is an open addressing table
+// of entry index + 1 keyed by the slot's key
+const int BUKKIT_HEADER = 5;
+const int BUKKIT_REFERENCE = -1;
+
+float heap_get(machine *vm, int addr, int index) {
+    machine_load_at(vm, addr, 1, index);
+    return machine_pop(vm);
+}
+
+void heap_set(machine *vm, int addr, int index, float value) {
+    machine_push(vm, value);
+    machine_store_at(vm, addr, 1, index);
+}
+
+int heap_allocate(machine *vm, int floats) {
+    machine_push(vm, floats);
+    machine_allocate(vm);
+    return machine_pop(vm);
+}
+
+void heap_free(machine *vm, int addr, int floats) {
+    machine_push(vm, floats);
+    machine_push(vm, addr);
+    machine_free(vm);
+}
+
+int yarn_allocate(machine *vm, int letters) {
+    machine_push(vm, letters);
+    machine_str_alloc(vm);
+    return machine_pop(vm);
+}
+
+void yarn_free(machine *vm, int addr, int letters) {
+    machine_push(vm, letters);
+    machine_push(vm, addr);
+    machine_str_free(vm);
+}
+
+// the index of the entry for key, or -1 if there isn't one
+int bukkit_find(machine *vm, int bukkit, int key) {
+    int capacity = heap_get(vm, bukkit, 1);
+    int entries = heap_get(vm, bukkit, 2);
+    int slots = heap_get(vm, bukkit, 3);
+
+    for (int i = key % capacity;; i = (i + 1) % capacity) {
+        int entry = heap_get(vm, slots, i) - 1;
+        if (entry == -1) {
+            return -1;
+        }
+        if (heap_get(vm, entries, entry * 3) == key) {
+            return entry;
+        }
+    }
+}
+
+void bukkit_index(machine *vm, int bukkit, int key, int entry) {
+    int capacity = heap_get(vm, bukkit, 1);
+    int slots = heap_get(vm, bukkit, 3);
+
+    int i = key % capacity;
+    while (heap_get(vm, slots, i) != 0) {
+        i = (i + 1) % capacity;
+    }
+    heap_set(vm, slots, i, entry + 1);
+}
+
+void bukkit_grow(machine *vm, int bukkit) {
+    int length = heap_get(vm, bukkit, 0);
+    int capacity = heap_get(vm, bukkit, 1);
+    int entries = heap_get(vm, bukkit, 2);
+    int slots = heap_get(vm, bukkit, 3);
+
+    int new_entries = heap_allocate(vm, capacity * 2 * 3);
+    for (int i = 0; i < length * 3; i++) {
+        heap_set(vm, new_entries, i, heap_get(vm, entries, i));
+    }
+    heap_free(vm, entries, capacity * 3);
+    heap_free(vm, slots, capacity);
+
+    heap_set(vm, bukkit, 1, capacity * 2);
+    heap_set(vm, bukkit, 2, new_entries);
+    heap_set(vm, bukkit, 3, heap_allocate(vm, capacity * 2));
+    for (int i = 0; i < length; i++) {
+        bukkit_index(vm, bukkit, heap_get(vm, new_entries, i * 3), i);
+    }
+}
+
+int bukkit_create(machine *vm, int capacity) {
+    int bukkit = heap_allocate(vm, BUKKIT_HEADER);
+    heap_set(vm, bukkit, 0, 0);
+    heap_set(vm, bukkit, 1, capacity);
+    heap_set(vm, bukkit, 2, heap_allocate(vm, capacity * 3));
+    heap_set(vm, bukkit, 3, heap_allocate(vm, capacity));
+    heap_set(vm, bukkit, 4, 1);
+    return bukkit;
+}
+
+// drops a reference, freeing the bukkit and everything it owns with the last one
+void bukkit_drop(machine *vm, int bukkit) {
+    int references = heap_get(vm, bukkit, 4) - 1;
+    if (references > 0) {
+        heap_set(vm, bukkit, 4, references);
+        return;
+    }
+
+    int length = heap_get(vm, bukkit, 0);
+    int capacity = heap_get(vm, bukkit, 1);
+    int entries = heap_get(vm, bukkit, 2);
+
+    for (int i = 0; i < length; i++) {
+        int value = heap_get(vm, entries, i * 3 + 1);
+        int size = heap_get(vm, entries, i * 3 + 2);
+        if (size == BUKKIT_REFERENCE) {
+            bukkit_drop(vm, value);
+        } else if (size > 0) {
+            yarn_free(vm, value, size);
+        }
+    }
+
+    heap_free(vm, entries, capacity * 3);
+    heap_free(vm, heap_get(vm, bukkit, 3), capacity);
+    heap_free(vm, bukkit, BUKKIT_HEADER);
+}
+
+int bukkit_clone(machine *vm, int bukkit) {
+    int length = heap_get(vm, bukkit, 0);
+    int capacity = heap_get(vm, bukkit, 1);
+    int entries = heap_get(vm, bukkit, 2);
+
+    int clone = bukkit_create(vm, capacity);
+    int clone_entries = heap_get(vm, clone, 2);
+    heap_set(vm, clone, 0, length);
+
+    for (int i = 0; i < length; i++) {
+        int key = heap_get(vm, entries, i * 3);
+        float value = heap_get(vm, entries, i * 3 + 1);
+        int size = heap_get(vm, entries, i * 3 + 2);
+
+        if (size == BUKKIT_REFERENCE) {
+            value = bukkit_clone(vm, value);
+        } else if (size > 0) {
+            int yarn = yarn_allocate(vm, size);
+            for (int j = 0; j < size; j++) {
+                yarn_set(vm, yarn, j, yarn_get(vm, value, j));
+            }
+            value = yarn;
+        }
+
+        heap_set(vm, clone_entries, i * 3, key);
+        heap_set(vm, clone_entries, i * 3 + 1, value);
+        heap_set(vm, clone_entries, i * 3 + 2, size);
+        bukkit_index(vm, clone, key, i);
+    }
+
+    return clone;
+}
+
+void bukkit_new(machine *vm) {
+    machine_push(vm, bukkit_create(vm, 4));
+}
+
+// adds a reference to the bukkit on top of the stack, leaving it there
+void bukkit_retain(machine *vm) {
+    int bukkit = machine_pop(vm);
+    heap_set(vm, bukkit, 4, heap_get(vm, bukkit, 4) + 1);
+    machine_push(vm, bukkit);
+}
+
+void bukkit_release(machine *vm) {
+    bukkit_drop(vm, machine_pop(vm));
+}
+
+// replaces the bukkit on top of the stack with a deep copy of it
+void bukkit_kopy(machine *vm) {
+    int bukkit = machine_pop(vm);
+    machine_push(vm, bukkit_clone(vm, bukkit));
+    bukkit_drop(vm, bukkit);
+}
+
+// pops the key, the bukkit, the value's size and the value, taking over the
+// value if it is a YARN or a bukkit reference
+void bukkit_put(machine *vm) {
+    int key = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+    int size = machine_pop(vm);
+    float value = machine_pop(vm);
+
+    int entry = bukkit_find(vm, bukkit, key);
+    if (entry == -1) {
+        int length = heap_get(vm, bukkit, 0);
+        // keep the slot table at most three quarters full
+        if ((length + 1) * 4 > heap_get(vm, bukkit, 1) * 3) {
+            bukkit_grow(vm, bukkit);
+        }
+
+        entry = length;
+        heap_set(vm, bukkit, 0, length + 1);
+        heap_set(vm, heap_get(vm, bukkit, 2), entry * 3, key);
+        bukkit_index(vm, bukkit, key, entry);
+    } else {
+        int entries = heap_get(vm, bukkit, 2);
+        int old_value = heap_get(vm, entries, entry * 3 + 1);
+        int old_size = heap_get(vm, entries, entry * 3 + 2);
+        if (old_size == BUKKIT_REFERENCE) {
+            bukkit_drop(vm, old_value);
+        } else if (old_size > 0) {
+            yarn_free(vm, old_value, old_size);
+        }
+    }
+
+    int entries = heap_get(vm, bukkit, 2);
+    heap_set(vm, entries, entry * 3 + 1, value);
+    heap_set(vm, entries, entry * 3 + 2, size);
+}
+
+// pops the key and the bukkit, pushing the slot's value
+void bukkit_get(machine *vm) {
+    int key = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+
+    int entry = bukkit_find(vm, bukkit, key);
+    if (entry == -1) {
+        printf("panic: ");
+        printf("bukkit has no slot with key %d\n", key);
+        machine_abort(vm, 1);
+    }
+
+    machine_push(vm, heap_get(vm, heap_get(vm, bukkit, 2), entry * 3 + 1));
+}
+
+void bukkit_length(machine *vm) {
+    machine_push(vm, heap_get(vm, machine_pop(vm), 0));
+}
+
+// pops the index and the bukkit, pushing whether the bukkit has that many slots
+void bukkit_has_index(machine *vm) {
+    int index = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+    machine_push(vm, index < heap_get(vm, bukkit, 0));
+}
+
+// pops the index and the bukkit, pushing the value of the index'th slot
+void bukkit_value(machine *vm) {
+    int index = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+    machine_push(vm, heap_get(vm, heap_get(vm, bukkit, 2), index * 3 + 1));
+}
+
+// pops a YARN, the index and the bukkit, copying the index'th slot into the
+// YARN, which must be at least as long
+void bukkit_copy_yarn(machine *vm) {
+    int yarn = machine_pop(vm);
+    int index = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+
+    int entries = heap_get(vm, bukkit, 2);
+    int value = heap_get(vm, entries, index * 3 + 1);
+    int size = heap_get(vm, entries, index * 3 + 2);
+    for (int i = 0; i < size; i++) {
+        yarn_set(vm, yarn, i, yarn_get(vm, value, i));
+    }
+}
+
+// pops a stack position and drops everything pushed after it
+void stack_unwind(machine *vm) {
+    int position = machine_pop(vm);
+    while (vm->stack_pointer > position + 1) {
+        machine_pop(vm);
+    }
+}
+
+// pops the index of the statement about to run in a traced program, which
+// only the interpreter's debugger makes use of
+void trace(machine *vm) {
+    machine_pop(vm);
+}
+
+// pops the line of an ASSURE whose TROOF was FAIL
+void assure_failed(machine *vm) {
+    int line = machine_pop(vm);
+    printf("panic: ");
+    printf("ASSURE failed at line %d\n", line);
+    machine_abort(vm, 1);
+}
+
+// pops the line of an ASSURE whose TROOF was FAIL and the size and address
+// of its message
+void assure_failed_sayin(machine *vm) {
+    int line = machine_pop(vm);
+    int size = machine_pop(vm);
+    int message = machine_pop(vm);
+    printf("panic: ");
+    printf("ASSURE failed at line %d: ", line);
+    for (int i = 0; i < size; i++) {
+        int ch = yarn_get(vm, message, i);
+        if (ch == 0) {
+            break;
+        }
+        printf("%c", (char)ch);
+    }
+    printf("\n");
+    machine_abort(vm, 1);
+}
+
+// the DUMPZ helpers each pop a value of their type (a YARN's size first) and
+// print it after its type
+
+void yarn_compare(machine *vm) {
+    int right_size = machine_pop(vm);
+    int right = machine_pop(vm);
+    int left_size = machine_pop(vm);
+    int left = machine_pop(vm);
+
+    int longest = left_size > right_size ? left_size : right_size;
+    int order = 0;
+    for (int i = 0; i < longest; i++) {
+        float a = i < left_size ? yarn_get(vm, left, i) : 0;
+        float b = i < right_size ? yarn_get(vm, right, i) : 0;
+        if (a != b) {
+            order = a < b ? -1 : 1;
+            break;
+        }
+        if (a == 0) {
+            break;
+        }
+    }
+
+    machine_push(vm, order);
+}
+
+// the letters of a YARN before its first 0
+int text_length(machine *vm, int addr, int size) {
+    int length = 0;
+    while (length < size && yarn_get(vm, addr, length) != 0) {
+        length++;
+    }
+    return length;
+}
+
+// pops the size and address of a YARN of one letter and pushes its code
+void letter_code(machine *vm) {
+    int size = machine_pop(vm);
+    int addr = machine_pop(vm);
+    int length = text_length(vm, addr, size);
+    if (length != 1) {
+        printf("panic: ");
+        printf("CODEZ OF needs a YARN of one letter, not %d\n", length);
+        machine_abort(vm, 1);
+    }
+    machine_push(vm, yarn_get(vm, addr, 0));
+}
+
+// pops a character code and pushes a YARN of just that letter
+void code_letter(machine *vm) {
+    int code = machine_pop(vm);
+    if (code < 1 || code > 0x10FFFF) {
+        printf("panic: ");
+        printf("LETTAR OF %d is not a letter\n", code);
+        machine_abort(vm, 1);
+    }
+    machine_push(vm, 1);
+    int addr = machine_str_alloc(vm);
+    yarn_set(vm, addr, 0, code);
+}
+
+// SPLITZ keys its pieces from here up, past the key of any named slot
+const int PIECE_KEY = 1 << 20;
+
+// adds letters [start, end) of a YARN to a SPLITZ bukkit as its index'th
+// piece, as long as the whole YARN
+void split_piece(machine *vm, int bukkit, int index, int yarn, int size, int start, int end) {
+    int piece_size = size > 0 ? size : 1;
+    int piece = yarn_allocate(vm, piece_size);
+    for (int i = 0; i < piece_size; i++) {
+        yarn_set(vm, piece, i, start + i < end ? yarn_get(vm, yarn, start + i) : 0);
+    }
+
+    machine_push(vm, piece);
+    machine_push(vm, piece_size);
+    machine_push(vm, bukkit);
+    machine_push(vm, PIECE_KEY + index);
+    bukkit_put(vm);
+}
+
+// pops the size and address of a separator and of a YARN, pushing a bukkit
+// of the pieces of the YARN between separators, or of its letters if the
+// separator is empty
+void yarn_split(machine *vm) {
+    int separator_size = machine_pop(vm);
+    int separator = machine_pop(vm);
+    int size = machine_pop(vm);
+    int yarn = machine_pop(vm);
+
+    int length = text_length(vm, yarn, size);
+    int separator_length = text_length(vm, separator, separator_size);
+
+    int bukkit = bukkit_create(vm, 4);
+    int pieces = 0;
+    if (separator_length == 0) {
+        for (int i = 0; i < length; i++) {
+            split_piece(vm, bukkit, pieces++, yarn, size, i, i + 1);
+        }
+    } else {
+        int start = 0;
+        int i = 0;
+        while (i + separator_length <= length) {
+            bool matched = true;
+            for (int j = 0; j < separator_length; j++) {
+                if (yarn_get(vm, yarn, i + j) != yarn_get(vm, separator, j)) {
+                    matched = false;
+                    break;
+                }
+            }
+
+            if (matched) {
+                split_piece(vm, bukkit, pieces++, yarn, size, start, i);
+                i += separator_length;
+                start = i;
+            } else {
+                i++;
+            }
+        }
+        split_piece(vm, bukkit, pieces, yarn, size, start, length);
+    }
+    machine_push(vm, bukkit);
+}
+
+// pops the size and address of a separator, a size and a bukkit of YARNs,
+// pushing a YARN of that size holding the bukkit's YARNs in order with the
+// separator between each
+void yarn_join(machine *vm) {
+    int separator_size = machine_pop(vm);
+    int separator = machine_pop(vm);
+    int size = machine_pop(vm);
+    int bukkit = machine_pop(vm);
+
+    int separator_length = text_length(vm, separator, separator_size);
+    int joined = yarn_allocate(vm, size);
+    int length = heap_get(vm, bukkit, 0);
+    int entries = heap_get(vm, bukkit, 2);
+
+    int at = 0;
+    for (int i = 0; i < length; i++) {
+        if (i > 0) {
+            for (int j = 0; j < separator_length && at < size; j++) {
+                yarn_set(vm, joined, at++, yarn_get(vm, separator, j));
+            }
+        }
+
+        int value = heap_get(vm, entries, i * 3 + 1);
+        int value_size = heap_get(vm, entries, i * 3 + 2);
+        int value_length = text_length(vm, value, value_size);
+        for (int j = 0; j < value_length && at < size; j++) {
+            yarn_set(vm, joined, at++, yarn_get(vm, value, j));
+        }
+    }
+    while (at < size) {
+        yarn_set(vm, joined, at++, 0);
+    }
+    machine_push(vm, joined);
+}
+
+// pops the size and address of a pattern and of a YARN, pushing whether all
+// of the YARN matches the pattern, where * is any letters and ? any one
+// letter. A * that fails to match goes back to the last one and lets it take
+// one more letter, so there is no exponential backtracking.
+void yarn_matchez(machine *vm) {
+    int pattern_size = machine_pop(vm);
+    int pattern = machine_pop(vm);
+    int size = machine_pop(vm);
+    int yarn = machine_pop(vm);
+
+    int pattern_length = text_length(vm, pattern, pattern_size);
+    int length = text_length(vm, yarn, size);
+    int t = 0, p = 0, star_p = -1, star_t = 0;
+
+    while (t < length) {
+        float letter = yarn_get(vm, yarn, t);
+        float wanted = p < pattern_length ? yarn_get(vm, pattern, p) : 0;
+        if (p < pattern_length && wanted == '*') {
+            star_p = p++;
+            star_t = t;
+        } else if (p < pattern_length && (wanted == '?' || wanted == letter)) {
+            t++;
+            p++;
+        } else if (star_p != -1) {
+            p = star_p + 1;
+            t = ++star_t;
+        } else {
+            machine_push(vm, 0);
+            return;
+        }
+    }
+
+    while (p < pattern_length && yarn_get(vm, pattern, p) == '*') {
+        p++;
+    }
+    machine_push(vm, p == pattern_length);
+}
+
+// writes a value of `type` as DUMPZ shows it, without the newline. Only
+// `letters` letters of a YARN are shown, up to its first NUL. A bukkit
+// shows how many slots it has rather than what is in them, whose types only
+// the compiler knows.
+void write_value(machine *vm, FILE *out, const char *type, int letters, float value) {
+    if (strcmp(type, "NUMBER") == 0) {
+        fprintf(out, "NUMBER: %d", (int)value);
+    } else if (strcmp(type, "NUMBAR") == 0) {
+        char buffer[64];
+        format_float(buffer, sizeof(buffer), value);
+        fprintf(out, "NUMBAR: %s", buffer);
+    } else if (strcmp(type, "YARN") == 0) {
+        int addr = value;
+        int length = 0;
+        while (length < letters && yarn_get(vm, addr, length) != 0) {
+            length++;
+        }
+        fprintf(out, "YARN(%d): \"", length);
+        for (int i = 0; i < length; i++) {
+            fputc((char)yarn_get(vm, addr, i), out);
+        }
+        fputc('"', out);
+    } else if (strcmp(type, "TROOF") == 0) {
+        fprintf(out, "TROOF: %s", value ? "WIN" : "FAIL");
+    } else if (strcmp(type, "BUKKIT") == 0) {
+        fprintf(out, "BUKKIT(%d)", (int)heap_get(vm, value, 0));
+    } else {
+        fprintf(out, "NOOB");
+    }
+}
+
+void dump_value(machine *vm, const char *type, int letters) {
+    write_value(vm, stdout, type, letters, machine_pop(vm));
+    printf("\n");
+}
+
+void dump_number(machine *vm) {
+    dump_value(vm, "NUMBER", 0);
+}
+
+void dump_numbar(machine *vm) {
+    dump_value(vm, "NUMBAR", 0);
+}
+
+void dump_yarn(machine *vm) {
+    int size = machine_pop(vm);
+    dump_value(vm, "YARN", size);
+}
+
+void dump_troof(machine *vm) {
+    dump_value(vm, "TROOF", 0);
+}
+
+void dump_noob(machine *vm) {
+    dump_value(vm, "NOOB", 0);
+}
+
+void dump_bukkit(machine *vm) {
+    dump_value(vm, "BUKKIT", 0);
+}
+
+// pops the index of a variable in HOOK_NAMES, naming it before DUMPZ shows
+// its value
+void dump_name(machine *vm) {
+    printf("%s = ", HOOK_NAMES[(int)machine_pop(vm)].name);
+}
+
+// writes the variable a hook names, given the stack slot the hook points
+// at. The hook may have been handed to another value once its variable went
+// out of scope, so YARNs and bukkits are only followed to allocations of
+// their kind, whose letters are shown as far as the allocation goes.
+void write_variable(machine *vm, const hook_name *variable, float slot) {
+    if (slot < 0 || slot >= vm->stack_pointer) {
+        fprintf(stderr, "(not set)");
+        return;
+    }
+    float value = vm->stack[(int)slot];
+
+    bool yarn = strcmp(variable->type, "YARN") == 0;
+    if (yarn || strcmp(variable->type, "BUKKIT") == 0) {
+        int addr = value >= 0 && value < vm->heap_size ? value : -1;
+        if (addr == -1 || addr != value || !vm->blocks[addr] || vm->yarns[addr] != yarn) {
+            fprintf(stderr, "(not set)");
+            return;
+        }
+        write_value(vm, stderr, variable->type, vm->blocks[addr] / vm->yarn_width, value);
+        return;
+    }
+    write_value(vm, stderr, variable->type, 0, value);
+}
+
+// lists the variables of the running calls on stderr once a panic has been
+// printed, the innermost call first. Release builds name no variables.
+void dump_variables(machine *vm) {
+    if (HOOK_NAME_COUNT == 0) {
+        return;
+    }
+    fflush(stdout);
+
+    // each call's hooks end right below the slot holding the base of the
+    // frame before the next call's
+    int base = vm->hook_base, top = vm->hook_top;
+    for (int depth = vm->call_depth; depth >= 0; depth--) {
+        int function = depth > 0 ? vm->calls[depth - 1] : 0;
+        bool named = false;
+        for (int hook = 0; hook < top - base; hook++) {
+            const hook_name *variable = machine_hook_name(function, hook);
+            if (variable == NULL) {
+                continue;
+            }
+            if (!named) {
+                fprintf(stderr, "variables of %s:\n", FUNCTION_NAMES[function]);
+                named = true;
+            }
+            fprintf(stderr, "    %s = ", variable->name);
+            write_variable(vm, variable, vm->hooks[base + hook]);
+            fprintf(stderr, "\n");
+        }
+        if (base > 0) {
+            top = base - 1;
+            base = vm->hooks[top];
+        }
+    }
+}
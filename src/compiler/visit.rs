@@ -1,10 +1,152 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::compiler::ir;
+use crate::ice;
+use crate::lexer::lexer;
+use crate::lexer::symbol::Symbol;
 use crate::lexer::tokens;
 use crate::parser::ast;
 use crate::parser::ast::VariableAssignmentNodeVariableOption;
 use crate::parser::parser;
+use crate::parser::printer;
+use crate::source::SourceMap;
+use crate::utils;
+
+/// The slot types of a bukkit made by SPLITZ have its pieces under this name,
+/// which no slot can have, standing for any number of slots
+pub const ANY_SLOT: &str = "*";
+/// SPLITZ keys its pieces from here up, past the key of any named slot
+pub const PIECE_KEY: i32 = 1 << 20;
+
+/// The statements of each branch of an If
+fn branches(if_stmt: &ast::IfStatementNode) -> impl Iterator<Item = &ast::StatementNode> {
+    if_stmt
+        .statements
+        .iter()
+        .chain(
+            if_stmt
+                .else_ifs
+                .iter()
+                .flat_map(|else_if| &else_if.statements),
+        )
+        .chain(if_stmt.else_.iter().flatten())
+}
+
+/// Whether a statement can leave the WTF?, loop or function it is in: a
+/// GTFO or FOUND YR, or an If with one in a branch. A GTFO in a nested
+/// WTF? or loop leaves that instead, but a FOUND YR still leaves them all.
+fn leaves(statement: &ast::StatementNode) -> bool {
+    match &statement.value {
+        ast::StatementNodeValueOption::GTFOStatement(_) => true,
+        ast::StatementNodeValueOption::IfStatement(if_stmt) => branches(if_stmt).any(leaves),
+        _ => returns(statement),
+    }
+}
+
+/// Whether a statement can FOUND YR out of the function it is in
+fn returns(statement: &ast::StatementNode) -> bool {
+    match &statement.value {
+        ast::StatementNodeValueOption::ReturnStatement(_) => true,
+        ast::StatementNodeValueOption::IfStatement(if_stmt) => branches(if_stmt).any(returns),
+        ast::StatementNodeValueOption::SwitchStatement(switch) => switch
+            .cases
+            .iter()
+            .flat_map(|case| &case.statements)
+            .chain(switch.default.iter().flatten())
+            .any(returns),
+        ast::StatementNodeValueOption::LoopStatement(loop_stmt) => {
+            loop_stmt.statements.iter().any(returns)
+        }
+        ast::StatementNodeValueOption::WatchinLoopStatement(loop_stmt) => {
+            loop_stmt.statements.iter().any(returns)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a statement always FOUND YRs, so that nothing after it runs
+fn always_returns(statement: &ast::StatementNode) -> bool {
+    match &statement.value {
+        ast::StatementNodeValueOption::ReturnStatement(_) => true,
+        ast::StatementNodeValueOption::IfStatement(if_stmt) => {
+            if_stmt.statements.iter().any(always_returns)
+                && if_stmt
+                    .else_
+                    .as_ref()
+                    .is_some_and(|else_| else_.iter().any(always_returns))
+        }
+        _ => false,
+    }
+}
+
+/// Whether an expression calls a function anywhere in it
+fn calls_function(expression: &ast::ExpressionNode) -> bool {
+    matches!(
+        expression.value,
+        ast::ExpressionNodeValueOption::FunctionCallExpression(_)
+    ) || expression.operands().into_iter().any(calls_function)
+}
+
+/// The type a type keyword of a function definition stands for, the size
+/// of a YARN being left to the calls
+fn declared_type(token: &ast::TokenNode) -> Types {
+    match token.value().to_name().as_str() {
+        "Word_NUMBER" => Types::Number,
+        "Word_NUMBAR" => Types::Numbar,
+        "Word_TROOF" => Types::Troof,
+        "Word_YARN" => Types::Yarn(0),
+        "Word_NOOB" => Types::Noob,
+        _ => panic!("Unexpected type"),
+    }
+}
+
+/// Whether the types of the arguments of two calls make the same instance,
+/// a YARN's size included
+fn same_arguments(a: &[Types], b: &[Types]) -> bool {
+    a.iter().zip(b).all(|types| match types {
+        (Types::Yarn(a), Types::Yarn(b)) => a == b,
+        (a, b) => a.equals(b),
+    })
+}
+
+/// Whether two sets of types are the same, a YARN's size and which bukkit
+/// a BUKKIT is included
+fn same_types<K: Ord>(a: &BTreeMap<K, Types>, b: &BTreeMap<K, Types>) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|((a_key, a), (b_key, b))| {
+            a_key == b_key
+                && match (a, b) {
+                    (Types::Yarn(a), Types::Yarn(b)) => a == b,
+                    (Types::Bukkit(a), Types::Bukkit(b)) => a == b,
+                    (a, b) => a.equals(b),
+                }
+        })
+}
+
+/// The C identifier the IR function of a LOLCODE one is named after, any
+/// letter that is not ASCII written as its code point
+fn function_name(name: &Symbol) -> String {
+    let mut mangled = String::from("lol_");
+    for c in name.to_string().chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            mangled.push(c);
+        } else {
+            mangled.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    mangled
+}
+
+/// The type of a literal, None for any other expression
+fn literal_type(expression: &ast::ExpressionNode) -> Option<Types> {
+    match &expression.value {
+        ast::ExpressionNodeValueOption::NumberValue(_) => Some(Types::Number),
+        ast::ExpressionNodeValueOption::NumbarValue(_) => Some(Types::Numbar),
+        ast::ExpressionNodeValueOption::TroofValue(_) => Some(Types::Troof),
+        ast::ExpressionNodeValueOption::YarnValue(_) => Some(Types::Yarn(0)),
+        _ => None,
+    }
+}
 
 #[derive(Clone)]
 pub enum Types {
@@ -13,6 +155,7 @@ pub enum Types {
     Yarn(i32), // size of the string
     Troof,
     Noob,
+    Bukkit(usize), // index of the bukkit's slot types in Visitor::bukkits
 }
 
 impl Types {
@@ -23,6 +166,7 @@ impl Types {
             Types::Yarn(_) => "YARN".to_string(),
             Types::Troof => "TROOF".to_string(),
             Types::Noob => "NOOB".to_string(),
+            Types::Bukkit(_) => "BUKKIT".to_string(),
         }
     }
 
@@ -48,10 +192,12 @@ impl Types {
                 Types::Noob => true,
                 _ => false,
             },
+            Types::Bukkit(_) => matches!(other, Types::Bukkit(_)),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct VariableValue {
     pub hook: i32,
     pub type_: Types,
@@ -66,17 +212,23 @@ impl VariableValue {
         match self.type_ {
             Types::Yarn(size) => {
                 vec![
-                    ir::IRStatement::Push(size as f32),
+                    ir::IRStatement::Push(ir::Value::Int(size)),
                     ir::IRStatement::RefHook(self.hook),
                     ir::IRStatement::Copy,
-                    ir::IRStatement::Free,
+                    ir::IRStatement::StrFree,
                 ]
             }
+            Types::Bukkit(_) => vec![
+                ir::IRStatement::RefHook(self.hook),
+                ir::IRStatement::Copy,
+                ir::IRStatement::CallForeign("bukkit_release".to_string()),
+            ],
             _ => vec![],
         }
     }
 }
 
+#[derive(Clone)]
 pub struct VariableData {
     pub value: VariableValue,
 }
@@ -91,15 +243,20 @@ impl VariableData {
             Types::Yarn(size) => {
                 if size >= 0 {
                     vec![
-                        ir::IRStatement::Push(size as f32),
+                        ir::IRStatement::Push(ir::Value::Int(size)),
                         ir::IRStatement::RefHook(self.value.hook),
                         ir::IRStatement::Copy,
-                        ir::IRStatement::Free,
+                        ir::IRStatement::StrFree,
                     ]
                 } else {
                     vec![]
                 }
             }
+            Types::Bukkit(_) => vec![
+                ir::IRStatement::RefHook(self.value.hook),
+                ir::IRStatement::Copy,
+                ir::IRStatement::CallForeign("bukkit_release".to_string()),
+            ],
             _ => vec![],
         }
     }
@@ -135,19 +292,30 @@ impl VariableData {
             }
             Types::Yarn(size) => {
                 let ir = vec![
-                    ir::IRStatement::Push(size as f32),
-                    ir::IRStatement::Allocate,
+                    ir::IRStatement::Push(ir::Value::Int(size)),
+                    ir::IRStatement::StrAlloc,
                     ir::IRStatement::Hook(hook),
                     ir::IRStatement::RefHook(self.value.hook),
                     ir::IRStatement::Copy,
-                    ir::IRStatement::Load(size),
+                    ir::IRStatement::StrLoad(size),
                     ir::IRStatement::RefHook(hook),
                     ir::IRStatement::Copy,
-                    ir::IRStatement::Store(size),
+                    ir::IRStatement::StrStore(size),
                 ];
 
                 (VariableValue::new(hook, Types::Yarn(size)), ir)
             }
+            Types::Bukkit(bukkit) => {
+                // bukkits are shared, the copy is another reference to the same one
+                let ir = vec![
+                    ir::IRStatement::RefHook(self.value.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::CallForeign("bukkit_retain".to_string()),
+                    ir::IRStatement::Hook(hook),
+                ];
+
+                (VariableValue::new(hook, Types::Bukkit(bukkit)), ir)
+            }
             _ => panic!("Unexpected type"),
         }
     }
@@ -182,14 +350,23 @@ impl VariableData {
 
                 ir
             }
-            _ => panic!("Unexpected type"),
+            Types::Bukkit(_) => vec![
+                // assumes that the reference is already on the stack
+                ir::IRStatement::RefHook(self.value.hook),
+                ir::IRStatement::Mov,
+            ],
+            Types::Noob => vec![
+                // assumes that the value is already on the stack
+                ir::IRStatement::RefHook(self.value.hook),
+                ir::IRStatement::Mov,
+            ],
         }
     }
 }
 
 pub struct Scope<'a> {
     pub name: String,
-    pub variables: HashMap<String, VariableData>,
+    pub variables: BTreeMap<Symbol, VariableData>,
     pub parent: Option<&'a mut Scope<'a>>,
     pub sub_scopes: Vec<Scope<'a>>,
     pub used_hooks: Vec<i32>,
@@ -199,14 +376,14 @@ impl<'a> Scope<'a> {
     pub fn new(name: String, parent: Option<&'a mut Scope<'a>>) -> Scope<'a> {
         Scope {
             name,
-            variables: HashMap::new(),
+            variables: BTreeMap::new(),
             parent,
             sub_scopes: vec![],
             used_hooks: vec![],
         }
     }
 
-    pub fn get_variable(&self, name: &str) -> Option<&VariableData> {
+    pub fn get_variable(&self, name: &Symbol) -> Option<&VariableData> {
         match self.variables.get(name) {
             Some(data) => Some(data),
             None => match &self.parent {
@@ -216,7 +393,7 @@ impl<'a> Scope<'a> {
         }
     }
 
-    pub fn get_variable_mut(&mut self, name: &str) -> Option<&mut VariableData> {
+    pub fn get_variable_mut(&mut self, name: &Symbol) -> Option<&mut VariableData> {
         match self.variables.get_mut(name) {
             Some(data) => Some(data),
             None => match &mut self.parent {
@@ -226,7 +403,7 @@ impl<'a> Scope<'a> {
         }
     }
 
-    pub fn add_variable(&mut self, name: String, value: VariableData) {
+    pub fn add_variable(&mut self, name: Symbol, value: VariableData) {
         self.variables.insert(name, value);
     }
 
@@ -253,20 +430,163 @@ impl<'a> Scope<'a> {
     }
 }
 
+/// A statement of a traced program, whose index the trace foreign function
+/// is given right before the statement runs
+#[derive(Clone)]
+pub struct TracePoint {
+    pub token: ast::TokenNode,
+    /// the function the statement is in, main for the entry point
+    pub function: String,
+    /// every variable in scope, with the hook of its value and its type
+    pub variables: Vec<(String, i32, Types)>,
+}
+
+/// What is needed to map the state of a traced program back to its source
+#[derive(Clone, Default)]
+pub struct TraceInfo {
+    pub points: Vec<TracePoint>,
+    pub bukkits: Vec<BTreeMap<String, Types>>,
+    pub slot_keys: Vec<String>,
+}
+
+/// Stack, in floats, every program is given
+pub const STACK_SIZE: i32 = 1000;
+/// Heap, in bytes, every program is given
+pub const HEAP_SIZE: i32 = 4000;
+/// Times the body of an IM IN YR loop is visited on trial for the types its
+/// variables settle on, before a YARN that has not is taken to grow forever
+const LOOP_PASSES: usize = 8;
+
 #[derive(Clone)]
 pub struct VisitorError {
     pub message: String,
     pub token: ast::TokenNode,
 }
 
+/// The hooks handed out in one function's frame, numbered from 0
+#[derive(Clone, Default)]
+pub struct HookSpace {
+    pub max_hook: i32,
+    /// hooks below max_hook that are not in use, handed out lowest first
+    pub free_hooks: BTreeSet<i32>,
+}
+
+/// A WTF?, loop or function the statements being visited can leave, by the
+/// hook of the flag they clear to do so, which its statements after one
+/// that can leave it check
+#[derive(Clone)]
+pub struct Exit {
+    pub hook: i32,
+    /// whether it is the function's, left by FOUND YR or GTFO
+    pub function: bool,
+    /// the keyword or label it is reported at
+    pub token: ast::TokenNode,
+}
+
+/// A function compiled for the types of arguments it is called with, a
+/// YARN's size included, and the type of what it returns
+#[derive(Clone)]
+pub struct Instance {
+    pub name: Symbol,
+    pub arguments: Vec<Types>,
+    /// the name of its IR function
+    pub ir_name: String,
+    pub returns: Types,
+    /// how many instances the calls that made this one are nested in, which
+    /// only grows without bound if a YARN argument does with every call
+    pub generation: usize,
+    /// whether its body has been visited into its IR function
+    pub visited: bool,
+}
+
+/// The types an IM IN YR loop settled on when entered with variables and
+/// bukkit slots of the types it was entered with
+#[derive(Clone)]
+pub struct SettledLoop {
+    /// the index of its label's token
+    pub label: usize,
+    pub entry: BTreeMap<Symbol, Types>,
+    pub bukkits: Vec<BTreeMap<String, Types>>,
+    /// the types it goes into its body with, or the error that kept them
+    /// from settling
+    pub head: Result<BTreeMap<Symbol, Types>, VisitorError>,
+}
+
+/// The instance of a function whose body is being visited
+#[derive(Clone)]
+pub struct Callee {
+    pub instance: usize,
+    /// the size of the longest YARN it returns so far
+    pub found: i32,
+}
+
 pub struct Visitor<'a> {
     pub ast_tree: parser::ParserReturn<'a>,
     pub scopes: Vec<Scope<'a>>,
     pub current_scope_index: usize,
-    pub max_hook: i32,
-    pub used_hooks: Vec<i32>,
+    /// the hooks of every function, by its name, main for the entry point
+    pub hook_spaces: BTreeMap<String, HookSpace>,
     pub ir: ir::IR,
     pub errors: Vec<VisitorError>,
+    /// slot types of every bukkit declared so far
+    pub bukkits: Vec<BTreeMap<String, Types>>,
+    /// slot names, a slot's runtime key is its index + 1
+    pub slot_keys: Vec<String>,
+    /// when set every statement is preceded by a call to the trace foreign
+    /// function, for the interpreter's debugger
+    pub trace: Option<TraceInfo>,
+    /// the program's source, for runtime errors that name a line
+    pub source: SourceMap,
+    /// leaves ASSURE statements out of the IR, still type checking them
+    pub release: bool,
+    /// when unset statements are dropped instead of added to the IR, so only
+    /// the errors are worth anything, which is all --check needs
+    pub emit: bool,
+    /// leaves every free out of the IR, the runtime collecting the YARNs and
+    /// bukkits no variable reaches before each statement instead
+    pub gc: bool,
+    /// hooks of values that are no variable's but are still in use by the
+    /// statements being visited, such as the bukkit a loop watches
+    pub gc_roots: Vec<i32>,
+    /// the WTF?s, loops and function the statements being visited are in,
+    /// innermost last
+    pub exits: Vec<Exit>,
+    /// every HOW IZ I function of the program, by its name
+    pub definitions: BTreeMap<Symbol, ast::FunctionDefinitionStatementNode>,
+    /// the instances of the functions called so far
+    pub instances: Vec<Instance>,
+    /// the functions whose bodies are being visited, innermost last
+    pub callees: Vec<Callee>,
+    /// the type every instance made so far returns, by its function and
+    /// the types of its arguments, None for a YARN that grows with every
+    /// call. Trial visits leave it be, so an instance is only settled once.
+    pub settled: Vec<(Symbol, Vec<Types>, Option<Types>)>,
+    /// the instances whose YARN is being settled, innermost last, and
+    /// whether what they return depends on one of those outside them
+    pub settling: Vec<(usize, bool)>,
+    /// how many calls have been given the YARN tried for an instance still
+    /// being settled, so that what was visited meanwhile may change
+    pub guesses: usize,
+    /// the types every loop visited so far settled on, which trial visits
+    /// leave be, so that a nested loop is settled once rather than on every
+    /// trial of the loops around it
+    pub loops: Vec<SettledLoop>,
+}
+
+/// What visiting statements on trial changes, put back once they have been
+/// visited so that they can be visited again for real
+struct Checkpoint {
+    variables: BTreeMap<Symbol, VariableData>,
+    used_hooks: Vec<i32>,
+    hook_spaces: BTreeMap<String, HookSpace>,
+    errors: usize,
+    bukkits: usize,
+    slot_keys: usize,
+    hook_names: usize,
+    trace_points: usize,
+    instances: usize,
+    functions: usize,
+    emit: bool,
 }
 
 impl<'a> Visitor<'a> {
@@ -279,6 +599,10 @@ impl<'a> Visitor<'a> {
     }
 
     pub fn add_statements(&mut self, statements: Vec<ir::IRStatement>) {
+        if !self.emit {
+            return;
+        }
+
         let scope = self.get_scope();
         let name = scope.name.clone();
 
@@ -296,6 +620,13 @@ impl<'a> Visitor<'a> {
         }
     }
 
+    /// Adds the IR freeing a value, unless the runtime collects garbage
+    pub fn add_free(&mut self, free: Vec<ir::IRStatement>) {
+        if !self.gc {
+            self.add_statements(free);
+        }
+    }
+
     pub fn get_statements(&self) -> Vec<ir::IRStatement> {
         let scope = self.get_scope();
         let name = scope.name.clone();
@@ -331,55 +662,77 @@ impl<'a> Visitor<'a> {
         }
     }
 
+    /// The hooks of the function the current scope is in
+    pub fn get_hook_space(&mut self) -> &mut HookSpace {
+        let name = self.get_scope().name.clone();
+        self.hook_spaces.entry(name).or_default()
+    }
+
     pub fn get_hook(&mut self) -> (i32, ir::IRStatement) {
-        for hook in 0..self.max_hook {
-            if !self.used_hooks.contains(&hook) {
-                self.used_hooks.push(hook);
-                let scope = self.get_scope_mut();
-                scope.add_hook(hook);
-                return (hook, ir::IRStatement::Hook(hook));
+        let space = self.get_hook_space();
+        let hook = match space.free_hooks.pop_first() {
+            Some(hook) => hook,
+            None => {
+                space.max_hook += 1;
+                space.max_hook - 1
             }
-        }
+        };
 
-        let hook = self.max_hook;
-        self.used_hooks.push(hook);
-        let stmt = ir::IRStatement::Hook(hook);
         let scope = self.get_scope_mut();
         scope.add_hook(hook);
-        self.max_hook += 1;
-        return (hook, stmt);
+        (hook, ir::IRStatement::Hook(hook))
     }
 
     pub fn free_scope(&mut self) {
         let scope = self.get_scope();
         let ir = scope.free();
-        for hook in scope.used_hooks.clone().iter() {
-            self.used_hooks.retain(|&x| x != *hook);
+        let hooks = scope.used_hooks.clone();
+        let space = self.get_hook_space();
+        for hook in hooks.iter() {
+            space.free_hooks.insert(*hook);
         }
-        self.add_statements(ir);
+        self.add_free(ir);
     }
 
     pub fn free_hook(&mut self, hook: i32) {
-        self.used_hooks.retain(|&x| x != hook);
+        let space = self.get_hook_space();
+        if (0..space.max_hook).contains(&hook) {
+            space.free_hooks.insert(hook);
+        }
     }
 
     pub fn new(ast_tree: parser::ParserReturn<'a>, stack_size: i32, heap_size: i32) -> Self {
-        let entry = ir::IRFunctionEntry::new(stack_size, heap_size, vec![]);
+        let entry = ir::IRFunctionEntry::new(stack_size, heap_size, 0, vec![]);
         let mut visitor = Self {
             ast_tree,
             errors: vec![],
             scopes: vec![Scope::new("main".to_string(), None)],
             current_scope_index: 0,
-            max_hook: 0,
-            used_hooks: vec![],
+            hook_spaces: BTreeMap::new(),
             ir: ir::IR::new(vec![], entry),
+            bukkits: vec![],
+            slot_keys: vec![],
+            trace: None,
+            source: SourceMap::new(),
+            release: false,
+            emit: true,
+            gc: false,
+            gc_roots: vec![],
+            exits: vec![],
+            definitions: BTreeMap::new(),
+            instances: vec![],
+            callees: vec![],
+            settled: vec![],
+            settling: vec![],
+            guesses: 0,
+            loops: vec![],
         };
 
-        visitor.add_statements(vec![ir::IRStatement::Push(0.0)]);
+        visitor.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
         let (hook, stmt) = visitor.get_hook();
         let main_scope = visitor.get_scope_mut();
         main_scope.add_variable(
-            "IT".to_string(),
+            Symbol::intern("IT"),
             VariableData::new(VariableValue::new(hook, Types::Noob)),
         );
         visitor.add_statements(vec![stmt]);
@@ -389,50 +742,200 @@ impl<'a> Visitor<'a> {
 }
 
 impl<'a> Visitor<'a> {
-    pub fn visit(&mut self) -> (ir::IR, Vec<VisitorError>, i32) {
+    pub fn visit(&mut self) -> (ir::IR, Vec<VisitorError>) {
         self.visit_program(self.ast_tree.ast.clone());
 
-        (self.ir.clone(), self.errors.clone(), self.max_hook)
+        if let Some(trace) = self.trace.as_mut() {
+            trace.bukkits = self.bukkits.clone();
+            trace.slot_keys = self.slot_keys.clone();
+        }
+
+        let hooks = |name: &str| self.hook_spaces.get(name).map_or(0, |space| space.max_hook);
+        let mut ir = self.ir.clone();
+        ir.entry.hooks = hooks("main");
+        for function in ir.functions.iter_mut() {
+            function.hooks = hooks(&function.name);
+        }
+        ir.source_names = self
+            .instances
+            .iter()
+            .map(|instance| (instance.ir_name.clone(), instance.name.to_string()))
+            .collect();
+
+        // a function's body is visited once for each instance of it
+        let mut seen = BTreeSet::new();
+        let mut errors = self.errors.clone();
+        errors.retain(|error| seen.insert((error.token.token.index, error.message.clone())));
+        (ir, errors)
+    }
+
+    fn trace_statement(&mut self, statement: &ast::StatementNode) {
+        let token = match statement.token() {
+            Some(token) => token.clone(),
+            None => return,
+        };
+        // a statement desugared into several is one trace point, the later
+        // ones keeping the token of the first
+        let last = self.trace.as_ref().unwrap().points.last();
+        if last.is_some_and(|point| point.token.token.index == token.token.index) {
+            return;
+        }
+
+        let scope = self.get_scope();
+        let mut variables = vec![];
+        let mut current = Some(scope);
+        while let Some(scope) = current {
+            for (name, variable) in scope.variables.iter() {
+                if !variables
+                    .iter()
+                    .any(|(n, _, _): &(String, _, _)| name == n.as_str())
+                {
+                    variables.push((
+                        name.to_string(),
+                        variable.value.hook,
+                        variable.value.type_.clone(),
+                    ));
+                }
+            }
+            current = scope.parent.as_deref();
+        }
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let function = scope.name.clone();
+        let trace = self.trace.as_mut().unwrap();
+        trace.points.push(TracePoint {
+            token,
+            function,
+            variables,
+        });
+
+        let id = trace.points.len() - 1;
+        self.add_statements(vec![
+            ir::IRStatement::Push(ir::Value::Int(id as i32)),
+            ir::IRStatement::CallForeign("trace".to_string()),
+        ]);
+    }
+
+    /// Lets the runtime free what the statement about to run cannot reach,
+    /// handing it every YARN and bukkit in scope and the gc_roots
+    fn collect_garbage(&mut self) {
+        let mut roots = self.gc_roots.clone();
+        let mut current = Some(self.get_scope());
+        while let Some(scope) = current {
+            for variable in scope.variables.values() {
+                if matches!(variable.value.type_, Types::Yarn(_) | Types::Bukkit(_)) {
+                    roots.push(variable.value.hook);
+                }
+            }
+            current = scope.parent.as_deref();
+        }
+        // the variables come in no particular order
+        roots.sort();
+        roots.dedup();
+
+        let mut statements = vec![];
+        for hook in roots.iter() {
+            statements.push(ir::IRStatement::RefHook(*hook));
+            statements.push(ir::IRStatement::Copy);
+        }
+        statements.push(ir::IRStatement::Collect(roots.len() as i32));
+        self.add_statements(statements);
     }
 
+    /// Visits the statements of the program outside of its functions, and
+    /// then the bodies of the functions they call
     pub fn visit_program(&mut self, program: ast::ProgramNode) {
+        let mut statements = vec![];
         for statement in program.statements {
-            self.visit_statement(statement.clone());
+            match statement.value {
+                ast::StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                    self.define_function(function)
+                }
+                _ => statements.push(statement),
+            }
+        }
+
+        for statement in statements {
+            self.visit_statement(statement);
         }
+        self.visit_functions();
     }
 
     pub fn visit_statement(&mut self, statement: ast::StatementNode) {
+        ice::set_token(
+            statement
+                .token()
+                .map(|token| (token.token.index, token.token.span())),
+        );
+        if self.trace.is_some() {
+            self.trace_statement(&statement);
+        }
+        // a function cannot see its callers' variables to keep them
+        if self.gc && self.callees.is_empty() {
+            self.collect_garbage();
+        }
+
         match statement.value {
             ast::StatementNodeValueOption::Expression(expression) => {
-                let var = self.get_scope().get_variable("IT").unwrap();
-                self.add_statements(var.free());
+                let var = self
+                    .get_scope()
+                    .get_variable(&Symbol::intern("IT"))
+                    .unwrap();
+                self.add_free(var.free());
 
                 let (variable_value, _) = self.visit_expression(expression);
                 self.free_hook(variable_value.hook);
 
                 match variable_value.type_ {
                     Types::Number => {
-                        let it = self.get_scope_mut().get_variable_mut("IT").unwrap();
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
                         let stmts = it.assign(&Types::Number);
                         self.add_statements(stmts);
                     }
                     Types::Numbar => {
-                        let it = self.get_scope_mut().get_variable_mut("IT").unwrap();
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
                         let stmts = it.assign(&Types::Numbar);
                         self.add_statements(stmts);
                     }
                     Types::Troof => {
-                        let it = self.get_scope_mut().get_variable_mut("IT").unwrap();
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
                         let stmts = it.assign(&Types::Troof);
                         self.add_statements(stmts);
                     }
                     Types::Yarn(size) => {
-                        let it = self.get_scope_mut().get_variable_mut("IT").unwrap();
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
                         let stmts = it.assign(&Types::Yarn(size));
                         self.add_statements(stmts);
                     }
-                    _ => {
-                        panic!("Unexpected type");
+                    Types::Bukkit(bukkit) => {
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
+                        let stmts = it.assign(&Types::Bukkit(bukkit));
+                        self.add_statements(stmts);
+                    }
+                    // the error has already been reported
+                    Types::Noob if variable_value.hook == -1 => {}
+                    Types::Noob => {
+                        let it = self
+                            .get_scope_mut()
+                            .get_variable_mut(&Symbol::intern("IT"))
+                            .unwrap();
+                        let stmts = it.assign(&Types::Noob);
+                        self.add_statements(stmts);
                     }
                 }
             }
@@ -440,19 +943,72 @@ impl<'a> Visitor<'a> {
                 self.visit_variable_declaration(var_dec);
             }
             ast::StatementNodeValueOption::VariableAssignmentStatement(var_assign) => {
-                self.visit_variable_assignment(var_assign);
+                if self.assigns_plain_value(&var_assign) && !calls_function(&var_assign.expression)
+                {
+                    self.visit_in_arena(|visitor| visitor.visit_variable_assignment(var_assign));
+                } else {
+                    self.visit_variable_assignment(var_assign);
+                }
             }
             ast::StatementNodeValueOption::KTHXBYEStatement(_) => {
                 self.add_statements(vec![ir::IRStatement::Halt]);
             }
             ast::StatementNodeValueOption::VisibleStatement(visible_stmt) => {
-                self.visit_visible_statement(visible_stmt);
+                if visible_stmt.expressions.iter().any(calls_function) {
+                    self.visit_visible_statement(visible_stmt);
+                } else {
+                    self.visit_in_arena(|visitor| visitor.visit_visible_statement(visible_stmt));
+                }
             }
             ast::StatementNodeValueOption::GimmehStatement(gimmeh_stmt) => {
                 self.visit_gimmeh_statement(gimmeh_stmt);
             }
-            _ => {
-                panic!("Unexpected statement");
+            ast::StatementNodeValueOption::FLUSHZStatement(_) => {
+                self.add_statements(vec![ir::IRStatement::CallForeign("flush".to_string())]);
+            }
+            ast::StatementNodeValueOption::AssureStatement(assure_stmt) => {
+                if calls_function(&assure_stmt.expression)
+                    || assure_stmt.message.as_ref().is_some_and(calls_function)
+                {
+                    self.visit_assure_statement(assure_stmt);
+                } else {
+                    self.visit_in_arena(|visitor| visitor.visit_assure_statement(assure_stmt));
+                }
+            }
+            ast::StatementNodeValueOption::DumpzStatement(dumpz_stmt) => {
+                if calls_function(&dumpz_stmt.expression) {
+                    self.visit_dumpz_statement(dumpz_stmt);
+                } else {
+                    self.visit_in_arena(|visitor| visitor.visit_dumpz_statement(dumpz_stmt));
+                }
+            }
+            ast::StatementNodeValueOption::BukkitSlotDeclarationStatement(slot_dec) => {
+                self.visit_bukkit_slot_declaration(slot_dec);
+            }
+            ast::StatementNodeValueOption::WatchinLoopStatement(loop_stmt) => {
+                self.visit_watchin_loop_statement(loop_stmt);
+            }
+            ast::StatementNodeValueOption::LoopStatement(loop_stmt) => {
+                self.visit_loop_statement(loop_stmt);
+            }
+            ast::StatementNodeValueOption::IfStatement(if_stmt) => {
+                self.visit_if_statement(if_stmt);
+            }
+            ast::StatementNodeValueOption::SwitchStatement(switch) => {
+                self.visit_switch_statement(switch);
+            }
+            ast::StatementNodeValueOption::GTFOStatement(token) => {
+                self.visit_gtfo_statement(token);
+            }
+            ast::StatementNodeValueOption::ReturnStatement(return_stmt) => {
+                self.visit_return_statement(return_stmt);
+            }
+            ast::StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                self.errors.push(VisitorError {
+                    message: "Functions can only be defined at the top level of the program"
+                        .to_string(),
+                    token: function.identifier,
+                });
             }
         }
     }
@@ -461,7 +1017,9 @@ impl<'a> Visitor<'a> {
         &mut self,
         expression: ast::ExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        match expression.value {
+        let errors = self.errors.len();
+
+        let result = match &expression.value {
             ast::ExpressionNodeValueOption::NumberValue(number) => {
                 self.visit_number_value(number.clone())
             }
@@ -496,6 +1054,12 @@ impl<'a> Visitor<'a> {
             ast::ExpressionNodeValueOption::SmallrExpression(smallr_expr) => {
                 self.visit_smallr_expression(smallr_expr.clone())
             }
+            ast::ExpressionNodeValueOption::FurstExpression(furst_expr) => {
+                self.visit_furst_expression(furst_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::LatrExpression(latr_expr) => {
+                self.visit_latr_expression(latr_expr.clone())
+            }
             ast::ExpressionNodeValueOption::BothOfExpression(both_of_expr) => {
                 self.visit_both_of_expression(both_of_expr.clone())
             }
@@ -529,6 +1093,80 @@ impl<'a> Visitor<'a> {
             ast::ExpressionNodeValueOption::ItReference(it_ref) => {
                 self.visit_it_reference(it_ref.clone())
             }
+            ast::ExpressionNodeValueOption::BukkitSlot(slot) => {
+                self.visit_bukkit_slot(slot.clone())
+            }
+            ast::ExpressionNodeValueOption::KopyExpression(kopy_expr) => {
+                self.visit_kopy_expression(kopy_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::LengthzExpression(lengthz_expr) => {
+                self.visit_lengthz_expression(lengthz_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::CodezExpression(codez_expr) => {
+                self.visit_codez_expression(codez_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::LettarExpression(lettar_expr) => {
+                self.visit_lettar_expression(lettar_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::SplitzExpression(splitz_expr) => {
+                self.visit_splitz_expression(splitz_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::JoinzExpression(joinz_expr) => {
+                self.visit_joinz_expression(joinz_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::MatchezExpression(matchez_expr) => {
+                self.visit_matchez_expression(matchez_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::WunOfExpression(wun_of_expr) => {
+                self.visit_wun_of_expression(wun_of_expr.clone())
+            }
+            ast::ExpressionNodeValueOption::FunctionCallExpression(call) => {
+                self.visit_function_call_expression(call.clone())
+            }
+        };
+
+        self.describe_errors(errors, &expression);
+        result
+    }
+
+    /// Prefixes the errors reported since `errors` with the source of the
+    /// expression reporting them, or of the expression a statement reported
+    /// them for. Errors of its operands already name the operand, and single
+    /// tokens are already underlined in full.
+    ///
+    /// A failed operand is passed on with the token of its error, so an error
+    /// at the token of an earlier one only follows from it and is dropped.
+    fn describe_errors(&mut self, errors: usize, expression: &ast::ExpressionNode) {
+        let reported = self.errors.split_off(errors);
+        let mut seen: Vec<_> = self.errors.iter().map(|e| e.token.token.span()).collect();
+        for error in reported {
+            let span = error.token.token.span();
+            if !seen.contains(&span) {
+                seen.push(span);
+                self.errors.push(error);
+            }
+        }
+
+        if self.errors.len() == errors {
+            return;
+        }
+        if matches!(
+            expression.value,
+            ast::ExpressionNodeValueOption::NumberValue(_)
+                | ast::ExpressionNodeValueOption::NumbarValue(_)
+                | ast::ExpressionNodeValueOption::YarnValue(_)
+                | ast::ExpressionNodeValueOption::TroofValue(_)
+                | ast::ExpressionNodeValueOption::VariableReference(_)
+                | ast::ExpressionNodeValueOption::ItReference(_)
+        ) {
+            return;
+        }
+
+        let source = printer::print_expression(expression);
+        for error in self.errors[errors..].iter_mut() {
+            if !error.message.starts_with("in `") {
+                error.message = format!("in `{}`: {}", source, error.message);
+            }
         }
     }
 
@@ -536,7 +1174,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         number: ast::NumberValueNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(number.value() as f32)]);
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(number.value()))]);
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -549,7 +1187,9 @@ impl<'a> Visitor<'a> {
         &mut self,
         numbar: ast::NumbarValueNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(numbar.value())]);
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Float(
+            numbar.value(),
+        ))]);
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -562,11 +1202,9 @@ impl<'a> Visitor<'a> {
         &mut self,
         troof: ast::TroofValueNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(if troof.value() {
-            1.0
-        } else {
-            0.0
-        })]);
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(
+            troof.value() as i32
+        ))]);
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -580,23 +1218,23 @@ impl<'a> Visitor<'a> {
         yarn: ast::YarnValueNode,
     ) -> (VariableValue, ast::TokenNode) {
         let string = yarn.value();
-        let size = string.len() as i32;
+        let size = string.chars().count() as i32;
         self.add_statements(vec![
-            ir::IRStatement::Push(size as f32),
-            ir::IRStatement::Allocate,
+            ir::IRStatement::Push(ir::Value::Int(size)),
+            ir::IRStatement::StrAlloc,
         ]);
 
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
         for c in string.chars() {
-            self.add_statements(vec![ir::IRStatement::Push(c as i32 as f32)]);
+            self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(c as i32))]);
         }
 
         self.add_statements(vec![
             ir::IRStatement::RefHook(hook),
             ir::IRStatement::Copy,
-            ir::IRStatement::Store(size),
+            ir::IRStatement::StrStore(size),
         ]);
 
         let variable = VariableValue::new(hook, Types::Yarn(size));
@@ -609,17 +1247,17 @@ impl<'a> Visitor<'a> {
         var_ref: ast::VariableReferenceNode,
     ) -> (VariableValue, ast::TokenNode) {
         let name = match var_ref.identifier.value() {
-            tokens::Token::Identifier(name) => name,
+            tokens::Token::Identifier(name) => name.clone(),
             _ => panic!("Expected Identifier token"),
         };
 
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
-        let variable = self.get_scope().get_variable(name);
+        let variable = self.get_scope().get_variable(&name);
         if let None = variable {
             self.errors.push(VisitorError {
-                message: format!("Variable {} not found", name),
+                message: format!("Variable {} not found", name.escaped()),
                 token: var_ref.identifier.clone(),
             });
             return (
@@ -837,7 +1475,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         biggr_expr: ast::BiggrExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(0.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -871,7 +1509,7 @@ impl<'a> Visitor<'a> {
             ir::IRStatement::Copy,
             ir::IRStatement::Subtract,
             ir::IRStatement::RefHook(right.hook),
-            ir::IRStatement::Push(1.0),
+            ir::IRStatement::Push(ir::Value::Int(1)),
             ir::IRStatement::Add,
             ir::IRStatement::Copy,
             ir::IRStatement::Sign,
@@ -882,16 +1520,12 @@ impl<'a> Visitor<'a> {
             ir::IRStatement::Copy,
             ir::IRStatement::Add,
             ir::IRStatement::Add,
-            ir::IRStatement::Push(2.0),
+            ir::IRStatement::Push(ir::Value::Int(2)),
             ir::IRStatement::Divide,
             ir::IRStatement::RefHook(hook),
             ir::IRStatement::Mov,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
+            ir::IRStatement::pop(),
+            ir::IRStatement::pop(),
         ]);
 
         self.free_hook(left.hook);
@@ -906,7 +1540,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         smallr_expr: ast::SmallrExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(0.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -940,7 +1574,7 @@ impl<'a> Visitor<'a> {
             ir::IRStatement::Copy,
             ir::IRStatement::Subtract,
             ir::IRStatement::RefHook(right.hook),
-            ir::IRStatement::Push(1.0),
+            ir::IRStatement::Push(ir::Value::Int(1)),
             ir::IRStatement::Add,
             ir::IRStatement::Copy,
             ir::IRStatement::Sign,
@@ -951,18 +1585,14 @@ impl<'a> Visitor<'a> {
             ir::IRStatement::Copy,
             ir::IRStatement::Add,
             ir::IRStatement::Subtract,
-            ir::IRStatement::Push(2.0),
+            ir::IRStatement::Push(ir::Value::Int(2)),
             ir::IRStatement::Divide,
-            ir::IRStatement::Push(-1.0),
+            ir::IRStatement::Push(ir::Value::Int(-1)),
             ir::IRStatement::Multiply,
             ir::IRStatement::RefHook(hook),
             ir::IRStatement::Mov,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
+            ir::IRStatement::pop(),
+            ir::IRStatement::pop(),
         ]);
 
         self.free_hook(left.hook);
@@ -977,7 +1607,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         both_of_expr: ast::BothOfExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(0.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -1005,12 +1635,14 @@ impl<'a> Visitor<'a> {
 
         self.add_statements(vec![
             ir::IRStatement::Multiply,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(1.0),
-            ir::IRStatement::RefHook(hook),
-            ir::IRStatement::Mov,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
+            ir::IRStatement::If {
+                then: vec![
+                    ir::IRStatement::Push(ir::Value::Int(1)),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Mov,
+                ],
+                else_: vec![],
+            },
         ]);
 
         let variable = VariableValue::new(hook, Types::Troof);
@@ -1056,7 +1688,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         won_of_expr: ast::WonOfExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(0.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -1084,14 +1716,16 @@ impl<'a> Visitor<'a> {
 
         self.add_statements(vec![
             ir::IRStatement::Add,
-            ir::IRStatement::Push(2.0),
+            ir::IRStatement::Push(ir::Value::Int(2)),
             ir::IRStatement::Modulo,
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(1.0),
-            ir::IRStatement::RefHook(hook),
-            ir::IRStatement::Mov,
-            ir::IRStatement::Push(0.0), // break out of loop
-            ir::IRStatement::EndWhile,
+            ir::IRStatement::If {
+                then: vec![
+                    ir::IRStatement::Push(ir::Value::Int(1)),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Mov,
+                ],
+                else_: vec![],
+            },
         ]);
 
         let variable = VariableValue::new(hook, Types::Troof);
@@ -1115,9 +1749,9 @@ impl<'a> Visitor<'a> {
         }
 
         self.add_statements(vec![
-            ir::IRStatement::Push(1.0),
+            ir::IRStatement::Push(ir::Value::Int(1)),
             ir::IRStatement::Add,
-            ir::IRStatement::Push(2.0),
+            ir::IRStatement::Push(ir::Value::Int(2)),
             ir::IRStatement::Modulo,
         ]);
 
@@ -1133,12 +1767,12 @@ impl<'a> Visitor<'a> {
         &mut self,
         all_of_expr: ast::AllOfExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(1.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(1))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
         let mut t = None;
-        self.add_statements(vec![ir::IRStatement::Push(1.0)]);
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(1))]);
         for expression in all_of_expr.expressions.iter() {
             let (exp, token) = self.visit_expression(expression.clone());
 
@@ -1160,26 +1794,24 @@ impl<'a> Visitor<'a> {
             self.add_statements(vec![
                 ir::IRStatement::RefHook(hook_of_running_total),
                 ir::IRStatement::Copy,
-                ir::IRStatement::Push(1.0),
+                ir::IRStatement::Push(ir::Value::Int(1)),
                 ir::IRStatement::Add,
-                ir::IRStatement::Push(2.0),
+                ir::IRStatement::Push(ir::Value::Int(2)),
                 ir::IRStatement::Modulo,
-                ir::IRStatement::BeginWhile,
-                ir::IRStatement::Push(0.0),
-                ir::IRStatement::RefHook(hook),
-                ir::IRStatement::Mov,
-                ir::IRStatement::Push(0.0),
-                ir::IRStatement::EndWhile,
+                ir::IRStatement::If {
+                    then: vec![
+                        ir::IRStatement::Push(ir::Value::Int(0)),
+                        ir::IRStatement::RefHook(hook),
+                        ir::IRStatement::Mov,
+                    ],
+                    else_: vec![],
+                },
             ]);
 
             self.free_hook(hook_of_running_total);
         }
 
-        self.add_statements(vec![
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
-        ]);
+        self.add_statements(vec![ir::IRStatement::pop()]);
 
         (VariableValue::new(hook, Types::Troof), t.unwrap())
     }
@@ -1188,7 +1820,7 @@ impl<'a> Visitor<'a> {
         &mut self,
         any_of_expr: ast::AnyOfExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(0.0)]); // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
@@ -1207,29 +1839,133 @@ impl<'a> Visitor<'a> {
             }
             t = Some(token);
 
-            self.add_statements(vec![
-                ir::IRStatement::BeginWhile,
-                ir::IRStatement::Push(1.0),
-                ir::IRStatement::RefHook(hook),
-                ir::IRStatement::Mov,
-                ir::IRStatement::Push(0.0),
-                ir::IRStatement::EndWhile,
-            ]);
+            self.add_statements(vec![ir::IRStatement::If {
+                then: vec![
+                    ir::IRStatement::Push(ir::Value::Int(1)),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Mov,
+                ],
+                else_: vec![],
+            }]);
         }
 
         (VariableValue::new(hook, Types::Troof), t.unwrap())
     }
 
+    /// WIN if the left YARN sorts before (or, with `after`, after) the
+    /// right one, comparing character codes. Equal YARNs are FAIL either way.
+    fn visit_yarn_order(
+        &mut self,
+        left: ast::ExpressionNode,
+        right: ast::ExpressionNode,
+        after: bool,
+    ) -> (VariableValue, ast::TokenNode) {
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(1))]); // return value
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        let (left, left_token) = self.visit_expression(left);
+        let (right, right_token) = self.visit_expression(right);
+
+        let left_size = match left.type_ {
+            Types::Yarn(size) => size,
+            _ => {
+                self.errors.push(VisitorError {
+                    message: "Expected YARN type".to_string(),
+                    token: left_token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), left_token);
+            }
+        };
+        let right_size = match right.type_ {
+            Types::Yarn(size) => size,
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!("Expected YARN type but got {}", right.type_.to_string()),
+                    token: right_token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), right_token);
+            }
+        };
+
+        // yarn_compare gives -1, 0 or 1, which is moved to 0 for the order
+        // asked for so anything else clears the return value
+        let wanted = if after { 1 } else { -1 };
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(left.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(left_size)),
+            ir::IRStatement::RefHook(right.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(right_size)),
+            ir::IRStatement::CallForeign("yarn_compare".to_string()),
+            ir::IRStatement::Push(ir::Value::Int(wanted)),
+            ir::IRStatement::Subtract,
+            ir::IRStatement::If {
+                then: vec![
+                    ir::IRStatement::Push(ir::Value::Int(0)),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Mov,
+                ],
+                else_: vec![],
+            },
+        ]);
+
+        // the YARNs are freed through their slots before they are dropped
+        self.add_free(left.free());
+        self.add_free(right.free());
+        self.add_statements(vec![ir::IRStatement::pop(), ir::IRStatement::pop()]);
+
+        self.free_hook(left.hook);
+        self.free_hook(right.hook);
+
+        (VariableValue::new(hook, Types::Troof), left_token)
+    }
+
+    pub fn visit_furst_expression(
+        &mut self,
+        furst_expr: ast::FurstExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        self.visit_yarn_order(*furst_expr.left, *furst_expr.right, false)
+    }
+
+    pub fn visit_latr_expression(
+        &mut self,
+        latr_expr: ast::LatrExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        self.visit_yarn_order(*latr_expr.left, *latr_expr.right, true)
+    }
+
     pub fn visit_both_saem_expression(
         &mut self,
         both_saem_expr: ast::BothSaemExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(1.0)]); // return value
+        self.visit_comparison(*both_saem_expr.left, *both_saem_expr.right, true)
+    }
+
+    pub fn visit_diffrint_expression(
+        &mut self,
+        diffrint_expr: ast::DiffrintExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        self.visit_comparison(*diffrint_expr.left, *diffrint_expr.right, false)
+    }
+
+    /// BOTH SAEM when `same` is set and DIFFRINT otherwise, which only differ
+    /// in the TROOF they start with and set when the operands differ
+    fn visit_comparison(
+        &mut self,
+        left: ast::ExpressionNode,
+        right: ast::ExpressionNode,
+        same: bool,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (equal, different) = if same { (1, 0) } else { (0, 1) };
+        // return value
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(equal))]);
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
-        let (left, left_token) = self.visit_expression(*both_saem_expr.left.clone());
-        let (right, right_token) = self.visit_expression(*both_saem_expr.right.clone());
+        let (left, left_token) = self.visit_expression(left);
+        let (right, right_token) = self.visit_expression(right);
 
         if !left.type_.equals(&right.type_) {
             self.errors.push(VisitorError {
@@ -1243,56 +1979,36 @@ impl<'a> Visitor<'a> {
             return (VariableValue::new(-1, Types::Noob), right_token);
         }
 
+        let set_different = vec![
+            ir::IRStatement::Push(ir::Value::Int(different)),
+            ir::IRStatement::RefHook(hook),
+            ir::IRStatement::Mov,
+        ];
         match left.type_ {
             Types::Number | Types::Numbar | Types::Troof => {
                 self.add_statements(vec![
                     ir::IRStatement::Subtract,
-                    ir::IRStatement::BeginWhile,
-                    ir::IRStatement::Push(0.0),
-                    ir::IRStatement::RefHook(hook),
-                    ir::IRStatement::Mov,
-                    ir::IRStatement::Push(0.0),
-                    ir::IRStatement::EndWhile,
+                    ir::IRStatement::If {
+                        then: set_different,
+                        else_: vec![],
+                    },
                 ]);
             }
             Types::Yarn(size) => match right.type_ {
                 Types::Yarn(size2) => {
                     if size != size2 {
-                        self.add_statements(vec![
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::RefHook(hook),
-                            ir::IRStatement::Mov,
-                        ]);
+                        self.add_statements(set_different);
                     } else {
-                        for i in 0..size {
-                            self.add_statements(vec![
-                                ir::IRStatement::RefHook(left.hook),
-                                ir::IRStatement::Copy,
-                                ir::IRStatement::Push(i as f32 * 4.0),
-                                ir::IRStatement::Add,
-                                ir::IRStatement::Load(1),
-                                ir::IRStatement::RefHook(right.hook),
-                                ir::IRStatement::Copy,
-                                ir::IRStatement::Push(i as f32 * 4.0),
-                                ir::IRStatement::Add,
-                                ir::IRStatement::Load(1),
-                                ir::IRStatement::Subtract,
-                                ir::IRStatement::BeginWhile,
-                                ir::IRStatement::Push(0.0),
-                                ir::IRStatement::RefHook(hook),
-                                ir::IRStatement::Mov,
-                                ir::IRStatement::Push(0.0),
-                                ir::IRStatement::EndWhile,
-                            ]);
-                        }
-
                         self.add_statements(vec![
-                            ir::IRStatement::BeginWhile,
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::EndWhile,
-                            ir::IRStatement::BeginWhile,
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::EndWhile,
+                            ir::IRStatement::RefHook(left.hook),
+                            ir::IRStatement::Copy,
+                            ir::IRStatement::RefHook(right.hook),
+                            ir::IRStatement::Copy,
+                            ir::IRStatement::StrCmp(size),
+                            ir::IRStatement::If {
+                                then: set_different,
+                                else_: vec![],
+                            },
                         ]);
                     }
                 }
@@ -1305,117 +2021,15 @@ impl<'a> Visitor<'a> {
             }
         };
 
-        self.add_statements(left.free());
-        self.add_statements(right.free());
-
-        self.free_hook(left.hook);
-        self.free_hook(right.hook);
-
-        (VariableValue::new(hook, Types::Troof), left_token)
-    }
-
-    pub fn visit_diffrint_expression(
-        &mut self,
-        diffrint_expr: ast::DiffrintExpressionNode,
-    ) -> (VariableValue, ast::TokenNode) {
-        self.add_statements(vec![ir::IRStatement::Push(1.0)]); // return value
-        let (hook, stmt) = self.get_hook();
-        self.add_statements(vec![stmt]);
-
-        let (left, left_token) = self.visit_expression(*diffrint_expr.left.clone());
-        let (right, right_token) = self.visit_expression(*diffrint_expr.right.clone());
-
-        if !left.type_.equals(&right.type_) {
-            self.errors.push(VisitorError {
-                message: format!(
-                    "Expected {} type but got {}",
-                    left.type_.to_string(),
-                    right.type_.to_string()
-                ),
-                token: right_token.clone(),
-            });
-            return (VariableValue::new(-1, Types::Noob), right_token);
+        self.add_free(left.free());
+        self.add_free(right.free());
+        if let Types::Yarn(_) = left.type_ {
+            self.add_statements(vec![ir::IRStatement::pop(), ir::IRStatement::pop()]);
         }
 
-        match left.type_ {
-            Types::Number | Types::Numbar | Types::Troof => {
-                self.add_statements(vec![
-                    ir::IRStatement::Subtract,
-                    ir::IRStatement::BeginWhile,
-                    ir::IRStatement::Push(0.0),
-                    ir::IRStatement::RefHook(hook),
-                    ir::IRStatement::Mov,
-                    ir::IRStatement::Push(0.0),
-                    ir::IRStatement::EndWhile,
-                ]);
-            }
-            Types::Yarn(size) => match right.type_ {
-                Types::Yarn(size2) => {
-                    if size != size2 {
-                        self.add_statements(vec![
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::RefHook(hook),
-                            ir::IRStatement::Mov,
-                        ]);
-                    } else {
-                        for i in 0..size {
-                            self.add_statements(vec![
-                                ir::IRStatement::RefHook(left.hook),
-                                ir::IRStatement::Copy,
-                                ir::IRStatement::Push(i as f32 * 4.0),
-                                ir::IRStatement::Add,
-                                ir::IRStatement::Load(1),
-                                ir::IRStatement::RefHook(right.hook),
-                                ir::IRStatement::Copy,
-                                ir::IRStatement::Push(i as f32 * 4.0),
-                                ir::IRStatement::Add,
-                                ir::IRStatement::Load(1),
-                                ir::IRStatement::Subtract,
-                                ir::IRStatement::BeginWhile,
-                                ir::IRStatement::Push(0.0),
-                                ir::IRStatement::RefHook(hook),
-                                ir::IRStatement::Mov,
-                                ir::IRStatement::Push(0.0),
-                                ir::IRStatement::EndWhile,
-                            ]);
-                        }
-
-                        self.add_statements(vec![
-                            ir::IRStatement::BeginWhile,
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::EndWhile,
-                            ir::IRStatement::BeginWhile,
-                            ir::IRStatement::Push(0.0),
-                            ir::IRStatement::EndWhile,
-                        ]);
-                    }
-                }
-                _ => {
-                    panic!("Unexpected type");
-                }
-            },
-            _ => {
-                panic!("Unexpected type");
-            }
-        };
-
-        self.add_statements(left.free());
-        self.add_statements(right.free());
-
         self.free_hook(left.hook);
         self.free_hook(right.hook);
 
-        self.add_statements(vec![
-            ir::IRStatement::RefHook(hook),
-            ir::IRStatement::Copy,
-            ir::IRStatement::Push(1.0),
-            ir::IRStatement::Add,
-            ir::IRStatement::Push(2.0),
-            ir::IRStatement::Modulo,
-            ir::IRStatement::RefHook(hook),
-            ir::IRStatement::Mov,
-        ]);
-
         (VariableValue::new(hook, Types::Troof), left_token)
     }
 
@@ -1432,10 +2046,13 @@ impl<'a> Visitor<'a> {
             let (exp, t) = self.visit_expression(expression.clone());
 
             if !exp.type_.equals(&Types::Yarn(-1)) {
-                self.errors.push(VisitorError {
-                    message: "Expected YARN type".to_string(),
-                    token: t.clone(),
-                });
+                if exp.hook != -1 {
+                    self.errors.push(VisitorError {
+                        message: "Expected YARN type".to_string(),
+                        token: t.clone(),
+                    });
+                }
+                self.set_statements(old_scope);
                 return (VariableValue::new(-1, Types::Noob), t);
             }
 
@@ -1452,8 +2069,8 @@ impl<'a> Visitor<'a> {
         self.set_statements(old_scope);
 
         self.add_statements(vec![
-            ir::IRStatement::Push(size as f32),
-            ir::IRStatement::Allocate,
+            ir::IRStatement::Push(ir::Value::Int(size)),
+            ir::IRStatement::StrAlloc,
         ]);
 
         let (hook, stmt) = self.get_hook();
@@ -1469,24 +2086,17 @@ impl<'a> Visitor<'a> {
             };
 
             self.add_statements(vec![
-                ir::IRStatement::RefHook(exp.hook),
-                ir::IRStatement::Copy,
-                ir::IRStatement::Load(size_local),
                 ir::IRStatement::RefHook(hook),
                 ir::IRStatement::Copy,
-                ir::IRStatement::Push(size_passed as f32 * 4.0),
-                ir::IRStatement::Add,
-                ir::IRStatement::Store(size_local),
+                ir::IRStatement::RefHook(exp.hook),
+                ir::IRStatement::Copy,
+                ir::IRStatement::StrConcat(size_passed, size_local),
             ]);
 
-            self.add_statements(exp.free());
+            self.add_free(exp.free());
             self.free_hook(exp.hook);
 
-            self.add_statements(vec![
-                ir::IRStatement::BeginWhile,
-                ir::IRStatement::Push(0.0),
-                ir::IRStatement::EndWhile,
-            ]);
+            self.add_statements(vec![ir::IRStatement::pop()]);
 
             size_passed += size_local;
         }
@@ -1494,167 +2104,204 @@ impl<'a> Visitor<'a> {
         (VariableValue::new(hook, Types::Yarn(size)), token.unwrap())
     }
 
+    /// MAEK of a YARN written in the program is checked as it compiles
+    /// rather than left to panic when it runs
+    fn check_literal_number(
+        &mut self,
+        expression: &ast::ExpressionNode,
+        numbar: bool,
+        token: &ast::TokenNode,
+    ) {
+        if let ast::ExpressionNodeValueOption::YarnValue(yarn) = &expression.value {
+            let text = yarn.value().split('\0').next().unwrap_or("");
+            if utils::parse_number(text, numbar).is_none() {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot convert \"{}\" to {}",
+                        text,
+                        if numbar { "NUMBAR" } else { "NUMBER" }
+                    ),
+                    token: token.clone(),
+                });
+            }
+        }
+    }
+
+    /// Pushes WIN if the number `value` leaves on the stack is not 0 and FAIL
+    /// if it is, returning the hook of the TROOF
+    fn push_truth(&mut self, value: Vec<ir::IRStatement>) -> i32 {
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        self.add_statements(value);
+        self.add_statements(vec![ir::IRStatement::If {
+            then: vec![
+                ir::IRStatement::Push(ir::Value::Int(1)),
+                ir::IRStatement::RefHook(hook),
+                ir::IRStatement::Mov,
+            ],
+            else_: vec![],
+        }]);
+        hook
+    }
+
+    /// Converts the value of a MAEK to `type_` as the 1.2 spec does: NOOB
+    /// becomes 0, an empty YARN or FAIL, a TROOF is 1 or 0 as a number and
+    /// WIN or FAIL as a YARN, a number or YARN is WIN unless it is 0 or
+    /// empty, and converting to the type a value already has keeps it as it
+    /// is. Anything can be made NOOB, but a BUKKIT cannot be converted.
     pub fn visit_maek_expression(
         &mut self,
         maek_expr: ast::MaekExpressionNode,
     ) -> (VariableValue, ast::TokenNode) {
-        let (expression, token) = self.visit_expression(*maek_expr.expression.clone());
-
-        self.free_hook(expression.hook);
-
-        let mut type_ = match maek_expr.type_.token.token.to_name().as_str() {
+        let target = match maek_expr.type_.token.token.to_name().as_str() {
             "Word_NUMBER" => Types::Number,
             "Word_NUMBAR" => Types::Numbar,
             "Word_TROOF" => Types::Troof,
             "Word_YARN" => Types::Yarn(-1), // unknown size
-            _ => panic!("Unexpected type"),
+            "Word_NOOB" => Types::Noob,
+            _ => {
+                self.errors.push(VisitorError {
+                    message: "Expected NUMBER, NUMBAR, TROOF, YARN or NOOB to convert to"
+                        .to_string(),
+                    token: maek_expr.type_.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), maek_expr.type_);
+            }
         };
 
-        match type_ {
-            Types::Number => {
-                match expression.type_ {
-                    Types::Number => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Numbar => {
-                        self.add_statements(vec![ir::IRStatement::CallForeign(
-                            "float_to_int".to_string(),
-                        )]);
-                    }
-                    Types::Troof => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Yarn(size) => {
-                        self.add_statements(vec![
-                            ir::IRStatement::Push(size as f32),
-                            ir::IRStatement::CallForeign("string_to_int".to_string()),
-                        ]);
-                    }
-                    Types::Noob => {
-                        self.errors.push(VisitorError {
-                            message: "Cannot convert type NOOB to NUMBER".to_string(),
-                            token: token.clone(),
-                        });
-                        return (VariableValue::new(-1, Types::Noob), token);
-                    }
-                };
+        // an IT not set yet is NOOB, which is an error anywhere but here
+        if let ast::ExpressionNodeValueOption::ItReference(it_ref) = &maek_expr.expression.value {
+            let it = self.get_scope().get_variable(&Symbol::intern("IT"));
+            if it.is_some_and(|it| it.value.type_.equals(&Types::Noob)) {
+                return self.visit_noob_conversion(target, it_ref.token.clone());
             }
-            Types::Numbar => {
-                match expression.type_ {
-                    Types::Number => {
-                        self.add_statements(vec![ir::IRStatement::CallForeign(
-                            "int_to_float".to_string(),
-                        )]);
-                    }
-                    Types::Numbar => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Troof => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Yarn(size) => {
-                        self.add_statements(vec![
-                            ir::IRStatement::Push(size as f32),
-                            ir::IRStatement::CallForeign("string_to_float".to_string()),
-                        ]);
-                    }
-                    Types::Noob => {
-                        self.errors.push(VisitorError {
-                            message: "Cannot convert type NOOB to NUMBAR".to_string(),
-                            token: token.clone(),
-                        });
-                        return (VariableValue::new(-1, Types::Noob), token);
-                    }
-                };
+        }
+
+        let (expression, token) = self.visit_expression(*maek_expr.expression.clone());
+        if expression.hook == -1 {
+            return (expression, token); // the error has already been reported
+        }
+        if expression.type_.equals(&target) {
+            return (expression, token);
+        }
+
+        // the hook of the converted value, None if it is left on the stack
+        let mut converted = None;
+        let mut type_ = target.clone();
+        match (&expression.type_, &target) {
+            (Types::Noob, _) => {
+                self.free_hook(expression.hook);
+                return self.visit_noob_conversion(target, token);
             }
-            Types::Troof => {
-                match expression.type_ {
-                    Types::Number => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Numbar => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Troof => {
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Yarn(size) => {
-                        self.add_statements(vec![ir::IRStatement::Push(if size == 0 {
-                            0.0
-                        } else {
-                            1.0
-                        })]);
-                    }
-                    Types::Noob => {
-                        self.errors.push(VisitorError {
-                            message: "Cannot convert type NOOB to TROOF".to_string(),
-                            token: token.clone(),
-                        });
-                        return (VariableValue::new(-1, Types::Noob), token);
-                    }
-                };
+            (Types::Bukkit(_), _) => {
+                self.errors.push(VisitorError {
+                    message: format!("Cannot convert type BUKKIT to {}", target.to_string()),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
             }
-            Types::Yarn(_) => {
-                match expression.type_ {
-                    Types::Number => {
-                        type_ = Types::Yarn(32);
-                        self.add_statements(vec![ir::IRStatement::CallForeign(
-                            "int_to_string".to_string(),
-                        )]);
-                    }
-                    Types::Numbar => {
-                        type_ = Types::Yarn(32);
-                        self.add_statements(vec![ir::IRStatement::CallForeign(
-                            "float_to_string".to_string(),
-                        )]);
-                    }
-                    Types::Troof => {
-                        type_ = Types::Yarn(32);
-                        self.add_statements(vec![ir::IRStatement::CallForeign(
-                            "int_to_string".to_string(),
-                        )]);
-                    }
-                    Types::Yarn(size) => {
-                        type_ = Types::Yarn(size);
-                        self.add_statements(vec![
-                            ir::IRStatement::RefHook(expression.hook),
-                            ir::IRStatement::Copy,
-                        ]);
-                    }
-                    Types::Noob => {
-                        self.errors.push(VisitorError {
-                            message: "Cannot convert type NOOB to YARN".to_string(),
-                            token: token.clone(),
-                        });
-                        return (VariableValue::new(-1, Types::Noob), token);
-                    }
+            (_, Types::Noob) => {
+                self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+            }
+            (Types::Number, Types::Numbar) => {
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "int_to_float".to_string(),
+                )]);
+            }
+            (Types::Numbar, Types::Number) => {
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "float_to_int".to_string(),
+                )]);
+            }
+            (Types::Number | Types::Numbar | Types::Troof, _)
+                if !matches!(target, Types::Yarn(_)) =>
+            {
+                // between numbers and TROOFs, which are WIN for anything but 0
+                converted = Some(self.push_truth(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                ]));
+            }
+            (Types::Number, _) => {
+                type_ = Types::Yarn(32);
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "int_to_string".to_string(),
+                )]);
+            }
+            (Types::Numbar, _) => {
+                type_ = Types::Yarn(32);
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "float_to_string".to_string(),
+                )]);
+            }
+            (Types::Troof, _) => {
+                type_ = Types::Yarn(5);
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "troof_to_string".to_string(),
+                )]);
+            }
+            (Types::Yarn(size), Types::Troof) => {
+                converted = Some(self.push_truth(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::Push(ir::Value::Int(*size)),
+                    ir::IRStatement::CallForeign("yarn_length".to_string()),
+                ]));
+            }
+            (Types::Yarn(size), _) => {
+                let numbar = target.equals(&Types::Numbar);
+                // a YARN that is no number still makes one, so that the
+                // expression it is in is checked as written
+                self.check_literal_number(&maek_expr.expression, numbar, &token);
+                let function = if numbar {
+                    "string_to_float"
+                } else {
+                    "string_to_int"
                 };
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::Push(ir::Value::Int(*size)),
+                    ir::IRStatement::CallForeign(function.to_string()),
+                ]);
             }
-            _ => panic!("Unexpected type"),
         }
 
-        self.add_statements(expression.free());
+        self.add_free(expression.free());
+        self.free_hook(expression.hook);
+
+        let hook = match converted {
+            Some(hook) => hook,
+            None => {
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+                hook
+            }
+        };
+        (VariableValue::new(hook, type_), token)
+    }
+
+    /// The value MAEK makes of NOOB: 0, an empty YARN, FAIL or NOOB itself
+    fn visit_noob_conversion(
+        &mut self,
+        target: Types,
+        token: ast::TokenNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let type_ = match target {
+            Types::Yarn(_) => {
+                self.add_statements(vec![
+                    ir::IRStatement::Push(ir::Value::Int(1)),
+                    ir::IRStatement::StrAlloc,
+                ]);
+                Types::Yarn(1)
+            }
+            type_ => {
+                self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+                type_
+            }
+        };
 
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
@@ -1669,7 +2316,7 @@ impl<'a> Visitor<'a> {
         self.add_statements(vec![stmt]);
 
         let scope = self.get_scope();
-        let variable = scope.get_variable("IT");
+        let variable = scope.get_variable(&Symbol::intern("IT"));
         if let None = variable {
             self.errors.push(VisitorError {
                 message: "IT variable not declared".to_string(),
@@ -1695,7 +2342,7 @@ impl<'a> Visitor<'a> {
     pub fn visit_variable_declaration(&mut self, var_dec: ast::VariableDeclarationStatementNode) {
         let token = var_dec.identifier;
         let name = match token.value() {
-            tokens::Token::Identifier(name) => name,
+            tokens::Token::Identifier(name) => name.clone(),
             _ => panic!("Expected Identifier token"),
         };
 
@@ -1703,7 +2350,7 @@ impl<'a> Visitor<'a> {
         let variable = scope.get_variable(&name);
         if let Some(_) = variable {
             self.errors.push(VisitorError {
-                message: format!("Variable {} already declared", name),
+                message: format!("Variable {} already declared", name.escaped()),
                 token,
             });
             return;
@@ -1714,28 +2361,91 @@ impl<'a> Visitor<'a> {
             "Word_NUMBAR" => Types::Numbar,
             "Word_TROOF" => Types::Troof,
             "Word_YARN" => Types::Yarn(1),
+            "Word_BUKKIT" => {
+                self.bukkits.push(BTreeMap::new());
+                Types::Bukkit(self.bukkits.len() - 1)
+            }
             _ => panic!("Unexpected type"),
         };
 
         if type_.equals(&Types::Yarn(1)) {
-            self.add_statements(vec![ir::IRStatement::Push(1.0), ir::IRStatement::Allocate]);
+            self.add_statements(vec![
+                ir::IRStatement::Push(ir::Value::Int(1)),
+                ir::IRStatement::StrAlloc,
+            ]);
+        } else if type_.equals(&Types::Bukkit(0)) {
+            self.add_statements(vec![ir::IRStatement::CallForeign("bukkit_new".to_string())]);
         } else {
-            self.add_statements(vec![ir::IRStatement::Push(0.0)]);
+            self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
         }
 
         let (hook, stmt) = self.get_hook();
         self.add_statements(vec![stmt]);
 
+        self.name_hook(&name, hook, &type_);
         let variable = VariableData::new(VariableValue::new(hook, type_));
         let scope_mut = self.get_scope_mut();
-        scope_mut.add_variable(name.clone(), variable);
+        scope_mut.add_variable(name, variable);
+    }
+
+    /// Adds a variable of the current function to the IR's hook table, which
+    /// release builds leave empty
+    fn name_hook(&mut self, name: &Symbol, hook: i32, type_: &Types) {
+        if self.release {
+            return;
+        }
+        let function = self.get_scope().name.clone();
+        self.ir.hook_names.push(ir::HookName {
+            function,
+            hook,
+            name: name.to_string(),
+            type_: type_.to_string(),
+        });
+    }
+
+    /// The index in the IR's hook table of the variable `name` in scope
+    fn hook_name_index(&self, name: &Symbol) -> Option<usize> {
+        let hook = self.get_scope().get_variable(name)?.value.hook;
+        let function = &self.get_scope().name;
+        self.ir.hook_names.iter().rposition(|entry| {
+            entry.function == *function && entry.hook == hook && entry.name == name.to_string()
+        })
+    }
+
+    /// Visits a statement none of whose allocations outlive it between the
+    /// markers of an arena
+    fn visit_in_arena(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.add_statements(vec![ir::IRStatement::BeginArena]);
+        visit(self);
+        self.add_statements(vec![ir::IRStatement::EndArena]);
+    }
+
+    /// Whether an assignment stores a NUMBER, NUMBAR or TROOF in a declared
+    /// variable, which keeps nothing the expression allocates
+    fn assigns_plain_value(&self, var_assign: &ast::VariableAssignmentStatementNode) -> bool {
+        let name = match &var_assign.variable {
+            VariableAssignmentNodeVariableOption::Identifier(token) => match token.value() {
+                tokens::Token::Identifier(name) => name.clone(),
+                _ => return false,
+            },
+            _ => return false,
+        };
+
+        self.get_scope()
+            .get_variable(&name)
+            .is_some_and(|variable| {
+                matches!(
+                    variable.value.type_,
+                    Types::Number | Types::Numbar | Types::Troof
+                )
+            })
     }
 
     pub fn visit_variable_assignment(&mut self, var_assign: ast::VariableAssignmentStatementNode) {
         match var_assign.variable {
             VariableAssignmentNodeVariableOption::Identifier(token) => {
                 let name = match token.value() {
-                    tokens::Token::Identifier(name) => name,
+                    tokens::Token::Identifier(name) => name.clone(),
                     _ => panic!("Expected Identifier token"),
                 };
 
@@ -1743,14 +2453,12 @@ impl<'a> Visitor<'a> {
                 let variable = scope.get_variable(&name);
                 if let None = variable {
                     self.errors.push(VisitorError {
-                        message: format!("Variable {} not declared", name),
+                        message: format!("Variable {} not declared", name.escaped()),
                         token,
                     });
                     return;
                 }
 
-                self.add_statements(variable.unwrap().free());
-
                 let (expression, t) = self.visit_expression(var_assign.expression.clone());
                 self.free_hook(expression.hook);
 
@@ -1758,71 +2466,36 @@ impl<'a> Visitor<'a> {
                 let variable = scope.get_variable(&name);
 
                 if !expression.type_.equals(&variable.unwrap().value.type_) {
+                    let errors = self.errors.len();
                     self.errors.push(VisitorError {
                         message: format!(
                             "Variable {} is of type {} but expression is of type {}",
-                            name,
+                            name.escaped(),
                             variable.unwrap().value.type_.to_string(),
                             expression.type_.to_string()
                         ),
                         token: t,
                     });
+                    self.describe_errors(errors, &var_assign.expression);
                     return;
                 }
 
+                // the old value is freed only now as the expression may have used it
+                self.add_free(variable.unwrap().free());
+
                 let scope_mut = self.get_scope_mut();
                 let variable_mut = scope_mut.get_variable_mut(&name).unwrap();
                 let stmts = variable_mut.assign(&expression.type_);
                 self.add_statements(stmts);
             }
-            ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(var_dec) => {
-                self.visit_variable_declaration(var_dec.clone());
-
-                let token = var_dec.identifier;
-
-                let name = match token.value() {
-                    tokens::Token::Identifier(name) => name,
-                    _ => panic!("Expected Identifier token"),
-                };
-
-                let scope = self.get_scope();
-                let variable = scope.get_variable(&name);
-                if let None = variable {
-                    self.errors.push(VisitorError {
-                        message: format!("Variable {} not declared", name),
-                        token,
-                    });
-                    return;
-                }
-
-                self.add_statements(variable.unwrap().free());
-
-                let (expression, t) = self.visit_expression(var_assign.expression.clone());
-                self.free_hook(expression.hook);
-
-                let scope = self.get_scope();
-                let variable = scope.get_variable(&name);
-
-                if !expression.type_.equals(&variable.unwrap().value.type_) {
-                    self.errors.push(VisitorError {
-                        message: format!(
-                            "Variable {} is of type {} but expression is of type {}",
-                            name,
-                            variable.unwrap().value.type_.to_string(),
-                            expression.type_.to_string()
-                        ),
-                        token: t,
-                    });
-                    return;
-                }
-
-                let scope_mut = self.get_scope_mut();
-                let variable_mut = scope_mut.get_variable_mut(&name).unwrap();
-                let stmts = variable_mut.assign(&expression.type_);
-                self.add_statements(stmts);
-            }
-        }
-    }
+            ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(_) => {
+                panic!("Unexpected declaration, desugar splits it from its assignment")
+            }
+            ast::VariableAssignmentNodeVariableOption::BukkitSlot(slot) => {
+                self.visit_bukkit_slot_assignment(slot, var_assign.expression);
+            }
+        }
+    }
 
     pub fn visit_visible_statement(&mut self, visible: ast::VisibleStatementNode) {
         let (expr, _) = self.visit_smoosh_expression(ast::SmooshExpressionNode {
@@ -1836,10 +2509,10 @@ impl<'a> Visitor<'a> {
                 self.add_statements(vec![
                     ir::IRStatement::RefHook(expr.hook),
                     ir::IRStatement::Copy,
-                    ir::IRStatement::Push(size as f32),
-                    ir::IRStatement::CallForeign("print_string".to_string()),
+                    ir::IRStatement::StrPrint(size),
                 ]);
             }
+            Types::Noob => return, // the error has already been reported
             _ => panic!("Unexpected type"),
         }
 
@@ -1847,18 +2520,140 @@ impl<'a> Visitor<'a> {
             self.add_statements(vec![ir::IRStatement::CallForeign("prend".to_string())]);
         }
 
-        self.add_statements(expr.free());
+        self.add_free(expr.free());
+        self.add_statements(vec![ir::IRStatement::pop()]);
+    }
+
+    /// The line, counting from 1, that `token` starts on
+    pub fn line_of(&self, token: &ast::TokenNode) -> usize {
+        self.source.line(token.token.start)
+    }
+
+    pub fn visit_assure_statement(&mut self, assure: ast::AssureStatementNode) {
+        let start = self.get_statements().len();
+        let (expr, token) = self.visit_expression(assure.expression.clone());
+        self.free_hook(expr.hook);
+
+        if !expr.type_.equals(&Types::Troof) {
+            if !expr.type_.equals(&Types::Noob) {
+                let errors = self.errors.len();
+                self.errors.push(VisitorError {
+                    message: "Expected TROOF type".to_string(),
+                    token,
+                });
+                self.describe_errors(errors, &assure.expression);
+            }
+            return;
+        }
+
+        let line = self.line_of(&assure.token);
+        let failed = match assure.message {
+            Some(message_expression) => {
+                // the message is only built once the assertion has failed
+                let (message, message_token, mut statements) =
+                    self.visit_branch(message_expression.clone());
+                self.free_hook(message.hook);
+
+                match message.type_ {
+                    Types::Yarn(size) => statements.extend(vec![
+                        ir::IRStatement::RefHook(message.hook),
+                        ir::IRStatement::Copy,
+                        ir::IRStatement::Push(ir::Value::Int(size)),
+                        ir::IRStatement::Push(ir::Value::Int(line as i32)),
+                        ir::IRStatement::CallForeign("assure_failed_sayin".to_string()),
+                    ]),
+                    Types::Noob => return, // the error has already been reported
+                    _ => {
+                        let errors = self.errors.len();
+                        self.errors.push(VisitorError {
+                            message: "Expected YARN type".to_string(),
+                            token: message_token,
+                        });
+                        self.describe_errors(errors, &message_expression);
+                        return;
+                    }
+                }
+                statements
+            }
+            None => vec![
+                ir::IRStatement::Push(ir::Value::Int(line as i32)),
+                ir::IRStatement::CallForeign("assure_failed".to_string()),
+            ],
+        };
+
         self.add_statements(vec![
-            ir::IRStatement::BeginWhile,
-            ir::IRStatement::Push(0.0),
-            ir::IRStatement::EndWhile,
+            ir::IRStatement::RefHook(expr.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::If {
+                then: vec![],
+                else_: failed,
+            },
+            ir::IRStatement::pop(),
         ]);
+
+        if self.release {
+            let mut statements = self.get_statements();
+            statements.truncate(start);
+            self.set_statements(statements);
+        }
+    }
+
+    pub fn visit_dumpz_statement(&mut self, dumpz: ast::DumpzStatementNode) {
+        let start = self.get_statements().len();
+        // a variable is dumped under its name
+        let name = match &dumpz.expression.value {
+            ast::ExpressionNodeValueOption::VariableReference(var_ref) => {
+                match var_ref.identifier.value() {
+                    tokens::Token::Identifier(name) => self.hook_name_index(name),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let (expr, _) = self.visit_expression(dumpz.expression);
+        self.free_hook(expr.hook);
+
+        let mut statements = vec![];
+        if let Some(index) = name {
+            statements.push(ir::IRStatement::Push(ir::Value::Int(index as i32)));
+            statements.push(ir::IRStatement::CallForeign("dump_name".to_string()));
+        }
+        statements.push(ir::IRStatement::RefHook(expr.hook));
+        statements.push(ir::IRStatement::Copy);
+        let dump = match expr.type_ {
+            Types::Number => "dump_number",
+            Types::Numbar => "dump_numbar",
+            Types::Yarn(size) => {
+                statements.push(ir::IRStatement::Push(ir::Value::Int(size)));
+                "dump_yarn"
+            }
+            Types::Troof => "dump_troof",
+            Types::Noob => "dump_noob",
+            Types::Bukkit(_) => "dump_bukkit",
+        };
+        statements.push(ir::IRStatement::CallForeign(dump.to_string()));
+        self.add_statements(statements);
+
+        self.add_free(expr.free());
+        self.add_statements(vec![ir::IRStatement::pop()]);
+
+        // like ASSURE it is only type checked in release builds
+        if self.release {
+            let mut statements = self.get_statements();
+            statements.truncate(start);
+            self.set_statements(statements);
+        }
     }
 
     pub fn visit_gimmeh_statement(&mut self, gimmeh: ast::GimmehStatementNode) {
+        let letter = match &gimmeh.mode {
+            Some(mode) => matches!(mode.value(), tokens::Token::Word(word) if word == "LETTAR"),
+            None => false,
+        };
+
         let token = gimmeh.identifier;
         let name = match token.value() {
-            tokens::Token::Identifier(name) => name,
+            tokens::Token::Identifier(name) => name.clone(),
             _ => panic!("Expected Identifier token"),
         };
 
@@ -1866,7 +2661,7 @@ impl<'a> Visitor<'a> {
         let variable = scope.get_variable(&name);
         if let None = variable {
             self.errors.push(VisitorError {
-                message: format!("Variable {} not declared", name),
+                message: format!("Variable {} not declared", name.escaped()),
                 token,
             });
             return;
@@ -1874,23 +2669,2325 @@ impl<'a> Visitor<'a> {
 
         let variable = variable.unwrap();
 
+        // a single letter can also be read as its character code
+        if letter && variable.value.type_.equals(&Types::Number) {
+            self.add_statements(vec![ir::IRStatement::CallForeign("read_char".to_string())]);
+
+            let scope_mut = self.get_scope_mut();
+            let variable_mut = scope_mut.get_variable_mut(&name).unwrap();
+            let stmts = variable_mut.assign(&Types::Number);
+            self.add_statements(stmts);
+            self.set_it_to_read_status();
+            return;
+        }
+
         if !variable.value.type_.equals(&Types::Yarn(-1)) {
+            let expected = if letter { "YARN or NUMBER" } else { "YARN" };
             self.errors.push(VisitorError {
-                message: format!("Variable {} is not of type YARN", name),
+                message: format!("Variable {} is not of type {}", name.escaped(), expected),
                 token,
             });
             return;
         }
 
-        self.add_statements(variable.free());
+        self.add_free(variable.free());
 
-        self.add_statements(vec![ir::IRStatement::CallForeign(
-            "read_string".to_string(),
-        )]);
+        let (function, size) = if letter {
+            ("read_letter", 1)
+        } else {
+            ("read_string", 256) // 256 is the default buffer size
+        };
+        self.add_statements(vec![ir::IRStatement::CallForeign(function.to_string())]);
 
         let scope_mut = self.get_scope_mut();
         let variable_mut = scope_mut.get_variable_mut(&name).unwrap();
-        let stmts = variable_mut.assign(&Types::Yarn(256)); // 256 is the default buffer size
+        let stmts = variable_mut.assign(&Types::Yarn(size));
+        self.add_statements(stmts);
+        self.set_it_to_read_status();
+    }
+
+    /// IT becomes WIN if the last GIMMEH read something and FAIL at the end of input
+    fn set_it_to_read_status(&mut self) {
+        let it = match self.get_scope().get_variable(&Symbol::intern("IT")) {
+            Some(it) => it,
+            None => return,
+        };
+        self.add_free(it.free());
+
+        self.add_statements(vec![ir::IRStatement::CallForeign(
+            "read_succeeded".to_string(),
+        )]);
+        let it = self
+            .get_scope_mut()
+            .get_variable_mut(&Symbol::intern("IT"))
+            .unwrap();
+        let stmts = it.assign(&Types::Troof);
         self.add_statements(stmts);
     }
+
+    /// Statements pushing the bukkit a slot belongs to, that bukkit's slot
+    /// types and the slot's name
+    fn get_bukkit(
+        &mut self,
+        slot: &ast::BukkitSlotNode,
+    ) -> Option<(Vec<ir::IRStatement>, usize, String)> {
+        let name = match slot.bukkit.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+
+        let variable = match self.get_scope().get_variable(&name) {
+            Some(variable) => variable,
+            None => {
+                self.errors.push(VisitorError {
+                    message: format!("Variable {} not declared", name.escaped()),
+                    token: slot.bukkit.clone(),
+                });
+                return None;
+            }
+        };
+
+        let mut bukkit = match variable.value.type_ {
+            Types::Bukkit(bukkit) => bukkit,
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!("Variable {} is not of type BUKKIT", name.escaped()),
+                    token: slot.bukkit.clone(),
+                });
+                return None;
+            }
+        };
+        let mut statements = vec![
+            ir::IRStatement::RefHook(variable.value.hook),
+            ir::IRStatement::Copy,
+        ];
+
+        for inner in slot.path.iter() {
+            let inner_name = match inner.value() {
+                tokens::Token::Identifier(name) => name.clone(),
+                _ => panic!("Expected Identifier token"),
+            };
+
+            bukkit = match self.bukkits[bukkit].get(inner_name.as_str()) {
+                Some(Types::Bukkit(inner_bukkit)) => *inner_bukkit,
+                Some(_) => {
+                    self.errors.push(VisitorError {
+                        message: format!("Slot {} is not of type BUKKIT", inner_name.escaped()),
+                        token: inner.clone(),
+                    });
+                    return None;
+                }
+                None => {
+                    self.errors.push(VisitorError {
+                        message: format!("Slot {} not declared", inner_name.escaped()),
+                        token: inner.clone(),
+                    });
+                    return None;
+                }
+            };
+
+            // the nested bukkit is only borrowed while its slot is used
+            statements.push(ir::IRStatement::Push(
+                self.get_slot_key(inner_name.as_str()),
+            ));
+            statements.push(ir::IRStatement::CallForeign("bukkit_get".to_string()));
+        }
+
+        let slot_name = match slot.slot.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+
+        Some((statements, bukkit, slot_name.to_string()))
+    }
+
+    fn get_slot_key(&mut self, slot_name: &str) -> ir::Value {
+        let index = match self.slot_keys.iter().position(|key| key == slot_name) {
+            Some(index) => index,
+            None => {
+                self.slot_keys.push(slot_name.to_string());
+                self.slot_keys.len() - 1
+            }
+        };
+
+        ir::Value::Int(index as i32 + 1)
+    }
+
+    /// Slot types for a deep copy of a bukkit, nested bukkits get copies too
+    fn copy_bukkit(&mut self, bukkit: usize) -> usize {
+        let mut slots = self.bukkits[bukkit].clone();
+        for type_ in slots.values_mut() {
+            if let Types::Bukkit(inner) = type_ {
+                *type_ = Types::Bukkit(self.copy_bukkit(*inner));
+            }
+        }
+
+        self.bukkits.push(slots);
+        self.bukkits.len() - 1
+    }
+
+    /// Evaluates `expression` into the slot, returning its type if it could be stored
+    fn put_bukkit_slot(
+        &mut self,
+        get_bukkit: Vec<ir::IRStatement>,
+        slot_name: &str,
+        expression: ast::ExpressionNode,
+    ) -> Option<(Types, ast::TokenNode)> {
+        let (expression, t) = self.visit_expression(expression);
+        self.free_hook(expression.hook);
+
+        let size = match expression.type_ {
+            Types::Number | Types::Numbar | Types::Troof => 0,
+            Types::Yarn(size) => size,
+            Types::Bukkit(_) => -1,
+            Types::Noob => return None, // the error has already been reported
+        };
+
+        // the bukkit takes over the value, freeing any it replaces
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(size))]);
+        self.add_statements(get_bukkit);
+        let key = self.get_slot_key(slot_name);
+        self.add_statements(vec![
+            ir::IRStatement::Push(key),
+            ir::IRStatement::CallForeign("bukkit_put".to_string()),
+        ]);
+
+        Some((expression.type_, t))
+    }
+
+    pub fn visit_bukkit_slot_declaration(
+        &mut self,
+        slot_dec: ast::BukkitSlotDeclarationStatementNode,
+    ) {
+        let (get_bukkit, bukkit, slot_name) = match self.get_bukkit(&slot_dec.slot) {
+            Some(bukkit) => bukkit,
+            None => return,
+        };
+
+        if self.bukkits[bukkit].contains_key(&slot_name) {
+            self.errors.push(VisitorError {
+                message: format!(
+                    "Slot {} already declared",
+                    lexer::escape_identifier(&slot_name)
+                ),
+                token: slot_dec.slot.slot,
+            });
+            return;
+        }
+
+        if let Some((type_, _)) = self.put_bukkit_slot(get_bukkit, &slot_name, slot_dec.expression)
+        {
+            self.bukkits[bukkit].insert(slot_name, type_);
+        }
+    }
+
+    pub fn visit_bukkit_slot_assignment(
+        &mut self,
+        slot: ast::BukkitSlotNode,
+        expression: ast::ExpressionNode,
+    ) {
+        let (get_bukkit, bukkit, slot_name) = match self.get_bukkit(&slot) {
+            Some(bukkit) => bukkit,
+            None => return,
+        };
+
+        let slot_type = match self.bukkits[bukkit].get(&slot_name) {
+            Some(type_) => type_.clone(),
+            None => {
+                self.errors.push(VisitorError {
+                    message: format!("Slot {} not declared", lexer::escape_identifier(&slot_name)),
+                    token: slot.slot,
+                });
+                return;
+            }
+        };
+
+        let (type_, t) = match self.put_bukkit_slot(get_bukkit, &slot_name, expression.clone()) {
+            Some(stored) => stored,
+            None => return,
+        };
+
+        if !type_.equals(&slot_type) {
+            let errors = self.errors.len();
+            self.errors.push(VisitorError {
+                message: format!(
+                    "Slot {} is of type {} but expression is of type {}",
+                    lexer::escape_identifier(&slot_name),
+                    slot_type.to_string(),
+                    type_.to_string()
+                ),
+                token: t,
+            });
+            self.describe_errors(errors, &expression);
+            return;
+        }
+
+        self.bukkits[bukkit].insert(slot_name, type_);
+    }
+
+    pub fn visit_bukkit_slot(
+        &mut self,
+        slot: ast::BukkitSlotNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (get_bukkit, bukkit, slot_name) = match self.get_bukkit(&slot) {
+            Some(bukkit) => bukkit,
+            None => return (VariableValue::new(-1, Types::Noob), slot.slot),
+        };
+
+        let type_ = match self.bukkits[bukkit].get(&slot_name) {
+            Some(type_) => type_.clone(),
+            None => {
+                self.errors.push(VisitorError {
+                    message: format!("Slot {} not declared", lexer::escape_identifier(&slot_name)),
+                    token: slot.slot.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), slot.slot);
+            }
+        };
+
+        let mut get = get_bukkit;
+        get.push(ir::IRStatement::Push(self.get_slot_key(&slot_name)));
+        get.push(ir::IRStatement::CallForeign("bukkit_get".to_string()));
+
+        match type_ {
+            Types::Yarn(size) => {
+                // the bukkit keeps its YARN, the expression gets a copy
+                self.add_statements(vec![
+                    ir::IRStatement::Push(ir::Value::Int(size)),
+                    ir::IRStatement::StrAlloc,
+                ]);
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+
+                self.add_statements(get);
+                self.add_statements(vec![
+                    ir::IRStatement::StrLoad(size),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::StrStore(size),
+                ]);
+
+                (VariableValue::new(hook, type_), slot.slot)
+            }
+            Types::Bukkit(_) => {
+                self.add_statements(get);
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "bukkit_retain".to_string(),
+                )]);
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+
+                (VariableValue::new(hook, type_), slot.slot)
+            }
+            _ => {
+                self.add_statements(get);
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+
+                (VariableValue::new(hook, type_), slot.slot)
+            }
+        }
+    }
+
+    pub fn visit_kopy_expression(
+        &mut self,
+        kopy_expr: ast::KopyExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (expression, token) = self.visit_expression(*kopy_expr.expression.clone());
+
+        self.free_hook(expression.hook);
+
+        let bukkit = match expression.type_ {
+            Types::Bukkit(bukkit) => bukkit,
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot KOPY type {}, only BUKKITs can be copied",
+                        expression.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        };
+
+        // bukkit_kopy gives up the expression's reference to the original
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(expression.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::CallForeign("bukkit_kopy".to_string()),
+        ]);
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        let type_ = Types::Bukkit(self.copy_bukkit(bukkit));
+        (VariableValue::new(hook, type_), token)
+    }
+
+    /// Visits one branch of a WUN OF into statements of its own
+    fn visit_branch(
+        &mut self,
+        expression: ast::ExpressionNode,
+    ) -> (VariableValue, ast::TokenNode, Vec<ir::IRStatement>) {
+        let start = self.get_statements().len();
+        let (value, token) = self.visit_expression(expression);
+
+        let mut statements = self.get_statements();
+        let branch = statements.split_off(start);
+        self.set_statements(statements);
+
+        (value, token, branch)
+    }
+
+    /// Copies a YARN into a zero padded one of `size`, freeing the original
+    fn pad_yarn(&mut self, value: &VariableValue, size: i32) -> Vec<ir::IRStatement> {
+        let yarn_size = match value.type_ {
+            Types::Yarn(yarn_size) if yarn_size < size => yarn_size,
+            _ => return vec![],
+        };
+
+        let (hook, stmt) = self.get_hook();
+        self.free_hook(hook);
+
+        let mut statements = vec![
+            ir::IRStatement::Push(ir::Value::Int(size)),
+            ir::IRStatement::StrAlloc,
+            stmt,
+            ir::IRStatement::RefHook(value.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::StrLoad(yarn_size),
+            ir::IRStatement::RefHook(hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::StrStore(yarn_size),
+        ];
+        if !self.gc {
+            statements.extend(value.free());
+        }
+        statements
+    }
+
+    pub fn visit_wun_of_expression(
+        &mut self,
+        wun_of_expr: ast::WunOfExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]); // return value
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        let (condition, condition_token) = self.visit_expression(*wun_of_expr.condition.clone());
+        self.free_hook(condition.hook);
+
+        if !condition.type_.equals(&Types::Troof) {
+            self.errors.push(VisitorError {
+                message: "Expected TROOF type".to_string(),
+                token: condition_token.clone(),
+            });
+            return (VariableValue::new(-1, Types::Noob), condition_token);
+        }
+
+        let (then, _, mut then_statements) = self.visit_branch(*wun_of_expr.then.clone());
+        let (else_, else_token, mut else_statements) =
+            self.visit_branch(*wun_of_expr.else_.clone());
+
+        if then.type_.equals(&Types::Noob) || else_.type_.equals(&Types::Noob) {
+            // the error has already been reported
+            return (VariableValue::new(-1, Types::Noob), condition_token);
+        }
+
+        let type_ = match (&then.type_, &else_.type_) {
+            (Types::Yarn(a), Types::Yarn(b)) => Types::Yarn(*a.max(b)),
+            (Types::Bukkit(a), Types::Bukkit(b)) if a != b => {
+                self.errors.push(VisitorError {
+                    message: "Expected the same BUKKIT in both branches".to_string(),
+                    token: else_token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), else_token);
+            }
+            (a, b) if a.equals(b) => a.clone(),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Expected {} type but got {}",
+                        then.type_.to_string(),
+                        else_.type_.to_string()
+                    ),
+                    token: else_token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), else_token);
+            }
+        };
+
+        // both branches leave a value of the same size in the return value
+        if let Types::Yarn(size) = type_ {
+            then_statements.extend(self.pad_yarn(&then, size));
+            else_statements.extend(self.pad_yarn(&else_, size));
+        }
+        self.free_hook(then.hook);
+        self.free_hook(else_.hook);
+        // and drop their temporaries so the return value is on top again
+        for branch in [&mut then_statements, &mut else_statements] {
+            branch.extend(vec![
+                ir::IRStatement::RefHook(hook),
+                ir::IRStatement::Mov,
+                ir::IRStatement::RefHook(hook),
+                ir::IRStatement::CallForeign("stack_unwind".to_string()),
+            ]);
+        }
+
+        self.add_statements(vec![ir::IRStatement::If {
+            then: then_statements,
+            else_: else_statements,
+        }]);
+
+        (VariableValue::new(hook, type_), condition_token)
+    }
+
+    pub fn visit_lengthz_expression(
+        &mut self,
+        lengthz_expr: ast::LengthzExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (expression, token) = self.visit_expression(*lengthz_expr.expression.clone());
+
+        self.free_hook(expression.hook);
+
+        match expression.type_ {
+            Types::Yarn(size) => {
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::Push(ir::Value::Int(size)),
+                    ir::IRStatement::CallForeign("yarn_length".to_string()),
+                ]);
+            }
+            Types::Bukkit(_) => {
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::CallForeign("bukkit_length".to_string()),
+                ]);
+            }
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot take LENGTHZ OF type {}, only YARNs and BUKKITs have a length",
+                        expression.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        }
+
+        self.add_free(expression.free());
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        (VariableValue::new(hook, Types::Number), token)
+    }
+
+    pub fn visit_codez_expression(
+        &mut self,
+        codez_expr: ast::CodezExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (expression, token) = self.visit_expression(*codez_expr.expression.clone());
+
+        self.free_hook(expression.hook);
+
+        match expression.type_ {
+            Types::Yarn(size) => {
+                // panics unless the YARN is one letter long
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(expression.hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::Push(ir::Value::Int(size)),
+                    ir::IRStatement::CallForeign("letter_code".to_string()),
+                ]);
+            }
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot take CODEZ OF type {}, only a YARN has letters",
+                        expression.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        }
+
+        self.add_free(expression.free());
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        (VariableValue::new(hook, Types::Number), token)
+    }
+
+    pub fn visit_lettar_expression(
+        &mut self,
+        lettar_expr: ast::LettarExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (expression, token) = self.visit_expression(*lettar_expr.expression.clone());
+
+        self.free_hook(expression.hook);
+
+        match expression.type_ {
+            // panics unless the NUMBER is a character code
+            Types::Number => self.add_statements(vec![
+                ir::IRStatement::RefHook(expression.hook),
+                ir::IRStatement::Copy,
+                ir::IRStatement::CallForeign("code_letter".to_string()),
+            ]),
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot take LETTAR OF type {}, expected a NUMBER character code",
+                        expression.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        }
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        (VariableValue::new(hook, Types::Yarn(1)), token)
+    }
+
+    /// The size of a YARN separator, or None after reporting an error
+    fn separator_size(&mut self, separator: &VariableValue, token: &ast::TokenNode) -> Option<i32> {
+        match separator.type_ {
+            Types::Yarn(size) => Some(size),
+            Types::Noob => None,
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Expected YARN type for the separator but got {}",
+                        separator.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    pub fn visit_splitz_expression(
+        &mut self,
+        splitz_expr: ast::SplitzExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (yarn, token) = self.visit_expression(*splitz_expr.yarn.clone());
+        let (separator, separator_token) = self.visit_expression(*splitz_expr.separator.clone());
+
+        self.free_hook(yarn.hook);
+        self.free_hook(separator.hook);
+
+        let size = match yarn.type_ {
+            Types::Yarn(size) => size,
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot SPLITZ type {}, only a YARN can be split",
+                        yarn.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        };
+        let separator_size = match self.separator_size(&separator, &separator_token) {
+            Some(size) => size,
+            None => return (VariableValue::new(-1, Types::Noob), separator_token),
+        };
+
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(yarn.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(size)),
+            ir::IRStatement::RefHook(separator.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(separator_size)),
+            ir::IRStatement::CallForeign("yarn_split".to_string()),
+        ]);
+
+        self.add_free(yarn.free());
+        self.add_free(separator.free());
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        // every piece is as long as the whole YARN, which no piece outgrows
+        let mut slots = BTreeMap::new();
+        slots.insert(ANY_SLOT.to_string(), Types::Yarn(size.max(1)));
+        self.bukkits.push(slots);
+        (
+            VariableValue::new(hook, Types::Bukkit(self.bukkits.len() - 1)),
+            token,
+        )
+    }
+
+    /// The longest YARN JOINZ can make of a bukkit with these slot types and a
+    /// separator of this size
+    fn joined_size(&self, bukkit: usize, separator: i32) -> Result<i32, String> {
+        let mut letters = 0;
+        let mut pieces = 0;
+
+        for (name, type_) in self.bukkits[bukkit].iter() {
+            let size = match type_ {
+                Types::Yarn(size) => *size,
+                _ => {
+                    return Err(format!(
+                        "Cannot JOINZ a BUKKIT with a {} slot, every slot must be a YARN",
+                        type_.to_string()
+                    ))
+                }
+            };
+
+            letters += size;
+            // SPLITZ makes at most one piece more than the YARN has letters
+            pieces += if name == ANY_SLOT { size + 1 } else { 1 };
+        }
+
+        Ok((letters + (pieces - 1).max(0) * separator).max(1))
+    }
+
+    pub fn visit_joinz_expression(
+        &mut self,
+        joinz_expr: ast::JoinzExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (bukkit, token) = self.visit_expression(*joinz_expr.bukkit.clone());
+        let (separator, separator_token) = self.visit_expression(*joinz_expr.separator.clone());
+
+        self.free_hook(bukkit.hook);
+        self.free_hook(separator.hook);
+
+        let shape = match bukkit.type_ {
+            Types::Bukkit(shape) => shape,
+            Types::Noob => return (VariableValue::new(-1, Types::Noob), token),
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot JOINZ type {}, only a BUKKIT of YARNs can be joined",
+                        bukkit.type_.to_string()
+                    ),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        };
+        let separator_size = match self.separator_size(&separator, &separator_token) {
+            Some(size) => size,
+            None => return (VariableValue::new(-1, Types::Noob), separator_token),
+        };
+        let size = match self.joined_size(shape, separator_size) {
+            Ok(size) => size,
+            Err(message) => {
+                self.errors.push(VisitorError {
+                    message,
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        };
+
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(bukkit.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(size)),
+            ir::IRStatement::RefHook(separator.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(separator_size)),
+            ir::IRStatement::CallForeign("yarn_join".to_string()),
+        ]);
+
+        self.add_free(bukkit.free());
+        self.add_free(separator.free());
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        (VariableValue::new(hook, Types::Yarn(size)), token)
+    }
+
+    pub fn visit_matchez_expression(
+        &mut self,
+        matchez_expr: ast::MatchezExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let (yarn, token) = self.visit_expression(*matchez_expr.yarn.clone());
+        let (pattern, pattern_token) = self.visit_expression(*matchez_expr.pattern.clone());
+
+        self.free_hook(yarn.hook);
+        self.free_hook(pattern.hook);
+
+        let mut sizes = vec![];
+        for (value, token) in [(&yarn, &token), (&pattern, &pattern_token)] {
+            match value.type_ {
+                Types::Yarn(size) => sizes.push(size),
+                Types::Noob => return (VariableValue::new(-1, Types::Noob), token.clone()),
+                _ => {
+                    self.errors.push(VisitorError {
+                        message: format!(
+                            "Expected YARN type for MATCHEZ but got {}",
+                            value.type_.to_string()
+                        ),
+                        token: token.clone(),
+                    });
+                    return (VariableValue::new(-1, Types::Noob), token.clone());
+                }
+            }
+        }
+
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(yarn.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(sizes[0])),
+            ir::IRStatement::RefHook(pattern.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(sizes[1])),
+            ir::IRStatement::CallForeign("yarn_matchez".to_string()),
+        ]);
+
+        self.add_free(yarn.free());
+        self.add_free(pattern.free());
+
+        let (hook, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        (VariableValue::new(hook, Types::Troof), token)
+    }
+
+    /// The type every slot of a bukkit has, which a WATCHIN loop's item takes
+    fn watched_type(&self, bukkit: usize) -> Result<Types, String> {
+        let mut watched: Option<Types> = None;
+
+        for type_ in self.bukkits[bukkit].values() {
+            watched = match (watched, type_) {
+                (None, type_) => Some(type_.clone()),
+                // the item is long enough for the longest YARN
+                (Some(Types::Yarn(a)), Types::Yarn(b)) => Some(Types::Yarn(a.max(*b))),
+                (Some(Types::Bukkit(a)), Types::Bukkit(b)) if a != *b => {
+                    return Err(
+                        "Every BUKKIT slot of a watched BUKKIT must be the same BUKKIT".to_string(),
+                    );
+                }
+                (Some(watched), type_) if watched.equals(type_) => Some(watched),
+                _ => {
+                    return Err("Slots of a watched BUKKIT must all be of one type".to_string());
+                }
+            };
+        }
+
+        watched.ok_or_else(|| "Cannot watch a BUKKIT without slots".to_string())
+    }
+
+    pub fn visit_watchin_loop_statement(&mut self, loop_stmt: ast::WatchinLoopStatementNode) {
+        let item_name = match loop_stmt.item.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+
+        let (bukkit, token) = self.visit_expression(loop_stmt.bukkit.clone());
+        let item_type = match bukkit.type_ {
+            Types::Bukkit(shape) => match self.watched_type(shape) {
+                Ok(type_) => type_,
+                Err(message) => {
+                    self.errors.push(VisitorError { message, token });
+                    return;
+                }
+            },
+            Types::Noob => return, // the error has already been reported
+            _ => {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Cannot watch type {}, only BUKKITs can be watched",
+                        bukkit.type_.to_string()
+                    ),
+                    token,
+                });
+                return;
+            }
+        };
+
+        if self.get_scope().get_variable(&item_name).is_some() {
+            self.errors.push(VisitorError {
+                message: format!("Variable {} already declared", item_name.escaped()),
+                token: loop_stmt.item,
+            });
+            return;
+        }
+
+        let running = self.enter_loop(&loop_stmt.statements, &loop_stmt.label);
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+        let (index, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        let condition = vec![
+            ir::IRStatement::RefHook(bukkit.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::RefHook(index),
+            ir::IRStatement::Copy,
+            ir::IRStatement::CallForeign("bukkit_has_index".to_string()),
+        ];
+        self.add_statements(condition.clone());
+        let start = self.get_statements().len();
+
+        let get_entry = vec![
+            ir::IRStatement::RefHook(bukkit.hook),
+            ir::IRStatement::Copy,
+            ir::IRStatement::RefHook(index),
+            ir::IRStatement::Copy,
+        ];
+        let item = match item_type {
+            Types::Yarn(size) => {
+                self.add_statements(vec![
+                    ir::IRStatement::Push(ir::Value::Int(size)),
+                    ir::IRStatement::StrAlloc,
+                ]);
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+                self.add_statements(get_entry);
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Copy,
+                    ir::IRStatement::CallForeign("bukkit_copy_yarn".to_string()),
+                ]);
+                hook
+            }
+            _ => {
+                self.add_statements(get_entry);
+                self.add_statements(vec![ir::IRStatement::CallForeign(
+                    "bukkit_value".to_string(),
+                )]);
+                if item_type.equals(&Types::Bukkit(0)) {
+                    self.add_statements(vec![ir::IRStatement::CallForeign(
+                        "bukkit_retain".to_string(),
+                    )]);
+                }
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+                hook
+            }
+        };
+
+        let outer: Vec<Symbol> = self.get_scope().variables.keys().cloned().collect();
+        self.name_hook(&item_name, item, &item_type);
+        self.get_scope_mut().add_variable(
+            item_name,
+            VariableData::new(VariableValue::new(item, item_type)),
+        );
+
+        self.gc_roots.push(bukkit.hook);
+        self.visit_statements(loop_stmt.statements);
+        self.gc_roots.pop();
+
+        // the item and anything declared in the body only live for one pass
+        let inner: Vec<Symbol> = self
+            .get_scope()
+            .variables
+            .keys()
+            .filter(|name| !outer.contains(name))
+            .cloned()
+            .collect();
+        for name in inner {
+            let variable = self.get_scope_mut().variables.remove(&name).unwrap();
+            self.add_free(variable.free());
+            self.free_hook(variable.value.hook);
+        }
+
+        self.add_statements(vec![
+            ir::IRStatement::RefHook(index),
+            ir::IRStatement::CallForeign("stack_unwind".to_string()),
+            ir::IRStatement::RefHook(index),
+            ir::IRStatement::Copy,
+            ir::IRStatement::Push(ir::Value::Int(1)),
+            ir::IRStatement::Add,
+            ir::IRStatement::RefHook(index),
+            ir::IRStatement::Mov,
+        ]);
+        self.add_statements(Self::next_pass(running, condition));
+
+        let mut statements = self.get_statements();
+        let body = statements.split_off(start);
+        statements.push(ir::IRStatement::Loop { body });
+        self.set_statements(statements);
+
+        self.add_free(bukkit.free());
+        self.free_hook(bukkit.hook);
+        self.free_hook(index);
+        self.leave_loop(running);
+    }
+
+    /// Runs the body while the TIL expression is FAIL or the WILE one WIN,
+    /// or for ever with neither, after each pass counting the loop's
+    /// variable, if it has one, which only lives in the loop and starts at
+    /// 0, up by one with UPPIN or down with NERFIN
+    pub fn visit_loop_statement(&mut self, loop_stmt: ast::LoopStatementNode) {
+        let counting = match &loop_stmt.counter {
+            Some((operation, variable)) => {
+                let name = match variable.value() {
+                    tokens::Token::Identifier(name) => name.clone(),
+                    _ => panic!("Expected Identifier token"),
+                };
+                if self.get_scope().get_variable(&name).is_some() {
+                    self.errors.push(VisitorError {
+                        message: format!("Variable {} already declared", name.escaped()),
+                        token: variable.clone(),
+                    });
+                    return;
+                }
+                let step = match operation.value() {
+                    tokens::Token::Word(word) if word == "NERFIN" => -1,
+                    _ => 1,
+                };
+                Some((name, step))
+            }
+            None => None,
+        };
+
+        let running = self.enter_loop(&loop_stmt.statements, &loop_stmt.label);
+        let counter = counting.map(|(name, step)| {
+            self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+            let (counter, stmt) = self.get_hook();
+            self.add_statements(vec![stmt]);
+            self.name_hook(&name, counter, &Types::Number);
+            self.get_scope_mut().add_variable(
+                name.clone(),
+                VariableData::new(VariableValue::new(counter, Types::Number)),
+            );
+            (name, counter, step)
+        });
+
+        if let Some(head) = self.loop_types(&loop_stmt.statements, &loop_stmt.label) {
+            let before = self.variable_types();
+            for (name, type_) in &head {
+                let stmts = self.convert_variable(name, &before[name], type_);
+                self.add_statements(stmts);
+            }
+            self.set_variable_types(&head);
+
+            if let Some(condition) = self.loop_condition(&loop_stmt) {
+                self.add_statements(condition.clone());
+
+                let (mut body, after) = self.visit_block(loop_stmt.statements);
+                for (name, type_) in &after {
+                    body.extend(self.convert_variable(name, type_, &head[name]));
+                }
+                self.set_variable_types(&head);
+
+                if let Some((_, counter, step)) = counter {
+                    body.extend(vec![
+                        ir::IRStatement::RefHook(counter),
+                        ir::IRStatement::CallForeign("stack_unwind".to_string()),
+                        ir::IRStatement::RefHook(counter),
+                        ir::IRStatement::Copy,
+                        ir::IRStatement::Push(ir::Value::Int(step)),
+                        ir::IRStatement::Add,
+                        ir::IRStatement::RefHook(counter),
+                        ir::IRStatement::Mov,
+                    ]);
+                }
+                body.extend(Self::next_pass(running, condition));
+                self.add_statements(vec![ir::IRStatement::Loop { body }]);
+            }
+        }
+
+        if let Some((name, counter, _)) = counter {
+            self.get_scope_mut().variables.remove(&name);
+            self.free_hook(counter);
+        }
+        self.leave_loop(running);
+    }
+
+    /// The hook of the flag a loop whose body can leave it clears to do
+    /// so, set before the loop, or None if it cannot
+    fn enter_loop(
+        &mut self,
+        statements: &[ast::StatementNode],
+        label: &ast::TokenNode,
+    ) -> Option<i32> {
+        if !statements.iter().any(leaves) {
+            return None;
+        }
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(1))]);
+        let (running, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        self.exits.push(Exit {
+            hook: running,
+            function: false,
+            token: label.clone(),
+        });
+        Some(running)
+    }
+
+    /// Statements pushing whether a loop runs another pass after its body,
+    /// which is FAIL once the body has cleared `running` to leave it
+    fn next_pass(running: Option<i32>, condition: Vec<ir::IRStatement>) -> Vec<ir::IRStatement> {
+        match running {
+            Some(running) => vec![
+                ir::IRStatement::RefHook(running),
+                ir::IRStatement::Copy,
+                ir::IRStatement::If {
+                    then: condition,
+                    else_: vec![ir::IRStatement::Push(ir::Value::Int(0))],
+                },
+            ],
+            None => condition,
+        }
+    }
+
+    fn leave_loop(&mut self, running: Option<i32>) {
+        if let Some(running) = running {
+            self.exits.pop();
+            self.free_hook(running);
+        }
+    }
+
+    /// Statements pushing whether a loop runs another pass: WIN without a
+    /// condition, and otherwise its WILE expression or NOT its TIL one
+    fn loop_condition(
+        &mut self,
+        loop_stmt: &ast::LoopStatementNode,
+    ) -> Option<Vec<ir::IRStatement>> {
+        let expression = match &loop_stmt.condition_expression {
+            Some(expression) => expression.clone(),
+            None => return Some(vec![ir::IRStatement::Push(ir::Value::Int(1))]),
+        };
+        let til = matches!(
+            loop_stmt.condition.as_ref().map(|token| token.value()),
+            Some(tokens::Token::Word(word)) if word == "TIL"
+        );
+
+        // TIL of a comparison is the opposite comparison, as desugar makes
+        // of a NOT of one
+        let start = self.get_statements().len();
+        let errors = self.errors.len();
+        let (value, token) = match expression.value.clone() {
+            ast::ExpressionNodeValueOption::BothSaemExpression(e) if til => {
+                self.visit_comparison(*e.left, *e.right, false)
+            }
+            ast::ExpressionNodeValueOption::DiffrintExpression(e) if til => {
+                self.visit_comparison(*e.left, *e.right, true)
+            }
+            _ => {
+                let (value, token) = self.visit_expression(expression.clone());
+                if til && value.type_.equals(&Types::Troof) {
+                    self.add_statements(vec![
+                        ir::IRStatement::Push(ir::Value::Int(1)),
+                        ir::IRStatement::Add,
+                        ir::IRStatement::Push(ir::Value::Int(2)),
+                        ir::IRStatement::Modulo,
+                    ]);
+                }
+                (value, token)
+            }
+        };
+        self.describe_errors(errors, &expression);
+        self.free_hook(value.hook);
+        let mut statements = self.get_statements();
+        let condition = statements.split_off(start);
+        self.set_statements(statements);
+
+        if !value.type_.equals(&Types::Troof) {
+            if !value.type_.equals(&Types::Noob) {
+                self.errors.push(VisitorError {
+                    message: "Expected TROOF type".to_string(),
+                    token,
+                });
+            }
+            return None;
+        }
+        Some(condition)
+    }
+
+    /// The types the variables in scope go into a loop with, so that a pass
+    /// leaves them types they can be turned back into for the next: each
+    /// YARN as long as any pass makes it and IT NOOB if a pass changes its
+    /// type. They are settled once for the types the loop is entered with.
+    fn loop_types(
+        &mut self,
+        statements: &[ast::StatementNode],
+        label: &ast::TokenNode,
+    ) -> Option<BTreeMap<Symbol, Types>> {
+        let entry = self.variable_types();
+        let settled = self.loops.iter().find(|settled| {
+            settled.label == label.token.index
+                && same_types(&settled.entry, &entry)
+                && settled.bukkits.len() == self.bukkits.len()
+                && settled
+                    .bukkits
+                    .iter()
+                    .zip(&self.bukkits)
+                    .all(|(a, b)| same_types(a, b))
+        });
+        let head = match settled {
+            Some(settled) => settled.head.clone(),
+            None => {
+                let guesses = self.guesses;
+                let head = self.settle_loop(entry.clone(), statements, label);
+                if self.guesses == guesses {
+                    self.loops.push(SettledLoop {
+                        label: label.token.index,
+                        entry,
+                        bukkits: self.bukkits.clone(),
+                        head: head.clone(),
+                    });
+                }
+                head
+            }
+        };
+
+        match head {
+            Ok(head) => Some(head),
+            Err(error) => {
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Visits the body of a loop on trial until the types of the variables
+    /// in scope stop changing, starting from those it is entered with
+    fn settle_loop(
+        &mut self,
+        mut head: BTreeMap<Symbol, Types>,
+        statements: &[ast::StatementNode],
+        label: &ast::TokenNode,
+    ) -> Result<BTreeMap<Symbol, Types>, VisitorError> {
+        let label_name = match label.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+        let mut changed = None;
+        for _ in 0..LOOP_PASSES {
+            let checkpoint = self.checkpoint();
+            self.emit = false;
+            self.set_variable_types(&head);
+            let (_, after) = self.visit_block(statements.to_vec());
+            self.restore(checkpoint);
+
+            changed = None;
+            let mut next = BTreeMap::new();
+            for (name, type_) in &head {
+                let merged = match Self::merge_types(name, type_, &after[name]) {
+                    Some(merged) => merged,
+                    None => {
+                        return Err(VisitorError {
+                            message: format!(
+                                "Variable {} is given a different BUKKIT in loop {}",
+                                name.escaped(),
+                                label_name.escaped()
+                            ),
+                            token: label.clone(),
+                        });
+                    }
+                };
+                let same = match (type_, &merged) {
+                    (Types::Yarn(a), Types::Yarn(b)) => a == b,
+                    (a, b) => a.equals(b),
+                };
+                if !same {
+                    changed = Some(name.clone());
+                }
+                next.insert(name.clone(), merged);
+            }
+
+            if changed.is_none() {
+                return Ok(head);
+            }
+            head = next;
+        }
+
+        // only a YARN can change type on every pass, growing each time
+        Err(VisitorError {
+            message: format!(
+                "YARN {} grows with every pass of loop {}, so no size fits it",
+                changed.unwrap().escaped(),
+                label_name.escaped()
+            ),
+            token: label.clone(),
+        })
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        let scope = self.get_scope();
+        Checkpoint {
+            variables: scope.variables.clone(),
+            used_hooks: scope.used_hooks.clone(),
+            hook_spaces: self.hook_spaces.clone(),
+            errors: self.errors.len(),
+            bukkits: self.bukkits.len(),
+            slot_keys: self.slot_keys.len(),
+            hook_names: self.ir.hook_names.len(),
+            trace_points: self.trace.as_ref().map_or(0, |trace| trace.points.len()),
+            instances: self.instances.len(),
+            functions: self.ir.functions.len(),
+            emit: self.emit,
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        let scope = self.get_scope_mut();
+        scope.variables = checkpoint.variables;
+        scope.used_hooks = checkpoint.used_hooks;
+        self.hook_spaces = checkpoint.hook_spaces;
+        self.errors.truncate(checkpoint.errors);
+        self.bukkits.truncate(checkpoint.bukkits);
+        self.slot_keys.truncate(checkpoint.slot_keys);
+        self.ir.hook_names.truncate(checkpoint.hook_names);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.points.truncate(checkpoint.trace_points);
+        }
+        self.instances.truncate(checkpoint.instances);
+        self.ir.functions.truncate(checkpoint.functions);
+        self.emit = checkpoint.emit;
+    }
+
+    /// Runs YA RLY when IT is WIN and NO WAI otherwise. Desugaring has made
+    /// each MEBBE an If in the NO WAI of the one before.
+    pub fn visit_if_statement(&mut self, if_stmt: ast::IfStatementNode) {
+        let condition = match self.it_truth(&if_stmt.token) {
+            Some(condition) => condition,
+            None => return,
+        };
+        self.visit_branches(
+            condition,
+            if_stmt.statements,
+            if_stmt.else_.unwrap_or_default(),
+            &if_stmt.token,
+        );
+    }
+
+    /// Runs the OMG case whose literal IT is the same as, then every case
+    /// after it, OMGWTF included, until a GTFO, or OMGWTF alone if none is.
+    /// `choice` holds the number of the case that matched counting from 1,
+    /// or 0, and `running` whether the case reached is run.
+    pub fn visit_switch_statement(&mut self, switch: ast::SwitchStatementNode) {
+        let mut literals: Vec<&tokens::Token> = vec![];
+        for case in switch.cases.iter() {
+            let token = case.expression.token().unwrap_or(&switch.token);
+            if literal_type(&case.expression).is_none() {
+                self.errors.push(VisitorError {
+                    message: "Expected a literal after OMG".to_string(),
+                    token: token.clone(),
+                });
+                return;
+            }
+            if literals.contains(&token.value()) {
+                self.errors.push(VisitorError {
+                    message: "Another OMG of this WTF? has the same literal".to_string(),
+                    token: token.clone(),
+                });
+                return;
+            }
+            literals.push(token.value());
+        }
+
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+        let (choice, stmt) = self.get_hook();
+        self.add_statements(vec![stmt, ir::IRStatement::Push(ir::Value::Int(0))]);
+        let (running, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+
+        // a literal of another type than IT's is never the same as it, and
+        // the cases are tried last first so the first to match is chosen
+        let it_type = self
+            .get_scope()
+            .get_variable(&Symbol::intern("IT"))
+            .unwrap()
+            .value
+            .type_
+            .clone();
+        for (i, case) in switch.cases.iter().enumerate().rev() {
+            if !literal_type(&case.expression).unwrap().equals(&it_type) {
+                continue;
+            }
+            let it = ast::ExpressionNode {
+                value: ast::ExpressionNodeValueOption::ItReference(ast::ItReferenceNode {
+                    token: switch.token.clone(),
+                }),
+            };
+            let (same, _) = self.visit_comparison(it, case.expression.clone(), true);
+            self.add_statements(vec![ir::IRStatement::If {
+                then: vec![
+                    ir::IRStatement::Push(ir::Value::Int(i as i32 + 1)),
+                    ir::IRStatement::RefHook(choice),
+                    ir::IRStatement::Mov,
+                ],
+                else_: vec![],
+            }]);
+            self.free_hook(same.hook);
+        }
+
+        let run = vec![
+            ir::IRStatement::Push(ir::Value::Int(1)),
+            ir::IRStatement::RefHook(running),
+            ir::IRStatement::Mov,
+        ];
+        self.exits.push(Exit {
+            hook: running,
+            function: false,
+            token: switch.token.clone(),
+        });
+        for (i, case) in switch.cases.into_iter().enumerate() {
+            self.add_statements(vec![
+                ir::IRStatement::RefHook(choice),
+                ir::IRStatement::Copy,
+                ir::IRStatement::Push(ir::Value::Int(i as i32 + 1)),
+                ir::IRStatement::Subtract,
+                ir::IRStatement::If {
+                    then: vec![],
+                    else_: run.clone(),
+                },
+            ]);
+            self.visit_branches(
+                vec![ir::IRStatement::RefHook(running), ir::IRStatement::Copy],
+                case.statements,
+                vec![],
+                &switch.token,
+            );
+        }
+        if let Some(default) = switch.default {
+            self.add_statements(vec![
+                ir::IRStatement::RefHook(choice),
+                ir::IRStatement::Copy,
+                ir::IRStatement::If {
+                    then: vec![],
+                    else_: run,
+                },
+            ]);
+            self.visit_branches(
+                vec![ir::IRStatement::RefHook(running), ir::IRStatement::Copy],
+                default,
+                vec![],
+                &switch.token,
+            );
+        }
+        self.exits.pop();
+
+        self.free_hook(choice);
+        self.free_hook(running);
+    }
+
+    /// Clears the flag of the innermost WTF?, loop or function to leave it,
+    /// a function being left with NOOB
+    pub fn visit_gtfo_statement(&mut self, token: ast::TokenNode) {
+        let exit = match self.exits.last() {
+            Some(exit) => exit.clone(),
+            None => {
+                self.errors.push(VisitorError {
+                    message: "GTFO can only leave a WTF?, loop or function".to_string(),
+                    token,
+                });
+                return;
+            }
+        };
+        if exit.function {
+            let instance = &self.instances[self.callees.last().unwrap().instance];
+            if !instance.returns.equals(&Types::Noob) {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "GTFO returns NOOB, but function {} returns {}",
+                        instance.name.escaped(),
+                        instance.returns.to_string()
+                    ),
+                    token,
+                });
+                return;
+            }
+        }
+
+        self.add_statements(vec![
+            ir::IRStatement::Push(ir::Value::Int(0)),
+            ir::IRStatement::RefHook(exit.hook),
+            ir::IRStatement::Mov,
+        ]);
+    }
+
+    /// Returns the value of the expression from the function being visited,
+    /// clearing the flag of every WTF? and loop it is in as well as its own
+    pub fn visit_return_statement(&mut self, return_stmt: ast::ReturnStatementNode) {
+        let (value, token) = self.visit_expression(return_stmt.expression.clone());
+        let callee = match self.callees.last() {
+            Some(callee) => callee.clone(),
+            None => {
+                self.errors.push(VisitorError {
+                    message: "FOUND YR can only be used in a function".to_string(),
+                    token,
+                });
+                return;
+            }
+        };
+        // the error has already been reported
+        if value.type_.equals(&Types::Noob) && value.hook == -1 {
+            return;
+        }
+
+        let instance = &self.instances[callee.instance];
+        if !value.type_.equals(&instance.returns) {
+            let errors = self.errors.len();
+            self.errors.push(VisitorError {
+                message: format!(
+                    "Expected {} type for FOUND YR of {} but got {}",
+                    instance.returns.to_string(),
+                    instance.name.escaped(),
+                    value.type_.to_string()
+                ),
+                token,
+            });
+            self.describe_errors(errors, &return_stmt.expression);
+            self.free_hook(value.hook);
+            return;
+        }
+
+        self.set_return(value);
+        for exit in self.exits.clone() {
+            self.add_statements(vec![
+                ir::IRStatement::Push(ir::Value::Int(0)),
+                ir::IRStatement::RefHook(exit.hook),
+                ir::IRStatement::Mov,
+            ]);
+        }
+    }
+
+    /// Gives the caller a value of the type the function being visited
+    /// returns, padding a YARN to the size its calls expect
+    fn set_return(&mut self, value: VariableValue) {
+        let callee = self.callees.last_mut().unwrap();
+        let returns = self.instances[callee.instance].returns.clone();
+        match (&value.type_, &returns) {
+            (Types::Yarn(size), Types::Yarn(expected)) if size < expected => {
+                callee.found = callee.found.max(*size);
+                let statements = self.pad_yarn(&value, *expected);
+                self.add_statements(statements);
+            }
+            (type_, _) => {
+                if let Types::Yarn(size) = type_ {
+                    callee.found = callee.found.max(*size);
+                }
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(value.hook),
+                    ir::IRStatement::Copy,
+                ]);
+            }
+        }
+        self.add_statements(vec![ir::IRStatement::SetReturnRegister]);
+        self.free_hook(value.hook);
+    }
+
+    fn define_function(&mut self, function: ast::FunctionDefinitionStatementNode) {
+        let name = match function.identifier.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+        if self.definitions.contains_key(&name) {
+            self.errors.push(VisitorError {
+                message: format!("Function {} already defined", name.escaped()),
+                token: function.identifier,
+            });
+            return;
+        }
+        self.definitions.insert(name, function);
+    }
+
+    /// Visits the body of every instance of a function the program calls,
+    /// which can call for more, and then, only for their errors, those of
+    /// the functions it never calls
+    fn visit_functions(&mut self) {
+        while let Some(index) = self.instances.iter().position(|i| !i.visited) {
+            self.instances[index].visited = true;
+            self.visit_function(index);
+        }
+
+        let uncalled: Vec<Symbol> = self
+            .definitions
+            .keys()
+            .filter(|name| !self.instances.iter().any(|i| i.name == **name))
+            .cloned()
+            .collect();
+        for name in uncalled {
+            let definition = self.definitions[&name].clone();
+            let arguments = definition
+                .arguments
+                .iter()
+                .map(|(_, type_)| declared_type(type_))
+                .collect();
+
+            let checkpoint = self.checkpoint();
+            self.emit = false;
+            if let Some(index) = self.instance(name, arguments, &definition.identifier) {
+                self.visit_function(index);
+            }
+            let errors = self.errors.split_off(checkpoint.errors);
+            self.restore(checkpoint);
+            self.errors.extend(errors);
+        }
+    }
+
+    /// The instance of function `name` for arguments of types `arguments`,
+    /// made the first time it is called with them. A YARN it returns is as
+    /// long as the longest FOUND YR gives, its body being visited on trial
+    /// until that stops growing.
+    fn instance(
+        &mut self,
+        name: Symbol,
+        arguments: Vec<Types>,
+        token: &ast::TokenNode,
+    ) -> Option<usize> {
+        if let Some(index) = self
+            .instances
+            .iter()
+            .position(|i| i.name == name && same_arguments(&i.arguments, &arguments))
+        {
+            // a call back into an instance still being settled makes the
+            // ones settling inside it depend on the size tried for it
+            if let Some(position) = self.settling.iter().position(|(i, _)| *i == index) {
+                for (_, tentative) in &mut self.settling[position + 1..] {
+                    *tentative = true;
+                }
+                self.guesses += 1;
+            }
+            return Some(index);
+        }
+
+        // only a call passing a longer YARN than it was given on every call
+        // makes instances of the same functions over and over
+        let generation = self
+            .callees
+            .last()
+            .map_or(0, |callee| self.instances[callee.instance].generation + 1);
+        if generation >= self.definitions.len() + LOOP_PASSES {
+            self.errors.push(VisitorError {
+                message: format!(
+                    "A YARN argument of {} grows with every call, so no size fits it",
+                    name.escaped()
+                ),
+                token: token.clone(),
+            });
+            return None;
+        }
+
+        let mut ir_name = function_name(&name);
+        let mut count = 1;
+        while self.instances.iter().any(|i| i.ir_name == ir_name) {
+            count += 1;
+            ir_name = format!("{}_{}", function_name(&name), count);
+        }
+        let returns = declared_type(&self.definitions[&name].return_type);
+        let settled = self
+            .settled
+            .iter()
+            .find(|(n, a, _)| *n == name && same_arguments(a, &arguments))
+            .map(|(_, _, returns)| returns.clone());
+        self.instances.push(Instance {
+            name: name.clone(),
+            arguments: arguments.clone(),
+            ir_name,
+            returns: returns.clone(),
+            generation,
+            visited: false,
+        });
+        let index = self.instances.len() - 1;
+        if !matches!(returns, Types::Yarn(_)) {
+            return Some(index);
+        }
+
+        let settled = match settled {
+            Some(settled) => settled,
+            None => {
+                self.settling.push((index, false));
+                let settled = self.settle(index);
+                if !self.settling.pop().unwrap().1 {
+                    self.settled
+                        .push((name.clone(), arguments, settled.clone()));
+                }
+                settled
+            }
+        };
+        if let Some(returns) = settled {
+            self.instances[index].returns = returns;
+            return Some(index);
+        }
+
+        self.errors.push(VisitorError {
+            message: format!(
+                "YARN returned by {} grows with every call, so no size fits it",
+                name.escaped()
+            ),
+            token: token.clone(),
+        });
+        self.instances[index].visited = true;
+        None
+    }
+
+    /// Visits the body of an instance on trial until the YARN it returns
+    /// stops growing, giving its type, or None if it never does
+    fn settle(&mut self, index: usize) -> Option<Types> {
+        for _ in 0..LOOP_PASSES {
+            let checkpoint = self.checkpoint();
+            self.emit = false;
+            let found = self.visit_function(index);
+            self.restore(checkpoint);
+
+            if let Types::Yarn(size) = self.instances[index].returns {
+                if found <= size {
+                    return Some(Types::Yarn(size));
+                }
+            }
+            self.instances[index].returns = Types::Yarn(found);
+        }
+        None
+    }
+
+    /// Visits the body of an instance of a function into its IR function,
+    /// giving the size of the longest YARN it returns. The arguments are
+    /// below the frame the function sets up, and are its to free.
+    fn visit_function(&mut self, index: usize) -> i32 {
+        let instance = self.instances[index].clone();
+        let definition = self.definitions[&instance.name].clone();
+        self.ir
+            .functions
+            .push(ir::IRFunction::new(instance.ir_name.clone(), 0, vec![]));
+        self.scopes.push(Scope::new(instance.ir_name.clone(), None));
+        let scope = std::mem::replace(&mut self.current_scope_index, self.scopes.len() - 1);
+        let exits = std::mem::take(&mut self.exits);
+        let gc_roots = std::mem::take(&mut self.gc_roots);
+        self.callees.push(Callee {
+            instance: index,
+            found: 0,
+        });
+
+        self.add_statements(vec![ir::IRStatement::EstablishStackFrame]);
+        let count = definition.arguments.len() as i32;
+        for (i, ((identifier, _), type_)) in definition
+            .arguments
+            .iter()
+            .zip(instance.arguments.iter())
+            .enumerate()
+        {
+            let name = match identifier.value() {
+                tokens::Token::Identifier(name) => name.clone(),
+                _ => panic!("Expected Identifier token"),
+            };
+            if self.get_scope().get_variable(&name).is_some() {
+                self.errors.push(VisitorError {
+                    message: format!("Variable {} already declared", name.escaped()),
+                    token: identifier.clone(),
+                });
+                continue;
+            }
+            let (hook, _) = self.get_hook();
+            self.add_statements(vec![
+                ir::IRStatement::LoadBasePtr,
+                ir::IRStatement::Push(ir::Value::Int(i as i32 - count - 1)),
+                ir::IRStatement::Add,
+                ir::IRStatement::SetHook(hook),
+            ]);
+            self.name_hook(&name, hook, type_);
+            self.get_scope_mut().add_variable(
+                name,
+                VariableData::new(VariableValue::new(hook, type_.clone())),
+            );
+        }
+
+        self.add_statements(vec![ir::IRStatement::Push(ir::Value::Int(0))]);
+        let (it, stmt) = self.get_hook();
+        self.add_statements(vec![stmt, ir::IRStatement::Push(ir::Value::Int(1))]);
+        self.get_scope_mut().add_variable(
+            Symbol::intern("IT"),
+            VariableData::new(VariableValue::new(it, Types::Noob)),
+        );
+        let (running, stmt) = self.get_hook();
+        self.add_statements(vec![stmt]);
+        self.exits.push(Exit {
+            hook: running,
+            function: true,
+            token: definition.identifier.clone(),
+        });
+
+        let leaves = definition.statements.iter().any(leaves);
+        let always_returns = definition.statements.iter().any(always_returns);
+        self.visit_statements(definition.statements);
+
+        // without a FOUND YR the function returns IT
+        if !always_returns && !instance.returns.equals(&Types::Noob) {
+            let it = self
+                .get_scope()
+                .get_variable(&Symbol::intern("IT"))
+                .unwrap()
+                .clone();
+            if it.value.type_.equals(&instance.returns) {
+                let start = self.get_statements().len();
+                let (hook, _) = self.get_hook();
+                let (copy, statements) = it.copy(hook);
+                self.add_statements(statements);
+                self.set_return(copy);
+
+                if leaves {
+                    let mut statements = self.get_statements();
+                    let then = statements.split_off(start);
+                    statements.extend(vec![
+                        ir::IRStatement::RefHook(running),
+                        ir::IRStatement::Copy,
+                        ir::IRStatement::If {
+                            then,
+                            else_: vec![],
+                        },
+                    ]);
+                    self.set_statements(statements);
+                }
+            } else {
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Function {} can end without FOUND YR, returning IT of type {} instead of {}",
+                        instance.name.escaped(),
+                        it.value.type_.to_string(),
+                        instance.returns.to_string()
+                    ),
+                    token: definition.identifier.clone(),
+                });
+            }
+        }
+
+        self.free_scope();
+        self.add_statements(vec![
+            ir::IRStatement::LoadBasePtr,
+            ir::IRStatement::CallForeign("stack_unwind".to_string()),
+            ir::IRStatement::EndStackFrame(count, 0),
+        ]);
+
+        let found = self.callees.pop().unwrap().found;
+        self.gc_roots = gc_roots;
+        self.exits = exits;
+        self.current_scope_index = scope;
+        self.scopes.pop();
+        found
+    }
+
+    /// Calls the instance of a function for the types of the arguments
+    /// given, passing it a copy of each
+    pub fn visit_function_call_expression(
+        &mut self,
+        call: ast::FunctionCallExpressionNode,
+    ) -> (VariableValue, ast::TokenNode) {
+        let token = call.identifier.clone();
+        let name = match token.value() {
+            tokens::Token::Identifier(name) => name.clone(),
+            _ => panic!("Expected Identifier token"),
+        };
+        let definition = match self.definitions.get(&name) {
+            Some(definition) => definition.clone(),
+            None => {
+                self.errors.push(VisitorError {
+                    message: format!("Function {} not defined", name.escaped()),
+                    token: token.clone(),
+                });
+                return (VariableValue::new(-1, Types::Noob), token);
+            }
+        };
+        if definition.arguments.len() != call.arguments.len() {
+            self.errors.push(VisitorError {
+                message: format!(
+                    "Function {} takes {} arguments but was given {}",
+                    name.escaped(),
+                    definition.arguments.len(),
+                    call.arguments.len()
+                ),
+                token: token.clone(),
+            });
+            return (VariableValue::new(-1, Types::Noob), token);
+        }
+
+        let mut values = vec![];
+        for (argument, (identifier, type_)) in call.arguments.into_iter().zip(&definition.arguments)
+        {
+            let (value, argument_token) = self.visit_expression(argument);
+            // the error has already been reported
+            if value.type_.equals(&Types::Noob) && value.hook == -1 {
+                continue;
+            }
+            let expected = declared_type(type_);
+            if !value.type_.equals(&expected) {
+                let argument = match identifier.value() {
+                    tokens::Token::Identifier(argument) => argument.clone(),
+                    _ => panic!("Expected Identifier token"),
+                };
+                self.errors.push(VisitorError {
+                    message: format!(
+                        "Expected {} type for argument {} of {} but got {}",
+                        expected.to_string(),
+                        argument.escaped(),
+                        name.escaped(),
+                        value.type_.to_string()
+                    ),
+                    token: argument_token,
+                });
+                continue;
+            }
+            values.push(value);
+        }
+        if values.len() != definition.arguments.len() {
+            return (VariableValue::new(-1, Types::Noob), token);
+        }
+
+        let arguments = values.iter().map(|value| value.type_.clone()).collect();
+        let instance = match self.instance(name, arguments, &token) {
+            Some(index) => self.instances[index].clone(),
+            None => return (VariableValue::new(-1, Types::Noob), token),
+        };
+        // the arguments are copied to be next to each other, and the result
+        // takes the place of the first so that only it is left on the stack
+        for value in values.iter() {
+            self.add_statements(vec![
+                ir::IRStatement::RefHook(value.hook),
+                ir::IRStatement::Copy,
+            ]);
+        }
+        self.add_statements(vec![
+            ir::IRStatement::Call(instance.ir_name),
+            ir::IRStatement::AccessReturnRegister,
+        ]);
+        let hook = match values.first() {
+            Some(first) => {
+                self.add_statements(vec![
+                    ir::IRStatement::RefHook(first.hook),
+                    ir::IRStatement::Mov,
+                    ir::IRStatement::RefHook(first.hook),
+                    ir::IRStatement::CallForeign("stack_unwind".to_string()),
+                ]);
+                first.hook
+            }
+            None => {
+                let (hook, stmt) = self.get_hook();
+                self.add_statements(vec![stmt]);
+                hook
+            }
+        };
+        for value in values.iter().skip(1) {
+            self.free_hook(value.hook);
+        }
+
+        (VariableValue::new(hook, instance.returns), token)
+    }
+
+    /// Statements pushing whether IT is WIN once made a TROOF, which for a
+    /// NOOB IT is FAIL
+    fn it_truth(&mut self, token: &ast::TokenNode) -> Option<Vec<ir::IRStatement>> {
+        let it = self
+            .get_scope()
+            .get_variable(&Symbol::intern("IT"))
+            .unwrap();
+        let hook = it.value.hook;
+        match it.value.type_ {
+            Types::Number | Types::Numbar | Types::Troof => {
+                Some(vec![ir::IRStatement::RefHook(hook), ir::IRStatement::Copy])
+            }
+            Types::Yarn(size) => Some(vec![
+                ir::IRStatement::RefHook(hook),
+                ir::IRStatement::Copy,
+                ir::IRStatement::Push(ir::Value::Int(size)),
+                ir::IRStatement::CallForeign("yarn_length".to_string()),
+            ]),
+            Types::Noob => Some(vec![ir::IRStatement::Push(ir::Value::Int(0))]),
+            Types::Bukkit(_) => {
+                self.errors.push(VisitorError {
+                    message: "Cannot convert type BUKKIT to TROOF".to_string(),
+                    token: token.clone(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Adds an If running `then` when `condition` is WIN and `else_`
+    /// otherwise. Both branches start from the variables as they were before
+    /// it and end with them as the other leaves them.
+    fn visit_branches(
+        &mut self,
+        condition: Vec<ir::IRStatement>,
+        then: Vec<ast::StatementNode>,
+        else_: Vec<ast::StatementNode>,
+        token: &ast::TokenNode,
+    ) {
+        let before = self.variable_types();
+        let (mut then_statements, then_types) = self.visit_block(then);
+        self.set_variable_types(&before);
+        let (mut else_statements, else_types) = self.visit_block(else_);
+
+        for (name, then_type) in then_types {
+            let else_type = else_types[&name].clone();
+            let type_ = match Self::merge_types(&name, &then_type, &else_type) {
+                Some(type_) => type_,
+                None => {
+                    self.errors.push(VisitorError {
+                        message: format!(
+                            "Variable {} is given a different BUKKIT in each branch",
+                            name.escaped()
+                        ),
+                        token: token.clone(),
+                    });
+                    then_type.clone()
+                }
+            };
+            then_statements.extend(self.convert_variable(&name, &then_type, &type_));
+            else_statements.extend(self.convert_variable(&name, &else_type, &type_));
+            let variable = self.get_scope_mut().get_variable_mut(&name).unwrap();
+            variable.value.type_ = type_;
+        }
+
+        self.add_statements(condition);
+        self.add_statements(vec![ir::IRStatement::If {
+            then: then_statements,
+            else_: else_statements,
+        }]);
+    }
+
+    /// The type of every variable in scope, by its name
+    fn variable_types(&self) -> BTreeMap<Symbol, Types> {
+        self.get_scope()
+            .variables
+            .iter()
+            .map(|(name, variable)| (name.clone(), variable.value.type_.clone()))
+            .collect()
+    }
+
+    fn set_variable_types(&mut self, types: &BTreeMap<Symbol, Types>) {
+        for (name, type_) in types {
+            let variable = self.get_scope_mut().get_variable_mut(name).unwrap();
+            variable.value.type_ = type_.clone();
+        }
+    }
+
+    /// Visits the statements of a branch into statements of their own,
+    /// returning them with the types the variables in scope before them end
+    /// up with
+    fn visit_block(
+        &mut self,
+        statements: Vec<ast::StatementNode>,
+    ) -> (Vec<ir::IRStatement>, BTreeMap<Symbol, Types>) {
+        let before = self.variable_types();
+        let start = self.get_statements().len();
+        self.visit_statements(statements);
+        self.free_block(&before);
+        let types = self.variable_types();
+
+        let mut statements = self.get_statements();
+        let block = statements.split_off(start);
+        self.set_statements(statements);
+        (block, types)
+    }
+
+    /// Visits statements in order, those after one that can leave the WTF?,
+    /// loop or function they are in only running if it did not
+    fn visit_statements(&mut self, statements: Vec<ast::StatementNode>) {
+        let mut statements = statements.into_iter();
+        while let Some(statement) = statements.next() {
+            let exit = match leaves(&statement) {
+                true => self.exits.last().cloned(),
+                false => None,
+            };
+            self.visit_statement(statement);
+            if let Some(exit) = exit {
+                let rest: Vec<ast::StatementNode> = statements.collect();
+                if !rest.is_empty() {
+                    self.visit_branches(
+                        vec![ir::IRStatement::RefHook(exit.hook), ir::IRStatement::Copy],
+                        rest,
+                        vec![],
+                        &exit.token,
+                    );
+                }
+                return;
+            }
+        }
+    }
+
+    /// Frees the variables declared since `before`, which only live until
+    /// the end of the block they were declared in
+    fn free_block(&mut self, before: &BTreeMap<Symbol, Types>) {
+        let inner: Vec<Symbol> = self
+            .get_scope()
+            .variables
+            .keys()
+            .filter(|name| !before.contains_key(name))
+            .cloned()
+            .collect();
+        for name in inner {
+            let variable = self.get_scope_mut().variables.remove(&name).unwrap();
+            self.add_free(variable.free());
+            self.free_hook(variable.value.hook);
+        }
+    }
+
+    /// The type a variable has after an If whose branches leave it `then`
+    /// and `else_`: the longer of two YARNs, and for IT, which can be given
+    /// anything, NOOB when they differ otherwise. None for two different
+    /// BUKKITs, which no value can be turned into.
+    fn merge_types(name: &Symbol, then: &Types, else_: &Types) -> Option<Types> {
+        match (then, else_) {
+            (Types::Yarn(a), Types::Yarn(b)) => Some(Types::Yarn(*a.max(b))),
+            (Types::Bukkit(a), Types::Bukkit(b)) if a == b => Some(then.clone()),
+            (a, b) if a.equals(b) && !matches!(a, Types::Bukkit(_)) => Some(then.clone()),
+            _ if *name == Symbol::intern("IT") => Some(Types::Noob),
+            _ => None,
+        }
+    }
+
+    /// Statements turning a variable's value of type `from` into one of
+    /// `to`, padding a YARN or, for NOOB, freeing the value
+    fn convert_variable(
+        &mut self,
+        name: &Symbol,
+        from: &Types,
+        to: &Types,
+    ) -> Vec<ir::IRStatement> {
+        let hook = self.get_scope().get_variable(name).unwrap().value.hook;
+        let value = VariableValue::new(hook, from.clone());
+        match (from, to) {
+            (Types::Yarn(a), Types::Yarn(b)) if a < b => {
+                let mut statements = self.pad_yarn(&value, *b);
+                statements.extend(vec![ir::IRStatement::RefHook(hook), ir::IRStatement::Mov]);
+                statements
+            }
+            (Types::Noob, Types::Noob) => vec![],
+            (_, Types::Noob) => {
+                let mut statements = if self.gc { vec![] } else { value.free() };
+                statements.extend(vec![
+                    ir::IRStatement::Push(ir::Value::Int(0)),
+                    ir::IRStatement::RefHook(hook),
+                    ir::IRStatement::Mov,
+                ]);
+                statements
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::compiler::cache;
+    use crate::driver;
+    use crate::interpreter;
+
+    fn visit(text: &str) -> (Visitor<'static>, ir::IR, Vec<VisitorError>) {
+        let mut visitor = driver::visitor(text);
+        let (ir, errors) = visitor.visit();
+        (visitor, ir, errors)
+    }
+
+    #[test]
+    fn a_deep_chain_of_yarn_functions_is_settled_once_each() {
+        let depth = 40;
+        let mut text = String::from("HAI 1.2\n");
+        text.push_str("HOW IZ I f0 ITZ YARN YR x ITZ YARN\n");
+        text.push_str("  FOUND YR SMOOSH x AN \"a\" MKAY\nIF U SAY SO\n");
+        for i in 1..depth {
+            text.push_str(&format!("HOW IZ I f{} ITZ YARN YR x ITZ YARN\n", i));
+            text.push_str(&format!(
+                "  FOUND YR SMOOSH I IZ f{} YR x MKAY AN \"a\" MKAY\nIF U SAY SO\n",
+                i - 1
+            ));
+        }
+        text.push_str(&format!(
+            "VISIBLE I IZ f{} YR \"x\" MKAY\nKTHXBYE\n",
+            depth - 1
+        ));
+
+        let start = Instant::now();
+        let (visitor, _, errors) = visit(&text);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(errors.is_empty());
+
+        // each function returns one letter more than the one it calls
+        assert_eq!(visitor.instances.len(), depth);
+        let size = |name: String| {
+            let instance = visitor
+                .instances
+                .iter()
+                .find(|i| i.name == Symbol::intern(&name))
+                .unwrap();
+            match instance.returns {
+                Types::Yarn(size) => size,
+                _ => panic!("Expected a YARN"),
+            }
+        };
+        for i in 1..depth {
+            assert_eq!(size(format!("f{}", i)), size(format!("f{}", i - 1)) + 1);
+        }
+    }
+
+    #[test]
+    fn every_instance_is_shown_under_the_name_of_its_function() {
+        let text = "HAI 1.2\n\
+            HOW IZ I greet ITZ YARN YR s ITZ YARN\n\
+            FOUND YR SMOOSH \"hi \" AN s MKAY\n\
+            IF U SAY SO\n\
+            VISIBLE I IZ greet YR \"a\" MKAY\n\
+            VISIBLE I IZ greet YR \"bob\" MKAY\n\
+            KTHXBYE\n";
+
+        let (_, ir, errors) = visit(text);
+        assert!(errors.is_empty());
+        assert_eq!(ir.functions.len(), 2);
+        assert_ne!(ir.functions[0].name, ir.functions[1].name);
+        for function in ir.functions.iter() {
+            assert_eq!(ir.source_name(&function.name), "greet");
+        }
+        assert_eq!(ir.source_name("main"), "main");
+    }
+
+    #[test]
+    fn deeply_nested_loops_are_settled_once_each() {
+        let depth = 24;
+        let mut text = String::from("HAI 1.2\nI HAS A s ITZ YARN R \"\"\n");
+        for i in 0..depth {
+            text.push_str(&format!(
+                "IM IN YR l{} UPPIN YR i{} TIL BOTH SAEM i{} AN 2\n",
+                i, i, i
+            ));
+        }
+        text.push_str("s R SMOOSH s AN \"\" MKAY\n");
+        for i in (0..depth).rev() {
+            text.push_str(&format!("IM OUTTA YR l{}\n", i));
+        }
+        text.push_str("VISIBLE s\nKTHXBYE\n");
+
+        let start = Instant::now();
+        let (_, _, errors) = visit(&text);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn compiling_a_program_twice_gives_the_same_bytes() {
+        // enough variables and slots that maps iterating in an order of
+        // their own would show up in the hooks and frees
+        let mut text = String::from("HAI 1.2\nI HAS A cat ITZ BUKKIT\n");
+        for i in 0..16 {
+            text.push_str(&format!("cat HAS A slot{} ITZ \"{}\"\n", i, i));
+        }
+        text.push_str("HOW IZ I f ITZ YARN YR x ITZ YARN\n");
+        for i in 0..16 {
+            text.push_str(&format!(
+                "  I HAS A v{} ITZ YARN\n  v{} R SMOOSH x AN \"{}\" MKAY\n",
+                i, i, i
+            ));
+        }
+        text.push_str("  FOUND YR v15\nIF U SAY SO\n");
+        text.push_str("IM IN YR l WATCHIN YR slot IN cat\n");
+        for i in 0..16 {
+            text.push_str(&format!(
+                "  I HAS A w{} ITZ YARN\n  w{} R I IZ f YR slot MKAY\n",
+                i, i
+            ));
+        }
+        text.push_str("  VISIBLE w0\nIM OUTTA YR l\nKTHXBYE\n");
+
+        let (_, first, errors) = visit(&text);
+        assert!(errors.is_empty());
+        let (_, second, _) = visit(&text);
+        assert_eq!(cache::serialize(&first), cache::serialize(&second));
+    }
+
+    /// What `MAEK value A type A YARN` prints, after `declarations`, or "-"
+    /// if it panics
+    fn convert(declarations: &str, value: &str, type_: &str) -> String {
+        let text = format!(
+            "HAI 1.2\n{}VISIBLE MAEK MAEK {} A {} A YARN\nKTHXBYE\n",
+            declarations, value, type_
+        );
+        let (_, ir, errors) = visit(&text);
+        assert!(errors.is_empty(), "{}", text);
+        let (code, output) =
+            interpreter::run_captured(&ir, Vec::new(), |machine| machine.newline = "\n");
+        if code != 0 {
+            assert!(output.contains("cannot convert"), "{}", output);
+            return "-".to_string();
+        }
+        output.trim_end_matches('\n').to_string()
+    }
+
+    #[test]
+    fn maek_converts_every_type_to_every_other() {
+        let declarations = "I HAS A three ITZ NUMBER R 3\n\
+            I HAS A zero ITZ NUMBER R 0\n\
+            I HAS A half ITZ NUMBAR R 2.5\n\
+            I HAS A none ITZ NUMBAR R 0.0\n\
+            I HAS A win ITZ TROOF R WIN\n\
+            I HAS A fail ITZ TROOF R FAIL\n\
+            I HAS A digits ITZ YARN R \"-7\"\n\
+            I HAS A empty ITZ YARN R \"\"\n";
+        let types = ["NUMBER", "NUMBAR", "TROOF", "YARN", "NOOB"];
+        // each value converted to each of the types, "-" where it panics
+        let matrix: &[(&str, [&str; 5])] = &[
+            ("three", ["3", "3.000000", "WIN", "3", ""]),
+            ("zero", ["0", "0.000000", "FAIL", "0", ""]),
+            ("half", ["2", "2.500000", "WIN", "2.500000", ""]),
+            ("none", ["0", "0.000000", "FAIL", "0.000000", ""]),
+            ("win", ["1", "1.000000", "WIN", "WIN", ""]),
+            ("fail", ["0", "0.000000", "FAIL", "FAIL", ""]),
+            ("digits", ["-7", "-7.000000", "WIN", "-7", ""]),
+            ("empty", ["-", "-", "FAIL", "", ""]),
+            ("MAEK three A NOOB", ["0", "0.000000", "FAIL", "", ""]),
+            ("IT", ["0", "0.000000", "FAIL", "", ""]),
+        ];
+
+        for (value, row) in matrix {
+            for (type_, expected) in types.iter().zip(row.iter()) {
+                assert_eq!(
+                    convert(declarations, value, type_),
+                    *expected,
+                    "MAEK {} A {}",
+                    value,
+                    type_
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn maek_reports_what_it_cannot_convert() {
+        let cases = [
+            ("MAEK \"12 cats\" A NUMBER\n", "12 cats"),
+            ("MAEK \"\" A NUMBAR\n", "NUMBAR"),
+            (
+                "I HAS A n ITZ NUMBER R SUM OF MAEK \"1x\" A NUMBER AN 1\n",
+                "1x",
+            ),
+            (
+                "I HAS A cat ITZ BUKKIT\nMAEK cat A YARN\n",
+                "Cannot convert type BUKKIT to YARN",
+            ),
+        ];
+        for (statements, message) in cases {
+            let (_, _, errors) = visit(&format!("HAI 1.2\n{}KTHXBYE\n", statements));
+            assert_eq!(errors.len(), 1, "{}", statements);
+            assert!(errors[0].message.contains(message), "{}", errors[0].message);
+        }
+    }
+
+    #[test]
+    fn one_bad_operand_is_reported_once() {
+        let text = "HAI 1.2\n\
+            I HAS A x ITZ NUMBER R SUM OF 1 AN PRODUKT OF \"a\" AN 2\n\
+            VISIBLE SMOOSH \"a\" AN SUM OF 1 AN \"b\" MKAY\n\
+            VISIBLE SMOOSH \"a\" AN nope MKAY\n\
+            KTHXBYE\n";
+        let (_, _, errors) = visit(text);
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "in `PRODUKT OF \"a\" AN 2`: Expected NUMBER or NUMBAR type",
+                "in `SUM OF 1 AN \"b\"`: Expected NUMBER type but got YARN",
+                "in `SMOOSH \"a\" AN nope MKAY`: Variable nope not found",
+            ]
+        );
+    }
 }
@@ -0,0 +1,379 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::lexer::lexer::LexedToken;
+use crate::lexer::tokens;
+use crate::source::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+    /// Says something about the other diagnostics rather than the program
+    Note,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+            Severity::Note => write!(f, "Note"),
+        }
+    }
+}
+
+/// A diagnostic rendered for printing
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Diagnostic {
+    pub fn error(text: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            text,
+        }
+    }
+
+    pub fn warning(text: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            text,
+        }
+    }
+
+    pub fn note(text: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Note,
+            text,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// The error for a program the C compiler could not build, the same from
+/// every command that builds one
+pub fn build_failed(error: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::error(format!(
+        "{}: Could not build the executable: {}",
+        Severity::Error,
+        error
+    ))
+}
+
+/// Errors reported for a program before the rest are left out, 0 for all
+static ERROR_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_ERROR_LIMIT);
+
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+/// Sets how many errors limit_errors keeps, for --error-limit
+pub fn set_error_limit(limit: usize) {
+    ERROR_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+/// Whether warnings are reported as errors, for --deny-warnings
+static DENY_WARNINGS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_deny_warnings(deny: bool) {
+    DENY_WARNINGS.store(deny, Ordering::Relaxed);
+}
+
+/// `diagnostics` with every warning made an error if warnings are denied
+pub fn deny_warnings(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    if !DENY_WARNINGS.load(Ordering::Relaxed) {
+        return diagnostics;
+    }
+
+    diagnostics
+        .into_iter()
+        .map(|diagnostic| match diagnostic.severity {
+            Severity::Warning => {
+                // the reason goes at the end of the message, before the
+                // line it points at
+                let text = diagnostic.text.replacen("Warning: ", "Error: ", 1);
+                let (message, rest) = match text.split_once('\n') {
+                    Some((message, rest)) => (message, format!("\n{}", rest)),
+                    None => (text.as_str(), String::new()),
+                };
+                Diagnostic::error(format!("{} (--deny-warnings){}", message, rest))
+            }
+            _ => diagnostic,
+        })
+        .collect()
+}
+
+/// How many errors and warnings there are among `diagnostics`
+pub fn count(diagnostics: &[Diagnostic]) -> (usize, usize) {
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .count();
+    (errors, warnings)
+}
+
+/// The line that ends a command's diagnostics, such as "3 errors, 5
+/// warnings emitted", for CI to read without parsing each one
+pub fn summary(errors: usize, warnings: usize) -> String {
+    format!(
+        "{} error{}, {} warning{} emitted",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" }
+    )
+}
+
+/// `diagnostics` up to the error limit's worth of errors, with a note of
+/// how many more there were in place of the rest, so a badly broken file
+/// does not bury the first errors
+pub fn limit_errors(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    limit_errors_to(diagnostics, ERROR_LIMIT.load(Ordering::Relaxed))
+}
+
+fn limit_errors_to(diagnostics: Vec<Diagnostic>, limit: usize) -> Vec<Diagnostic> {
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if limit == 0 || errors <= limit {
+        return diagnostics;
+    }
+
+    let mut kept = vec![];
+    let mut seen = 0;
+    for diagnostic in diagnostics.into_iter() {
+        if diagnostic.is_error() {
+            seen += 1;
+            if seen > limit {
+                break;
+            }
+        }
+        kept.push(diagnostic);
+    }
+    kept.push(Diagnostic::note(format!(
+        "Note: too many errors, {} more not shown (raise --error-limit, or set it to 0, to see them all)",
+        errors - limit
+    )));
+    kept
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// What happens when a lint finds something
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// A check for code that compiles but is probably not what was meant
+pub struct Lint {
+    pub name: &'static str,
+    pub default: Level,
+    pub description: &'static str,
+}
+
+pub const UNUSED_VARIABLE: Lint = Lint {
+    name: "unused-variable",
+    default: Level::Warn,
+    description: "a variable that is never mentioned after its declaration",
+};
+
+pub const OVERWRITTEN_IT: Lint = Lint {
+    name: "overwritten-it",
+    default: Level::Warn,
+    description: "a bare expression whose value in IT is replaced before anything reads it",
+};
+
+pub const LINTS: &[&Lint] = &[&UNUSED_VARIABLE, &OVERWRITTEN_IT];
+
+/// Something a lint found
+pub struct LintHit {
+    pub lint: &'static Lint,
+    pub span: Span,
+    pub message: String,
+}
+
+/// A `BTW lolcat:` comment, which holds from where it is written until a
+/// later one changes the same lints
+struct Directive {
+    offset: usize,
+    /// None for every lint
+    lint: Option<&'static str>,
+    /// None to go back to the lint's default
+    level: Option<Level>,
+}
+
+/// The level of every lint at every point of a program, as set by the
+/// directives in its comments:
+///
+/// BTW lolcat: allow unused-variable, other-lint
+/// BTW lolcat: warn unused-variable
+/// BTW lolcat: deny unused-variable
+/// BTW lolcat: warn-off    (allow every lint)
+/// BTW lolcat: warn-on     (every lint back to its default)
+#[derive(Default)]
+pub struct LintLevels {
+    directives: Vec<Directive>,
+}
+
+impl LintLevels {
+    /// Reads the directives from the comments the lexer kept, returning the
+    /// ones it could not make sense of as (span, message)
+    pub fn from_comments(comments: &[LexedToken]) -> (LintLevels, Vec<(Span, String)>) {
+        let mut levels = LintLevels::default();
+        let mut problems = vec![];
+
+        for comment in comments.iter() {
+            let text = match &comment.token {
+                tokens::Token::SingleLineComment(text) => text.trim(),
+                _ => continue,
+            };
+            let directive = match text.strip_prefix("lolcat:") {
+                Some(directive) => directive.trim(),
+                None => continue,
+            };
+
+            if let Err(message) = levels.add(comment.start, directive) {
+                problems.push((comment.span(), message));
+            }
+        }
+
+        (levels, problems)
+    }
+
+    fn add(&mut self, offset: usize, directive: &str) -> Result<(), String> {
+        match directive {
+            "warn-off" => {
+                self.directives.push(Directive {
+                    offset,
+                    lint: None,
+                    level: Some(Level::Allow),
+                });
+                return Ok(());
+            }
+            "warn-on" => {
+                self.directives.push(Directive {
+                    offset,
+                    lint: None,
+                    level: None,
+                });
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let (level, names) = match directive.split_once(char::is_whitespace) {
+            Some((level, names)) => (level, names),
+            None => (directive, ""),
+        };
+        let level = match level {
+            "allow" => Level::Allow,
+            "warn" => Level::Warn,
+            "deny" => Level::Deny,
+            _ => {
+                return Err(format!(
+                    "Unknown directive '{}', expected allow, warn, deny, warn-off or warn-on",
+                    directive
+                ))
+            }
+        };
+
+        let names = names
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<&str>>();
+        if names.is_empty() {
+            return Err("Expected the name of a lint after the level".to_string());
+        }
+        for name in names.iter() {
+            match LINTS.iter().find(|lint| lint.name == *name) {
+                Some(lint) => self.directives.push(Directive {
+                    offset,
+                    lint: Some(lint.name),
+                    level: Some(level),
+                }),
+                None => return Err(format!("Unknown lint '{}'", name)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The level of `lint` at `offset`
+    pub fn level(&self, lint: &Lint, offset: usize) -> Level {
+        let directive = self.directives.iter().rev().find(|directive| {
+            directive.offset <= offset && directive.lint.is_none_or(|name| name == lint.name)
+        });
+
+        match directive.and_then(|directive| directive.level) {
+            Some(level) => level,
+            None => lint.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `errors` errors with a warning after every one
+    fn diagnostics(errors: usize) -> Vec<Diagnostic> {
+        (0..errors)
+            .flat_map(|i| {
+                vec![
+                    Diagnostic::error(format!("Error: {}", i)),
+                    Diagnostic::warning(format!("Warning: {}", i)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn limit_errors_keeps_the_first_errors_and_notes_the_rest() {
+        let kept = limit_errors_to(diagnostics(300), 20);
+
+        assert_eq!(count(&kept), (20, 20));
+        assert_eq!(kept[0].text, "Error: 0");
+        assert_eq!(kept[kept.len() - 3].text, "Error: 19");
+
+        let note = kept.last().unwrap();
+        assert_eq!(note.severity, Severity::Note);
+        assert_eq!(
+            note.text,
+            "Note: too many errors, 280 more not shown (raise --error-limit, or set it to 0, to see them all)"
+        );
+    }
+
+    #[test]
+    fn limit_errors_leaves_diagnostics_within_the_limit_alone() {
+        let kept = limit_errors_to(diagnostics(20), 20);
+        assert_eq!(kept.len(), 40);
+        assert!(kept.iter().all(|d| d.severity != Severity::Note));
+    }
+
+    #[test]
+    fn limit_errors_keeps_everything_with_a_limit_of_zero() {
+        let kept = limit_errors_to(diagnostics(300), 0);
+        assert_eq!(count(&kept), (300, 300));
+        assert!(kept.iter().all(|d| d.severity != Severity::Note));
+    }
+
+    #[test]
+    fn limit_errors_counts_only_errors_against_the_limit() {
+        let mut many = vec![Diagnostic::warning("Warning: first".to_string()); 50];
+        many.extend(diagnostics(5));
+        let kept = limit_errors_to(many, 3);
+
+        assert_eq!(count(&kept), (3, 53));
+        assert!(kept.last().unwrap().text.contains(", 2 more not shown"));
+    }
+}
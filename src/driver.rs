@@ -0,0 +1,336 @@
+//! The phases of compiling a program as functions of their own, each
+//! returning what it made along with its diagnostics, so a tool can stop
+//! after any of them. The command line is built on these.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::analysis::lints;
+use crate::compiler::cache::IrCache;
+use crate::compiler::ir;
+use crate::compiler::opt;
+use crate::compiler::target::Target;
+use crate::compiler::visit as v;
+use crate::diagnostics::{self as diag, Diagnostic, LintLevels, Severity};
+use crate::lexer::lexer as l;
+use crate::lexer::tokens as t;
+use crate::logger::Logger;
+use crate::parser::desugar;
+use crate::parser::parser as p;
+use crate::source::SourceMap;
+
+/// What a phase made and the warnings it gave, or every diagnostic, warnings
+/// included, if it found errors
+pub type PhaseResult<T> = Result<(T, Vec<Diagnostic>), Vec<Diagnostic>>;
+
+/// The tokens of a program, and its comments apart from them
+pub struct Lexed {
+    pub tokens: Vec<l::LexedToken>,
+    pub comments: Vec<l::LexedToken>,
+}
+
+/// The IR of a program and its trace points, if they were asked for
+pub struct Lowered {
+    pub ir: ir::IR,
+    pub trace: Option<v::TraceInfo>,
+}
+
+/// How a program is lowered to IR. `release` leaves ASSURE statements out,
+/// `gc` every free, and without `emit` the IR is left empty.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowerOptions {
+    pub trace: bool,
+    pub release: bool,
+    pub gc: bool,
+    pub emit: bool,
+}
+
+/// Splits `contents`, the text `source` holds, into tokens, stopping at the
+/// first illegal one
+pub fn lex(contents: &str, source: &SourceMap, logger: &Logger) -> PhaseResult<Lexed> {
+    let (tokens, comments) = logger.phase("lex", || {
+        let mut l = l::Lexer::init(contents);
+        let tokens = l.get_tokens();
+        (tokens, l.comments)
+    });
+    logger.trace("lex", format!("{} tokens", tokens.len()).as_str());
+
+    if let Some(error) = l::Lexer::get_first_error(&tokens) {
+        match &error.token {
+            t::Token::Illegal(e) => {
+                return Err(vec![Diagnostic::error(
+                    source.format_error(error.span(), e.to_string().as_str()),
+                )]);
+            }
+            _ => {
+                panic!("Unexpected error token");
+            }
+        }
+    }
+
+    Ok((Lexed { tokens, comments }, vec![]))
+}
+
+/// Parses the tokens of a program, giving its errors as one diagnostic that
+/// chains each to the error it was caused by
+pub fn parse<'a>(
+    tokens: Vec<l::LexedToken>,
+    source: &SourceMap,
+    logger: &Logger,
+) -> PhaseResult<p::ParserReturn<'a>> {
+    let p = logger.phase("parse", || p::Parser::parse(tokens));
+    logger.trace(
+        "parse",
+        format!("{} top level statements", p.ast.statements.len()).as_str(),
+    );
+
+    if !p.errors.is_empty() {
+        let chain = p
+            .errors
+            .iter()
+            .rev()
+            .map(|error| source.format_error(error.token.span(), error.message))
+            .collect::<Vec<String>>();
+
+        return Err(vec![Diagnostic::error(
+            chain.join("\n\nWhich was caused by:\n"),
+        )]);
+    }
+
+    Ok((p, vec![]))
+}
+
+/// The lints of a parsed program, at the levels its comments set. A lint
+/// denied gives an error among them, which does not keep the program from
+/// being lowered, so that lower can report its own errors too.
+pub fn analyze(
+    program: &p::ParserReturn,
+    comments: &[l::LexedToken],
+    source: &SourceMap,
+    logger: &Logger,
+) -> Vec<Diagnostic> {
+    logger.phase("lint", || {
+        let (levels, problems) = LintLevels::from_comments(comments);
+        let mut diagnostics = problems
+            .into_iter()
+            .map(|(span, message)| {
+                Diagnostic::warning(source.format_diagnostic(span, Severity::Warning, &message))
+            })
+            .collect::<Vec<Diagnostic>>();
+
+        for hit in lints::check(&program.ast.statements) {
+            let severity = match levels.level(hit.lint, hit.span.start) {
+                diag::Level::Allow => continue,
+                diag::Level::Warn => Severity::Warning,
+                diag::Level::Deny => Severity::Error,
+            };
+            let message = format!("{} [{}]", hit.message, hit.lint.name);
+            diagnostics.push(Diagnostic {
+                severity,
+                text: source.format_diagnostic(hit.span, severity, &message),
+            });
+        }
+        diagnostics
+    })
+}
+
+/// Desugars a parsed program and visits it, giving its IR
+pub fn lower(
+    mut program: p::ParserReturn,
+    source: SourceMap,
+    options: &LowerOptions,
+    logger: &Logger,
+) -> PhaseResult<Lowered> {
+    program.ast = logger.phase("desugar", || desugar::desugar(program.ast));
+    let (ir, errors, trace, source) = logger.phase("visit", || {
+        let mut v = v::Visitor::new(program, v::STACK_SIZE, v::HEAP_SIZE);
+        v.source = source;
+        v.release = options.release;
+        v.gc = options.gc;
+        v.emit = options.emit;
+        if options.trace {
+            v.trace = Some(v::TraceInfo::default());
+        }
+        let (ir, errors) = v.visit();
+        (ir, errors, v.trace, v.source)
+    });
+    logger.trace(
+        "visit",
+        format!(
+            "{} functions, {} hooks",
+            ir.functions.len(),
+            ir.hook_count()
+        )
+        .as_str(),
+    );
+
+    if !errors.is_empty() {
+        return Err(errors
+            .iter()
+            .map(|error| {
+                Diagnostic::error(
+                    source.format_error(error.token.token.span(), error.message.as_str()),
+                )
+            })
+            .collect());
+    }
+
+    Ok((Lowered { ir, trace }, vec![]))
+}
+
+/// Runs the optimizer passes `options` picks over the IR, logging each,
+/// reusing the functions `cache` has already optimized
+pub fn optimize(
+    ir: ir::IR,
+    options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    logger: &Logger,
+) -> ir::IR {
+    let before = ir.statement_count();
+    let ir = logger.phase("optimize", || {
+        opt::optimize_with(ir, options, cache, &mut |run| {
+            logger.info(
+                "optimize",
+                format!(
+                    "{}: {} IR statements before, {} after, in {:.2}ms",
+                    run.name,
+                    run.before,
+                    run.ir.statement_count(),
+                    run.time.as_secs_f64() * 1000.0
+                )
+                .as_str(),
+            )
+        })
+    });
+    logger.info(
+        "optimize",
+        format!(
+            "{} IR statements before, {} after",
+            before,
+            ir.statement_count()
+        )
+        .as_str(),
+    );
+
+    ir
+}
+
+/// The code `target` makes of the IR, which its compile turns into an
+/// executable
+pub fn assemble(ir: &ir::IR, target: &impl Target, logger: &Logger) -> String {
+    assemble_after(ir, &ir::prelude(target), target, logger)
+}
+
+/// assemble, reusing the prelude a session keeps
+pub fn assemble_after(ir: &ir::IR, prelude: &str, target: &impl Target, logger: &Logger) -> String {
+    let asm = logger.phase("assemble", || ir.assemble_after(prelude, target));
+    logger.trace(
+        "assemble",
+        format!("{} bytes of output", asm.len()).as_str(),
+    );
+    asm
+}
+
+/// Programs a session keeps the IR of before it starts over, so a long
+/// running server does not hold every edit of a file
+const SESSION_PROGRAMS: usize = 64;
+
+/// A visitor of the program `text`, desugared as lower does, for tests of
+/// the visitor itself. Panics if the program does not parse.
+#[cfg(test)]
+pub fn visitor(text: &str) -> v::Visitor<'static> {
+    let (source, logger) = (SourceMap::single("test.lol", text), Logger::new(0));
+    let (lexed, _) = lex(text, &source, &logger).expect("the program does not lex");
+    let (mut program, _) =
+        parse(lexed.tokens, &source, &logger).expect("the program does not parse");
+    program.ast = desugar::desugar(program.ast);
+    v::Visitor::new(program, v::STACK_SIZE, v::HEAP_SIZE)
+}
+
+/// The IR of the program `text`, for tests. Panics if it does not compile.
+#[cfg(test)]
+pub fn lower_text(text: &str) -> ir::IR {
+    let (source, logger) = (SourceMap::single("test.lol", text), Logger::new(0));
+    let (lexed, _) = lex(text, &source, &logger).expect("the program does not lex");
+    let (program, _) = parse(lexed.tokens, &source, &logger).expect("the program does not parse");
+    let options = LowerOptions {
+        emit: true,
+        ..Default::default()
+    };
+    let (lowered, _) =
+        lower(program, source, &options, &logger).expect("the program does not compile");
+    lowered.ir
+}
+
+/// What repeated compiles in one process share, for the serve command and
+/// other tools that compile many programs or the same one again: the IR of
+/// the programs compiled so far with their warnings, the prelude every
+/// program is assembled after, and the C compiler the target found.
+#[derive(Default)]
+pub struct Session {
+    programs: HashMap<String, (ir::IR, Vec<Diagnostic>, Duration)>,
+    prelude: Option<String>,
+    cc: Option<String>,
+    compiles: u32,
+    reused: u32,
+    saved: Duration,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// The IR and warnings `build` gives for the program `key` names, only
+    /// built the first time it is asked for. Programs with errors are built
+    /// again, as there is nothing to skip once they are reported.
+    pub fn ir(
+        &mut self,
+        key: String,
+        build: impl FnOnce() -> PhaseResult<ir::IR>,
+    ) -> PhaseResult<ir::IR> {
+        self.compiles += 1;
+        if let Some((ir, warnings, took)) = self.programs.get(&key) {
+            self.reused += 1;
+            self.saved += *took;
+            return Ok((ir.clone(), warnings.clone()));
+        }
+
+        let start = Instant::now();
+        let (ir, warnings) = build()?;
+        if self.programs.len() >= SESSION_PROGRAMS {
+            self.programs.clear();
+        }
+        self.programs
+            .insert(key, (ir.clone(), warnings.clone(), start.elapsed()));
+        Ok((ir, warnings))
+    }
+
+    /// The prelude of `target`, only put together once
+    pub fn prelude(&mut self, target: &impl Target) -> &str {
+        self.prelude.get_or_insert_with(|| ir::prelude(target))
+    }
+
+    /// The C compiler `find` looks for, only looked for once
+    pub fn cc(
+        &mut self,
+        find: impl FnOnce() -> std::io::Result<String>,
+    ) -> std::io::Result<String> {
+        if let Some(cc) = &self.cc {
+            return Ok(cc.clone());
+        }
+        let cc = find()?;
+        self.cc = Some(cc.clone());
+        Ok(cc)
+    }
+
+    /// What --stats prints about the session after a compile
+    pub fn stats(&self) -> String {
+        format!(
+            "session: {} of {} compiles reused their IR, saving {:.2}ms\n",
+            self.reused,
+            self.compiles,
+            self.saved.as_secs_f64() * 1000.0
+        )
+    }
+}
@@ -0,0 +1,9 @@
+//! The compiler's exit codes, other than 0 for success. Once `run`, `debug`
+//! and the like have started a program they exit with its code instead.
+
+/// The program has errors, or a check, build or test found some
+pub const COMPILE_ERROR: i32 = 1;
+/// A bad command line, or a file that could not be read or written
+pub const USAGE: i32 = 2;
+/// The compiler itself crashed
+pub const INTERNAL_ERROR: i32 = 101;
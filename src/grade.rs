@@ -0,0 +1,221 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::diagnostics::Diagnostic;
+use crate::json::Json;
+
+/// What a program may use on each input before it fails
+pub struct Limits {
+    /// loop iterations and calls
+    pub max_steps: u64,
+    pub time: Duration,
+}
+
+/// How a program did on one input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// exited with code 0, printing the expected output if there is one
+    Pass,
+    /// exited with code 0 but printed something else
+    WrongOutput,
+    /// panicked, ran out of time or steps, or otherwise exited with an error
+    Error,
+    /// did not compile, so it was never run
+    CompileError,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::WrongOutput => write!(f, "wrong-output"),
+            Verdict::Error => write!(f, "error"),
+            Verdict::CompileError => write!(f, "compile-error"),
+        }
+    }
+}
+
+/// One input a program is run on, read from <name>.in with the output
+/// expected of it from <name>.out if there is one
+pub struct Case {
+    pub name: String,
+    pub input: Vec<u8>,
+    pub expected: Option<String>,
+}
+
+/// How a program did on one case
+pub struct Run {
+    pub case: String,
+    pub verdict: Verdict,
+    /// None if the program was never run
+    pub exit_code: Option<i32>,
+    /// why it did not pass, empty if it did
+    pub detail: String,
+}
+
+/// Everything a report says about one program
+pub struct Submission {
+    pub path: String,
+    /// its warnings, or its errors if it did not compile
+    pub diagnostics: Vec<Diagnostic>,
+    pub runs: Vec<Run>,
+}
+
+impl Submission {
+    /// A program that did not compile, failing every case
+    pub fn not_compiled(path: String, diagnostics: Vec<Diagnostic>, cases: &[Case]) -> Self {
+        let detail = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.is_error())
+            .map(|diagnostic| diagnostic.to_string())
+            .unwrap_or_default();
+        let runs = cases
+            .iter()
+            .map(|case| Run {
+                case: case.name.clone(),
+                verdict: Verdict::CompileError,
+                exit_code: None,
+                detail: detail.clone(),
+            })
+            .collect();
+
+        Submission {
+            path,
+            diagnostics,
+            runs,
+        }
+    }
+
+    pub fn passed(&self) -> usize {
+        self.runs
+            .iter()
+            .filter(|run| run.verdict == Verdict::Pass)
+            .count()
+    }
+}
+
+/// Where `actual` first differs from `expected`, by line
+fn first_difference(expected: &str, actual: &str) -> String {
+    let describe = |line: Option<&str>| match line {
+        Some(line) => format!("\"{}\"", line),
+        None => "the end of the output".to_string(),
+    };
+
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (Some(wanted), Some(got)) if wanted == got => continue,
+            (wanted, got) => {
+                return format!(
+                    "line {}: expected {}, got {}",
+                    number,
+                    describe(wanted),
+                    describe(got)
+                )
+            }
+        }
+    }
+    "the output differs only in its line endings or final newline".to_string()
+}
+
+/// Judges a run of a program on `case` from its exit code and output
+pub fn judge(case: &Case, code: i32, output: &str) -> Run {
+    let (verdict, detail) = if code != 0 {
+        let detail = match output.lines().find(|line| line.starts_with("panic: ")) {
+            Some(panic) => panic.to_string(),
+            None => format!("exited with code {}", code),
+        };
+        (Verdict::Error, detail)
+    } else {
+        match &case.expected {
+            Some(expected) if expected != output => {
+                (Verdict::WrongOutput, first_difference(expected, output))
+            }
+            _ => (Verdict::Pass, String::new()),
+        }
+    };
+
+    Run {
+        case: case.name.clone(),
+        verdict,
+        exit_code: Some(code),
+        detail,
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// A row for every program and case
+pub fn csv(submissions: &[Submission]) -> String {
+    let mut report = String::from("submission,input,result,exit_code,detail\n");
+    for submission in submissions.iter() {
+        for run in submission.runs.iter() {
+            let exit_code = run
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_default();
+            report.push_str(
+                [
+                    csv_field(&submission.path),
+                    csv_field(&run.case),
+                    run.verdict.to_string(),
+                    exit_code,
+                    csv_field(&run.detail),
+                ]
+                .join(",")
+                .as_str(),
+            );
+            report.push('\n');
+        }
+    }
+    report
+}
+
+/// Every program with its diagnostics, how many cases it passed and how it
+/// did on each
+pub fn json(submissions: &[Submission]) -> Json {
+    let submissions = submissions
+        .iter()
+        .map(|submission| {
+            let runs = submission
+                .runs
+                .iter()
+                .map(|run| {
+                    Json::object(vec![
+                        ("input", Json::from(run.case.as_str())),
+                        ("result", Json::from(run.verdict.to_string())),
+                        (
+                            "exit_code",
+                            run.exit_code
+                                .map_or(Json::Null, |code| Json::from(code as i64)),
+                        ),
+                        ("detail", Json::from(run.detail.as_str())),
+                    ])
+                })
+                .collect::<Vec<Json>>();
+            let diagnostics = submission
+                .diagnostics
+                .iter()
+                .map(|diagnostic| Json::from(diagnostic.to_string()))
+                .collect::<Vec<Json>>();
+
+            Json::object(vec![
+                ("submission", Json::from(submission.path.as_str())),
+                ("passed", Json::from(submission.passed())),
+                ("total", Json::from(submission.runs.len())),
+                ("diagnostics", Json::from(diagnostics)),
+                ("runs", Json::from(runs)),
+            ])
+        })
+        .collect::<Vec<Json>>();
+
+    Json::object(vec![("submissions", Json::from(submissions))])
+}
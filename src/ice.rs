@@ -0,0 +1,348 @@
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::compiler::cache;
+use crate::exit;
+use crate::source::{SourceMap, Span};
+
+const ISSUES: &str = "https://github.com/SpideyZac/LOLCatCompiler/issues";
+
+/// Compilations tried while minimizing, so a slow crash still ends
+const MAX_ATTEMPTS: usize = 500;
+/// Lines shown on each side of the token in a report's snippet
+const CONTEXT_LINES: usize = 3;
+
+/// The source a thread is compiling
+#[derive(Clone)]
+struct Input {
+    name: String,
+    contents: String,
+    hash: u64,
+}
+
+/// What is known about the first crash, for its report
+struct Crash {
+    message: String,
+    /// where in the compiler it panicked, `file:line:col`
+    location: Option<String>,
+    phase: String,
+    input: Option<Input>,
+    /// the index and span of the token being parsed or the statement being
+    /// visited
+    token: Option<(usize, Span)>,
+    backtrace: String,
+    report: Option<PathBuf>,
+}
+
+thread_local! {
+    /// the phase this thread is in, as named to Logger::phase
+    static PHASE: RefCell<String> = RefCell::new("startup".to_string());
+    /// the source this thread is compiling
+    static INPUT: RefCell<Option<Input>> = const { RefCell::new(None) };
+    /// the token this thread is at, see Crash::token
+    static TOKEN: Cell<Option<(usize, Span)>> = const { Cell::new(None) };
+    /// set while a minimization attempt runs, whose panics are expected
+    static ATTEMPTING: Cell<bool> = const { Cell::new(false) };
+    /// where the last minimization attempt panicked
+    static ATTEMPT_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static CRASH: Mutex<Option<Crash>> = Mutex::new(None);
+/// whether reports may quote the source and minimize it
+static INCLUDE_SOURCE: AtomicBool = AtomicBool::new(false);
+
+/// Records the phase the current thread is entering, returning the one it
+/// was in
+pub fn set_phase(phase: &str) -> String {
+    PHASE.with(|current| current.replace(phase.to_string()))
+}
+
+/// Records the source the current thread is compiling
+pub fn set_input(name: &str, contents: &str) {
+    let hash = cache::hash(contents.as_bytes());
+    INPUT.with(|input| {
+        *input.borrow_mut() = Some(Input {
+            name: name.to_string(),
+            contents: contents.to_string(),
+            hash,
+        })
+    });
+    TOKEN.with(|token| token.set(None));
+}
+
+/// Records the token the current thread is working on, by its index in the
+/// lexer's tokens, None when it is at none in particular
+pub fn set_token(current: Option<(usize, Span)>) {
+    TOKEN.with(|token| token.set(current));
+}
+
+/// Lets reports quote the lines around the crash and a minimized input,
+/// which otherwise only name the input by its hash
+pub fn include_source(include: bool) {
+    INCLUDE_SOURCE.store(include, Ordering::Relaxed);
+}
+
+/// Whether some thread has crashed, so workers can stop taking new work
+pub fn crashed() -> bool {
+    lock_crash().is_some()
+}
+
+/// The crash, whichever thread panicked while holding it
+fn lock_crash() -> MutexGuard<'static, Option<Crash>> {
+    CRASH
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The lines around `span`, numbered, with the token's line marked
+fn snippet(input: &Input, span: Span) -> String {
+    let map = SourceMap::single(&input.name, &input.contents);
+    let (line, _) = map.line_col(span.start);
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+
+    let mut snippet = String::new();
+    for (i, text) in input.contents.lines().enumerate() {
+        let number = i + 1;
+        if number < first || number > line + CONTEXT_LINES {
+            continue;
+        }
+        let marker = if number == line { '>' } else { ' ' };
+        snippet.push_str(&format!("{} {:>4} | {}\n", marker, number, text));
+    }
+    snippet
+}
+
+/// The report file's text, without the minimized input
+fn report_text(crash: &Crash) -> String {
+    let mut text = format!("internal compiler error: {}\n", crash.message);
+    if let Some(location) = &crash.location {
+        text.push_str(&format!("at: {}\n", location));
+    }
+    text.push_str(&format!("phase: {}\n", crash.phase));
+    match &crash.input {
+        Some(input) => text.push_str(&format!(
+            "input: {} (hash {:016x})\n",
+            input.name, input.hash
+        )),
+        None => text.push_str("input: none\n"),
+    }
+    match (&crash.input, crash.token) {
+        (Some(input), Some((index, span))) => {
+            let map = SourceMap::single(&input.name, &input.contents);
+            text.push_str(&format!(
+                "token: #{} at {} ({} characters)\n",
+                index,
+                map.location(span.start),
+                span.end.saturating_sub(span.start)
+            ));
+        }
+        _ => text.push_str("token: unknown\n"),
+    }
+    text.push_str(&format!("version: {}\n", env!("CARGO_PKG_VERSION")));
+    text.push_str(&format!(
+        "command: {}\n",
+        env::args().collect::<Vec<String>>().join(" ")
+    ));
+
+    if INCLUDE_SOURCE.load(Ordering::Relaxed) {
+        if let (Some(input), Some((_, span))) = (&crash.input, crash.token) {
+            text.push_str(&format!(
+                "\nsource around the token:\n{}",
+                snippet(input, span)
+            ));
+        }
+    } else {
+        text.push_str("\nrun again with --ice-include-source to add the lines around the token and a minimized input\n");
+    }
+
+    text.push_str(&format!("\nbacktrace:\n{}\n", crash.backtrace));
+    text
+}
+
+/// Writes the report to the temp directory, returning its path
+fn write_report(crash: &Crash) -> Option<PathBuf> {
+    let hash = crash.input.as_ref().map_or(0, |input| input.hash);
+    let path = env::temp_dir().join(format!(
+        "lolcat-ice-{:016x}-{}.txt",
+        hash,
+        std::process::id()
+    ));
+    fs::write(&path, report_text(crash)).ok()?;
+    Some(path)
+}
+
+/// Whether a panic is print! or println! failing as what the output was
+/// piped into has exited, e.g. `| head -1`
+fn is_broken_pipe(message: &str) -> bool {
+    message.starts_with("failed printing to std")
+        && (message.contains("Broken pipe") || message.contains("(os error 232)"))
+}
+
+/// Replaces the default panic message and backtrace with an internal
+/// compiler error report asking for a bug report, also written to a file
+/// in the temp directory. The panic then unwinds to the driver, which calls
+/// finish; only the first crash is reported, as a panic on a worker thread
+/// is raised again by the thread that joins it. The backtrace is still shown
+/// with RUST_BACKTRACE. A broken pipe is no crash, and exits quietly.
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown panic".to_string(),
+            },
+        };
+        if is_broken_pipe(&message) {
+            std::process::exit(0);
+        }
+
+        let location = info.location().map(|location| location.to_string());
+        if ATTEMPTING.with(|attempting| attempting.get()) {
+            ATTEMPT_LOCATION.with(|attempt| *attempt.borrow_mut() = location);
+            return;
+        }
+
+        let mut crash = lock_crash();
+        if crash.is_some() {
+            return;
+        }
+
+        eprintln!("error: internal compiler error: {}", message);
+        if let Some(location) = &location {
+            eprintln!("  at: {}", location);
+        }
+        let phase = PHASE.with(|phase| phase.borrow().clone());
+        eprintln!("  phase: {}", phase);
+        let input = INPUT.with(|input| input.borrow().clone());
+        match &input {
+            Some(input) => eprintln!("  input: {} (hash {:016x})", input.name, input.hash),
+            None => eprintln!("  input: none"),
+        }
+        eprintln!("  version: {}", env!("CARGO_PKG_VERSION"));
+
+        let backtrace = std::backtrace::Backtrace::capture();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            eprintln!("{}", backtrace);
+        }
+
+        let mut new_crash = Crash {
+            message,
+            location,
+            phase,
+            input,
+            token: TOKEN.with(|token| token.get()),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            report: None,
+        };
+        new_crash.report = write_report(&new_crash);
+        match &new_crash.report {
+            Some(path) => eprintln!(
+                "This is a bug in LOLCatCompiler. Please report it at {} with the report written to {}.",
+                ISSUES,
+                path.display()
+            ),
+            None => eprintln!(
+                "This is a bug in LOLCatCompiler. Please report it at {} with the lines above and, if you can, the input.",
+                ISSUES
+            ),
+        }
+        *crash = Some(new_crash);
+    }));
+}
+
+/// Whether compiling `text` with `reproduce` panics where the crash did
+fn crashes(text: &str, location: &Option<String>, reproduce: &dyn Fn(&str)) -> bool {
+    ATTEMPTING.with(|attempting| attempting.set(true));
+    ATTEMPT_LOCATION.with(|attempt| *attempt.borrow_mut() = None);
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| reproduce(text))).is_err();
+    ATTEMPTING.with(|attempting| attempting.set(false));
+
+    panicked && ATTEMPT_LOCATION.with(|attempt| *attempt.borrow() == *location)
+}
+
+/// The smallest input found, by dropping ever smaller runs of lines, that
+/// still crashes at the same place, or None if `text` itself does not
+fn minimize(text: &str, location: &Option<String>, reproduce: &dyn Fn(&str)) -> Option<String> {
+    if !crashes(text, location, reproduce) {
+        return None;
+    }
+
+    let mut lines = text.lines().collect::<Vec<&str>>();
+    let mut chunk = lines.len().div_ceil(2).max(1);
+    let mut attempts = 0;
+
+    loop {
+        let mut removed = false;
+        let mut start = 0;
+        while start < lines.len() && attempts < MAX_ATTEMPTS {
+            let end = (start + chunk).min(lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+            attempts += 1;
+            if crashes(&candidate.join("\n"), location, reproduce) {
+                lines = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+
+        if attempts >= MAX_ATTEMPTS || (chunk == 1 && !removed) {
+            break;
+        }
+        if !removed {
+            chunk = chunk.div_ceil(2);
+        }
+    }
+
+    let mut minimized = lines.join("\n");
+    minimized.push('\n');
+    Some(minimized)
+}
+
+/// Ends the process after a crash has unwound to the driver. With
+/// include_source set, the input is first minimized by running `reproduce`,
+/// which compiles a program the way the crashed command did, and the result
+/// is added to the report.
+pub fn finish(reproduce: &dyn Fn(&str)) -> ! {
+    let crash = lock_crash().take();
+    let crash = match crash {
+        Some(crash) => crash,
+        None => std::process::exit(exit::INTERNAL_ERROR),
+    };
+
+    if INCLUDE_SOURCE.load(Ordering::Relaxed) {
+        if let (Some(input), Some(path)) = (&crash.input, &crash.report) {
+            eprintln!("Minimizing the input...");
+            let text = match minimize(&input.contents, &crash.location, reproduce) {
+                Some(minimized) => {
+                    eprintln!(
+                        "Minimized the input from {} to {} line(s)",
+                        input.contents.lines().count(),
+                        minimized.lines().count()
+                    );
+                    format!("\nminimized input:\n{}", minimized)
+                }
+                None => {
+                    eprintln!(
+                        "Compiling the input on its own does not crash, so it was not minimized"
+                    );
+                    "\nminimized input: compiling the input on its own does not crash\n".to_string()
+                }
+            };
+            let mut report = report_text(&crash);
+            report.push_str(&text);
+            if fs::write(path, report).is_err() {
+                eprintln!("Could not add the minimized input to {}", path.display());
+            }
+        }
+    }
+
+    std::process::exit(exit::INTERNAL_ERROR);
+}
@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+use super::{trace_lines, Machine, Observer, Stop};
+use crate::compiler::visit::TraceInfo;
+use crate::source::SourceMap;
+
+/// Counts how often every trace point runs
+pub struct Coverage {
+    pub counts: Vec<u64>,
+}
+
+impl Coverage {
+    pub fn new(trace: &TraceInfo) -> Self {
+        Coverage {
+            counts: vec![0; trace.points.len()],
+        }
+    }
+
+    /// An lcov tracefile for `path`. A line with several statements counts
+    /// as often as the one that ran most.
+    pub fn lcov(&self, trace: &TraceInfo, source: &SourceMap, path: &str) -> String {
+        let mut lines = BTreeMap::new();
+        for (line, count) in trace_lines(trace, source).iter().zip(self.counts.iter()) {
+            let hits = lines.entry(*line).or_insert(0);
+            *hits = (*hits).max(*count);
+        }
+
+        let mut out = format!("TN:\nSF:{}\n", path);
+        for (line, count) in lines.iter() {
+            out.push_str(format!("DA:{},{}\n", line, count).as_str());
+        }
+        out.push_str(
+            format!(
+                "LF:{}\nLH:{}\nend_of_record\n",
+                lines.len(),
+                lines.values().filter(|count| **count > 0).count()
+            )
+            .as_str(),
+        );
+
+        out
+    }
+}
+
+impl Observer for Coverage {
+    fn trace(&mut self, _machine: &mut Machine, id: usize) -> Result<(), Stop> {
+        self.counts[id] += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,535 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use super::debugger::{Debugger, Mode};
+use super::{Machine, Observer, Stop};
+use crate::compiler::ir;
+use crate::compiler::visit::TraceInfo;
+use crate::json::Json;
+use crate::source::SourceMap;
+use crate::utils::decode_source;
+
+/// There is no threading in LOLCODE, so every request is about this one
+const THREAD_ID: i64 = 1;
+
+/// Turns a file's path and source into IR and trace points, or diagnostics
+pub type FrontEnd<'f> = &'f dyn Fn(&str, &str) -> Result<(ir::IR, TraceInfo), Vec<String>>;
+
+/// Reads a Content-Length framed message, None at the end of input or if
+/// it is malformed
+fn read_message(input: &mut dyn BufRead) -> Option<Json> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    Json::parse(&String::from_utf8_lossy(&body))
+}
+
+/// Writes numbered messages to stdout
+struct Sender {
+    seq: i64,
+}
+
+impl Sender {
+    fn send(&mut self, kind: &str, mut fields: Vec<(&str, Json)>) {
+        fields.insert(0, ("seq", Json::from(self.seq)));
+        fields.insert(1, ("type", Json::from(kind)));
+        self.seq += 1;
+
+        let body = Json::object(fields).to_string();
+        let mut stdout = std::io::stdout().lock();
+        let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = stdout.flush();
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        let mut fields = vec![("event", Json::from(event))];
+        if body != Json::Null {
+            fields.push(("body", body));
+        }
+        self.send("event", fields);
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) {
+        let mut fields = vec![
+            (
+                "request_seq",
+                request.get("seq").cloned().unwrap_or(Json::Null),
+            ),
+            ("success", Json::from(result.is_ok())),
+            (
+                "command",
+                request.get("command").cloned().unwrap_or(Json::Null),
+            ),
+        ];
+        match result {
+            Ok(Json::Null) => {}
+            Ok(body) => fields.push(("body", body)),
+            Err(message) => fields.push(("message", Json::from(message))),
+        }
+        self.send("response", fields);
+    }
+
+    fn output(&mut self, category: &str, text: &str) {
+        self.event(
+            "output",
+            Json::object(vec![
+                ("category", Json::from(category)),
+                ("output", Json::from(text)),
+            ]),
+        );
+    }
+}
+
+/// The program's output, sent to the editor as an output event whenever it
+/// is flushed
+struct OutputEvents {
+    sender: Rc<RefCell<Sender>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = String::from_utf8_lossy(&self.buffer).replace('\0', "");
+            self.sender.borrow_mut().output("stdout", &text);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+fn arguments(request: &Json) -> &Json {
+    request.get("arguments").unwrap_or(&Json::Null)
+}
+
+/// The lines of a setBreakpoints request
+fn requested_lines(request: &Json) -> Vec<usize> {
+    arguments(request)
+        .get("breakpoints")
+        .and_then(|b| b.as_array())
+        .map(|breakpoints| {
+            breakpoints
+                .iter()
+                .filter_map(|b| b.get("line").and_then(|l| l.as_f64()))
+                .map(|line| line as usize)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn breakpoints_body(lines: Vec<Option<usize>>) -> Json {
+    let breakpoints = lines
+        .into_iter()
+        .map(|line| match line {
+            Some(line) => Json::object(vec![
+                ("verified", Json::from(true)),
+                ("line", Json::from(line)),
+            ]),
+            None => Json::object(vec![
+                ("verified", Json::from(false)),
+                ("message", Json::from("No statement at or after this line")),
+            ]),
+        })
+        .collect::<Vec<Json>>();
+
+    Json::object(vec![("breakpoints", Json::from(breakpoints))])
+}
+
+fn threads_body() -> Json {
+    Json::object(vec![(
+        "threads",
+        Json::from(vec![Json::object(vec![
+            ("id", Json::from(THREAD_ID)),
+            ("name", Json::from("main")),
+        ])]),
+    )])
+}
+
+/// A program compiled by a launch request
+struct Program {
+    path: String,
+    source: String,
+    ir: ir::IR,
+    trace: TraceInfo,
+}
+
+fn launch(request: &Json, front_end: FrontEnd) -> Result<Program, String> {
+    let path = match arguments(request).get("program").and_then(|p| p.as_str()) {
+        Some(path) => path.to_string(),
+        None => return Err("launch needs a program to run".to_string()),
+    };
+
+    let bytes = fs::read(&path).map_err(|e| format!("Could not read file '{}': {}", path, e))?;
+    let source = decode_source(&bytes, false)
+        .map_err(|e| format!("Could not decode file '{}': {}", path, e))?
+        .contents;
+    let (ir, trace) = front_end(&path, &source).map_err(|diagnostics| diagnostics.join("\n\n"))?;
+
+    Ok(Program {
+        path,
+        source,
+        ir,
+        trace,
+    })
+}
+
+/// Answers the editor's requests whenever the program stops
+struct Adapter<'a> {
+    debugger: Debugger<'a>,
+    input: &'a mut dyn BufRead,
+    sender: Rc<RefCell<Sender>>,
+    path: &'a str,
+    stopped_before: bool,
+    disconnected: bool,
+}
+
+impl Adapter<'_> {
+    fn stack_trace(&self, machine: &Machine) -> Json {
+        let name = Path::new(self.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let frames = self
+            .debugger
+            .backtrace(machine)
+            .iter()
+            .enumerate()
+            .map(|(depth, frame)| {
+                Json::object(vec![
+                    ("id", Json::from(depth)),
+                    ("name", Json::from(frame.function.as_str())),
+                    ("line", Json::from(frame.line.unwrap_or(0))),
+                    ("column", Json::from(1_usize)),
+                    (
+                        "source",
+                        Json::object(vec![
+                            ("name", Json::from(name.as_str())),
+                            ("path", Json::from(self.path)),
+                        ]),
+                    ),
+                ])
+            })
+            .collect::<Vec<Json>>();
+
+        Json::object(vec![
+            ("totalFrames", Json::from(frames.len())),
+            ("stackFrames", Json::from(frames)),
+        ])
+    }
+
+    /// The trace point a frame of the backtrace last reached
+    fn frame_trace(&self, machine: &Machine, frame: usize, id: usize) -> usize {
+        self.debugger
+            .backtrace(machine)
+            .get(frame)
+            .and_then(|f| f.trace)
+            .unwrap_or(id)
+    }
+
+    fn variables(&self, machine: &Machine, id: usize) -> Json {
+        let variables = self
+            .debugger
+            .variable_names(id)
+            .into_iter()
+            .filter_map(|name| {
+                let (type_, value) = self.debugger.variable(machine, id, name).ok()?;
+                Some(Json::object(vec![
+                    ("name", Json::from(name)),
+                    ("value", Json::from(value)),
+                    ("type", Json::from(type_.to_string())),
+                    ("variablesReference", Json::from(0_usize)),
+                ]))
+            })
+            .collect::<Vec<Json>>();
+
+        Json::object(vec![("variables", Json::from(variables))])
+    }
+
+    /// Answers one request while stopped at trace point `id`, returning
+    /// whether it resumes the program
+    fn handle(&mut self, machine: &mut Machine, id: usize, request: &Json) -> Result<bool, Stop> {
+        let command = request
+            .get("command")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+        let args = arguments(request);
+        let frame = args.get("frameId").and_then(|f| f.as_f64()).unwrap_or(0.0) as usize;
+
+        let (result, resume) = match command {
+            "threads" => (Ok(threads_body()), false),
+            "stackTrace" => (Ok(self.stack_trace(machine)), false),
+            "scopes" => {
+                let scope = Json::object(vec![
+                    ("name", Json::from("Locals")),
+                    ("variablesReference", Json::from(frame + 1)),
+                    ("expensive", Json::from(false)),
+                ]);
+                (
+                    Ok(Json::object(vec![("scopes", Json::from(vec![scope]))])),
+                    false,
+                )
+            }
+            "variables" => {
+                let reference = args
+                    .get("variablesReference")
+                    .and_then(|r| r.as_f64())
+                    .unwrap_or(1.0) as usize;
+                let trace = self.frame_trace(machine, reference.saturating_sub(1), id);
+                (Ok(self.variables(machine, trace)), false)
+            }
+            "evaluate" => {
+                let expression = args
+                    .get("expression")
+                    .and_then(|e| e.as_str())
+                    .unwrap_or("")
+                    .trim();
+                let trace = self.frame_trace(machine, frame, id);
+                let result =
+                    self.debugger
+                        .variable(machine, trace, expression)
+                        .map(|(type_, value)| {
+                            Json::object(vec![
+                                ("result", Json::from(value)),
+                                ("type", Json::from(type_.to_string())),
+                                ("variablesReference", Json::from(0_usize)),
+                            ])
+                        });
+                (result, false)
+            }
+            "setBreakpoints" => {
+                self.debugger.breakpoints.clear();
+                let lines = requested_lines(request)
+                    .into_iter()
+                    .map(|line| self.debugger.add_breakpoint(line))
+                    .collect();
+                (Ok(breakpoints_body(lines)), false)
+            }
+            "continue" => {
+                self.debugger.mode = Mode::Continue;
+                (
+                    Ok(Json::object(vec![(
+                        "allThreadsContinued",
+                        Json::from(true),
+                    )])),
+                    true,
+                )
+            }
+            "next" => {
+                self.debugger.mode = Mode::Next(machine.frames.len());
+                (Ok(Json::Null), true)
+            }
+            "stepIn" => {
+                self.debugger.mode = Mode::Step;
+                (Ok(Json::Null), true)
+            }
+            "stepOut" => {
+                self.debugger.mode = Mode::Out(machine.frames.len());
+                (Ok(Json::Null), true)
+            }
+            // already stopped
+            "pause" => (Ok(Json::Null), false),
+            "disconnect" | "terminate" => {
+                self.sender.borrow_mut().respond(request, Ok(Json::Null));
+                self.disconnected = true;
+                return Err(Stop::Quit);
+            }
+            _ => (Err(format!("Unsupported request {}", command)), false),
+        };
+
+        self.sender.borrow_mut().respond(request, result);
+        Ok(resume)
+    }
+}
+
+impl Observer for Adapter<'_> {
+    fn trace(&mut self, machine: &mut Machine, id: usize) -> Result<(), Stop> {
+        if !self.debugger.should_stop(machine, id) {
+            return Ok(());
+        }
+
+        let reason = match self.debugger.mode {
+            Mode::Step if !self.stopped_before => "entry",
+            Mode::Continue => "breakpoint",
+            _ => "step",
+        };
+        self.stopped_before = true;
+
+        // the program's output so far comes before the stop
+        machine.flush();
+        self.sender.borrow_mut().event(
+            "stopped",
+            Json::object(vec![
+                ("reason", Json::from(reason)),
+                ("threadId", Json::from(THREAD_ID)),
+                ("allThreadsStopped", Json::from(true)),
+            ]),
+        );
+
+        loop {
+            let request = match read_message(self.input) {
+                Some(request) => request,
+                None => {
+                    self.disconnected = true;
+                    return Err(Stop::Quit);
+                }
+            };
+            if self.handle(machine, id, &request)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Speaks the Debug Adapter Protocol over `input` and stdout until the editor
+/// disconnects, running one launched program in the interpreter. The program
+/// has no input of its own, its output is sent as output events.
+pub fn serve(input: &mut dyn BufRead, front_end: FrontEnd) {
+    let sender = Rc::new(RefCell::new(Sender { seq: 1 }));
+
+    let mut program = None;
+    let mut requested_breakpoints = vec![];
+    let mut stop_on_entry = false;
+    let mut configured = false;
+
+    while program.is_none() || !configured {
+        let request = match read_message(input) {
+            Some(request) => request,
+            None => return,
+        };
+        let command = request
+            .get("command")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+
+        let result = match command {
+            "initialize" => Ok(Json::object(vec![
+                ("supportsConfigurationDoneRequest", Json::from(true)),
+                ("supportsEvaluateForHovers", Json::from(true)),
+                ("supportsTerminateRequest", Json::from(true)),
+            ])),
+            "launch" => match launch(&request, front_end) {
+                Ok(launched) => {
+                    stop_on_entry = arguments(&request)
+                        .get("stopOnEntry")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    program = Some(launched);
+                    Ok(Json::Null)
+                }
+                Err(message) => {
+                    sender
+                        .borrow_mut()
+                        .output("stderr", &format!("{}\n", message));
+                    Err(message)
+                }
+            },
+            // lines are only checked against the program once it is launched
+            "setBreakpoints" => {
+                requested_breakpoints = requested_lines(&request);
+                Ok(breakpoints_body(
+                    requested_breakpoints.iter().map(|l| Some(*l)).collect(),
+                ))
+            }
+            "setExceptionBreakpoints" => Ok(Json::Null),
+            "configurationDone" => {
+                configured = true;
+                Ok(Json::Null)
+            }
+            "threads" => Ok(threads_body()),
+            "disconnect" | "terminate" => {
+                sender.borrow_mut().respond(&request, Ok(Json::Null));
+                return;
+            }
+            _ => Err(format!("Unsupported request {}", command)),
+        };
+
+        sender.borrow_mut().respond(&request, result);
+        if command == "initialize" {
+            sender.borrow_mut().event("initialized", Json::Null);
+        }
+    }
+
+    let program = program.unwrap();
+    let mut debugger = Debugger::new(
+        &program.trace,
+        SourceMap::single(&program.path, &program.source),
+    );
+    for line in requested_breakpoints.iter() {
+        debugger.add_breakpoint(*line);
+    }
+    if !stop_on_entry {
+        debugger.mode = Mode::Continue;
+    }
+
+    let mut adapter = Adapter {
+        debugger,
+        input,
+        sender: sender.clone(),
+        path: &program.path,
+        stopped_before: false,
+        disconnected: false,
+    };
+    let mut machine = Machine::new(
+        program.ir.entry.stack_size,
+        program.ir.entry.heap_size,
+        Box::new(std::io::empty()),
+        Box::new(OutputEvents {
+            sender: sender.clone(),
+            buffer: vec![],
+        }),
+    );
+    let result = machine.run(&program.ir, &mut adapter);
+    let code = machine.finish(result);
+
+    if adapter.disconnected {
+        return;
+    }
+    sender.borrow_mut().event(
+        "exited",
+        Json::object(vec![("exitCode", Json::from(code as i64))]),
+    );
+    sender.borrow_mut().event("terminated", Json::Null);
+
+    // the editor still asks about the program until it disconnects
+    while let Some(request) = read_message(adapter.input) {
+        let command = request
+            .get("command")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+        match command {
+            "disconnect" | "terminate" => {
+                sender.borrow_mut().respond(&request, Ok(Json::Null));
+                return;
+            }
+            "threads" => sender.borrow_mut().respond(&request, Ok(threads_body())),
+            _ => sender
+                .borrow_mut()
+                .respond(&request, Err("The program has ended".to_string())),
+        }
+    }
+}
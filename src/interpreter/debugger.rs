@@ -0,0 +1,319 @@
+use std::collections::BTreeSet;
+
+use super::{trace_lines, Machine, Observer, Stop};
+use crate::compiler::visit::{TraceInfo, Types, ANY_SLOT, PIECE_KEY};
+use crate::parser::printer;
+use crate::source::SourceMap;
+
+const HELP: &str = "\
+break <line>    stop whenever the first statement of a line is about to run (b)
+delete [line]   remove a breakpoint, or every breakpoint (d)
+step            run a single statement, following calls (s)
+next            run to the next statement in this call or the one it returns to (n)
+finish          run until the current call returns (f)
+continue        run until a breakpoint (c)
+print <name>    show a variable (p)
+locals          show every variable in scope
+backtrace       show the calls being run (bt)
+quit            end the program (q)
+An empty line repeats the last command.";
+
+pub enum Mode {
+    Step,
+    /// step over calls, stopping once at most this many calls are running
+    Next(usize),
+    /// stop once fewer than this many calls are running
+    Out(usize),
+    Continue,
+}
+
+/// A call being run, as shown in a backtrace
+pub struct StackFrame {
+    pub function: String,
+    pub line: Option<usize>,
+    /// the trace point the call last reached
+    pub trace: Option<usize>,
+}
+
+/// What any front end to the debugger needs: where the trace points are,
+/// when to stop and how to show the program's variables
+pub struct Debugger<'a> {
+    trace: &'a TraceInfo,
+    pub source: SourceMap,
+    /// the line, counting from 1, of every trace point
+    point_lines: Vec<usize>,
+    pub breakpoints: BTreeSet<usize>,
+    pub mode: Mode,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(trace: &'a TraceInfo, source: SourceMap) -> Self {
+        Debugger {
+            trace,
+            point_lines: trace_lines(trace, &source),
+            source,
+            breakpoints: BTreeSet::new(),
+            mode: Mode::Step,
+        }
+    }
+
+    pub fn line_of(&self, id: usize) -> usize {
+        self.point_lines[id]
+    }
+
+    /// Breaks at the first line at or after `line` with a statement on it,
+    /// returning that line
+    pub fn add_breakpoint(&mut self, line: usize) -> Option<usize> {
+        let found = *self.point_lines.iter().filter(|l| **l >= line).min()?;
+        self.breakpoints.insert(found);
+        Some(found)
+    }
+
+    /// Whether `id` is the first trace point of its line, so a line with
+    /// several statements is only stopped at once
+    fn starts_line(&self, id: usize) -> bool {
+        id == 0 || self.point_lines[id - 1] != self.point_lines[id]
+    }
+
+    /// Whether the program should stop before trace point `id`
+    pub fn should_stop(&self, machine: &Machine, id: usize) -> bool {
+        match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => machine.frames.len() <= depth,
+            Mode::Out(depth) => machine.frames.len() < depth,
+            Mode::Continue => {
+                self.breakpoints.contains(&self.point_lines[id]) && self.starts_line(id)
+            }
+        }
+    }
+
+    pub fn format_value(&self, machine: &Machine, value: f32, type_: &Types) -> String {
+        match type_ {
+            Types::Number => (value as i32).to_string(),
+            Types::Numbar => value.to_string(),
+            Types::Troof => if value != 0.0 { "WIN" } else { "FAIL" }.to_string(),
+            Types::Noob => "NOOB".to_string(),
+            Types::Yarn(size) => {
+                let mut bytes = vec![];
+                for i in 0..*size {
+                    match machine.heap_get(value, i) {
+                        Ok(ch) if ch != 0.0 => bytes.push(ch as i32 as u8),
+                        Ok(_) => break,
+                        Err(_) => return "<invalid YARN>".to_string(),
+                    }
+                }
+                printer::print_yarn(&String::from_utf8_lossy(&bytes))
+            }
+            Types::Bukkit(shape) => {
+                let slots = match self.bukkit_slots(machine, value, *shape) {
+                    Some(slots) => slots,
+                    None => return "<invalid BUKKIT>".to_string(),
+                };
+                let slots = slots
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect::<Vec<String>>();
+                format!("BUKKIT {{{}}}", slots.join(", "))
+            }
+        }
+    }
+
+    /// The names and formatted values of the slots of a bukkit, in the order
+    /// they were added
+    fn bukkit_slots(
+        &self,
+        machine: &Machine,
+        bukkit: f32,
+        shape: usize,
+    ) -> Option<Vec<(String, String)>> {
+        let length = machine.heap_get(bukkit, 0).ok()? as i32;
+        let entries = machine.heap_get(bukkit, 2).ok()?;
+
+        let mut slots = vec![];
+        for i in 0..length {
+            let key = machine.heap_get(entries, i * 3).ok()? as usize;
+            let value = machine.heap_get(entries, i * 3 + 1).ok()?;
+
+            let slot_types = self.trace.bukkits.get(shape)?;
+            // SPLITZ pieces are named by their index
+            let (name, type_) = match key.checked_sub(PIECE_KEY as usize) {
+                Some(index) => (index.to_string(), slot_types.get(ANY_SLOT)?),
+                None => {
+                    let name = self.trace.slot_keys.get(key.wrapping_sub(1))?;
+                    (name.clone(), slot_types.get(name)?)
+                }
+            };
+            slots.push((name, self.format_value(machine, value, type_)));
+        }
+
+        Some(slots)
+    }
+
+    /// The names of the variables in scope at trace point `id`, sorted
+    pub fn variable_names(&self, id: usize) -> Vec<&str> {
+        self.trace.points[id]
+            .variables
+            .iter()
+            .map(|(name, _, _)| name.as_str())
+            .collect()
+    }
+
+    /// The type and formatted value of a variable in scope at trace point
+    /// `id`, Err with a message if there is none
+    pub fn variable(
+        &self,
+        machine: &Machine,
+        id: usize,
+        name: &str,
+    ) -> Result<(Types, String), String> {
+        let variables = &self.trace.points[id].variables;
+        let (_, hook, type_) = match variables.iter().find(|(n, _, _)| n == name) {
+            Some(variable) => variable,
+            None => return Err(format!("No variable {} in scope", name)),
+        };
+
+        // a hook holds the stack position of the value
+        let value = machine
+            .hook_value(*hook)
+            .and_then(|position| machine.stack_value(position));
+        match value {
+            Some(value) => Ok((type_.clone(), self.format_value(machine, value, type_))),
+            None => Err(format!("{} has no value yet", name)),
+        }
+    }
+
+    /// The calls being run, innermost first
+    pub fn backtrace(&self, machine: &Machine) -> Vec<StackFrame> {
+        machine
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| StackFrame {
+                function: frame.function.clone(),
+                line: frame.trace.map(|id| self.point_lines[id]),
+                trace: frame.trace,
+            })
+            .collect()
+    }
+}
+
+/// Stops a traced program at breakpoints and between steps, reading commands
+/// from the program's input. Everything it prints goes to stderr so the
+/// program's own output stays as it is.
+pub struct Console<'a> {
+    pub debugger: Debugger<'a>,
+    last_command: String,
+}
+
+impl<'a> Console<'a> {
+    pub fn new(debugger: Debugger<'a>) -> Self {
+        Console {
+            debugger,
+            last_command: String::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, line: usize) {
+        match self.debugger.add_breakpoint(line) {
+            Some(found) => eprintln!("Breakpoint at line {}", found),
+            None => eprintln!("No statement at or after line {}", line),
+        }
+    }
+
+    fn print_variable(&self, machine: &Machine, id: usize, name: &str) {
+        match self.debugger.variable(machine, id, name) {
+            Ok((type_, value)) => eprintln!("{} ITZ {} = {}", name, type_.to_string(), value),
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+
+    fn backtrace(&self, machine: &Machine) {
+        for (depth, frame) in self.debugger.backtrace(machine).iter().enumerate() {
+            match frame.line {
+                Some(line) => eprintln!("#{} {} at line {}", depth, frame.function, line),
+                None => eprintln!("#{} {}", depth, frame.function),
+            }
+        }
+    }
+
+    /// Reads commands until one resumes the program
+    fn prompt(&mut self, machine: &mut Machine, id: usize) -> Result<(), Stop> {
+        loop {
+            eprint!("(lolcat) ");
+            let command = match machine.read_line() {
+                Some(command) if command.trim().is_empty() => self.last_command.clone(),
+                Some(command) => command.trim().to_string(),
+                None => return Err(Stop::Quit),
+            };
+            self.last_command = command.clone();
+
+            let mut words = command.split_whitespace();
+            let argument = words.clone().nth(1);
+            match words.next() {
+                Some("s") | Some("step") => {
+                    self.debugger.mode = Mode::Step;
+                    return Ok(());
+                }
+                Some("n") | Some("next") => {
+                    self.debugger.mode = Mode::Next(machine.frames.len());
+                    return Ok(());
+                }
+                Some("f") | Some("finish") => {
+                    self.debugger.mode = Mode::Out(machine.frames.len());
+                    return Ok(());
+                }
+                Some("c") | Some("continue") => {
+                    self.debugger.mode = Mode::Continue;
+                    return Ok(());
+                }
+                Some("b") | Some("break") => match argument.and_then(|a| a.parse().ok()) {
+                    Some(line) => self.add_breakpoint(line),
+                    None => eprintln!("Usage: break <line>"),
+                },
+                Some("d") | Some("delete") => match argument.and_then(|a| a.parse().ok()) {
+                    Some(line) => {
+                        if !self.debugger.breakpoints.remove(&line) {
+                            eprintln!("No breakpoint at line {}", line);
+                        }
+                    }
+                    None => self.debugger.breakpoints.clear(),
+                },
+                Some("p") | Some("print") => match argument {
+                    Some(name) => self.print_variable(machine, id, name),
+                    None => eprintln!("Usage: print <name>"),
+                },
+                Some("locals") => {
+                    for name in self.debugger.variable_names(id) {
+                        self.print_variable(machine, id, name);
+                    }
+                }
+                Some("bt") | Some("backtrace") => self.backtrace(machine),
+                Some("q") | Some("quit") => return Err(Stop::Quit),
+                Some("h") | Some("help") => eprintln!("{}", HELP),
+                Some(other) => eprintln!("Unknown command {}, try help", other),
+                None => {}
+            }
+        }
+    }
+}
+
+impl Observer for Console<'_> {
+    fn trace(&mut self, machine: &mut Machine, id: usize) -> Result<(), Stop> {
+        if !self.debugger.should_stop(machine, id) {
+            return Ok(());
+        }
+
+        // the program's output so far comes before anything about the stop
+        machine.flush();
+        let line = self.debugger.line_of(id);
+        eprintln!(
+            "Stopped at line {}: {}",
+            line,
+            self.debugger
+                .source
+                .line_text(self.debugger.trace.points[id].token.token.start)
+                .trim()
+        );
+        self.prompt(machine, id)
+    }
+}
@@ -0,0 +1,798 @@
+use super::{panic, Machine, Observer, Stop};
+use crate::compiler::visit::PIECE_KEY;
+use crate::utils;
+
+// the foreign functions of std.c, see there for what each expects on the stack
+
+const BUKKIT_HEADER: i32 = 5;
+const BUKKIT_REFERENCE: i32 = -1;
+
+/// Whether all of `text` matches `pattern`, where `*` is any letters and `?`
+/// any one letter. A `*` that fails to match goes back to the last one and
+/// lets it take one more letter, so there is no exponential backtracking.
+fn glob_match(text: &[f32], pattern: &[f32]) -> bool {
+    let (star, any) = ('*' as i32 as f32, '?' as i32 as f32);
+    let (mut t, mut p) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == star {
+            last_star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == any || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if let Some((star_p, star_t)) = last_star {
+            p = star_p + 1;
+            t = star_t + 1;
+            last_star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|ch| *ch == star)
+}
+
+/// std.c's format_float: sprintf's %f in the C locale, with "nan" for every
+/// NaN. Rust's formatting never looks at the locale.
+fn format_float(n: f32) -> String {
+    if n.is_nan() {
+        return "nan".to_string();
+    }
+    format!("{:.6}", n)
+}
+
+impl Machine {
+    pub(super) fn call_foreign(
+        &mut self,
+        name: &str,
+        observer: &mut impl Observer,
+    ) -> Result<(), Stop> {
+        match name {
+            "prn" => {
+                let n = self.pop()?;
+                self.write(format_float(n).as_bytes());
+            }
+            "prs" => {
+                let mut i = self.pop()?;
+                loop {
+                    let ch = *self.slot(i)?;
+                    if ch == 0.0 {
+                        break;
+                    }
+                    self.write(&[ch as i32 as u8]);
+                    i += 1.0;
+                }
+            }
+            "prh" => {
+                let addr = self.pop()?;
+                let range = self.heap_range(addr, 1)?;
+                let byte = self.heap[range.start];
+                self.write(&[byte]);
+            }
+            "prc" => {
+                let n = self.pop()?;
+                self.write(&[n as i32 as u8]);
+            }
+            "flush" => self.flush(),
+            "prend" => self.write(self.newline.as_bytes()),
+            "getch" => {
+                let mut ch = self.getchar();
+                if ch == '\r' as i32 {
+                    ch = self.getchar();
+                }
+                self.push(ch as u8 as i8 as f32)?;
+            }
+            "float_to_int" => {
+                let n = self.pop()?;
+                self.push(n as i32 as f32)?;
+            }
+            "string_to_int" => {
+                let number = self.parse_string(false)?;
+                self.push(number)?;
+            }
+            "int_to_float" => {
+                let n = self.pop()? as i32;
+                self.push(n as f32)?;
+            }
+            "string_to_float" => {
+                let number = self.parse_string(true)?;
+                self.push(number)?;
+            }
+            "int_to_string" => {
+                let n = self.pop()? as i32;
+                self.new_string(n.to_string().as_bytes(), 32)?;
+            }
+            "float_to_string" => {
+                let n = self.pop()?;
+                self.new_string(format_float(n).as_bytes(), 32)?;
+            }
+            "troof_to_string" => {
+                let troof = self.pop()?;
+                let text = if troof != 0.0 { "WIN" } else { "FAIL" };
+                self.new_string(text.as_bytes(), 5)?;
+            }
+            "yarn_length" => {
+                let size = self.pop()? as i32;
+                let addr = self.pop()?;
+                let mut length = 0;
+                for i in 0..size {
+                    if self.heap_get(addr, i)? == 0.0 {
+                        break;
+                    }
+                    length += 1;
+                }
+                self.push(length as f32)?;
+            }
+            "yarn_compare" => {
+                let right_size = self.pop()? as i32;
+                let right = self.pop()?;
+                let left_size = self.pop()? as i32;
+                let left = self.pop()?;
+
+                let mut order = 0.0;
+                for i in 0..left_size.max(right_size) {
+                    let a = if i < left_size {
+                        self.heap_get(left, i)?
+                    } else {
+                        0.0
+                    };
+                    let b = if i < right_size {
+                        self.heap_get(right, i)?
+                    } else {
+                        0.0
+                    };
+                    if a != b {
+                        order = if a < b { -1.0 } else { 1.0 };
+                        break;
+                    }
+                    if a == 0.0 {
+                        break;
+                    }
+                }
+                self.push(order)?;
+            }
+            "letter_code" => {
+                let size = self.pop()? as i32;
+                let addr = self.pop()?;
+                let length = self.text_length(addr, size)?;
+                if length != 1 {
+                    return panic(
+                        &format!("CODEZ OF needs a YARN of one letter, not {}", length),
+                        1,
+                    );
+                }
+                let code = self.heap_get(addr, 0)?;
+                self.push(code)?;
+            }
+            "code_letter" => {
+                let code = self.pop()? as i32;
+                if !(1..=0x10FFFF).contains(&code) {
+                    return panic(&format!("LETTAR OF {} is not a letter", code), 1);
+                }
+                self.push(1.0)?;
+                self.allocate()?;
+                let addr = self.pop()?;
+                self.heap_set(addr, 0, code as f32)?;
+                self.push(addr)?;
+            }
+            "yarn_split" => {
+                let separator_size = self.pop()? as i32;
+                let separator = self.pop()?;
+                let size = self.pop()? as i32;
+                let yarn = self.pop()?;
+
+                let length = self.text_length(yarn, size)?;
+                let separator_length = self.text_length(separator, separator_size)?;
+
+                let bukkit = self.bukkit_create(4)?;
+                let mut pieces = 0;
+                if separator_length == 0 {
+                    for i in 0..length {
+                        self.split_piece(bukkit, pieces, yarn, size, i..i + 1)?;
+                        pieces += 1;
+                    }
+                } else {
+                    let mut start = 0;
+                    let mut i = 0;
+                    while i + separator_length <= length {
+                        let mut matched = true;
+                        for j in 0..separator_length {
+                            if self.heap_get(yarn, i + j)? != self.heap_get(separator, j)? {
+                                matched = false;
+                                break;
+                            }
+                        }
+
+                        if matched {
+                            self.split_piece(bukkit, pieces, yarn, size, start..i)?;
+                            pieces += 1;
+                            i += separator_length;
+                            start = i;
+                        } else {
+                            i += 1;
+                        }
+                    }
+                    self.split_piece(bukkit, pieces, yarn, size, start..length)?;
+                }
+                self.push(bukkit)?;
+            }
+            "yarn_join" => {
+                let separator_size = self.pop()? as i32;
+                let separator = self.pop()?;
+                let size = self.pop()? as i32;
+                let bukkit = self.pop()?;
+
+                let separator_length = self.text_length(separator, separator_size)?;
+                let joined = self.heap_allocate(size)?;
+                let length = self.heap_get(bukkit, 0)? as i32;
+                let entries = self.heap_get(bukkit, 2)?;
+
+                let mut at = 0;
+                for i in 0..length {
+                    if i > 0 {
+                        for j in 0..separator_length.min(size - at) {
+                            let ch = self.heap_get(separator, j)?;
+                            self.heap_set(joined, at, ch)?;
+                            at += 1;
+                        }
+                    }
+
+                    let value = self.heap_get(entries, i * 3 + 1)?;
+                    let value_size = self.heap_get(entries, i * 3 + 2)? as i32;
+                    let value_length = self.text_length(value, value_size)?;
+                    for j in 0..value_length.min(size - at) {
+                        let ch = self.heap_get(value, j)?;
+                        self.heap_set(joined, at, ch)?;
+                        at += 1;
+                    }
+                }
+                for i in at..size {
+                    self.heap_set(joined, i, 0.0)?;
+                }
+                self.push(joined)?;
+            }
+            "yarn_matchez" => {
+                let pattern_size = self.pop()? as i32;
+                let pattern = self.pop()?;
+                let size = self.pop()? as i32;
+                let yarn = self.pop()?;
+
+                let pattern_length = self.text_length(pattern, pattern_size)?;
+                let length = self.text_length(yarn, size)?;
+                let pattern = (0..pattern_length)
+                    .map(|i| self.heap_get(pattern, i))
+                    .collect::<Result<Vec<f32>, Stop>>()?;
+                let text = (0..length)
+                    .map(|i| self.heap_get(yarn, i))
+                    .collect::<Result<Vec<f32>, Stop>>()?;
+
+                let matched = if glob_match(&text, &pattern) {
+                    1.0
+                } else {
+                    0.0
+                };
+                self.push(matched)?;
+            }
+            "print_string" => {
+                let size = self.pop()? as i32;
+                let addr = self.pop()?;
+                self.str_print(addr, size)?;
+            }
+            "read_succeeded" => {
+                let succeeded = if self.input_eof { 0.0 } else { 1.0 };
+                self.push(succeeded)?;
+            }
+            "read_char" => {
+                let ch = self.read_char();
+                self.push(ch as f32)?;
+            }
+            "read_letter" => {
+                self.push(1.0)?;
+                self.allocate()?;
+                let addr = self.pop()?;
+                let ch = self.read_char();
+                // an empty letter rather than the EOF code
+                let ch = if self.input_eof { 0.0 } else { ch as f32 };
+                self.heap_set(addr, 0, ch)?;
+                self.push(addr)?;
+            }
+            "read_string" => self.read_string()?,
+            "bukkit_new" => {
+                let bukkit = self.bukkit_create(4)?;
+                self.push(bukkit)?;
+            }
+            "bukkit_retain" => {
+                let bukkit = self.pop()?;
+                let references = self.heap_get(bukkit, 4)?;
+                self.heap_set(bukkit, 4, references + 1.0)?;
+                self.push(bukkit)?;
+            }
+            "bukkit_release" => {
+                let bukkit = self.pop()?;
+                self.bukkit_drop(bukkit)?;
+            }
+            "bukkit_kopy" => {
+                let bukkit = self.pop()?;
+                let clone = self.bukkit_clone(bukkit)?;
+                self.push(clone)?;
+                self.bukkit_drop(bukkit)?;
+            }
+            "bukkit_put" => self.bukkit_put()?,
+            "bukkit_get" => {
+                let key = self.pop()? as i32;
+                let bukkit = self.pop()?;
+                let entry = self.bukkit_find(bukkit, key)?;
+                if entry == -1 {
+                    return panic(&format!("bukkit has no slot with key {}", key), 1);
+                }
+                let entries = self.heap_get(bukkit, 2)?;
+                let value = self.heap_get(entries, entry * 3 + 1)?;
+                self.push(value)?;
+            }
+            "bukkit_length" => {
+                let bukkit = self.pop()?;
+                let length = self.heap_get(bukkit, 0)?;
+                self.push(length)?;
+            }
+            "bukkit_has_index" => {
+                let index = self.pop()? as i32;
+                let bukkit = self.pop()?;
+                let length = self.heap_get(bukkit, 0)?;
+                self.push(if (index as f32) < length { 1.0 } else { 0.0 })?;
+            }
+            "bukkit_value" => {
+                let index = self.pop()? as i32;
+                let bukkit = self.pop()?;
+                let entries = self.heap_get(bukkit, 2)?;
+                let value = self.heap_get(entries, index * 3 + 1)?;
+                self.push(value)?;
+            }
+            "bukkit_copy_yarn" => {
+                let yarn = self.pop()?;
+                let index = self.pop()? as i32;
+                let bukkit = self.pop()?;
+                let entries = self.heap_get(bukkit, 2)?;
+                let value = self.heap_get(entries, index * 3 + 1)?;
+                let size = self.heap_get(entries, index * 3 + 2)? as i32;
+                for i in 0..size {
+                    let ch = self.heap_get(value, i)?;
+                    self.heap_set(yarn, i, ch)?;
+                }
+            }
+            "stack_unwind" => {
+                let position = self.pop()?;
+                while self.stack_pointer as f32 > position + 1.0 {
+                    self.pop()?;
+                }
+            }
+            "assure_failed" => {
+                let line = self.pop()? as i32;
+                return panic(&format!("ASSURE failed at line {}", line), 1);
+            }
+            "assure_failed_sayin" => {
+                let line = self.pop()? as i32;
+                let size = self.pop()? as i32;
+                let addr = self.pop()?;
+
+                let mut message = vec![];
+                for i in 0..size {
+                    let ch = self.heap_get(addr, i)? as i32 as u8;
+                    if ch == 0 {
+                        break;
+                    }
+                    message.push(ch);
+                }
+                return panic(
+                    &format!(
+                        "ASSURE failed at line {}: {}",
+                        line,
+                        String::from_utf8_lossy(&message)
+                    ),
+                    1,
+                );
+            }
+            "dump_number" => {
+                let n = self.pop()? as i32;
+                self.write(format!("NUMBER: {}\n", n).as_bytes());
+            }
+            "dump_numbar" => {
+                let n = self.pop()?;
+                self.write(format!("NUMBAR: {}\n", format_float(n)).as_bytes());
+            }
+            "dump_yarn" => {
+                let size = self.pop()? as i32;
+                let addr = self.pop()?;
+
+                let mut text = vec![];
+                for i in 0..size {
+                    let ch = self.heap_get(addr, i)? as i32 as u8;
+                    if ch == 0 {
+                        break;
+                    }
+                    text.push(ch);
+                }
+                self.write(format!("YARN({}): \"", text.len()).as_bytes());
+                self.write(&text);
+                self.write(b"\"\n");
+            }
+            "dump_troof" => {
+                let troof = self.pop()?;
+                let text = if troof != 0.0 { "WIN" } else { "FAIL" };
+                self.write(format!("TROOF: {}\n", text).as_bytes());
+            }
+            "dump_noob" => {
+                self.pop()?;
+                self.write(b"NOOB\n");
+            }
+            "dump_bukkit" => {
+                let bukkit = self.pop()?;
+                let slots = self.heap_get(bukkit, 0)? as i32;
+                self.write(format!("BUKKIT({})\n", slots).as_bytes());
+            }
+            "dump_name" => {
+                let index = self.pop()? as usize;
+                let name = self.hook_names.get(index).cloned().unwrap_or_default();
+                self.write(format!("{} = ", name).as_bytes());
+            }
+            "trace" => {
+                let id = self.pop()? as usize;
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.trace = Some(id);
+                }
+                observer.trace(self, id)?;
+            }
+            _ => return panic(&format!("unknown foreign function {}", name), 1),
+        }
+
+        Ok(())
+    }
+
+    /// Allocates `size` floats holding `bytes` followed by zeros, pushing the address
+    fn new_string(&mut self, bytes: &[u8], size: i32) -> Result<(), Stop> {
+        self.push(size as f32)?;
+        self.allocate()?;
+        let addr = self.pop()?;
+        // the C runtime leaves the last float of its buffer as a terminator
+        for (i, byte) in bytes.iter().take(size as usize - 1).enumerate() {
+            self.heap_set(addr, i as i32, *byte as i8 as f32)?;
+        }
+        self.push(addr)
+    }
+
+    /// Pops the size and address of a YARN and reads it as MAEK does, up to
+    /// its first NUL, panicking if it is not a number
+    fn parse_string(&mut self, numbar: bool) -> Result<f32, Stop> {
+        let size = self.pop()? as i32;
+        let addr = self.pop()?;
+        let mut text = String::new();
+        for i in 0..size {
+            let code = self.heap_get(addr, i)? as u32;
+            if code == 0 {
+                break;
+            }
+            text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+
+        match utils::parse_number(&text, numbar) {
+            Some(number) => Ok(number),
+            None => {
+                let type_ = if numbar { "NUMBAR" } else { "NUMBER" };
+                panic(&format!("cannot convert \"{}\" to {}", text, type_), 1)
+            }
+        }
+    }
+
+    fn read_char(&mut self) -> i32 {
+        self.flush(); // show any prompt before waiting for input
+        let mut ch = self.getchar();
+        if ch == '\r' as i32 {
+            ch = self.getchar();
+        }
+        self.input_eof = ch == -1;
+        ch
+    }
+
+    /// fgets into a 256 letter buffer, dropping the newline
+    fn read_string(&mut self) -> Result<(), Stop> {
+        self.flush(); // show any prompt before waiting for input
+
+        let mut buffer = vec![];
+        let mut ch = 0;
+        while buffer.len() < 255 && ch != '\n' as i32 {
+            ch = self.getchar();
+            if ch == -1 {
+                break;
+            }
+            buffer.push(ch as u8);
+        }
+        // at the end of input the buffer stays empty
+        self.input_eof = buffer.is_empty();
+
+        self.push(256.0)?;
+        self.allocate()?;
+        let addr = self.pop()?;
+        for (i, byte) in buffer.iter().enumerate() {
+            if *byte != b'\n' {
+                self.heap_set(addr, i as i32, *byte as i8 as f32)?;
+            }
+        }
+        self.push(addr)
+    }
+
+    /// The letters of a YARN before its first 0
+    fn text_length(&self, addr: f32, size: i32) -> Result<i32, Stop> {
+        let mut length = 0;
+        while length < size && self.heap_get(addr, length)? != 0.0 {
+            length += 1;
+        }
+        Ok(length)
+    }
+
+    /// Adds the letters `range` of a YARN to a SPLITZ bukkit as its
+    /// `index`th piece, as long as the whole YARN
+    fn split_piece(
+        &mut self,
+        bukkit: f32,
+        index: i32,
+        yarn: f32,
+        size: i32,
+        range: std::ops::Range<i32>,
+    ) -> Result<(), Stop> {
+        let piece_size = size.max(1);
+        let piece = self.heap_allocate(piece_size)?;
+        for i in 0..piece_size {
+            let ch = if range.start + i < range.end {
+                self.heap_get(yarn, range.start + i)?
+            } else {
+                0.0
+            };
+            self.heap_set(piece, i, ch)?;
+        }
+
+        self.push(piece)?;
+        self.push(piece_size as f32)?;
+        self.push(bukkit)?;
+        self.push((PIECE_KEY + index) as f32)?;
+        self.bukkit_put()
+    }
+
+    fn heap_allocate(&mut self, floats: i32) -> Result<f32, Stop> {
+        self.push(floats as f32)?;
+        self.allocate()?;
+        self.pop()
+    }
+
+    fn heap_free(&mut self, addr: f32, floats: i32) -> Result<(), Stop> {
+        self.push(floats as f32)?;
+        self.push(addr)?;
+        self.free()
+    }
+
+    /// The index of the entry for key, or -1 if there isn't one
+    fn bukkit_find(&mut self, bukkit: f32, key: i32) -> Result<i32, Stop> {
+        let capacity = self.heap_get(bukkit, 1)? as i32;
+        let entries = self.heap_get(bukkit, 2)?;
+        let slots = self.heap_get(bukkit, 3)?;
+
+        let mut i = key % capacity;
+        loop {
+            let entry = self.heap_get(slots, i)? as i32 - 1;
+            if entry == -1 {
+                return Ok(-1);
+            }
+            if self.heap_get(entries, entry * 3)? as i32 == key {
+                return Ok(entry);
+            }
+            i = (i + 1) % capacity;
+        }
+    }
+
+    fn bukkit_index(&mut self, bukkit: f32, key: i32, entry: i32) -> Result<(), Stop> {
+        let capacity = self.heap_get(bukkit, 1)? as i32;
+        let slots = self.heap_get(bukkit, 3)?;
+
+        let mut i = key % capacity;
+        while self.heap_get(slots, i)? != 0.0 {
+            i = (i + 1) % capacity;
+        }
+        self.heap_set(slots, i, (entry + 1) as f32)
+    }
+
+    fn bukkit_grow(&mut self, bukkit: f32) -> Result<(), Stop> {
+        let length = self.heap_get(bukkit, 0)? as i32;
+        let capacity = self.heap_get(bukkit, 1)? as i32;
+        let entries = self.heap_get(bukkit, 2)?;
+        let slots = self.heap_get(bukkit, 3)?;
+
+        let new_entries = self.heap_allocate(capacity * 2 * 3)?;
+        for i in 0..length * 3 {
+            let value = self.heap_get(entries, i)?;
+            self.heap_set(new_entries, i, value)?;
+        }
+        self.heap_free(entries, capacity * 3)?;
+        self.heap_free(slots, capacity)?;
+
+        self.heap_set(bukkit, 1, (capacity * 2) as f32)?;
+        self.heap_set(bukkit, 2, new_entries)?;
+        let new_slots = self.heap_allocate(capacity * 2)?;
+        self.heap_set(bukkit, 3, new_slots)?;
+        for i in 0..length {
+            let key = self.heap_get(new_entries, i * 3)? as i32;
+            self.bukkit_index(bukkit, key, i)?;
+        }
+        Ok(())
+    }
+
+    fn bukkit_create(&mut self, capacity: i32) -> Result<f32, Stop> {
+        let bukkit = self.heap_allocate(BUKKIT_HEADER)?;
+        self.heap_set(bukkit, 0, 0.0)?;
+        self.heap_set(bukkit, 1, capacity as f32)?;
+        let entries = self.heap_allocate(capacity * 3)?;
+        self.heap_set(bukkit, 2, entries)?;
+        let slots = self.heap_allocate(capacity)?;
+        self.heap_set(bukkit, 3, slots)?;
+        self.heap_set(bukkit, 4, 1.0)?;
+        Ok(bukkit)
+    }
+
+    /// Drops a reference, freeing the bukkit and everything it owns with the last one
+    fn bukkit_drop(&mut self, bukkit: f32) -> Result<(), Stop> {
+        let references = self.heap_get(bukkit, 4)? as i32 - 1;
+        if references > 0 {
+            return self.heap_set(bukkit, 4, references as f32);
+        }
+
+        let length = self.heap_get(bukkit, 0)? as i32;
+        let capacity = self.heap_get(bukkit, 1)? as i32;
+        let entries = self.heap_get(bukkit, 2)?;
+
+        for i in 0..length {
+            let value = self.heap_get(entries, i * 3 + 1)?;
+            let size = self.heap_get(entries, i * 3 + 2)? as i32;
+            if size == BUKKIT_REFERENCE {
+                self.bukkit_drop(value)?;
+            } else if size > 0 {
+                self.heap_free(value, size)?;
+            }
+        }
+
+        self.heap_free(entries, capacity * 3)?;
+        let slots = self.heap_get(bukkit, 3)?;
+        self.heap_free(slots, capacity)?;
+        self.heap_free(bukkit, BUKKIT_HEADER)
+    }
+
+    fn bukkit_clone(&mut self, bukkit: f32) -> Result<f32, Stop> {
+        let length = self.heap_get(bukkit, 0)? as i32;
+        let capacity = self.heap_get(bukkit, 1)? as i32;
+        let entries = self.heap_get(bukkit, 2)?;
+
+        let clone = self.bukkit_create(capacity)?;
+        let clone_entries = self.heap_get(clone, 2)?;
+        self.heap_set(clone, 0, length as f32)?;
+
+        for i in 0..length {
+            let key = self.heap_get(entries, i * 3)?;
+            let mut value = self.heap_get(entries, i * 3 + 1)?;
+            let size = self.heap_get(entries, i * 3 + 2)? as i32;
+
+            if size == BUKKIT_REFERENCE {
+                value = self.bukkit_clone(value)?;
+            } else if size > 0 {
+                let yarn = self.heap_allocate(size)?;
+                for j in 0..size {
+                    let ch = self.heap_get(value, j)?;
+                    self.heap_set(yarn, j, ch)?;
+                }
+                value = yarn;
+            }
+
+            self.heap_set(clone_entries, i * 3, key)?;
+            self.heap_set(clone_entries, i * 3 + 1, value)?;
+            self.heap_set(clone_entries, i * 3 + 2, size as f32)?;
+            self.bukkit_index(clone, key as i32, i)?;
+        }
+
+        Ok(clone)
+    }
+
+    fn bukkit_put(&mut self) -> Result<(), Stop> {
+        let key = self.pop()? as i32;
+        let bukkit = self.pop()?;
+        let size = self.pop()?;
+        let value = self.pop()?;
+
+        let mut entry = self.bukkit_find(bukkit, key)?;
+        if entry == -1 {
+            let length = self.heap_get(bukkit, 0)? as i32;
+            // keep the slot table at most three quarters full
+            if ((length + 1) * 4) as f32 > self.heap_get(bukkit, 1)? * 3.0 {
+                self.bukkit_grow(bukkit)?;
+            }
+
+            entry = length;
+            self.heap_set(bukkit, 0, (length + 1) as f32)?;
+            let entries = self.heap_get(bukkit, 2)?;
+            self.heap_set(entries, entry * 3, key as f32)?;
+            self.bukkit_index(bukkit, key, entry)?;
+        } else {
+            let entries = self.heap_get(bukkit, 2)?;
+            let old_value = self.heap_get(entries, entry * 3 + 1)?;
+            let old_size = self.heap_get(entries, entry * 3 + 2)? as i32;
+            if old_size == BUKKIT_REFERENCE {
+                self.bukkit_drop(old_value)?;
+            } else if old_size > 0 {
+                self.heap_free(old_value, old_size)?;
+            }
+        }
+
+        let entries = self.heap_get(bukkit, 2)?;
+        self.heap_set(entries, entry * 3 + 1, value)?;
+        self.heap_set(entries, entry * 3 + 2, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::driver;
+    use crate::interpreter;
+
+    fn run(text: &str) -> String {
+        let ir = driver::lower_text(text);
+        let (code, output) =
+            interpreter::run_captured(&ir, Vec::new(), |machine| machine.newline = "\n");
+        assert_eq!(code, 0);
+        output
+    }
+
+    /// FURST OF and LATR OF of each pair, as "WIN FAIL" lines
+    fn order(pairs: &[(&str, &str)]) -> Vec<String> {
+        let mut text = String::from("HAI 1.2\n");
+        for (a, b) in pairs {
+            text.push_str(&format!(
+                "VISIBLE MAEK FURST OF \"{a}\" AN \"{b}\" A YARN \" \" MAEK LATR OF \"{a}\" AN \"{b}\" A YARN\n"
+            ));
+        }
+        text.push_str("KTHXBYE\n");
+        run(&text).lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn equal_yarns_are_neither_first_nor_later() {
+        assert_eq!(
+            order(&[("abc", "abc"), ("", "")]),
+            ["FAIL FAIL", "FAIL FAIL"]
+        );
+    }
+
+    #[test]
+    fn a_yarn_comes_before_the_longer_ones_it_starts() {
+        assert_eq!(
+            order(&[("ab", "abc"), ("abc", "ab"), ("", "a"), ("a", "")]),
+            ["WIN FAIL", "FAIL WIN", "WIN FAIL", "FAIL WIN"]
+        );
+    }
+
+    #[test]
+    fn letters_are_ordered_by_their_codes() {
+        assert_eq!(
+            order(&[("Z", "a"), ("a", "Z"), ("abd", "abc")]),
+            ["WIN FAIL", "FAIL WIN", "FAIL WIN"]
+        );
+    }
+
+    #[test]
+    fn the_room_a_yarn_was_given_is_not_part_of_it() {
+        // a is given room for "abcdef" and then holds "ab", padded with NULs
+        let text = "HAI 1.2
+I HAS A a ITZ YARN R \"abcdef\"
+a R \"ab\"
+VISIBLE MAEK FURST OF a AN \"ab\" A YARN \" \" MAEK LATR OF a AN \"ab\" A YARN
+VISIBLE MAEK FURST OF a AN \"abc\" A YARN \" \" MAEK LATR OF \"abc\" AN a A YARN
+KTHXBYE
+";
+        assert_eq!(run(text), "FAIL FAIL\nWIN WIN\n");
+    }
+}
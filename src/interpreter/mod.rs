@@ -0,0 +1,665 @@
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
+mod foreign;
+pub mod profile;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::compiler::ir;
+use crate::compiler::visit::TraceInfo;
+use crate::source::SourceMap;
+
+/// Why a program stopped before the end of its entry point
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stop {
+    /// KTHXBYE
+    Halt,
+    /// A runtime error, with the message and exit code the C runtime uses
+    Panic(String, i32),
+    /// The observer ended the program
+    Quit,
+    /// Ctrl-C
+    Interrupted,
+}
+
+/// The exit code shells give a program killed by SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// What VISIBLE ends lines with unless told otherwise, as a compiled
+/// program's stdout does
+pub const NATIVE_NEWLINE: &str = if cfg!(windows) { "\r\n" } else { "\n" };
+
+const SIGINT: i32 = 2;
+
+/// Set by the SIGINT handler, the machine stops at the next loop iteration
+/// or call
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set while the machine waits for input, which it only does with its
+/// output flushed
+static READING: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    fn _exit(code: i32) -> !;
+}
+
+extern "C" fn on_interrupt(_signum: i32) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    if READING.load(Ordering::SeqCst) {
+        // a blocked read would never get to check the flag, and with nothing
+        // buffered _exit (which is async-signal-safe) loses no output
+        unsafe { _exit(INTERRUPTED_EXIT_CODE) }
+    }
+}
+
+/// Makes Ctrl-C stop programs run by the interpreter with their output
+/// flushed, like the compiled ones
+pub fn handle_interrupts() {
+    unsafe {
+        signal(SIGINT, on_interrupt);
+    }
+}
+
+fn check_interrupt() -> Result<(), Stop> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(Stop::Interrupted);
+    }
+    Ok(())
+}
+
+fn panic<T>(message: &str, code: i32) -> Result<T, Stop> {
+    Err(Stop::Panic(message.to_string(), code))
+}
+
+/// The address of the `index`th value from `addr` on
+fn value_addr(addr: f32, index: i32) -> f32 {
+    addr + index as f32 * VALUE_BYTES as f32
+}
+
+/// A function call the program is in
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: String,
+    /// the last trace point reached in this call
+    pub trace: Option<usize>,
+}
+
+/// The line, counting from 1, of every trace point
+pub fn trace_lines(trace: &TraceInfo, source: &SourceMap) -> Vec<usize> {
+    trace
+        .points
+        .iter()
+        .map(|point| source.line(point.token.token.start))
+        .collect()
+}
+
+/// Told about a traced program as it runs
+pub trait Observer {
+    /// Called before the statement of trace point `id` runs
+    fn trace(&mut self, machine: &mut Machine, id: usize) -> Result<(), Stop>;
+
+    /// Called once a HOW IZ I call has started
+    fn enter(&mut self, _function: &str) {}
+
+    /// Called once the innermost call has returned
+    fn leave(&mut self) {}
+}
+
+/// Runs programs without looking at them
+pub struct NoObserver;
+
+impl Observer for NoObserver {
+    fn trace(&mut self, _machine: &mut Machine, _id: usize) -> Result<(), Stop> {
+        Ok(())
+    }
+}
+
+/// Output kept in memory, which clones share so it can be read once the
+/// machine that owns one is done
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a program with `input` as its stdin, after `configure` has set up
+/// the machine, returning the exit code and everything it wrote, the panic
+/// report included, without the NULs YARNs are padded with
+pub fn run_captured(
+    ir: &ir::IR,
+    input: Vec<u8>,
+    configure: impl FnOnce(&mut Machine),
+) -> (i32, String) {
+    let output = Capture::default();
+    let mut machine = Machine::new(
+        ir.entry.stack_size,
+        ir.entry.heap_size,
+        Box::new(std::io::Cursor::new(input)),
+        Box::new(output.clone()),
+    );
+    configure(&mut machine);
+    let result = machine.run(ir, &mut NoObserver);
+    let code = machine.finish(result);
+
+    let written = String::from_utf8_lossy(&output.contents()).replace('\0', "");
+    (code, written)
+}
+
+/// Bytes a value takes on the heap, VALUE_BYTES of core.c. Sizes and
+/// offsets the IR counts in values are scaled by it here and nowhere else.
+const VALUE_BYTES: usize = 4;
+
+/// The C virtual machine of the vm target, implemented over the IR directly.
+/// Memory is laid out exactly as in core.c, floats on the heap are stored as
+/// VALUE_BYTES bytes each, so addresses and hooks mean the same thing in both.
+pub struct Machine {
+    pub stack: Vec<f32>,
+    pub heap: Vec<u8>,
+    allocated: Vec<bool>,
+    pub stack_pointer: usize,
+    base_ptr: f32,
+    /// hook slots of the running calls, laid out as in core.c
+    hooks: Vec<f32>,
+    hook_base: usize,
+    return_register: f32,
+    /// the calls being run, the entry point first
+    pub frames: Vec<Frame>,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    /// set by every read, GIMMEH copies it into IT
+    input_eof: bool,
+    /// loop iterations and calls the program may run before it panics
+    pub max_steps: Option<u64>,
+    /// what VISIBLE ends lines with
+    pub newline: &'static str,
+    /// when the program panics if it is still running
+    pub deadline: Option<Instant>,
+    steps: u64,
+    /// the names of the hook table's variables, for DUMPZ
+    hook_names: Vec<String>,
+    /// the name the program gives each function, by its IR name
+    source_names: BTreeMap<String, String>,
+}
+
+impl Machine {
+    pub fn new(
+        stack_size: i32,
+        heap_size: i32,
+        input: Box<dyn BufRead>,
+        output: Box<dyn Write>,
+    ) -> Self {
+        Machine {
+            stack: vec![0.0; stack_size.max(0) as usize],
+            heap: vec![0; heap_size.max(0) as usize],
+            allocated: vec![false; heap_size.max(0) as usize],
+            stack_pointer: 0,
+            base_ptr: 0.0,
+            hooks: vec![],
+            hook_base: 0,
+            return_register: 0.0,
+            frames: vec![],
+            input,
+            output,
+            input_eof: false,
+            max_steps: None,
+            newline: NATIVE_NEWLINE,
+            deadline: None,
+            steps: 0,
+            hook_names: vec![],
+            source_names: BTreeMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, n: f32) -> Result<(), Stop> {
+        if self.stack_pointer >= self.stack.len() {
+            return panic("no free memory", 1);
+        }
+        self.stack[self.stack_pointer] = n;
+        self.stack_pointer += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<f32, Stop> {
+        if self.stack_pointer == 0 {
+            return panic("stack underflow", 2);
+        }
+        self.stack_pointer -= 1;
+        let result = self.stack[self.stack_pointer];
+        self.stack[self.stack_pointer] = 0.0;
+        Ok(result)
+    }
+
+    /// The stack slot at `offset`, which core.c would index without checking
+    fn slot(&mut self, offset: f32) -> Result<&mut f32, Stop> {
+        let offset = offset as i64;
+        if offset < 0 || offset as usize >= self.stack.len() {
+            return panic("invalid stack offset", 3);
+        }
+        Ok(&mut self.stack[offset as usize])
+    }
+
+    /// Starts the hook frame of the function being entered, as
+    /// machine_reserve_hooks does in core.c
+    fn reserve_hooks(&mut self, hooks: i32) {
+        self.hooks.push(self.hook_base as f32);
+        self.hook_base = self.hooks.len();
+        self.hooks
+            .resize(self.hook_base + hooks.max(0) as usize, 0.0);
+    }
+
+    /// Ends the hook frame of the function returning, going back to its
+    /// caller's
+    fn release_hooks(&mut self) {
+        let base = self.hook_base;
+        self.hook_base = self.hooks[base - 1] as usize;
+        self.hooks.truncate(base - 1);
+    }
+
+    /// Hook `hook` of the running call, which core.c would index without
+    /// checking
+    fn hook_slot(&mut self, hook: i32) -> Result<&mut f32, Stop> {
+        match self.hooks.get_mut(self.hook_base + hook.max(0) as usize) {
+            Some(slot) if hook >= 0 => Ok(slot),
+            _ => panic("invalid hook", 3),
+        }
+    }
+
+    /// The position stored in hook `hook` of the running call, or None if it
+    /// has no such hook
+    pub fn hook_value(&self, hook: i32) -> Option<f32> {
+        if hook < 0 {
+            return None;
+        }
+        self.hooks.get(self.hook_base + hook as usize).copied()
+    }
+
+    /// The value at stack `offset`, or None if it is out of range
+    pub fn stack_value(&self, offset: f32) -> Option<f32> {
+        if offset < 0.0 {
+            return None;
+        }
+        self.stack.get(offset as usize).copied()
+    }
+
+    fn heap_range(&self, addr: f32, bytes: usize) -> Result<std::ops::Range<usize>, Stop> {
+        let addr = addr as i64;
+        if addr < 0 || addr as usize + bytes > self.heap.len() {
+            return panic("invalid heap address", 3);
+        }
+        Ok(addr as usize..addr as usize + bytes)
+    }
+
+    /// The index'th float of the allocation at `addr`
+    pub fn heap_get(&self, addr: f32, index: i32) -> Result<f32, Stop> {
+        let range = self.heap_range(value_addr(addr, index), VALUE_BYTES)?;
+        let mut bytes = [0; VALUE_BYTES];
+        bytes.copy_from_slice(&self.heap[range]);
+        Ok(f32::from_ne_bytes(bytes))
+    }
+
+    pub fn heap_set(&mut self, addr: f32, index: i32, value: f32) -> Result<(), Stop> {
+        let range = self.heap_range(value_addr(addr, index), VALUE_BYTES)?;
+        self.heap[range].copy_from_slice(&value.to_ne_bytes());
+        Ok(())
+    }
+
+    fn allocate(&mut self) -> Result<(), Stop> {
+        let size = (self.pop()? * VALUE_BYTES as f32) as i64;
+        let mut addr = -1;
+        let mut consecutive_free = 0;
+
+        for i in 0..self.heap.len() as i64 {
+            if !self.allocated[i as usize] {
+                consecutive_free += 1;
+            } else {
+                consecutive_free = 0;
+            }
+
+            if consecutive_free == size {
+                addr = i - size + 1;
+                break;
+            }
+        }
+
+        if addr == -1 {
+            return panic("no free memory", 1);
+        }
+
+        for i in addr..addr + size {
+            self.allocated[i as usize] = true;
+        }
+        self.push(addr as f32)
+    }
+
+    fn free(&mut self) -> Result<(), Stop> {
+        let addr = self.pop()?;
+        let size = (self.pop()? * VALUE_BYTES as f32).max(0.0) as usize;
+
+        let range = self.heap_range(addr, size)?;
+        for i in range {
+            self.allocated[i] = false;
+            self.heap[i] = 0;
+        }
+        Ok(())
+    }
+
+    /// Panics unless the `size` floats from the `offset`th of the allocation
+    /// at `addr`, which a Store or Load touches, are all allocated
+    fn checked_access(&self, addr: f32, size: i32, offset: i32) -> Result<(), Stop> {
+        if offset < 0 {
+            return panic("invalid heap address", 3);
+        }
+        let range =
+            self.heap_range(value_addr(addr, offset), size.max(0) as usize * VALUE_BYTES)?;
+        if !self.allocated[range].iter().all(|allocated| *allocated) {
+            return panic("invalid heap address", 3);
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, size: i32, offset: i32) -> Result<(), Stop> {
+        let addr = self.pop()?;
+        self.checked_access(addr, size, offset)?;
+        for i in (0..size).rev() {
+            let value = self.pop()?;
+            self.heap_set(addr, offset + i, value)?;
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, size: i32, offset: i32) -> Result<(), Stop> {
+        let addr = self.pop()?;
+        self.checked_access(addr, size, offset)?;
+        for i in 0..size {
+            let value = self.heap_get(addr, offset + i)?;
+            self.push(value)?;
+        }
+        Ok(())
+    }
+
+    fn str_concat(&mut self, left: i32, right: i32) -> Result<(), Stop> {
+        let right_addr = self.pop()?;
+        let left_addr = self.pop()?;
+        for i in 0..right {
+            let letter = self.heap_get(right_addr, i)?;
+            self.heap_set(left_addr, left + i, letter)?;
+        }
+        Ok(())
+    }
+
+    fn str_cmp(&mut self, letters: i32) -> Result<(), Stop> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        let mut order = 0.0;
+        for i in 0..letters {
+            let a = self.heap_get(left, i)?;
+            let b = self.heap_get(right, i)?;
+            if a != b {
+                order = if a < b { -1.0 } else { 1.0 };
+                break;
+            }
+        }
+        self.push(order)
+    }
+
+    /// Writes `letters` letters of the YARN at `addr`, a byte each
+    fn str_print(&mut self, addr: f32, letters: i32) -> Result<(), Stop> {
+        for i in 0..letters {
+            let ch = self.heap_get(addr, i)?;
+            self.write(&[ch as i32 as u8]);
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, f: impl Fn(f32, f32) -> Result<f32, Stop>) -> Result<(), Stop> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        let result = f(a, b)?;
+        self.push(result)
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.output.flush();
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let _ = self.output.write_all(bytes);
+    }
+
+    /// Reads a byte of input like getchar, -1 at the end of input
+    fn getchar(&mut self) -> i32 {
+        self.flush();
+        READING.store(true, Ordering::SeqCst);
+        let buffer = self.input.fill_buf();
+        READING.store(false, Ordering::SeqCst);
+
+        let byte = match buffer {
+            Ok(buffer) if !buffer.is_empty() => buffer[0] as i32,
+            _ => return -1,
+        };
+        self.input.consume(1);
+        byte
+    }
+
+    /// Reads a line of input for the observer, None at the end of input
+    pub fn read_line(&mut self) -> Option<String> {
+        self.flush();
+        let mut line = String::new();
+        READING.store(true, Ordering::SeqCst);
+        let read = self.input.read_line(&mut line);
+        READING.store(false, Ordering::SeqCst);
+        match read {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+        }
+    }
+
+    /// Runs `ir` the way its assembled entry point would
+    pub fn run(&mut self, ir: &ir::IR, observer: &mut impl Observer) -> Result<(), Stop> {
+        let functions = ir
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f))
+            .collect::<HashMap<&str, &ir::IRFunction>>();
+
+        self.hook_names = ir.hook_names.iter().map(|hook| hook.name.clone()).collect();
+        self.source_names = ir.source_names.clone();
+        self.reserve_hooks(ir.entry.hooks);
+        self.establish_stack_frame()?;
+
+        self.frames.push(Frame {
+            function: "main".to_string(),
+            trace: None,
+        });
+        self.execute(&ir.entry.statements, &functions, observer)
+    }
+
+    /// Flushes the output and reports how the program stopped, returning the
+    /// exit code the compiled program would have had
+    pub fn finish(&mut self, result: Result<(), Stop>) -> i32 {
+        let code = match result {
+            Ok(()) | Err(Stop::Halt) | Err(Stop::Quit) => 0,
+            Err(Stop::Interrupted) => INTERRUPTED_EXIT_CODE,
+            Err(Stop::Panic(message, code)) => {
+                let mut report = format!("panic: {}\n", message);
+                for frame in self.frames.iter().rev() {
+                    report.push_str(format!("    at {}\n", frame.function).as_str());
+                }
+                report.push('\n');
+                self.write(report.as_bytes());
+                code
+            }
+        };
+
+        self.flush();
+        code
+    }
+
+    /// Runs before every loop iteration and call, the only ways a program can
+    /// keep running, as machine_step does in core.c
+    fn step(&mut self) -> Result<(), Stop> {
+        check_interrupt()?;
+        self.steps += 1;
+        if let Some(max_steps) = self.max_steps {
+            if self.steps > max_steps {
+                return panic("program exceeded step limit", 1);
+            }
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => panic("program exceeded time limit", 1),
+            _ => Ok(()),
+        }
+    }
+
+    fn establish_stack_frame(&mut self) -> Result<(), Stop> {
+        self.push(self.base_ptr)?;
+        self.base_ptr = self.stack_pointer as f32 - 1.0;
+        Ok(())
+    }
+
+    fn execute(
+        &mut self,
+        statements: &[ir::IRStatement],
+        functions: &HashMap<&str, &ir::IRFunction>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Stop> {
+        for statement in statements.iter() {
+            match statement {
+                ir::IRStatement::Push(n) => self.push(n.as_f32())?,
+                ir::IRStatement::Add => self.binary(|a, b| Ok(a + b))?,
+                ir::IRStatement::Subtract => self.binary(|a, b| Ok(a - b))?,
+                ir::IRStatement::Multiply => self.binary(|a, b| Ok(a * b))?,
+                ir::IRStatement::Divide => self.binary(|a, b| Ok(a / b))?,
+                ir::IRStatement::Modulo => {
+                    self.binary(|a, b| match (a as i32).checked_rem(b as i32) {
+                        Some(result) => Ok(result as f32),
+                        None => panic("modulo by zero", 1),
+                    })?
+                }
+                ir::IRStatement::Sign => {
+                    let x = self.pop()?;
+                    self.push(if x >= 0.0 { 1.0 } else { -1.0 })?;
+                }
+                ir::IRStatement::Allocate => self.allocate()?,
+                ir::IRStatement::Free => self.free()?,
+                ir::IRStatement::Store(size, offset) => self.store(*size, *offset)?,
+                ir::IRStatement::Load(size, offset) => self.load(*size, *offset)?,
+                // YARNs keep a float a letter here, as they do in the VM by
+                // default
+                ir::IRStatement::StrAlloc => self.allocate()?,
+                ir::IRStatement::StrFree => self.free()?,
+                ir::IRStatement::StrStore(letters) => self.store(*letters, 0)?,
+                ir::IRStatement::StrLoad(letters) => self.load(*letters, 0)?,
+                // the interpreter always searches the heap
+                ir::IRStatement::BeginArena | ir::IRStatement::EndArena => {}
+                // programs are only run here without --gc, so they free
+                // everything themselves
+                ir::IRStatement::Collect(roots) => {
+                    for _ in 0..*roots {
+                        self.pop()?;
+                    }
+                }
+                ir::IRStatement::StrConcat(left, right) => self.str_concat(*left, *right)?,
+                ir::IRStatement::StrCmp(letters) => self.str_cmp(*letters)?,
+                ir::IRStatement::StrPrint(letters) => {
+                    let addr = self.pop()?;
+                    self.str_print(addr, *letters)?;
+                }
+                ir::IRStatement::Copy => {
+                    let offset = self.pop()?;
+                    let value = *self.slot(offset)?;
+                    self.push(value)?;
+                }
+                ir::IRStatement::Mov => {
+                    let offset = self.pop()?;
+                    let value = self.pop()?;
+                    *self.slot(offset)? = value;
+                }
+                ir::IRStatement::Hook(hook) => {
+                    let position = self.stack_pointer as f32 - 1.0;
+                    *self.hook_slot(*hook)? = position;
+                }
+                ir::IRStatement::RefHook(hook) => {
+                    let position = *self.hook_slot(*hook)?;
+                    self.push(position)?;
+                }
+                ir::IRStatement::SetHook(hook) => {
+                    let value = self.pop()?;
+                    *self.hook_slot(*hook)? = value;
+                }
+                ir::IRStatement::Call(name) => {
+                    let function = match functions.get(name.as_str()) {
+                        Some(function) => *function,
+                        None => return panic(&format!("unknown function {}", name), 1),
+                    };
+
+                    self.step()?;
+                    // a placeholder return address, as in the vm target
+                    self.push(1.0)?;
+                    let shown = self.source_names.get(name).unwrap_or(name).clone();
+                    self.frames.push(Frame {
+                        function: shown.clone(),
+                        trace: None,
+                    });
+                    observer.enter(&shown);
+                    self.reserve_hooks(function.hooks);
+                    self.execute(&function.statements, functions, observer)?;
+                    self.release_hooks();
+                    self.frames.pop();
+                    observer.leave();
+                }
+                ir::IRStatement::CallForeign(name) => self.call_foreign(name, observer)?,
+                ir::IRStatement::If { then, else_ } => {
+                    if self.pop()? != 0.0 {
+                        self.execute(then, functions, observer)?;
+                    } else {
+                        self.execute(else_, functions, observer)?;
+                    }
+                }
+                ir::IRStatement::Loop { body } => {
+                    while self.pop()? != 0.0 {
+                        self.step()?;
+                        self.execute(body, functions, observer)?;
+                    }
+                }
+                ir::IRStatement::LoadBasePtr => self.push(self.base_ptr)?,
+                ir::IRStatement::EstablishStackFrame => self.establish_stack_frame()?,
+                ir::IRStatement::EndStackFrame(arg_size, local_scope_size) => {
+                    for _ in 0..*local_scope_size {
+                        self.pop()?;
+                    }
+                    self.base_ptr = self.pop()?;
+                    self.pop()?; // the return address
+                    for _ in 0..*arg_size {
+                        self.pop()?;
+                    }
+                }
+                ir::IRStatement::SetReturnRegister => self.return_register = self.pop()?,
+                ir::IRStatement::AccessReturnRegister => self.push(self.return_register)?,
+                ir::IRStatement::Halt => return Err(Stop::Halt),
+            }
+        }
+
+        Ok(())
+    }
+}
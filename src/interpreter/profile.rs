@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use super::{trace_lines, Machine, Observer, Stop};
+use crate::compiler::visit::TraceInfo;
+use crate::source::SourceMap;
+
+/// How many of the slowest lines the report lists
+const HOT_LINES: usize = 10;
+
+#[derive(Default)]
+struct Node {
+    calls: u64,
+    /// time spent in this call path itself, not in calls made from it
+    time: Duration,
+}
+
+/// Times every statement and HOW IZ I call as the interpreter runs them.
+/// Time between two trace points goes to the statement of the first and to
+/// the call it ran in.
+pub struct Profiler {
+    last: Instant,
+    point: Option<usize>,
+    point_times: Vec<Duration>,
+    point_counts: Vec<u64>,
+    /// the calls being run, the entry point first
+    stack: Vec<String>,
+    tree: BTreeMap<Vec<String>, Node>,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Profiler {
+    pub fn new(trace: &TraceInfo) -> Self {
+        let mut tree = BTreeMap::new();
+        tree.insert(
+            vec!["main".to_string()],
+            Node {
+                calls: 1,
+                time: Duration::ZERO,
+            },
+        );
+
+        Profiler {
+            last: Instant::now(),
+            point: None,
+            point_times: vec![Duration::ZERO; trace.points.len()],
+            point_counts: vec![0; trace.points.len()],
+            stack: vec!["main".to_string()],
+            tree,
+        }
+    }
+
+    /// Charges the time since the last event to the current statement and call
+    fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+
+        if let Some(point) = self.point {
+            self.point_times[point] += elapsed;
+        }
+        if let Some(node) = self.tree.get_mut(&self.stack) {
+            node.time += elapsed;
+        }
+    }
+
+    /// A flat profile of the calls, the slowest lines and the call tree
+    pub fn report(&mut self, trace: &TraceInfo, source: &SourceMap) -> String {
+        self.tick();
+        let mut out = String::new();
+
+        // a call's total is the time of every path it is part of, counting
+        // recursive calls once
+        let mut functions: HashMap<&str, (u64, Duration, Duration)> = HashMap::new();
+        for (path, node) in self.tree.iter() {
+            let last = path.last().unwrap().as_str();
+            let entry = functions.entry(last).or_default();
+            entry.0 += node.calls;
+            entry.1 += node.time;
+
+            let mut seen = vec![];
+            for function in path.iter() {
+                if !seen.contains(&function) {
+                    functions.entry(function.as_str()).or_default().2 += node.time;
+                    seen.push(function);
+                }
+            }
+        }
+        let mut functions = functions.into_iter().collect::<Vec<_>>();
+        functions.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+
+        out.push_str("Flat profile:\n     self ms    total ms     calls  function\n");
+        for (name, (calls, self_time, total_time)) in functions.iter() {
+            out.push_str(
+                format!(
+                    "{:>12.3}{:>12.3}{:>10}  {}\n",
+                    ms(*self_time),
+                    ms(*total_time),
+                    calls,
+                    name
+                )
+                .as_str(),
+            );
+        }
+
+        let mut lines: BTreeMap<usize, (Duration, u64)> = BTreeMap::new();
+        for (i, line) in trace_lines(trace, source).iter().enumerate() {
+            let entry = lines.entry(*line).or_default();
+            entry.0 += self.point_times[i];
+            entry.1 = entry.1.max(self.point_counts[i]);
+        }
+        let mut lines = lines.into_iter().collect::<Vec<_>>();
+        lines.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(&b.0)));
+
+        out.push_str("\nHot lines:\n          ms      runs  line\n");
+        for (line, (time, count)) in lines.iter().take(HOT_LINES) {
+            out.push_str(
+                format!(
+                    "{:>12.3}{:>10}  {:<5} {}\n",
+                    ms(*time),
+                    count,
+                    line,
+                    source.file(0).line_text(*line).unwrap_or("").trim()
+                )
+                .as_str(),
+            );
+        }
+
+        out.push_str("\nCall tree:\n");
+        for (path, node) in self.tree.iter() {
+            out.push_str(
+                format!(
+                    "{}{} {:.3} ms self, {} call(s)\n",
+                    "  ".repeat(path.len() - 1),
+                    path.last().unwrap(),
+                    ms(node.time),
+                    node.calls
+                )
+                .as_str(),
+            );
+        }
+
+        out
+    }
+}
+
+impl Observer for Profiler {
+    fn trace(&mut self, _machine: &mut Machine, id: usize) -> Result<(), Stop> {
+        self.tick();
+        self.point = Some(id);
+        self.point_counts[id] += 1;
+        Ok(())
+    }
+
+    fn enter(&mut self, function: &str) {
+        self.tick();
+        self.stack.push(function.to_string());
+        self.tree.entry(self.stack.clone()).or_default().calls += 1;
+    }
+
+    fn leave(&mut self) {
+        self.tick();
+        self.stack.pop();
+    }
+}
@@ -1,4 +1,6 @@
+use crate::lexer::symbol::Symbol;
 use crate::lexer::tokens;
+use crate::source::Span;
 
 #[derive(Debug, Clone)]
 pub struct LexedToken {
@@ -8,6 +10,12 @@ pub struct LexedToken {
     pub index: usize,
 }
 
+impl LexedToken {
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.end)
+    }
+}
+
 fn is_int(c: char) -> bool {
     c.is_digit(10)
 }
@@ -20,46 +28,223 @@ fn is_newline(c: char) -> bool {
     c == '\n' || c == '\r'
 }
 
+/// Whether `name` is made of letters, digits and underscores and does not
+/// start with a digit, keyword or not
+pub fn is_identifier_text(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if is_char(first) => chars.all(|c| is_char(c) || is_int(c)),
+        _ => false,
+    }
+}
+
+/// Whether `name` lexes as an identifier on its own. Lexing it catches
+/// keywords as well as names the lexer would split, such as ones starting
+/// with WIN or FAIL.
+pub fn is_plain_identifier(name: &str) -> bool {
+    let mut l = Lexer::init(name);
+    let tokens = l.get_tokens();
+
+    tokens.len() == 2 && tokens[0].token == tokens::Token::Identifier(Symbol::intern(name))
+}
+
+/// `name` as it has to be written in source: in backticks if it would not
+/// lex as an identifier without them, e.g. `SUM`
+pub fn escape_identifier(name: &str) -> String {
+    if is_plain_identifier(name) {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+/// Keywords written as several words, which get_tokens fuses into one Word
+/// token spelled with single spaces, e.g. `SUM OF`, so the parser matches
+/// them whole. A phrase comes before any shorter one it starts with.
+const PHRASES: &[&[&str]] = &[
+    &["I", "HAS", "A"],
+    &["I", "IZ"],
+    &["HAS", "A"],
+    &["HOW", "IZ", "I"],
+    &["IF", "U", "SAY", "SO"],
+    &["IM", "IN", "YR"],
+    &["IM", "IN"],
+    &["IM", "OUTTA", "YR"],
+    &["FOUND", "YR"],
+    &["O", "RLY"],
+    &["YA", "RLY"],
+    &["NO", "WAI"],
+    &["BOTH", "SAEM"],
+    &["BOTH", "OF"],
+    &["EITHER", "OF"],
+    &["WON", "OF"],
+    &["ALL", "OF"],
+    &["ANY", "OF"],
+    &["SUM", "OF"],
+    &["DIFF", "OF"],
+    &["PRODUKT", "OF"],
+    &["QUOSHUNT", "OF"],
+    &["MOD", "OF"],
+    &["BIGGR", "OF"],
+    &["SMALLR", "OF"],
+    &["FURST", "OF"],
+    &["LATR", "OF"],
+    &["WUN", "OF"],
+    &["LENGTHZ", "OF"],
+    &["CODEZ", "OF"],
+    &["LETTAR", "OF"],
+    &["KOPY", "OF"],
+];
+
+/// Words that start a phrase but are also keywords on their own, e.g. the
+/// LETTAR of GIMMEH LETTAR
+const ALONE: &[&str] = &["LETTAR"];
+
+fn is_word(token: &LexedToken, word: &str) -> bool {
+    matches!(&token.token, tokens::Token::Word(w) if w == word)
+}
+
+/// The number of words of the phrase `tokens` starts with, if any
+fn phrase_length(tokens: &[LexedToken]) -> Option<usize> {
+    PHRASES
+        .iter()
+        .find(|phrase| {
+            phrase.len() <= tokens.len()
+                && phrase
+                    .iter()
+                    .zip(tokens.iter())
+                    .all(|(word, token)| is_word(token, word))
+        })
+        .map(|phrase| phrase.len())
+}
+
+/// The phrases `tokens` starts part of, by the most words any of them has
+/// in common with it, and how many words that is. None if it does not start
+/// a phrase or is a keyword on its own.
+fn partial_phrases(tokens: &[LexedToken]) -> Option<(Vec<String>, usize)> {
+    let common = |phrase: &[&str]| {
+        phrase
+            .iter()
+            .zip(tokens.iter())
+            .take_while(|(word, token)| is_word(token, word))
+            .count()
+    };
+    let length = PHRASES.iter().map(|phrase| common(phrase)).max()?;
+    if length == 0 || (length == 1 && ALONE.iter().any(|word| is_word(&tokens[0], word))) {
+        return None;
+    }
+
+    let keywords = PHRASES
+        .iter()
+        .filter(|phrase| common(phrase) == length)
+        .map(|phrase| phrase.join(" "))
+        .collect();
+    Some((keywords, length))
+}
+
+/// Fuses the words of every phrase in PHRASES into one token spanning them
+/// all, numbering the tokens again by their place in the result. Only words
+/// with nothing but spaces or comments between them are fused, so a phrase
+/// never crosses a newline or comma. The words of a phrase left unfinished,
+/// e.g. `I HAS x`, become one Illegal token naming the phrases it could be.
+fn fuse_phrases(tokens: Vec<LexedToken>) -> Vec<LexedToken> {
+    let mut fused: Vec<LexedToken> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let (mut token, length) = match phrase_length(&tokens[i..]) {
+            Some(length) => {
+                let words = tokens[i..i + length]
+                    .iter()
+                    .filter_map(|token| token.token.symbol())
+                    .collect::<Vec<Symbol>>();
+                let words = words.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+                let token = LexedToken {
+                    token: tokens::Token::Word(Symbol::intern(&words.join(" "))),
+                    start: tokens[i].start,
+                    end: tokens[i + length - 1].end,
+                    index: 0,
+                };
+                (token, length)
+            }
+            None => match partial_phrases(&tokens[i..]) {
+                Some((keywords, length)) => {
+                    let token = LexedToken {
+                        token: tokens::Token::Illegal(tokens::Errors::IncompleteKeyword(keywords)),
+                        start: tokens[i].start,
+                        end: tokens[i + length - 1].end,
+                        index: 0,
+                    };
+                    (token, length)
+                }
+                None => (tokens[i].clone(), 1),
+            },
+        };
+        token.index = fused.len();
+        fused.push(token);
+        i += length;
+    }
+    fused
+}
+
 pub struct Lexer<'a> {
-    src: &'a str,
+    pub src: &'a str,
+    chars: Vec<char>,
 
     pos: usize,
     read_pos: usize,
     curr_ch: char,
     token_count: usize,
+    /// the comments get_tokens left out of the tokens, for the directives in
+    /// them
+    pub comments: Vec<LexedToken>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn init(src: &'a str) -> Self {
         let mut l = Self {
             src,
+            chars: src.chars().collect(),
             pos: 0,
             read_pos: 0,
             curr_ch: '\0',
             token_count: 0,
+            comments: vec![],
         };
 
         l.read_ch();
+        l.skip_shebang();
         l
     }
 
+    fn skip_shebang(&mut self) {
+        // a leading #! line lets scripts be executed directly, e.g. #!/usr/bin/env lolcat run
+        if self.curr_ch == '#' && self.peek_ch() == '!' {
+            self.skip_single_comment();
+            self.read_ch();
+        }
+    }
+
     fn read_ch(&mut self) {
-        if self.read_pos >= self.src.len() {
+        if self.read_pos >= self.chars.len() {
             self.curr_ch = '\0';
             return;
         }
 
-        self.curr_ch = self.src.chars().nth(self.read_pos).unwrap();
+        self.curr_ch = self.chars[self.read_pos];
         self.pos = self.read_pos;
         self.read_pos += 1;
     }
 
+    fn slice(&self, start: usize, end: usize) -> String {
+        self.chars[start..end].iter().collect()
+    }
+
     fn peek_ch(&self) -> char {
-        if self.read_pos >= self.src.len() {
+        if self.read_pos >= self.chars.len() {
             return '\0';
         }
 
-        self.src.chars().nth(self.read_pos).unwrap()
+        self.chars[self.read_pos]
     }
 
     fn read_number(&mut self) -> tokens::Token {
@@ -76,10 +261,10 @@ impl<'a> Lexer<'a> {
         }
 
         if is_float {
-            return tokens::Token::NumbarValue(self.src[start_pos..self.read_pos].to_string());
+            return tokens::Token::NumbarValue(self.slice(start_pos, self.read_pos));
         }
 
-        tokens::Token::NumberValue(self.src[start_pos..self.read_pos].to_string())
+        tokens::Token::NumberValue(self.slice(start_pos, self.read_pos))
     }
 
     pub fn special_check_identifier(&self, word: &str) -> bool {
@@ -99,6 +284,8 @@ impl<'a> Lexer<'a> {
             "MOD" => false,
             "BIGGR" => false,
             "SMALLR" => false,
+            "FURST" => false,
+            "LATR" => false,
             "BOTH" => false,
             "EITHER" => false,
             "WON" => false,
@@ -111,6 +298,12 @@ impl<'a> Lexer<'a> {
             "MAEK" => false,
             "VISIBLE" => false,
             "GIMMEH" => false,
+            "LETTAR" => false,
+            "LINE" => false,
+            "FLUSHZ" => false,
+            "DUMPZ" => false,
+            "ASSURE" => false,
+            "SAYIN" => false,
             "IT" => false,
             "O" => false,
             "RLY" => false,
@@ -131,6 +324,7 @@ impl<'a> Lexer<'a> {
             "OUTTA" => false,
             "UPPIN" => false,
             "NERFIN" => false,
+            "WATCHIN" => false,
             "HOW" => false,
             "IZ" => false,
             "IF" => false,
@@ -145,6 +339,19 @@ impl<'a> Lexer<'a> {
             "YARN" => false,
             "TROOF" => false,
             "NOOB" => false,
+            "BUKKIT" => false,
+            "KOPY" => false,
+            "LENGTHZ" => false,
+            "CODEZ" => false,
+            "SPLITZ" => false,
+            "ON" => false,
+            "JOINZ" => false,
+            "WIF" => false,
+            "MATCHEZ" => false,
+            "AGENST" => false,
+            "WUN" => false,
+            "DEN" => false,
+            "ELSS" => false,
             "FOUND" => false,
             _ => true,
         };
@@ -157,11 +364,29 @@ impl<'a> Lexer<'a> {
             self.read_ch();
         }
 
-        let word = &self.src[start_pos..self.read_pos];
+        let word = self.slice(start_pos, self.read_pos);
+        let word = word.as_str();
         if self.special_check_identifier(word) {
-            return tokens::Token::Identifier(word.to_string());
+            return tokens::Token::Identifier(Symbol::intern(word));
+        }
+        tokens::Token::Word(Symbol::intern(word))
+    }
+
+    /// Reads a backtick escaped identifier, which may be a keyword, e.g.
+    /// `SUM`, into an Identifier without the backticks
+    fn read_escaped_identifier(&mut self) -> tokens::Token {
+        let start = self.read_pos;
+        while self.peek_ch() != '`' && !is_newline(self.peek_ch()) && self.peek_ch() != '\0' {
+            self.read_ch();
+        }
+
+        let name = self.slice(start, self.read_pos);
+        if self.peek_ch() != '`' || !is_identifier_text(&name) {
+            return tokens::Token::Illegal(tokens::Errors::InvalidEscapedIdentifier);
         }
-        tokens::Token::Word(word.to_string())
+        self.read_ch();
+
+        tokens::Token::Identifier(Symbol::intern(&name))
     }
 
     fn read_string(&mut self) -> tokens::Token {
@@ -193,17 +418,27 @@ impl<'a> Lexer<'a> {
         tokens::Token::YarnValue(string_array.iter().collect())
     }
 
+    /// la, only if `t` ends a word
+    fn la_word(&mut self, t: &str) -> bool {
+        let after = self.chars.get(self.read_pos + t.chars().count());
+        match after {
+            Some(ch) if is_char(*ch) || is_int(*ch) => false,
+            _ => self.la(t),
+        }
+    }
+
     fn la(&mut self, t: &str) -> bool {
-        if self.read_pos + t.len() >= self.src.len() {
+        let len = t.chars().count();
+        if self.read_pos + len > self.chars.len() {
             return false;
         }
         let mut success = false;
-        if self.src[self.read_pos..self.read_pos + t.len()] == *t {
+        if self.slice(self.read_pos, self.read_pos + len) == *t {
             success = true;
         }
 
         if success {
-            for _ in 0..t.len() {
+            for _ in 0..len {
                 self.read_ch();
             }
         }
@@ -230,6 +465,16 @@ impl<'a> Lexer<'a> {
         tokens::Token::MultiLineComment(comment_contents.iter().collect())
     }
 
+    /// Reads the rest of the line after BTW, leaving the newline
+    fn read_single_comment(&mut self) -> tokens::Token {
+        let start = self.read_pos;
+        while !is_newline(self.peek_ch()) && self.peek_ch() != '\0' {
+            self.read_ch();
+        }
+
+        tokens::Token::SingleLineComment(self.slice(start, self.read_pos))
+    }
+
     fn skip_whitespace(&mut self) {
         while self.curr_ch == ' ' || self.curr_ch == '\t' || self.curr_ch == '\r' {
             self.read_ch();
@@ -272,6 +517,8 @@ impl<'a> Lexer<'a> {
             'A'..='Z' => {
                 if self.curr_ch == 'O' && self.la("BTW") {
                     self.read_multiline()
+                } else if self.curr_ch == 'B' && self.la_word("TW") {
+                    self.read_single_comment()
                 } else {
                     self.read_word()
                 }
@@ -279,6 +526,15 @@ impl<'a> Lexer<'a> {
             'a'..='z' => self.read_word(),
             '_' => self.read_word(),
             '"' => self.read_string(),
+            '`' => self.read_escaped_identifier(),
+            '\'' => {
+                // the 'Z in bukkit'Z slot
+                if self.la("Z") {
+                    tokens::Token::Word(Symbol::intern("'Z"))
+                } else {
+                    tokens::Token::Illegal(tokens::Errors::UnexpectedToken)
+                }
+            }
             ',' => tokens::Token::Comma,
             '!' => tokens::Token::ExclamationMark,
             '?' => tokens::Token::QuestionMark,
@@ -288,10 +544,6 @@ impl<'a> Lexer<'a> {
             _ => tokens::Token::Illegal(tokens::Errors::UnrecognizedToken),
         };
 
-        if let tokens::Token::SingleLineComment = token {
-            self.skip_single_comment();
-        }
-
         let end = self.read_pos;
         self.read_ch();
 
@@ -310,14 +562,15 @@ impl<'a> Lexer<'a> {
         while self.curr_ch != '\0' {
             let token = self.next_token();
             match token.token {
-                tokens::Token::SingleLineComment => {}
-                tokens::Token::MultiLineComment(_) => {}
+                tokens::Token::SingleLineComment(_) | tokens::Token::MultiLineComment(_) => {
+                    self.comments.push(token)
+                }
                 _ => tokens.push(token),
             }
         }
         tokens.push(self.next_token());
 
-        tokens
+        fuse_phrases(tokens)
     }
 
     pub fn has_errors(tokens: &Vec<LexedToken>) -> bool {
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::lexer::lexer;
+
+/// An interned identifier or keyword. Every Symbol with the same text points
+/// at the same string, so comparing and hashing them never looks at the
+/// text, and cloning one never allocates.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+/// Symbols interned before the interner first drops the unused ones
+const INTERNED_AT_FIRST: usize = 1024;
+
+/// The text of the symbols in use, and how many there may be before those no
+/// longer used are dropped. A long running server sees new names with every
+/// program, so the interner only keeps the names some Symbol still holds.
+struct Interned {
+    symbols: HashSet<Arc<str>>,
+    limit: usize,
+}
+
+fn interned() -> &'static Mutex<Interned> {
+    static INTERNED: OnceLock<Mutex<Interned>> = OnceLock::new();
+    INTERNED.get_or_init(|| {
+        Mutex::new(Interned {
+            symbols: HashSet::new(),
+            limit: INTERNED_AT_FIRST,
+        })
+    })
+}
+
+impl Symbol {
+    pub fn intern(text: &str) -> Symbol {
+        let mut interned = interned().lock().unwrap();
+        if let Some(existing) = interned.symbols.get(text) {
+            return Symbol(existing.clone());
+        }
+
+        if interned.symbols.len() >= interned.limit {
+            // only the interner holds a name no Symbol uses any more, and
+            // none can get it again but through here
+            interned.symbols.retain(|text| Arc::strong_count(text) > 1);
+            interned.limit = (interned.symbols.len() * 2).max(INTERNED_AT_FIRST);
+        }
+
+        let text: Arc<str> = Arc::from(text);
+        interned.symbols.insert(text.clone());
+        Symbol(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The identifier as it has to be written in source, e.g. `SUM` in
+    /// backticks, for diagnostics
+    pub fn escaped(&self) -> String {
+        lexer::escape_identifier(&self.0)
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state);
+    }
+}
+
+/// Symbols sort by their text rather than where it happens to be interned,
+/// so maps of them iterate the same way on every run
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_no_symbol_holds_are_dropped_from_the_interner() {
+        let kept = Symbol::intern("kept_across_programs");
+        for i in 0..INTERNED_AT_FIRST * 8 {
+            Symbol::intern(&format!("name_of_program_{}", i));
+        }
+
+        let interned = interned().lock().unwrap();
+        assert!(interned.symbols.len() <= INTERNED_AT_FIRST * 2);
+        assert!(interned.symbols.contains("kept_across_programs"));
+        drop(interned);
+        assert!(kept == Symbol::intern("kept_across_programs"));
+    }
+}
@@ -1,9 +1,15 @@
+use crate::lexer::symbol::Symbol;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Errors {
     UnrecognizedToken,
     UnexpectedToken,
     UnterminatedMultiLineComment,
     UnterminatedString,
+    InvalidEscapedIdentifier,
+    /// the start of a keyword of several words, with the keywords it could
+    /// have been
+    IncompleteKeyword(Vec<String>),
 }
 
 impl std::error::Error for Errors {}
@@ -15,6 +21,12 @@ impl std::fmt::Display for Errors {
             Errors::UnexpectedToken => write!(f, "Unexpected token"),
             Errors::UnterminatedMultiLineComment => write!(f, "Unterminated multi-line comment"),
             Errors::UnterminatedString => write!(f, "Unterminated string"),
+            Errors::InvalidEscapedIdentifier => {
+                write!(f, "Expected an identifier between the backticks")
+            }
+            Errors::IncompleteKeyword(keywords) => {
+                write!(f, "Expected {}", keywords.join(" or "))
+            }
         }
     }
 }
@@ -25,14 +37,15 @@ pub enum Token {
     EOF,
     Newline,
 
-    Word(String),
-    Identifier(String),
+    Word(Symbol),
+    Identifier(Symbol),
 
     Comma,
     ExclamationMark,
     QuestionMark,
 
-    SingleLineComment,
+    /// the text after BTW
+    SingleLineComment(String),
     MultiLineComment(String),
 
     NumberValue(String),
@@ -42,22 +55,46 @@ pub enum Token {
 }
 
 impl Token {
+    /// The name of the kind of token, Word for every keyword
+    fn kind(&self) -> &'static str {
+        match self {
+            Token::Illegal(_) => "Illegal",
+            Token::EOF => "EOF",
+            Token::Newline => "Newline",
+            Token::Word(_) => "Word",
+            Token::Identifier(_) => "Identifier",
+            Token::Comma => "Comma",
+            Token::ExclamationMark => "ExclamationMark",
+            Token::QuestionMark => "QuestionMark",
+            Token::SingleLineComment(_) => "SingleLineComment",
+            Token::MultiLineComment(_) => "MultiLineComment",
+            Token::NumberValue(_) => "NumberValue",
+            Token::NumbarValue(_) => "NumbarValue",
+            Token::YarnValue(_) => "YarnValue",
+            Token::TroofValue(_) => "TroofValue",
+        }
+    }
+
     pub fn to_name(&self) -> String {
         match self {
-            Token::Illegal(_) => "Illegal".to_string(),
-            Token::EOF => "EOF".to_string(),
-            Token::Newline => "Newline".to_string(),
-            Token::Word(w) => format!("Word_{}", w).to_string(),
-            Token::Identifier(_) => "Identifier".to_string(),
-            Token::Comma => "Comma".to_string(),
-            Token::ExclamationMark => "ExclamationMark".to_string(),
-            Token::QuestionMark => "QuestionMark".to_string(),
-            Token::SingleLineComment => "SingleLineComment".to_string(),
-            Token::MultiLineComment(_) => "MultiLineComment".to_string(),
-            Token::NumberValue(_) => "NumberValue".to_string(),
-            Token::NumbarValue(_) => "NumbarValue".to_string(),
-            Token::YarnValue(_) => "YarnValue".to_string(),
-            Token::TroofValue(_) => "TroofValue".to_string(),
+            Token::Word(w) => format!("Word_{}", w),
+            _ => self.kind().to_string(),
+        }
+    }
+
+    /// Whether `to_name` is `name`, without building it
+    pub fn has_name(&self, name: &str) -> bool {
+        match self {
+            Token::Word(w) => name.strip_prefix("Word_") == Some(w.as_str()),
+            _ => self.kind() == name,
+        }
+    }
+
+    /// The interned text of an identifier or keyword
+    pub fn symbol(&self) -> Option<Symbol> {
+        match self {
+            Token::Word(symbol) | Token::Identifier(symbol) => Some(symbol.clone()),
+            _ => None,
         }
     }
 }
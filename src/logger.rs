@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+use crate::ice;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Info,
+    Trace,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Level::Info => write!(f, "info"),
+            Level::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Logger {
+    pub verbosity: u8,
+}
+
+impl Logger {
+    pub fn new(verbosity: u8) -> Self {
+        Logger { verbosity }
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        match level {
+            Level::Info => self.verbosity >= 1,
+            Level::Trace => self.verbosity >= 2,
+        }
+    }
+
+    pub fn log(&self, level: Level, phase: &str, message: &str) {
+        if self.enabled(level) {
+            // logs go to stderr so they never mix with program output
+            eprintln!("level={} phase={} msg=\"{}\"", level, phase, message);
+        }
+    }
+
+    pub fn info(&self, phase: &str, message: &str) {
+        self.log(Level::Info, phase, message);
+    }
+
+    pub fn trace(&self, phase: &str, message: &str) {
+        self.log(Level::Trace, phase, message);
+    }
+
+    pub fn phase<T>(&self, phase: &str, f: impl FnOnce() -> T) -> T {
+        self.trace(phase, "start");
+        let start = Instant::now();
+        let outer = ice::set_phase(phase);
+
+        let result = f();
+
+        ice::set_phase(&outer);
+        if self.enabled(Level::Info) {
+            eprintln!(
+                "level={} phase={} elapsed_ms={:.3}",
+                Level::Info,
+                phase,
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+        }
+
+        result
+    }
+}
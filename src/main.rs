@@ -1,126 +1,1632 @@
+pub mod analysis;
 pub mod compiler;
+pub mod diagnostics;
+pub mod driver;
+pub mod exit;
+pub mod grade;
+pub mod ice;
+pub mod interpreter;
+pub mod jobs;
+pub mod json;
 pub mod lexer;
+pub mod logger;
 pub mod parser;
+pub mod playground;
+pub mod scaffold;
+pub mod serve;
+pub mod source;
 pub mod utils;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::cell::RefCell;
+use std::env::consts::EXE_SUFFIX;
 use std::fs;
+use std::io::{BufWriter, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
 
 use compiler::target::Target;
 
+use crate::analysis::{format, rename};
+use crate::compiler::cache::IrCache;
+use crate::compiler::callgraph::CallGraph;
+use crate::compiler::ir;
+use crate::compiler::opt;
+use crate::compiler::sandbox;
 use crate::compiler::target as targ;
 use crate::compiler::visit as v;
+use crate::diagnostics::{self as diag, Diagnostic};
+use crate::interpreter::coverage::Coverage;
+use crate::interpreter::dap;
+use crate::interpreter::debugger::{Console, Debugger, Mode};
+use crate::interpreter::profile::Profiler;
+use crate::interpreter::{Machine, Observer};
+use crate::jobs::Semaphore;
 use crate::lexer::lexer as l;
-use crate::lexer::tokens as t;
+use crate::logger::{Level, Logger};
 use crate::parser::parser as p;
-use crate::utils::get_line;
+use crate::parser::{desugar, grammar, syntax};
+use crate::source::SourceMap;
+use crate::utils::decode_source;
 
 #[derive(Parser)]
 #[command(name = "Lol Cat Compiler")]
-#[command(version = "0.1.0")]
+#[command(version = "0.1.0", disable_version_flag = true)]
 #[command(about = "A fast and efficient compiler for the LOLCODE programming language.", long_about = None)]
 #[command(author = "SpideyZac")]
 struct Cli {
-    input_file: String,
+    #[command(subcommand)]
+    command: Option<Commands>,
+    /// Source file to compile, or - to read from stdin
+    input_file: Option<String>,
     #[arg(short = 'o', long = "output")]
     output_file: Option<String>,
+    /// Emit an intermediate artifact (to the output file or stdout) instead of an executable
+    #[arg(long = "emit", value_enum)]
+    emit: Option<Emit>,
+    /// Only look for errors, stopping before code generation, and print nothing if there are none
+    #[arg(
+        long = "check",
+        visible_alias = "verify-only",
+        conflicts_with_all = ["output_file", "emit"]
+    )]
+    verify_only: bool,
+    /// Decode input that is not valid UTF-8 as Latin-1 instead of failing
+    #[arg(long = "latin1", global = true)]
+    latin1: bool,
+    /// Optimization level, 0 disables the IR optimizer and 2 adds block-level dataflow passes
+    #[arg(short = 'O', default_value_t = 0, global = true)]
+    opt_level: u8,
+    /// Largest function (in IR statements) that is inlined into its callers
+    #[arg(long = "inline-threshold", default_value_t = 32, global = true)]
+    inline_threshold: usize,
+    /// Optimizer passes to run whatever -O enables, in their usual order: inline, constprop, dataflow, ssa, licm
+    #[arg(
+        long = "passes",
+        value_name = "PASSES",
+        value_delimiter = ',',
+        global = true
+    )]
+    passes: Option<Vec<String>>,
+    /// Optimizer pass not to run even when enabled, may be repeated
+    #[arg(long = "disable-pass", value_name = "PASS", global = true)]
+    disable_pass: Vec<String>,
+    /// Print the IR to stderr after this optimizer pass runs, or after every pass with all
+    #[arg(long = "print-ir-after", value_name = "PASS", global = true)]
+    print_ir_after: Option<String>,
+    /// Report warnings as errors, failing the program, for CI (-W error does the same)
+    #[arg(long = "deny-warnings", global = true)]
+    deny_warnings: bool,
+    /// -W error reports warnings as errors, like --deny-warnings
+    #[arg(short = 'W', value_name = "error", value_parser = ["error"], global = true)]
+    warnings_as: Option<String>,
+    /// Errors to report for a program before leaving the rest out with a note, 0 for all of them
+    #[arg(long = "error-limit", value_name = "N", default_value_t = diag::DEFAULT_ERROR_LIMIT, global = true)]
+    error_limit: usize,
+    /// Leave ASSURE statements out of compiled programs (the interpreter always checks them)
+    #[arg(long = "release", global = true)]
+    release: bool,
+    /// Reject programs that call anything but the std's I/O and cap their memory and running time, for untrusted code
+    #[arg(long = "sandbox", global = true)]
+    sandbox: bool,
+    /// Panic with "program exceeded step limit" after this many loop iterations and calls
+    #[arg(long = "max-steps", value_name = "N", global = true)]
+    max_steps: Option<u64>,
+    /// Line ending VISIBLE writes (native is \r\n on Windows and \n elsewhere)
+    #[arg(long = "newline", value_enum, default_value_t = Newline::Native, global = true)]
+    newline: Newline,
+    /// Heap room compiled programs give each YARN letter: a float, or a byte for a quarter of the memory (letters past 255 then panic; the interpreter always uses floats)
+    #[arg(long = "yarn-width", value_enum, default_value_t = YarnWidth::Floats, global = true)]
+    yarn_width: YarnWidth,
+    /// How compiled programs allocate: searching the heap for room each time, or bumping through an arena dropped after each statement whose allocations cannot outlive it (faster, but memory freed inside such a statement is only reused after it)
+    #[arg(long = "runtime-alloc", value_enum, default_value_t = RuntimeAlloc::FirstFit, global = true)]
+    runtime_alloc: RuntimeAlloc,
+    /// Leave the freeing of YARNs and bukkits in compiled programs to a collector that runs between statements once a quarter of the heap is in use (the interpreter always frees them as it goes)
+    #[arg(long = "gc", global = true)]
+    gc: bool,
+    /// C compiler to build executables with (default: $LOLCAT_CC, then dep/tcc next to the compiler, then cc, gcc or clang)
+    #[arg(long = "cc", global = true)]
+    cc: Option<String>,
+    /// Keep the generated C next to the executable as <output>.c (it is always kept when the C compiler fails)
+    #[arg(long = "save-temps", global = true)]
+    save_temps: bool,
+    /// Generate the smallest C possible: no comments or formatting, short function names and merged pushes
+    #[arg(long = "minify", global = true)]
+    minify: bool,
+    /// Embed the source, compiler version and command line in executables, printed when they are run with --lolcat-info
+    #[arg(long = "embed-info", global = true)]
+    embed_info: bool,
+    /// Print how many IR statements each program compiles to, the memory it asks for and the hook slots each of its frames reserves, to stderr
+    #[arg(long = "stats", global = true)]
+    stats: bool,
+    /// Seconds the C compiler may run before it is killed, 0 for no limit
+    #[arg(
+        long = "cc-timeout",
+        value_name = "SECONDS",
+        default_value_t = 120,
+        global = true
+    )]
+    cc_timeout: u64,
+    /// Directory to cache optimized IR in, so unchanged programs skip the front end and optimizer, and unchanged functions the optimizer
+    #[arg(long = "cache-dir", global = true)]
+    cache_dir: Option<String>,
+    /// Files to check or build at once (default: one per CPU)
+    #[arg(short = 'j', long = "jobs", global = true)]
+    jobs: Option<usize>,
+    /// Let internal compiler error reports quote the source around the crash and a minimized input that still crashes
+    #[arg(long = "ice-include-source", global = true)]
+    ice_include_source: bool,
+    /// Print the version, with -v also the targets, defaults and C compiler for bug reports
+    #[arg(short = 'V', long = "version")]
+    version: bool,
+    /// Print phase timings (-v) and detailed tracing (-vv) to stderr
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Clone, ValueEnum)]
+enum Emit {
+    /// Function call graph with unreachable functions marked
+    Callgraph,
+    /// Function call graph in graphviz DOT format
+    CallgraphDot,
+    /// Directory (the output file, playground by default) with the program as a WASI module and a web page that runs it; needs clang and a WASI sysroot
+    Playground,
+    /// The grammar the parser accepts, as EBNF; needs no input file
+    Grammar,
+    /// The lossless syntax tree of the program, one node a line, which it has whether or not it parses
+    Syntax,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Newline {
+    Native,
+    Lf,
+    Crlf,
+}
 
-    let contents = fs::read_to_string(cli.input_file.clone());
-    if let Result::Err(_) = contents {
-        println!("Error: Could not read file '{}'", cli.input_file);
-        std::process::exit(1);
+impl Newline {
+    /// The line ending, None for the platform's own
+    fn text(self) -> Option<&'static str> {
+        match self {
+            Newline::Native => None,
+            Newline::Lf => Some("\n"),
+            Newline::Crlf => Some("\r\n"),
+        }
     }
-    let contents = contents.unwrap();
-    let contents = contents.as_str();
-    let lines = contents.split("\n").collect::<Vec<&str>>();
+}
 
-    let mut l = l::Lexer::init(contents);
-    let tokens = l.get_tokens();
+#[derive(Clone, Copy, ValueEnum)]
+enum YarnWidth {
+    Floats,
+    Bytes,
+}
 
-    if l::Lexer::has_errors(&tokens) {
-        let error = l::Lexer::get_first_error(&tokens).unwrap();
+impl YarnWidth {
+    /// Bytes of heap a letter takes
+    fn bytes(self) -> i32 {
+        match self {
+            YarnWidth::Floats => 4,
+            YarnWidth::Bytes => 1,
+        }
+    }
+}
 
-        let (line, count) = get_line(&lines, error.start);
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum RuntimeAlloc {
+    FirstFit,
+    Arena,
+}
 
-        match &error.token {
-            t::Token::Illegal(e) => {
-                println!("{}", lines[line]);
-                let arrow =
-                    " ".repeat(error.start - count) + "^".repeat(error.end - error.start).as_str();
-                println!("{}", arrow);
-                println!(
-                    "Error: {} at line {}, column {}:{}",
-                    e,
-                    line + 1,
-                    error.start - count + 1,
-                    error.end - count + 1
-                );
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// A row for every program and input
+    Csv,
+    /// An object for every program with its diagnostics and runs
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Compile a program to a temporary executable and run it
+    Run {
+        /// Source file to run, or - to read from stdin
+        input_file: String,
+        /// Run in the interpreter instead, writing an lcov report of how often each line ran
+        #[arg(long = "coverage", value_name = "LCOV_FILE")]
+        coverage: Option<String>,
+        /// Run in the interpreter instead, printing the time spent in each call and line to stderr
+        #[arg(long = "profile", conflicts_with = "coverage")]
+        profile: bool,
+    },
+    /// Run the front end on every .lol/.lc file in a directory and report diagnostics
+    Check {
+        /// Directory (searched recursively) or single file to check
+        path: String,
+    },
+    /// Compile every .lol/.lc file in the given directories and files to an executable next to it
+    Build {
+        /// Directories (searched recursively) and files to build
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Run a program in the interpreter, stopping at breakpoints to inspect it
+    Debug {
+        /// Source file to debug
+        input_file: String,
+        /// Line to stop at instead of the first statement, may be repeated
+        #[arg(short = 'b', long = "break")]
+        breakpoints: Vec<usize>,
+    },
+    /// Serve the Debug Adapter Protocol on stdin and stdout for editors
+    Dap,
+    /// Run every program in a directory on a set of inputs in the sandboxed interpreter and report how each did
+    Grade {
+        /// Directory (searched recursively) of the programs to grade
+        #[arg(long = "dir")]
+        dir: String,
+        /// Directory of <name>.in files to run every program on, each checked against <name>.out if there is one
+        #[arg(long = "input")]
+        input: String,
+        /// Format of the report
+        #[arg(long = "format", value_enum, default_value_t = ReportFormat::Csv)]
+        format: ReportFormat,
+        /// File to write the report to instead of stdout
+        #[arg(long = "report")]
+        report: Option<String>,
+        /// Seconds a program may run on one input before it fails
+        #[arg(long = "time-limit", value_name = "SECONDS", default_value_t = 10)]
+        time_limit: u64,
+    },
+    /// Answer JSON-RPC requests to check, compile, format or list the symbols of programs, one per line of stdin
+    Serve,
+    /// Run every program in a tests directory in the interpreter and report which pass
+    Test {
+        /// Directory (searched recursively) or single test file
+        #[arg(default_value = "tests")]
+        path: String,
+    },
+    /// Rename a variable or function and every reference to it in place
+    Rename {
+        /// Source file to rewrite
+        input_file: String,
+        /// Current name of the symbol
+        old: String,
+        /// Name to give the symbol
+        new: String,
+    },
+    /// Re-indent a program in place, or print it when reading from stdin
+    Fmt {
+        /// Source file to format, or - to read from stdin
+        input_file: String,
+    },
+    /// Scaffold a new project with a hello world program and an example test
+    New {
+        /// Directory to create the project in
+        path: String,
+    },
+    /// Run the backend conformance suite against the C target, with the target options given
+    #[cfg(feature = "conformance")]
+    Conformance {
+        /// Check the optimizer instead, on this many random programs, against the reference interpreter after every pass
+        #[arg(long = "fuzz", value_name = "PROGRAMS")]
+        fuzz: Option<u64>,
+        /// Seed of the first random program, each after it taking the next
+        #[arg(long = "seed", default_value_t = 1)]
+        seed: u64,
+    },
+}
+
+fn read_source(input_file: &str, latin1: bool, logger: &Logger) -> String {
+    let bytes = logger.phase("read", || {
+        if input_file == "-" {
+            let mut buffer = Vec::new();
+            std::io::stdin().read_to_end(&mut buffer).map(|_| buffer)
+        } else {
+            fs::read(input_file)
+        }
+    });
+    if let Result::Err(e) = bytes {
+        println!("Error: Could not read file '{}': {}", input_file, e);
+        std::process::exit(exit::USAGE);
+    }
+    let decoded = decode_source(&bytes.unwrap(), latin1);
+    if let Result::Err(e) = decoded {
+        println!("Error: Could not decode file '{}': {}", input_file, e);
+        std::process::exit(exit::USAGE);
+    }
+    let decoded = decoded.unwrap();
+    for warning in decoded.warnings.iter() {
+        println!("Warning: {} in '{}'", warning, input_file);
+    }
+
+    decoded.contents
+}
+
+/// A program's text and the path its diagnostics name it by
+#[derive(Clone, Copy)]
+struct Source<'a> {
+    name: &'a str,
+    contents: &'a str,
+}
+
+impl<'a> Source<'a> {
+    /// `input_file` as read by read_source, where - is stdin
+    fn new(input_file: &'a str, contents: &'a str) -> Self {
+        let name = if input_file == "-" {
+            "<stdin>"
+        } else {
+            input_file
+        };
+        Source { name, contents }
+    }
+
+    fn source_map(&self) -> SourceMap {
+        SourceMap::single(self.name, self.contents)
+    }
+}
+
+/// The IR, trace points and warnings of a program, or every
+/// diagnostic if it has errors
+type FrontEndResult = Result<(ir::IR, Option<v::TraceInfo>, Vec<Diagnostic>), Vec<Diagnostic>>;
+
+/// Lexes, parses, lints and visits a program through the driver, returning
+/// the IR, the trace points if `options.trace` is set and any warnings, or
+/// the rendered diagnostics, warnings included, if any phase failed. Every
+/// error is returned, so they can be counted before the error limit leaves
+/// some out of what is shown.
+fn front_end(input: Source, options: &driver::LowerOptions, logger: &Logger) -> FrontEndResult {
+    // positions are worked out on the text with its newlines normalized,
+    // which is also what the SourceMap holds
+    let contents = source::normalize_newlines(input.contents);
+    let input = Source::new(input.name, &contents);
+    ice::set_input(input.name, input.contents);
+    let source = input.source_map();
+
+    let (lexed, _) = driver::lex(input.contents, &source, logger)?;
+    let (program, _) = driver::parse(lexed.tokens, &source, logger)?;
+    let mut diagnostics =
+        diag::deny_warnings(driver::analyze(&program, &lexed.comments, &source, logger));
+
+    match driver::lower(program, source, options, logger) {
+        Ok((lowered, _)) if !diagnostics.iter().any(|diagnostic| diagnostic.is_error()) => {
+            Ok((lowered.ir, lowered.trace, diagnostics))
+        }
+        Ok(_) => Err(diagnostics),
+        Err(errors) => {
+            diagnostics.extend(errors);
+            Err(diagnostics)
+        }
+    }
+}
+
+/// How the assembled program is turned into an executable
+struct BuildOptions {
+    /// reject foreign calls outside the std and cap the stack and heap
+    sandbox: bool,
+    /// loop iterations and calls before the program panics
+    max_steps: Option<u64>,
+    /// what VISIBLE ends lines with, None for the platform's own
+    newline: Option<&'static str>,
+    /// bytes of heap a YARN letter takes
+    yarn_width: i32,
+    /// allocate from arenas in the regions the compiler marks
+    arena: bool,
+    /// check the heap accesses of Store and Load, left out by --release
+    checked: bool,
+    /// C compiler to use instead of looking for one
+    cc: Option<String>,
+    /// keep the generated C as <output>.c
+    save_temps: bool,
+    /// generate the smallest C possible
+    minify: bool,
+    /// embed the source and how it was compiled in the executable
+    embed_info: bool,
+    /// how long the C compiler may run
+    cc_timeout: Option<Duration>,
+    /// print the size of each program's IR and frames to stderr
+    stats: bool,
+}
+
+fn write_artifact(artifact: String, output_file: Option<String>) {
+    match output_file {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, artifact) {
+                println!("Error: Could not write file '{}': {}", path, e);
+                std::process::exit(exit::USAGE);
+            }
+        }
+        None => print!("{}", artifact),
+    }
+}
+
+/// Prints a program's diagnostics up to the error limit, ending with the
+/// summary line, which counts all of them, if there are any
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    let (errors, warnings) = diag::count(diagnostics);
+    for diagnostic in diag::limit_errors(diagnostics.to_vec()).iter() {
+        println!("{}", diagnostic);
+    }
+    if !diagnostics.is_empty() {
+        println!("{}", diag::summary(errors, warnings));
+    }
+}
+
+/// Runs just the front end, without building IR, for --check. Prints
+/// nothing if the program has no errors or warnings.
+fn verify(input: Source, logger: &Logger) {
+    match front_end(input, &driver::LowerOptions::default(), logger) {
+        Ok((_, _, warnings)) => print_diagnostics(&warnings),
+        Err(diagnostics) => {
+            print_diagnostics(&diagnostics);
+            std::process::exit(exit::COMPILE_ERROR);
+        }
+    }
+}
+
+/// Runs the front end and optimizer, returning the warnings with the IR, Err
+/// with the diagnostics on errors
+fn build_ir(
+    input: Source,
+    opt_options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    logger: &Logger,
+) -> Result<(ir::IR, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let (ir, _, warnings) = front_end(
+        input,
+        &driver::LowerOptions {
+            release: opt_options.release,
+            gc: opt_options.gc,
+            emit: true,
+            ..Default::default()
+        },
+        logger,
+    )?;
+
+    let ir = driver::optimize(ir, opt_options, cache, logger);
+
+    Ok((ir, warnings))
+}
+
+/// The optimized IR of a program and its warnings, from the cache if it has
+/// them, checked against the sandbox if there is one. Err with the
+/// diagnostics on errors.
+fn prepare_ir(
+    input: Source,
+    opt_options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    build: &BuildOptions,
+    logger: &Logger,
+) -> Result<(ir::IR, Vec<Diagnostic>), Vec<Diagnostic>> {
+    ice::set_input(input.name, input.contents);
+    let (mut ir, warnings) = match cache {
+        Some(cache) => {
+            let key = IrCache::key(input.name, input.contents, opt_options);
+            match cache.load(&key) {
+                Some((ir, warnings)) => {
+                    logger.info("cache", format!("reusing IR {}", key).as_str());
+                    // stored as warnings, which --deny-warnings may make errors
+                    let warnings = diag::deny_warnings(warnings);
+                    if warnings.iter().any(|warning| warning.is_error()) {
+                        return Err(warnings);
+                    }
+                    (ir, warnings)
+                }
+                None => {
+                    let (ir, warnings) = build_ir(input, opt_options, Some(cache), logger)?;
+                    if let Err(e) = cache.store(&key, &ir, &warnings) {
+                        println!("Warning: Could not write IR cache: {}", e);
+                    }
+                    (ir, warnings)
+                }
             }
-            _ => {
-                panic!("Unexpected error token");
+        }
+        None => build_ir(input, opt_options, None, logger)?,
+    };
+
+    // checked after the cache, whose files could have been edited
+    if build.sandbox {
+        if let Err(errors) = sandbox::check(&ir) {
+            return Err(errors.into_iter().map(Diagnostic::error).collect());
+        }
+        sandbox::limit(&mut ir);
+    }
+    if let Err(errors) = ir.check_foreign(&vm_target(build, false)) {
+        return Err(errors.into_iter().map(Diagnostic::error).collect());
+    }
+    if build.stats {
+        eprint!("{}", stats(input.name, &ir));
+    }
+
+    Ok((ir, warnings))
+}
+
+/// What --stats prints about the IR of `name`: how many statements it has,
+/// the memory it asks for and the hook slots each frame reserves
+fn stats(name: &str, ir: &ir::IR) -> String {
+    let mut frames = vec![format!("main {}", ir.entry.hooks)];
+    frames.extend(
+        ir.functions
+            .iter()
+            .map(|function| format!("{} {}", function.name, function.hooks)),
+    );
+    format!(
+        "stats for {}:\n    IR statements: {}\n    stack: {} floats, heap: {} bytes\n    hook slots: {} ({} in all)\n",
+        name,
+        ir.statement_count(),
+        ir.entry.stack_size,
+        ir.entry.heap_size,
+        frames.join(", "),
+        ir.hook_count()
+    )
+}
+
+/// What --embed-info puts in the executable built from `input`: the
+/// compiler's version, the command line it was run with and the source
+fn embedded_info(input: Source) -> String {
+    let arguments = std::env::args().collect::<Vec<String>>();
+    format!(
+        "LOLCatCompiler {}\ncommand: {}\nsource: {}\n\n{}",
+        env!("CARGO_PKG_VERSION"),
+        arguments.join(" "),
+        input.name,
+        source::normalize_newlines(input.contents)
+    )
+}
+
+fn vm_target(build: &BuildOptions, progress: bool) -> targ::vm::VM {
+    targ::vm::VM {
+        max_steps: build.max_steps,
+        newline: build.newline,
+        yarn_width: build.yarn_width,
+        arena: build.arena,
+        checked: build.checked,
+        cc: build.cc.clone(),
+        save_temps: build.save_temps,
+        timeout: build.cc_timeout,
+        progress,
+        minify: build.minify,
+        info: None,
+    }
+}
+
+/// The target to build `input` with, which embeds it with --embed-info
+fn program_target(build: &BuildOptions, progress: bool, input: Source) -> targ::vm::VM {
+    targ::vm::VM {
+        info: build.embed_info.then(|| embedded_info(input)),
+        ..vm_target(build, progress)
+    }
+}
+
+fn compile(
+    input: Source,
+    output_file: Option<String>,
+    emit: Option<Emit>,
+    opt_options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    build: &BuildOptions,
+    logger: &Logger,
+) {
+    if let Some(Emit::Syntax) = emit {
+        let text = source::normalize_newlines(input.contents);
+        let chars = text.chars().collect::<Vec<char>>();
+        let mut lexer = l::Lexer::init(&text);
+        let tokens = lexer.get_tokens();
+        let tree = syntax::build(&tokens, &lexer.comments, chars.len());
+        // the core statements the tree maps to, if the program parses
+        let parsed = p::Parser::parse(tokens);
+        let core = match parsed.errors.is_empty() {
+            true => desugar::desugar(parsed.ast).statements,
+            false => vec![],
+        };
+        return write_artifact(tree.dump(&chars, &core), output_file);
+    }
+
+    let ir = match prepare_ir(input, opt_options, cache, build, logger) {
+        Ok((ir, warnings)) => {
+            print_diagnostics(&warnings);
+            ir
+        }
+        Err(diagnostics) => {
+            print_diagnostics(&diagnostics);
+            std::process::exit(exit::COMPILE_ERROR);
+        }
+    };
+
+    match emit {
+        Some(Emit::Callgraph) => return write_artifact(CallGraph::new(&ir).to_text(), output_file),
+        Some(Emit::CallgraphDot) => {
+            return write_artifact(CallGraph::new(&ir).to_dot(), output_file)
+        }
+        Some(Emit::Grammar) => return write_artifact(grammar::ebnf(), output_file),
+        Some(Emit::Playground) | Some(Emit::Syntax) | None => {}
+    }
+
+    let target = program_target(build, logger.enabled(Level::Info), input);
+
+    let asm = driver::assemble(&ir, &target, logger);
+
+    if let Some(Emit::Playground) = emit {
+        let dir = output_file.unwrap_or_else(|| "playground".to_string());
+        let written = logger.phase("cc", || {
+            playground::write(Path::new(&dir), input.contents, asm, &target)
+        });
+        if let Err(e) = written {
+            println!("Error: Could not write playground '{}': {}", dir, e);
+            std::process::exit(exit::USAGE);
+        }
+        return;
+    }
+
+    if let Err(e) = logger.phase("cc", || target.compile(asm, output_file)) {
+        println!("{}", diag::build_failed(e));
+        std::process::exit(exit::USAGE);
+    }
+}
+
+/// The executable a program compiles to without -o: the input file's name
+/// without its extension, in the current directory. An input without an
+/// extension gets .out added so it is not overwritten.
+fn default_output(input_file: &str) -> String {
+    let path = Path::new(input_file);
+    match path.file_stem() {
+        Some(stem) if input_file != "-" => {
+            let stem = stem.to_string_lossy();
+            if path.extension().is_none() && EXE_SUFFIX.is_empty() {
+                format!("{}.out", stem)
+            } else {
+                format!("{}{}", stem, EXE_SUFFIX)
+            }
+        }
+        _ => format!("main{}", EXE_SUFFIX),
+    }
+}
+
+fn find_sources(path: &Path, sources: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_file() {
+        sources.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            find_sources(&entry_path, sources)?;
+        } else if let Some(extension) = entry_path.extension() {
+            if extension == "lol" || extension == "lc" {
+                sources.push(entry_path);
             }
         }
+    }
 
-        std::process::exit(1);
+    Ok(())
+}
+
+/// Every .lol/.lc file under `paths`, sorted, with its contents
+fn read_sources(paths: &[String], latin1: bool, logger: &Logger) -> Vec<(PathBuf, String)> {
+    let mut sources = Vec::new();
+    for path in paths.iter() {
+        if let Err(e) = find_sources(Path::new(path), &mut sources) {
+            println!("Error: Could not read directory '{}': {}", path, e);
+            std::process::exit(exit::USAGE);
+        }
     }
+    sources.sort();
+    sources.dedup();
+
+    sources
+        .into_iter()
+        .map(|source| {
+            let contents = read_source(&source.to_string_lossy(), latin1, logger);
+            (source, contents)
+        })
+        .collect()
+}
 
-    let p = p::Parser::parse(tokens);
+/// Runs the front end on every file, `jobs` at a time, reporting the
+/// diagnostics in file order
+fn check(path: &str, latin1: bool, jobs: usize, logger: &Logger) {
+    let sources = read_sources(&[path.to_string()], latin1, logger);
+    let results = jobs::map(&sources, jobs, |(source, contents)| {
+        let name = source.to_string_lossy();
+        match front_end(
+            Source::new(&name, contents),
+            &driver::LowerOptions::default(),
+            logger,
+        ) {
+            Ok((_, _, warnings)) => warnings,
+            Err(diagnostics) => diagnostics,
+        }
+    });
 
-    if p.errors.len() > 0 {
-        let reversed = p.errors.iter().rev().collect::<Vec<&p::ParserError>>();
+    let mut error_count = 0;
+    let mut warning_count = 0;
+    let mut failed_files = 0;
+    for ((source, _), diagnostics) in sources.iter().zip(results) {
+        if diagnostics.is_empty() {
+            continue;
+        }
 
-        for (i, error) in reversed.iter().enumerate() {
-            let (line, count) = get_line(&lines, error.token.start);
+        let (errors, warnings) = diag::count(&diagnostics);
+        error_count += errors;
+        warning_count += warnings;
+        if errors > 0 {
+            failed_files += 1;
+        }
 
-            println!("{}", lines[line]);
-            let arrow = " ".repeat(error.token.start - count)
-                + "^".repeat(error.token.end - error.token.start).as_str();
-            println!("{}", arrow);
-            println!(
-                "Error: {} at line {}, column {}:{}",
-                error.message,
-                line + 1,
-                error.token.start - count + 1,
-                error.token.end - count + 1
-            );
+        println!("==> {}", source.to_string_lossy());
+        for diagnostic in diag::limit_errors(diagnostics).iter() {
+            println!("{}\n", diagnostic);
+        }
+    }
+
+    println!(
+        "Checked {} file(s), {} with errors: {}",
+        sources.len(),
+        failed_files,
+        diag::summary(error_count, warning_count)
+    );
+    if error_count > 0 {
+        std::process::exit(exit::COMPILE_ERROR);
+    }
+}
+
+/// Compiles every program under `paths` to an executable next to it,
+/// running the front end `jobs` programs at a time and the C compiler half
+/// as many, since it is a process of its own
+fn build_all(
+    paths: &[String],
+    latin1: bool,
+    jobs: usize,
+    opt_options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    build: &BuildOptions,
+    logger: &Logger,
+) {
+    let sources = read_sources(paths, latin1, logger);
+    let cc_slots = Semaphore::new(jobs / 2);
+
+    let results = jobs::map(&sources, jobs, |(source, contents)| {
+        let name = source.to_string_lossy();
+        let (ir, warnings) = prepare_ir(
+            Source::new(&name, contents),
+            opt_options,
+            cache,
+            build,
+            logger,
+        )?;
+        let target = program_target(build, false, Source::new(&name, contents));
+        let asm = driver::assemble(&ir, &target, logger);
+        let output = source.with_file_name(default_output(&source.to_string_lossy()));
+
+        let _permit = cc_slots.acquire();
+        match target.compile(asm, Some(output.to_string_lossy().to_string())) {
+            Ok(()) => Ok((output, warnings)),
+            Err(e) => Err(vec![diag::build_failed(e)]),
+        }
+    });
 
-            if i != reversed.len() - 1 {
-                println!("\nWhich was caused by:");
+    let mut failed = 0;
+    let (mut error_count, mut warning_count) = (0, 0);
+    for ((source, _), result) in sources.iter().zip(results) {
+        let (errors, warnings) = match &result {
+            Ok((_, warnings)) => diag::count(warnings),
+            Err(diagnostics) => diag::count(diagnostics),
+        };
+        error_count += errors;
+        warning_count += warnings;
+        match result {
+            Ok((output, warnings)) => {
+                if !warnings.is_empty() {
+                    println!("==> {}", source.to_string_lossy());
+                    for warning in warnings.iter() {
+                        println!("{}\n", warning);
+                    }
+                }
+                println!("Built {}", output.to_string_lossy());
+            }
+            Err(diagnostics) => {
+                println!("==> {}", source.to_string_lossy());
+                for diagnostic in diag::limit_errors(diagnostics).iter() {
+                    println!("{}\n", diagnostic);
+                }
+                failed += 1;
             }
         }
+    }
+
+    println!(
+        "Built {} of {} program(s)",
+        sources.len() - failed,
+        sources.len()
+    );
+    if error_count + warning_count > 0 {
+        println!("{}", diag::summary(error_count, warning_count));
+    }
+    if failed > 0 {
+        std::process::exit(exit::COMPILE_ERROR);
+    }
+}
+
+/// Runs one test program, Err with why it failed
+fn run_test(
+    source: &Path,
+    latin1: bool,
+    max_steps: Option<u64>,
+    logger: &Logger,
+) -> Result<(), String> {
+    let name = source.to_string_lossy();
+    let contents = read_source(&name, latin1, logger);
+    let (ir, _, _) = front_end(
+        Source::new(&name, &contents),
+        &driver::LowerOptions {
+            emit: true,
+            ..Default::default()
+        },
+        logger,
+    )
+    .map_err(|diagnostics| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    })?;
+
+    // tests never wait on the terminal, they read <name>.in or nothing
+    let input = fs::read(source.with_extension("in")).unwrap_or_default();
+    let (code, actual) = logger.phase("run", || {
+        interpreter::run_captured(&ir, input, |machine| {
+            machine.max_steps = max_steps;
+            // .out files are compared byte for byte and written with \n
+            machine.newline = "\n";
+        })
+    });
+
+    if code != 0 {
+        return Err(format!("exited with code {}\n{}", code, actual.trim_end()));
+    }
+    if let Ok(expected) = fs::read_to_string(source.with_extension("out")) {
+        if actual != expected {
+            return Err(format!(
+                "output differs from {}\nexpected:\n{}\ngot:\n{}",
+                source.with_extension("out").to_string_lossy(),
+                expected.trim_end(),
+                actual.trim_end()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs every test program under `path` in the interpreter. A test passes if
+/// it exits with code 0 (so every ASSURE held) and, when there is a
+/// <name>.out next to it, printed exactly that.
+fn test(path: &str, latin1: bool, max_steps: Option<u64>, logger: &Logger) {
+    let mut sources = Vec::new();
+    if let Err(e) = find_sources(Path::new(path), &mut sources) {
+        println!("Error: Could not read directory '{}': {}", path, e);
+        std::process::exit(exit::USAGE);
+    }
+    sources.sort();
+
+    let mut failed = 0;
+    for source in sources.iter() {
+        match run_test(source, latin1, max_steps, logger) {
+            Ok(()) => println!("PASS {}", source.to_string_lossy()),
+            Err(reason) => {
+                println!("FAIL {}: {}", source.to_string_lossy(), reason);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", sources.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(exit::COMPILE_ERROR);
+    }
+}
+
+/// The <name>.in files in `dir`, sorted, with their <name>.out files
+fn read_cases(dir: &str) -> Vec<grade::Case> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Error: Could not read directory '{}': {}", dir, e);
+            std::process::exit(exit::USAGE);
+        }
+    };
+    let mut inputs = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "in"))
+        .collect::<Vec<PathBuf>>();
+    inputs.sort();
+
+    inputs
+        .into_iter()
+        .map(|path| grade::Case {
+            name: path.file_stem().unwrap().to_string_lossy().to_string(),
+            input: fs::read(&path).unwrap_or_default(),
+            expected: fs::read_to_string(path.with_extension("out")).ok(),
+        })
+        .collect()
+}
+
+/// Runs every program under `dir` on every case, `jobs` programs at a
+/// time, in the interpreter and under the sandbox since the programs are not
+/// trusted
+fn grade(
+    dir: &str,
+    cases: &[grade::Case],
+    limits: &grade::Limits,
+    latin1: bool,
+    jobs: usize,
+    logger: &Logger,
+) -> Vec<grade::Submission> {
+    let sources = read_sources(&[dir.to_string()], latin1, logger);
+    jobs::map(&sources, jobs, |(source, contents)| {
+        let name = source.to_string_lossy().to_string();
+        let compiled = front_end(
+            Source::new(&name, contents),
+            &driver::LowerOptions {
+                emit: true,
+                ..Default::default()
+            },
+            logger,
+        )
+        .and_then(|(mut ir, _, warnings)| {
+            if let Err(errors) = sandbox::check(&ir) {
+                return Err(errors.into_iter().map(Diagnostic::error).collect());
+            }
+            sandbox::limit(&mut ir);
+            Ok((ir, warnings))
+        });
+        let (ir, warnings) = match compiled {
+            Ok(compiled) => compiled,
+            Err(diagnostics) => {
+                let diagnostics = diag::limit_errors(diagnostics);
+                return grade::Submission::not_compiled(name, diagnostics, cases);
+            }
+        };
+
+        let runs = cases
+            .iter()
+            .map(|case| {
+                let (code, output) =
+                    interpreter::run_captured(&ir, case.input.clone(), |machine| {
+                        machine.max_steps = Some(limits.max_steps);
+                        machine.newline = "\n";
+                        machine.deadline = Some(Instant::now() + limits.time);
+                    });
+                grade::judge(case, code, &output)
+            })
+            .collect();
+        grade::Submission {
+            path: name,
+            diagnostics: warnings,
+            runs,
+        }
+    })
+}
+
+/// Writes the report of a grade to `report`, or stdout, and a summary to
+/// stderr
+fn write_report(
+    submissions: &[grade::Submission],
+    cases: usize,
+    format: ReportFormat,
+    report: Option<String>,
+) {
+    let text = match format {
+        ReportFormat::Csv => grade::csv(submissions),
+        ReportFormat::Json => format!("{}\n", grade::json(submissions)),
+    };
+    write_artifact(text, report);
+
+    let passed = submissions
+        .iter()
+        .filter(|submission| submission.passed() == cases)
+        .count();
+    eprintln!(
+        "Graded {} program(s) on {} input(s): {} passed every input",
+        submissions.len(),
+        cases,
+        passed
+    );
+}
+
+fn run(
+    input_file: &str,
+    latin1: bool,
+    opt_options: &opt::OptOptions,
+    cache: Option<&IrCache>,
+    build: &BuildOptions,
+    logger: &Logger,
+) {
+    let contents = read_source(input_file, latin1, logger);
+
+    let exe_path =
+        std::env::temp_dir().join(format!("lolcat-run-{}{}", std::process::id(), EXE_SUFFIX));
+    let exe = exe_path.to_str().unwrap().to_string();
+    compile(
+        Source::new(input_file, &contents),
+        Some(exe.clone()),
+        None,
+        opt_options,
+        cache,
+        build,
+        logger,
+    );
+
+    let status = logger.phase("run", || Command::new(&exe).status());
+    let _ = fs::remove_file(&exe_path);
+
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            println!("Error: Could not run compiled program: {}", e);
+            std::process::exit(exit::USAGE);
+        }
+    }
+}
+
+/// Runs the front end with trace points for the interpreter, exiting with
+/// the diagnostics on errors. The optimizer would merge and move statements,
+/// so it is skipped.
+fn build_traced_ir(input: Source, logger: &Logger) -> (ir::IR, v::TraceInfo) {
+    match front_end(
+        input,
+        &driver::LowerOptions {
+            trace: true,
+            emit: true,
+            ..Default::default()
+        },
+        logger,
+    ) {
+        Ok((ir, trace, warnings)) => {
+            print_diagnostics(&warnings);
+            (ir, trace.unwrap())
+        }
+        Err(diagnostics) => {
+            print_diagnostics(&diagnostics);
+            std::process::exit(exit::COMPILE_ERROR);
+        }
+    }
+}
+
+/// Runs IR in the interpreter on stdin and stdout, returning the exit code
+fn interpret(
+    ir: &ir::IR,
+    observer: &mut impl Observer,
+    max_steps: Option<u64>,
+    newline: &'static str,
+    logger: &Logger,
+) -> i32 {
+    interpreter::handle_interrupts();
+    let mut machine = Machine::new(
+        ir.entry.stack_size,
+        ir.entry.heap_size,
+        Box::new(std::io::stdin().lock()),
+        Box::new(BufWriter::new(std::io::stdout())),
+    );
+    machine.max_steps = max_steps;
+    machine.newline = newline;
+    let result = logger.phase("run", || machine.run(ir, observer));
+    machine.finish(result)
+}
+
+/// Runs the program in the interpreter, counting the runs of every trace
+/// point, and writes an lcov report of them
+fn run_with_coverage(
+    input_file: &str,
+    report: &str,
+    latin1: bool,
+    max_steps: Option<u64>,
+    newline: &'static str,
+    logger: &Logger,
+) {
+    let contents = read_source(input_file, latin1, logger);
+    let input = Source::new(input_file, &contents);
+    let (ir, trace) = build_traced_ir(input, logger);
 
-        std::process::exit(1);
+    let mut coverage = Coverage::new(&trace);
+    let code = interpret(&ir, &mut coverage, max_steps, newline, logger);
+
+    if let Err(e) = fs::write(
+        report,
+        coverage.lcov(&trace, &input.source_map(), input_file),
+    ) {
+        println!("Error: Could not write file '{}': {}", report, e);
+        std::process::exit(exit::USAGE);
     }
+    std::process::exit(code);
+}
 
-    let mut v = v::Visitor::new(p, 1000, 4000);
-    let (ir, errors, hooks) = v.visit();
+/// Runs the program in the interpreter, timing every line and call, and
+/// prints the profile to stderr once it exits
+fn run_with_profile(
+    input_file: &str,
+    latin1: bool,
+    max_steps: Option<u64>,
+    newline: &'static str,
+    logger: &Logger,
+) {
+    let contents = read_source(input_file, latin1, logger);
+    let input = Source::new(input_file, &contents);
+    let (ir, trace) = build_traced_ir(input, logger);
 
-    for error in errors.iter() {
-        let token = &error.token.token;
+    let mut profiler = Profiler::new(&trace);
+    let code = interpret(&ir, &mut profiler, max_steps, newline, logger);
 
-        let (line, count) = get_line(&lines, token.start);
+    eprint!("{}", profiler.report(&trace, &input.source_map()));
+    std::process::exit(code);
+}
+
+/// Runs the unoptimized program in the interpreter under the line debugger.
+/// Commands and the program's own input share stdin.
+fn debug(
+    input_file: &str,
+    breakpoints: &[usize],
+    latin1: bool,
+    max_steps: Option<u64>,
+    newline: &'static str,
+    logger: &Logger,
+) {
+    let contents = read_source(input_file, latin1, logger);
+    let input = Source::new(input_file, &contents);
+    let (ir, trace) = build_traced_ir(input, logger);
 
-        println!("{}", lines[line]);
-        let arrow = " ".repeat(token.start - count) + "^".repeat(token.end - token.start).as_str();
-        println!("{}", arrow);
+    let mut console = Console::new(Debugger::new(&trace, input.source_map()));
+    for line in breakpoints.iter() {
+        console.add_breakpoint(*line);
+    }
+    if !breakpoints.is_empty() {
+        console.debugger.mode = Mode::Continue;
+    }
+
+    let code = interpret(&ir, &mut console, max_steps, newline, logger);
+
+    eprintln!("Program exited with code {}", code);
+    std::process::exit(code);
+}
+
+/// The first line `cc` prints about its version, if it says anything
+fn cc_version(cc: &str) -> Option<String> {
+    for flag in ["--version", "-v"] {
+        let output = match Command::new(cc).arg(flag).output() {
+            Ok(output) => output,
+            Err(_) => return None,
+        };
+        let text = [output.stdout, output.stderr].concat();
+        let text = String::from_utf8_lossy(&text);
+        if let Some(line) = text.lines().find(|line| !line.trim().is_empty()) {
+            return Some(line.trim().to_string());
+        }
+    }
+    None
+}
+
+/// --version, with `verbose` everything about this build and machine that
+/// bears on what a program compiles to
+fn print_version(verbose: bool, build: &BuildOptions) {
+    println!("Lol Cat Compiler {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+
+    println!(
+        "host: {}-{} ({} build)",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    );
+    println!("features: conformance={}", cfg!(feature = "conformance"));
+    println!("targets: c (executables, through a C compiler), wasm32-wasi (--emit playground, through $WASI_CC or clang), interpreter (run --coverage/--profile, debug, test, grade)");
+    println!(
+        "stack: {} floats, heap: {} bytes (with --sandbox at most {} and {}, and {} steps)",
+        v::STACK_SIZE,
+        v::HEAP_SIZE,
+        sandbox::MAX_STACK_SIZE,
+        sandbox::MAX_HEAP_SIZE,
+        sandbox::MAX_STEPS
+    );
+    match vm_target(build, false).find_cc() {
+        Ok(cc) => match cc_version(&cc) {
+            Some(version) => println!("C compiler: {} ({})", cc, version),
+            None => println!("C compiler: {} (could not get its version)", cc),
+        },
+        Err(e) => println!("C compiler: none ({})", e),
+    }
+}
+
+fn main() {
+    ice::install_panic_hook();
+    let cli = Cli::parse();
+    // the other options are global, so they may come before a subcommand
+    if cli.command.is_some()
+        && (cli.input_file.is_some()
+            || cli.output_file.is_some()
+            || cli.emit.is_some()
+            || cli.verify_only)
+    {
         println!(
-            "Error: {} at line {}, column {}:{}",
-            error.message,
-            line + 1,
-            token.start - count + 1,
-            token.end - count + 1
+            "Error: The input file, -o, --emit and --check are for compiling without a subcommand"
         );
+        std::process::exit(exit::USAGE);
     }
-    if errors.len() > 0 {
-        std::process::exit(1);
+    let logger = Logger::new(cli.verbose);
+    let opt_options = opt::OptOptions {
+        level: cli.opt_level,
+        inline_threshold: cli.inline_threshold,
+        release: cli.release,
+        gc: cli.gc,
+        passes: cli.passes,
+        disabled: cli.disable_pass,
+        print_ir_after: cli.print_ir_after,
+    };
+    let pass_names = opt::PASSES
+        .iter()
+        .map(|pass| pass.name)
+        .collect::<Vec<&str>>();
+    let named = opt_options.passes.iter().flatten();
+    let named = named.chain(opt_options.disabled.iter());
+    let named = named.chain(
+        opt_options
+            .print_ir_after
+            .iter()
+            .filter(|pass| *pass != "all"),
+    );
+    for pass in named {
+        if !pass_names.contains(&pass.as_str()) {
+            println!(
+                "Error: Unknown optimizer pass '{}', the passes are {}",
+                pass,
+                pass_names.join(", ")
+            );
+            std::process::exit(exit::USAGE);
+        }
+    }
+    let cache = cli.cache_dir.map(|dir| IrCache::new(PathBuf::from(dir)));
+    let build = BuildOptions {
+        sandbox: cli.sandbox,
+        max_steps: cli.max_steps.or(cli.sandbox.then_some(sandbox::MAX_STEPS)),
+        newline: cli.newline.text(),
+        yarn_width: cli.yarn_width.bytes(),
+        arena: cli.runtime_alloc == RuntimeAlloc::Arena,
+        checked: !cli.release,
+        cc: cli.cc,
+        save_temps: cli.save_temps,
+        minify: cli.minify,
+        embed_info: cli.embed_info,
+        cc_timeout: (cli.cc_timeout > 0).then(|| Duration::from_secs(cli.cc_timeout)),
+        stats: cli.stats,
+    };
+    if cli.version {
+        print_version(cli.verbose > 0, &build);
+        return;
     }
+    let max_steps = build.max_steps;
+    let newline = build.newline.unwrap_or(interpreter::NATIVE_NEWLINE);
+    let jobs = cli.jobs.unwrap_or_else(jobs::default_jobs).max(1);
+    ice::include_source(cli.ice_include_source);
+    diag::set_error_limit(cli.error_limit);
+    diag::set_deny_warnings(cli.deny_warnings || cli.warnings_as.is_some());
 
-    let target = targ::vm::VM {};
+    // a crash unwinds to here, once the panic hook has reported it
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| match cli.command {
+        Some(Commands::Run {
+            input_file,
+            coverage: Some(report),
+            ..
+        }) => run_with_coverage(
+            &input_file,
+            &report,
+            cli.latin1,
+            max_steps,
+            newline,
+            &logger,
+        ),
+        Some(Commands::Run {
+            input_file,
+            profile: true,
+            ..
+        }) => run_with_profile(&input_file, cli.latin1, max_steps, newline, &logger),
+        Some(Commands::Run { input_file, .. }) => run(
+            &input_file,
+            cli.latin1,
+            &opt_options,
+            cache.as_ref(),
+            &build,
+            &logger,
+        ),
+        Some(Commands::Check { path }) => check(&path, cli.latin1, jobs, &logger),
+        Some(Commands::Build { paths }) => build_all(
+            &paths,
+            cli.latin1,
+            jobs,
+            &opt_options,
+            cache.as_ref(),
+            &build,
+            &logger,
+        ),
+        Some(Commands::Debug {
+            input_file,
+            breakpoints,
+        }) => debug(
+            &input_file,
+            &breakpoints,
+            cli.latin1,
+            max_steps,
+            newline,
+            &logger,
+        ),
+        Some(Commands::Test { path }) => test(&path, cli.latin1, max_steps, &logger),
+        Some(Commands::Dap) => {
+            let front_end = |name: &str, contents: &str| {
+                front_end(
+                    Source::new(name, contents),
+                    &driver::LowerOptions {
+                        trace: true,
+                        emit: true,
+                        ..Default::default()
+                    },
+                    &logger,
+                )
+                .map(|(ir, trace, _)| (ir, trace.unwrap()))
+                .map_err(|diagnostics| {
+                    let diagnostics = diag::limit_errors(diagnostics);
+                    diagnostics.iter().map(|d| d.to_string()).collect()
+                })
+            };
+            dap::serve(&mut std::io::stdin().lock(), &front_end);
+        }
+        Some(Commands::Grade {
+            dir,
+            input,
+            format,
+            report,
+            time_limit,
+        }) => {
+            let cases = read_cases(&input);
+            if cases.is_empty() {
+                println!("Error: There are no .in files in '{}'", input);
+                std::process::exit(exit::USAGE);
+            }
+            let limits = grade::Limits {
+                max_steps: cli.max_steps.unwrap_or(sandbox::MAX_STEPS),
+                time: Duration::from_secs(time_limit),
+            };
+            let submissions = grade(&dir, &cases, &limits, cli.latin1, jobs, &logger);
+            write_report(&submissions, cases.len(), format, report);
+        }
+        Some(Commands::Serve) => {
+            let check = |name: &str, contents: &str| match front_end(
+                Source::new(name, contents),
+                &driver::LowerOptions::default(),
+                &logger,
+            ) {
+                Ok((_, _, warnings)) => warnings,
+                Err(diagnostics) => diagnostics,
+            };
+            // a program sent again, unchanged, is not compiled again
+            let session = RefCell::new(driver::Session::new());
+            let compile = |name: &str, contents: &str, output: Option<&str>| {
+                let input = Source::new(name, contents);
+                let key = IrCache::key(name, contents, &opt_options);
+                let (ir, warnings) = session.borrow_mut().ir(key, || {
+                    prepare_ir(input, &opt_options, cache.as_ref(), &build, &logger)
+                })?;
+                if build.stats {
+                    eprint!("{}", session.borrow().stats());
+                }
+                let target = program_target(&build, false, input);
+                let asm = {
+                    let mut session = session.borrow_mut();
+                    driver::assemble_after(&ir, session.prelude(&target), &target, &logger)
+                };
+                let path = match output {
+                    Some(path) => path,
+                    None => return Ok((asm, warnings)),
+                };
+                // the C compiler is only looked for once
+                let built = session.borrow_mut().cc(|| target.find_cc()).and_then(|cc| {
+                    let target = targ::vm::VM {
+                        cc: Some(cc),
+                        ..target
+                    };
+                    target.compile(asm, Some(path.to_string()))
+                });
+                match built {
+                    Ok(()) => Ok((path.to_string(), warnings)),
+                    Err(e) => Err(vec![diag::build_failed(e)]),
+                }
+            };
+            serve::serve(
+                &mut std::io::stdin().lock(),
+                &mut std::io::stdout().lock(),
+                &serve::Compiler {
+                    check: &check,
+                    compile: &compile,
+                },
+            );
+        }
+        Some(Commands::Rename {
+            input_file,
+            old,
+            new,
+        }) => {
+            let contents = read_source(&input_file, cli.latin1, &logger);
+            match rename::rename(contents.as_str(), &old, &new) {
+                Ok((renamed, count)) => {
+                    if let Err(e) = fs::write(&input_file, renamed) {
+                        println!("Error: Could not write file '{}': {}", input_file, e);
+                        std::process::exit(exit::USAGE);
+                    }
+                    println!(
+                        "Renamed {} occurrence(s) of {} to {}",
+                        count,
+                        l::escape_identifier(&old),
+                        l::escape_identifier(&new)
+                    );
+                }
+                Err(e) => {
+                    println!("Error: Could not rename {}: {}", old, e);
+                    std::process::exit(exit::COMPILE_ERROR);
+                }
+            }
+        }
+        Some(Commands::Fmt { input_file }) => {
+            let contents = read_source(&input_file, cli.latin1, &logger);
+            let contents = source::normalize_newlines(&contents);
+            let formatted = if cli.minify {
+                match format::minify(&contents) {
+                    Ok(minified) => minified,
+                    Err((span, message)) => {
+                        let source = Source::new(&input_file, &contents).source_map();
+                        println!("{}", source.format_error(span, &message));
+                        std::process::exit(exit::COMPILE_ERROR);
+                    }
+                }
+            } else {
+                format::format(&contents)
+            };
+
+            if input_file == "-" {
+                print!("{}", formatted);
+            } else if let Err(e) = fs::write(&input_file, formatted) {
+                println!("Error: Could not write file '{}': {}", input_file, e);
+                std::process::exit(exit::USAGE);
+            }
+        }
+        Some(Commands::New { path }) => {
+            if let Err(e) = scaffold::new_project(Path::new(&path)) {
+                println!("Error: Could not create project '{}': {}", path, e);
+                std::process::exit(exit::USAGE);
+            }
+            println!("Created project '{}'", path);
+        }
+        #[cfg(feature = "conformance")]
+        Some(Commands::Conformance { fuzz, seed }) => {
+            let front_end = |contents: &str| {
+                front_end(
+                    Source::new("conformance.lc", contents),
+                    &driver::LowerOptions {
+                        gc: opt_options.gc,
+                        emit: true,
+                        ..Default::default()
+                    },
+                    &logger,
+                )
+                .map(|(ir, _, _)| ir)
+                .map_err(|diagnostics| {
+                    diagnostics
+                        .iter()
+                        .map(|d| d.to_string())
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                })
+            };
+            if let Some(count) = fuzz {
+                let failures = targ::conformance::fuzz(seed, count, &opt_options, &front_end);
+                for (program, failure) in failures.iter() {
+                    println!("FAIL {}:\n{}", failure, program);
+                }
+                println!(
+                    "\n{} passed, {} failed",
+                    count - failures.len() as u64,
+                    failures.len()
+                );
+                if !failures.is_empty() {
+                    std::process::exit(exit::COMPILE_ERROR);
+                }
+                return;
+            }
 
-    let asm = ir.assemble(&target, hooks);
-    let _ = target.compile(asm, cli.output_file).unwrap();
+            // the cases check out of bounds accesses panic, even with --release
+            let target = targ::vm::VM {
+                checked: true,
+                ..vm_target(&build, false)
+            };
+            let results =
+                targ::conformance::run(&target, &front_end, &targ::conformance::run_native);
+
+            let mut failed = 0;
+            for (name, failure) in results.iter() {
+                match failure {
+                    None => println!("PASS {}", name),
+                    Some(reason) => {
+                        println!("FAIL {}: {}", name, reason);
+                        failed += 1;
+                    }
+                }
+            }
+            println!("\n{} passed, {} failed", results.len() - failed, failed);
+            if failed > 0 {
+                std::process::exit(exit::COMPILE_ERROR);
+            }
+        }
+        None => {
+            if let Some(Emit::Grammar) = cli.emit {
+                return write_artifact(grammar::ebnf(), cli.output_file);
+            }
+
+            let input_file = match cli.input_file {
+                Some(input_file) => input_file,
+                None => {
+                    println!("Error: No input file given");
+                    std::process::exit(exit::USAGE);
+                }
+            };
+
+            let contents = read_source(&input_file, cli.latin1, &logger);
+            if cli.verify_only {
+                return verify(Source::new(&input_file, &contents), &logger);
+            }
+
+            // artifacts other than the executable default to stdout
+            let output_file = match cli.emit {
+                None => cli
+                    .output_file
+                    .or_else(|| Some(default_output(&input_file))),
+                Some(_) => cli.output_file,
+            };
+            compile(
+                Source::new(&input_file, &contents),
+                output_file,
+                cli.emit,
+                &opt_options,
+                cache.as_ref(),
+                &build,
+                &logger,
+            );
+        }
+    }));
+
+    if outcome.is_err() {
+        let quiet = Logger::new(0);
+        ice::finish(&|text| {
+            let input = Source::new("<minimized>", text);
+            if let Ok((ir, _, _)) = front_end(
+                input,
+                &driver::LowerOptions {
+                    release: opt_options.release,
+                    gc: opt_options.gc,
+                    emit: true,
+                    ..Default::default()
+                },
+                &quiet,
+            ) {
+                let ir = driver::optimize(ir, &opt_options, None, &quiet);
+                driver::assemble(&ir, &vm_target(&build, false), &quiet);
+            }
+        });
+    }
 }
@@ -28,6 +28,11 @@ pub enum StatementNodeValueOption {
     IfStatement(IfStatementNode),
     SwitchStatement(SwitchStatementNode),
     GTFOStatement(TokenNode),
+    FLUSHZStatement(TokenNode),
+    AssureStatement(AssureStatementNode),
+    DumpzStatement(DumpzStatementNode),
+    BukkitSlotDeclarationStatement(BukkitSlotDeclarationStatementNode),
+    WatchinLoopStatement(WatchinLoopStatementNode),
     LoopStatement(LoopStatementNode),
     ReturnStatement(ReturnStatementNode),
     FunctionDefinitionStatement(FunctionDefinitionStatementNode),
@@ -38,6 +43,52 @@ pub struct StatementNode {
     pub value: StatementNodeValueOption,
 }
 
+impl StatementNode {
+    /// A token on the statement's first line, if it kept one
+    pub fn token(&self) -> Option<&TokenNode> {
+        match &self.value {
+            StatementNodeValueOption::Expression(expression) => expression.token(),
+            StatementNodeValueOption::VariableDeclarationStatement(var_dec) => {
+                Some(&var_dec.identifier)
+            }
+            StatementNodeValueOption::VariableAssignmentStatement(var_assign) => {
+                match &var_assign.variable {
+                    VariableAssignmentNodeVariableOption::Identifier(token) => Some(token),
+                    VariableAssignmentNodeVariableOption::VariableDeclerationStatement(var_dec) => {
+                        Some(&var_dec.identifier)
+                    }
+                    VariableAssignmentNodeVariableOption::BukkitSlot(slot) => Some(&slot.bukkit),
+                }
+            }
+            StatementNodeValueOption::KTHXBYEStatement(token)
+            | StatementNodeValueOption::GTFOStatement(token)
+            | StatementNodeValueOption::FLUSHZStatement(token) => Some(token),
+            StatementNodeValueOption::VisibleStatement(visible) => {
+                match visible.expressions.first() {
+                    Some(expression) => expression.token(),
+                    None => visible.exclamation.as_ref(),
+                }
+            }
+            StatementNodeValueOption::GimmehStatement(gimmeh) => Some(&gimmeh.identifier),
+            StatementNodeValueOption::AssureStatement(assure) => Some(&assure.token),
+            StatementNodeValueOption::DumpzStatement(dumpz) => Some(&dumpz.token),
+            StatementNodeValueOption::IfStatement(if_stmt) => Some(&if_stmt.token),
+            StatementNodeValueOption::SwitchStatement(switch) => Some(&switch.token),
+            StatementNodeValueOption::BukkitSlotDeclarationStatement(slot_dec) => {
+                Some(&slot_dec.slot.bukkit)
+            }
+            StatementNodeValueOption::WatchinLoopStatement(loop_stmt) => Some(&loop_stmt.label),
+            StatementNodeValueOption::LoopStatement(loop_stmt) => Some(&loop_stmt.label),
+            StatementNodeValueOption::ReturnStatement(return_stmt) => {
+                return_stmt.expression.token()
+            }
+            StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                Some(&function.identifier)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ExpressionNodeValueOption {
     NumberValue(NumberValueNode),
@@ -52,6 +103,8 @@ pub enum ExpressionNodeValueOption {
     ModExpression(ModExpressionNode),
     BiggrExpression(BiggrExpressionNode),
     SmallrExpression(SmallrExpressionNode),
+    FurstExpression(FurstExpressionNode),
+    LatrExpression(LatrExpressionNode),
     BothOfExpression(BothOfExpressionNode),
     EitherOfExpression(EitherOfExpressionNode),
     WonOfExpression(WonOfExpressionNode),
@@ -63,6 +116,16 @@ pub enum ExpressionNodeValueOption {
     SmooshExpression(SmooshExpressionNode),
     MaekExpression(MaekExpressionNode),
     ItReference(ItReferenceNode),
+    BukkitSlot(BukkitSlotNode),
+    KopyExpression(KopyExpressionNode),
+    LengthzExpression(LengthzExpressionNode),
+    CodezExpression(CodezExpressionNode),
+    LettarExpression(LettarExpressionNode),
+    SplitzExpression(SplitzExpressionNode),
+    JoinzExpression(JoinzExpressionNode),
+    MatchezExpression(MatchezExpressionNode),
+    WunOfExpression(WunOfExpressionNode),
+    FunctionCallExpression(FunctionCallExpressionNode),
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +133,137 @@ pub struct ExpressionNode {
     pub value: ExpressionNodeValueOption,
 }
 
+impl ExpressionNode {
+    /// The first token the expression kept, keywords such as SUM OF are not
+    pub fn token(&self) -> Option<&TokenNode> {
+        match &self.value {
+            ExpressionNodeValueOption::NumberValue(value) => Some(&value.token),
+            ExpressionNodeValueOption::NumbarValue(value) => Some(&value.token),
+            ExpressionNodeValueOption::YarnValue(value) => Some(&value.token),
+            ExpressionNodeValueOption::TroofValue(value) => Some(&value.token),
+            ExpressionNodeValueOption::VariableReference(var_ref) => Some(&var_ref.identifier),
+            ExpressionNodeValueOption::ItReference(it) => Some(&it.token),
+            ExpressionNodeValueOption::BukkitSlot(slot) => Some(&slot.bukkit),
+            ExpressionNodeValueOption::SumExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::DiffExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::ProduktExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::QuoshuntExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::ModExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::BiggrExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::SmallrExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::FurstExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::LatrExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::BothOfExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::EitherOfExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::WonOfExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::BothSaemExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::DiffrintExpression(e) => e.left.token(),
+            ExpressionNodeValueOption::NotExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::KopyExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::LengthzExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::CodezExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::LettarExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::SplitzExpression(e) => e.yarn.token(),
+            ExpressionNodeValueOption::JoinzExpression(e) => e.bukkit.token(),
+            ExpressionNodeValueOption::MatchezExpression(e) => e.yarn.token(),
+            ExpressionNodeValueOption::MaekExpression(e) => e.expression.token(),
+            ExpressionNodeValueOption::WunOfExpression(e) => e.condition.token(),
+            ExpressionNodeValueOption::AllOfExpression(e) => e.expressions.first()?.token(),
+            ExpressionNodeValueOption::AnyOfExpression(e) => e.expressions.first()?.token(),
+            ExpressionNodeValueOption::SmooshExpression(e) => e.expressions.first()?.token(),
+            ExpressionNodeValueOption::FunctionCallExpression(call) => Some(&call.identifier),
+        }
+    }
+
+    /// The expressions this one is made of, in the order they are written
+    pub fn operands(&self) -> Vec<&ExpressionNode> {
+        match &self.value {
+            ExpressionNodeValueOption::NumberValue(_)
+            | ExpressionNodeValueOption::NumbarValue(_)
+            | ExpressionNodeValueOption::YarnValue(_)
+            | ExpressionNodeValueOption::TroofValue(_)
+            | ExpressionNodeValueOption::VariableReference(_)
+            | ExpressionNodeValueOption::ItReference(_)
+            | ExpressionNodeValueOption::BukkitSlot(_) => vec![],
+            ExpressionNodeValueOption::SumExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::DiffExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::ProduktExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::QuoshuntExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::ModExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::BiggrExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::SmallrExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::FurstExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::LatrExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::BothOfExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::EitherOfExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::WonOfExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::BothSaemExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::DiffrintExpression(e) => vec![&e.left, &e.right],
+            ExpressionNodeValueOption::NotExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::KopyExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::LengthzExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::CodezExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::LettarExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::MaekExpression(e) => vec![&e.expression],
+            ExpressionNodeValueOption::SplitzExpression(e) => vec![&e.yarn, &e.separator],
+            ExpressionNodeValueOption::JoinzExpression(e) => vec![&e.bukkit, &e.separator],
+            ExpressionNodeValueOption::MatchezExpression(e) => vec![&e.yarn, &e.pattern],
+            ExpressionNodeValueOption::WunOfExpression(e) => vec![&e.condition, &e.then, &e.else_],
+            ExpressionNodeValueOption::AllOfExpression(e) => e.expressions.iter().collect(),
+            ExpressionNodeValueOption::AnyOfExpression(e) => e.expressions.iter().collect(),
+            ExpressionNodeValueOption::SmooshExpression(e) => e.expressions.iter().collect(),
+            ExpressionNodeValueOption::FunctionCallExpression(call) => {
+                call.arguments.iter().collect()
+            }
+        }
+    }
+
+    /// operands, to rewrite them in place
+    pub fn operands_mut(&mut self) -> Vec<&mut ExpressionNode> {
+        match &mut self.value {
+            ExpressionNodeValueOption::NumberValue(_)
+            | ExpressionNodeValueOption::NumbarValue(_)
+            | ExpressionNodeValueOption::YarnValue(_)
+            | ExpressionNodeValueOption::TroofValue(_)
+            | ExpressionNodeValueOption::VariableReference(_)
+            | ExpressionNodeValueOption::ItReference(_)
+            | ExpressionNodeValueOption::BukkitSlot(_) => vec![],
+            ExpressionNodeValueOption::SumExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::DiffExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::ProduktExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::QuoshuntExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::ModExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::BiggrExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::SmallrExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::FurstExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::LatrExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::BothOfExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::EitherOfExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::WonOfExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::BothSaemExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::DiffrintExpression(e) => vec![&mut e.left, &mut e.right],
+            ExpressionNodeValueOption::NotExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::KopyExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::LengthzExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::CodezExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::LettarExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::MaekExpression(e) => vec![&mut e.expression],
+            ExpressionNodeValueOption::SplitzExpression(e) => vec![&mut e.yarn, &mut e.separator],
+            ExpressionNodeValueOption::JoinzExpression(e) => vec![&mut e.bukkit, &mut e.separator],
+            ExpressionNodeValueOption::MatchezExpression(e) => vec![&mut e.yarn, &mut e.pattern],
+            ExpressionNodeValueOption::WunOfExpression(e) => {
+                vec![&mut e.condition, &mut e.then, &mut e.else_]
+            }
+            ExpressionNodeValueOption::AllOfExpression(e) => e.expressions.iter_mut().collect(),
+            ExpressionNodeValueOption::AnyOfExpression(e) => e.expressions.iter_mut().collect(),
+            ExpressionNodeValueOption::SmooshExpression(e) => e.expressions.iter_mut().collect(),
+            ExpressionNodeValueOption::FunctionCallExpression(call) => {
+                call.arguments.iter_mut().collect()
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NumberValueNode {
     pub token: TokenNode,
@@ -181,6 +375,18 @@ pub struct SmallrExpressionNode {
     pub right: Box<ExpressionNode>,
 }
 
+#[derive(Debug, Clone)]
+pub struct FurstExpressionNode {
+    pub left: Box<ExpressionNode>,
+    pub right: Box<ExpressionNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LatrExpressionNode {
+    pub left: Box<ExpressionNode>,
+    pub right: Box<ExpressionNode>,
+}
+
 #[derive(Debug, Clone)]
 pub struct BothOfExpressionNode {
     pub left: Box<ExpressionNode>,
@@ -204,6 +410,59 @@ pub struct NotExpressionNode {
     pub expression: Box<ExpressionNode>,
 }
 
+#[derive(Debug, Clone)]
+pub struct KopyExpressionNode {
+    pub expression: Box<ExpressionNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LengthzExpressionNode {
+    pub expression: Box<ExpressionNode>,
+}
+
+/// `CODEZ OF yarn`, the character code of a YARN of one letter
+#[derive(Debug, Clone)]
+pub struct CodezExpressionNode {
+    pub expression: Box<ExpressionNode>,
+}
+
+/// `LETTAR OF number`, the YARN of one letter with that character code
+#[derive(Debug, Clone)]
+pub struct LettarExpressionNode {
+    pub expression: Box<ExpressionNode>,
+}
+
+/// `SPLITZ yarn ON separator`, a BUKKIT of the pieces between separators
+#[derive(Debug, Clone)]
+pub struct SplitzExpressionNode {
+    pub yarn: Box<ExpressionNode>,
+    pub separator: Box<ExpressionNode>,
+}
+
+/// `JOINZ bukkit WIF separator`, the YARNs of a BUKKIT with the separator
+/// between each
+#[derive(Debug, Clone)]
+pub struct JoinzExpressionNode {
+    pub bukkit: Box<ExpressionNode>,
+    pub separator: Box<ExpressionNode>,
+}
+
+/// `MATCHEZ yarn AGENST pattern`, whether the whole YARN matches a glob where
+/// `*` stands for any letters and `?` for any one letter
+#[derive(Debug, Clone)]
+pub struct MatchezExpressionNode {
+    pub yarn: Box<ExpressionNode>,
+    pub pattern: Box<ExpressionNode>,
+}
+
+/// `WUN OF condition DEN then ELSS else_`, only the chosen branch is evaluated
+#[derive(Debug, Clone)]
+pub struct WunOfExpressionNode {
+    pub condition: Box<ExpressionNode>,
+    pub then: Box<ExpressionNode>,
+    pub else_: Box<ExpressionNode>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AllOfExpressionNode {
     pub expressions: Vec<ExpressionNode>,
@@ -242,6 +501,15 @@ pub struct ItReferenceNode {
     pub token: TokenNode,
 }
 
+/// `bukkit'Z slot`, or `bukkit'Z inner'Z slot` for a slot of a nested bukkit
+#[derive(Debug, Clone)]
+pub struct BukkitSlotNode {
+    pub bukkit: TokenNode,
+    /// slots holding the nested bukkits on the way to `slot`
+    pub path: Vec<TokenNode>,
+    pub slot: TokenNode,
+}
+
 #[derive(Debug, Clone)]
 pub struct FunctionCallExpressionNode {
     pub identifier: TokenNode,
@@ -258,6 +526,7 @@ pub struct VariableDeclarationStatementNode {
 pub enum VariableAssignmentNodeVariableOption {
     Identifier(TokenNode),
     VariableDeclerationStatement(VariableDeclarationStatementNode),
+    BukkitSlot(BukkitSlotNode),
 }
 
 #[derive(Debug, Clone)]
@@ -266,6 +535,13 @@ pub struct VariableAssignmentStatementNode {
     pub expression: ExpressionNode,
 }
 
+/// `bukkit HAS A slot ITZ expression`
+#[derive(Debug, Clone)]
+pub struct BukkitSlotDeclarationStatementNode {
+    pub slot: BukkitSlotNode,
+    pub expression: ExpressionNode,
+}
+
 #[derive(Debug, Clone)]
 pub struct VisibleStatementNode {
     pub expressions: Vec<ExpressionNode>,
@@ -274,9 +550,26 @@ pub struct VisibleStatementNode {
 
 #[derive(Debug, Clone)]
 pub struct GimmehStatementNode {
+    /// LETTAR or LINE, a plain GIMMEH reads a line
+    pub mode: Option<TokenNode>,
     pub identifier: TokenNode,
 }
 
+#[derive(Debug, Clone)]
+pub struct AssureStatementNode {
+    /// the ASSURE keyword, whose line a failure reports
+    pub token: TokenNode,
+    pub expression: ExpressionNode,
+    /// the YARN after MKAY SAYIN, printed when the assertion fails
+    pub message: Option<ExpressionNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DumpzStatementNode {
+    pub token: TokenNode,
+    pub expression: ExpressionNode,
+}
+
 #[derive(Debug, Clone)]
 pub struct ElseIfStatementNode {
     pub expression: ExpressionNode,
@@ -285,6 +578,8 @@ pub struct ElseIfStatementNode {
 
 #[derive(Debug, Clone)]
 pub struct IfStatementNode {
+    /// the O RLY keyword, where a condition IT cannot be is reported
+    pub token: TokenNode,
     pub statements: Vec<StatementNode>,
     pub else_ifs: Vec<ElseIfStatementNode>,
     pub else_: Option<Vec<StatementNode>>,
@@ -298,6 +593,8 @@ pub struct SwitchCaseStatementNode {
 
 #[derive(Debug, Clone)]
 pub struct SwitchStatementNode {
+    /// the WTF keyword, where the errors of the switch as a whole are reported
+    pub token: TokenNode,
     pub cases: Vec<SwitchCaseStatementNode>,
     pub default: Option<Vec<StatementNode>>,
 }
@@ -305,13 +602,22 @@ pub struct SwitchStatementNode {
 #[derive(Debug, Clone)]
 pub struct LoopStatementNode {
     pub label: TokenNode,
-    pub operation: TokenNode,
-    pub variable: TokenNode,
+    /// UPPIN or NERFIN and the variable it counts, None for a loop without
+    pub counter: Option<(TokenNode, TokenNode)>,
     pub condition: Option<TokenNode>,
     pub condition_expression: Option<ExpressionNode>,
     pub statements: Vec<StatementNode>,
 }
 
+/// `IM IN YR label WATCHIN YR item IN bukkit`, runs once per slot of the bukkit
+#[derive(Debug, Clone)]
+pub struct WatchinLoopStatementNode {
+    pub label: TokenNode,
+    pub item: TokenNode,
+    pub bukkit: ExpressionNode,
+    pub statements: Vec<StatementNode>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ReturnStatementNode {
     pub expression: ExpressionNode,
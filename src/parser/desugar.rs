@@ -0,0 +1,239 @@
+//! Rewrites the sugar of a parsed program into the smaller core the visitor
+//! lowers, after lints have seen it as it was written. A declaration given a
+//! value with `ITZ type R expression` becomes the declaration and then an
+//! assignment, and each MEBBE an If in the NO WAI of the one before, so that
+//! the visitor only knows plain declarations and Ifs with one else branch. A
+//! NOT of a BOTH SAEM or DIFFRINT becomes the opposite comparison, which does
+//! not have to be negated, however many NOTs there are.
+//!
+//! The statements made keep the tokens of the ones they came from, so errors
+//! still point at what was written. The second statement of a split one keeps
+//! the token of the first, which is how the visitor knows not to trace it.
+
+use crate::parser::ast;
+
+/// The program with its sugar rewritten
+pub fn desugar(program: ast::ProgramNode) -> ast::ProgramNode {
+    let mut statements = block(program.statements);
+    negations(&mut statements);
+    ast::ProgramNode { statements }
+}
+
+fn block(statements: Vec<ast::StatementNode>) -> Vec<ast::StatementNode> {
+    statements.into_iter().flat_map(statement).collect()
+}
+
+fn statement(statement: ast::StatementNode) -> Vec<ast::StatementNode> {
+    use ast::StatementNodeValueOption as S;
+
+    let value = match statement.value {
+        S::VariableAssignmentStatement(var_assign) => match var_assign.variable {
+            ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(var_dec) => {
+                let identifier = var_dec.identifier.clone();
+                return vec![
+                    ast::StatementNode {
+                        value: S::VariableDeclarationStatement(var_dec),
+                    },
+                    ast::StatementNode {
+                        value: S::VariableAssignmentStatement(
+                            ast::VariableAssignmentStatementNode {
+                                variable: ast::VariableAssignmentNodeVariableOption::Identifier(
+                                    identifier,
+                                ),
+                                expression: var_assign.expression,
+                            },
+                        ),
+                    },
+                ];
+            }
+            variable => S::VariableAssignmentStatement(ast::VariableAssignmentStatementNode {
+                variable,
+                expression: var_assign.expression,
+            }),
+        },
+        S::IfStatement(if_stmt) => S::IfStatement(if_statement(if_stmt)),
+        S::SwitchStatement(switch) => S::SwitchStatement(ast::SwitchStatementNode {
+            token: switch.token,
+            cases: switch
+                .cases
+                .into_iter()
+                .map(|case| ast::SwitchCaseStatementNode {
+                    expression: case.expression,
+                    statements: block(case.statements),
+                })
+                .collect(),
+            default: switch.default.map(block),
+        }),
+        S::WatchinLoopStatement(loop_stmt) => {
+            S::WatchinLoopStatement(ast::WatchinLoopStatementNode {
+                statements: block(loop_stmt.statements),
+                ..loop_stmt
+            })
+        }
+        S::LoopStatement(loop_stmt) => S::LoopStatement(ast::LoopStatementNode {
+            statements: block(loop_stmt.statements),
+            ..loop_stmt
+        }),
+        S::FunctionDefinitionStatement(function) => {
+            S::FunctionDefinitionStatement(ast::FunctionDefinitionStatementNode {
+                statements: block(function.statements),
+                ..function
+            })
+        }
+        value => value,
+    };
+    vec![ast::StatementNode { value }]
+}
+
+/// An If with no MEBBEs: the last MEBBE becomes an If in the else branch,
+/// after its expression is given to IT, and so on back to the first. The If
+/// a MEBBE becomes reports a BUKKIT IT at the MEBBE's expression.
+fn if_statement(if_stmt: ast::IfStatementNode) -> ast::IfStatementNode {
+    let mut else_ = if_stmt.else_.map(block);
+    for else_if in if_stmt.else_ifs.into_iter().rev() {
+        let token = else_if.expression.token().unwrap_or(&if_stmt.token).clone();
+        else_ = Some(vec![
+            ast::StatementNode {
+                value: ast::StatementNodeValueOption::Expression(else_if.expression),
+            },
+            ast::StatementNode {
+                value: ast::StatementNodeValueOption::IfStatement(ast::IfStatementNode {
+                    token,
+                    statements: block(else_if.statements),
+                    else_ifs: vec![],
+                    else_,
+                }),
+            },
+        ]);
+    }
+
+    ast::IfStatementNode {
+        token: if_stmt.token,
+        statements: block(if_stmt.statements),
+        else_ifs: vec![],
+        else_,
+    }
+}
+
+/// The expressions a statement holds itself, leaving out the statements of
+/// its blocks
+fn expressions_mut(value: &mut ast::StatementNodeValueOption) -> Vec<&mut ast::ExpressionNode> {
+    use ast::StatementNodeValueOption as S;
+
+    match value {
+        S::Expression(expression) => vec![expression],
+        S::VariableAssignmentStatement(var_assign) => vec![&mut var_assign.expression],
+        S::VisibleStatement(visible) => visible.expressions.iter_mut().collect(),
+        S::IfStatement(if_stmt) => if_stmt
+            .else_ifs
+            .iter_mut()
+            .map(|else_if| &mut else_if.expression)
+            .collect(),
+        S::SwitchStatement(switch) => switch
+            .cases
+            .iter_mut()
+            .map(|case| &mut case.expression)
+            .collect(),
+        S::AssureStatement(assure) => {
+            let mut expressions = vec![&mut assure.expression];
+            expressions.extend(assure.message.as_mut());
+            expressions
+        }
+        S::DumpzStatement(dumpz) => vec![&mut dumpz.expression],
+        S::BukkitSlotDeclarationStatement(slot) => vec![&mut slot.expression],
+        S::WatchinLoopStatement(loop_stmt) => vec![&mut loop_stmt.bukkit],
+        S::LoopStatement(loop_stmt) => loop_stmt.condition_expression.iter_mut().collect(),
+        S::ReturnStatement(return_stmt) => vec![&mut return_stmt.expression],
+        S::VariableDeclarationStatement(_)
+        | S::KTHXBYEStatement(_)
+        | S::GimmehStatement(_)
+        | S::GTFOStatement(_)
+        | S::FLUSHZStatement(_)
+        | S::FunctionDefinitionStatement(_) => vec![],
+    }
+}
+
+/// The blocks of statements nested in a statement
+fn blocks_mut(value: &mut ast::StatementNodeValueOption) -> Vec<&mut Vec<ast::StatementNode>> {
+    use ast::StatementNodeValueOption as S;
+
+    match value {
+        S::IfStatement(if_stmt) => {
+            let mut blocks = vec![&mut if_stmt.statements];
+            blocks.extend(
+                if_stmt
+                    .else_ifs
+                    .iter_mut()
+                    .map(|else_if| &mut else_if.statements),
+            );
+            blocks.extend(if_stmt.else_.as_mut());
+            blocks
+        }
+        S::SwitchStatement(switch) => {
+            let mut blocks = switch
+                .cases
+                .iter_mut()
+                .map(|case| &mut case.statements)
+                .collect::<Vec<&mut Vec<ast::StatementNode>>>();
+            blocks.extend(switch.default.as_mut());
+            blocks
+        }
+        S::WatchinLoopStatement(loop_stmt) => vec![&mut loop_stmt.statements],
+        S::LoopStatement(loop_stmt) => vec![&mut loop_stmt.statements],
+        S::FunctionDefinitionStatement(function) => vec![&mut function.statements],
+        _ => vec![],
+    }
+}
+
+/// Rewrites every NOT of a comparison in the statements and their blocks
+fn negations(statements: &mut [ast::StatementNode]) {
+    for statement in statements.iter_mut() {
+        for expression in expressions_mut(&mut statement.value) {
+            negation(expression);
+        }
+        for block in blocks_mut(&mut statement.value) {
+            negations(block);
+        }
+    }
+}
+
+/// A NOT of a BOTH SAEM becomes a DIFFRINT and the other way around. The
+/// operands go first, so a NOT NOT of a comparison is the comparison itself.
+fn negation(expression: &mut ast::ExpressionNode) {
+    use ast::ExpressionNodeValueOption as E;
+
+    for operand in expression.operands_mut() {
+        negation(operand);
+    }
+
+    let comparison = match &expression.value {
+        E::NotExpression(not) => matches!(
+            not.expression.value,
+            E::BothSaemExpression(_) | E::DiffrintExpression(_)
+        ),
+        _ => false,
+    };
+    if !comparison {
+        return;
+    }
+
+    // a placeholder while the NOT is taken apart, which has no tokens
+    let empty = E::AllOfExpression(ast::AllOfExpressionNode {
+        expressions: vec![],
+    });
+    let not = match std::mem::replace(&mut expression.value, empty) {
+        E::NotExpression(not) => not,
+        _ => unreachable!(),
+    };
+    expression.value = match not.expression.value {
+        E::BothSaemExpression(e) => E::DiffrintExpression(ast::DiffrintExpressionNode {
+            left: e.left,
+            right: e.right,
+        }),
+        E::DiffrintExpression(e) => E::BothSaemExpression(ast::BothSaemExpressionNode {
+            left: e.left,
+            right: e.right,
+        }),
+        _ => unreachable!(),
+    };
+}
@@ -0,0 +1,460 @@
+use crate::lexer::lexer::LexedToken;
+
+/// The kinds of statement, each parsed by its own function of the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Production {
+    VariableDeclaration,
+    BukkitSlotDeclaration,
+    VariableAssignment,
+    Kthxbye,
+    Visible,
+    Gimmeh,
+    Assure,
+    Dumpz,
+    If,
+    Switch,
+    Gtfo,
+    Flushz,
+    WatchinLoop,
+    Loop,
+    Return,
+    FunctionDefinition,
+    Expression,
+}
+
+impl Production {
+    /// How the statement is written, with <...> for the parts that vary
+    pub fn form(&self) -> &'static str {
+        match self {
+            Production::VariableDeclaration => "I HAS A <identifier> ITZ <type> [R <expression>]",
+            Production::BukkitSlotDeclaration => {
+                "<identifier>['Z <slot>]* HAS A <slot> ITZ <expression>"
+            }
+            Production::VariableAssignment => "<variable> R <expression>",
+            Production::Kthxbye => "KTHXBYE",
+            Production::Visible => "VISIBLE <expression> [AN <expression>]* [!]",
+            Production::Gimmeh => "GIMMEH [LETTAR | LINE] <identifier>",
+            Production::Assure => "ASSURE <expression> [MKAY SAYIN <expression>]",
+            Production::Dumpz => "DUMPZ <expression>",
+            Production::If => {
+                "O RLY? YA RLY <statements> [MEBBE <expression> <statements>]* [NO WAI <statements>] OIC"
+            }
+            Production::Switch => "WTF? [OMG <expression> <statements>]* [OMGWTF <statements>] OIC",
+            Production::Gtfo => "GTFO",
+            Production::Flushz => "FLUSHZ",
+            Production::WatchinLoop => {
+                "IM IN YR <label> WATCHIN YR <identifier> IN <expression> <statements> IM OUTTA YR <label>"
+            }
+            Production::Loop => {
+                "IM IN YR <label> [(UPPIN | NERFIN) YR <identifier> [(TIL | WILE) <expression>]] <statements> IM OUTTA YR <label>"
+            }
+            Production::Return => "FOUND YR <expression>",
+            Production::FunctionDefinition => {
+                "HOW IZ I <identifier> ITZ <type> [YR <identifier> ITZ <type> [AN]]* <statements> IF U SAY SO"
+            }
+            Production::Expression => "<expression>",
+        }
+    }
+
+    /// The name and EBNF of the rule the production parses, as --emit
+    /// grammar writes it. Change it with the function parsing the statement.
+    pub fn rule(&self) -> (&'static str, &'static str) {
+        match self {
+            Production::VariableDeclaration => (
+                "variable-declaration",
+                "\"I HAS A\", identifier, \"ITZ\", ( type | \"BUKKIT\" ), [ \"R\", expression ]",
+            ),
+            Production::BukkitSlotDeclaration => (
+                "slot-declaration",
+                "identifier, { \"'Z\", identifier }, \"HAS A\", identifier, \"ITZ\", expression",
+            ),
+            Production::VariableAssignment => (
+                "assignment",
+                "identifier, { \"'Z\", identifier }, \"R\", expression",
+            ),
+            Production::Kthxbye => ("kthxbye", "\"KTHXBYE\""),
+            Production::Visible => (
+                "visible",
+                "\"VISIBLE\", expression, { [ \"AN\" ], expression }, [ \"!\" ]",
+            ),
+            Production::Gimmeh => (
+                "gimmeh",
+                "\"GIMMEH\", [ \"LETTAR\" | \"LINE\" ], identifier",
+            ),
+            Production::Assure => (
+                "assure",
+                "\"ASSURE\", expression, [ \"MKAY\", \"SAYIN\", expression ]",
+            ),
+            Production::Dumpz => ("dumpz", "\"DUMPZ\", expression"),
+            Production::If => (
+                "if",
+                "\"O RLY\", \"?\", [ end ], \"YA RLY\", end, block, \
+                 { \"MEBBE\", expression, end, block }, [ \"NO WAI\", end, block ], \"OIC\"",
+            ),
+            Production::Switch => (
+                "switch",
+                "\"WTF\", \"?\", end, { \"OMG\", expression, end, block }, \
+                 [ \"OMGWTF\", end, block ], \"OIC\"",
+            ),
+            Production::Gtfo => ("gtfo", "\"GTFO\""),
+            Production::Flushz => ("flushz", "\"FLUSHZ\""),
+            Production::WatchinLoop => (
+                "watchin-loop",
+                "\"IM IN YR\", identifier, \"WATCHIN\", \"YR\", identifier, \"IN\", expression, end, \
+                 block, \"IM OUTTA YR\", identifier",
+            ),
+            Production::Loop => (
+                "loop",
+                "\"IM IN YR\", identifier, [ ( \"UPPIN\" | \"NERFIN\" ), \"YR\", identifier, \
+                 [ ( \"TIL\" | \"WILE\" ), expression ] ], end, block, \"IM OUTTA YR\", identifier",
+            ),
+            Production::Return => ("return", "\"FOUND YR\", expression"),
+            Production::FunctionDefinition => (
+                "function-definition",
+                "\"HOW IZ I\", identifier, \"ITZ\", ( type | \"NOOB\" ), \
+                 { \"YR\", identifier, \"ITZ\", type, [ \"AN\" ] }, end, block, \"IF U SAY SO\"",
+            ),
+            Production::Expression => ("expression-statement", "expression"),
+        }
+    }
+}
+
+/// Every production, in the order --emit grammar lists them
+pub const PRODUCTIONS: &[Production] = &[
+    Production::VariableDeclaration,
+    Production::BukkitSlotDeclaration,
+    Production::VariableAssignment,
+    Production::Kthxbye,
+    Production::Visible,
+    Production::Gimmeh,
+    Production::Assure,
+    Production::Dumpz,
+    Production::If,
+    Production::Switch,
+    Production::Gtfo,
+    Production::Flushz,
+    Production::WatchinLoop,
+    Production::Loop,
+    Production::Return,
+    Production::FunctionDefinition,
+    Production::Expression,
+];
+
+/// The expressions, in the order the parser tries them, by the name and
+/// EBNF of their rule. A list of operands is closed by MKAY or the end of
+/// the line.
+pub const EXPRESSIONS: &[(&str, &str)] = &[
+    ("number", "? NUMBER literal ?"),
+    ("numbar", "? NUMBAR literal ?"),
+    ("yarn", "? YARN literal ?"),
+    ("troof", "\"WIN\" | \"FAIL\""),
+    (
+        "slot",
+        "identifier, \"'Z\", identifier, { \"'Z\", identifier }",
+    ),
+    ("variable", "identifier"),
+    ("sum", "\"SUM OF\", binary"),
+    ("diff", "\"DIFF OF\", binary"),
+    ("produkt", "\"PRODUKT OF\", binary"),
+    ("quoshunt", "\"QUOSHUNT OF\", binary"),
+    ("mod", "\"MOD OF\", binary"),
+    ("biggr", "\"BIGGR OF\", binary"),
+    ("smallr", "\"SMALLR OF\", binary"),
+    ("furst", "\"FURST OF\", binary"),
+    ("latr", "\"LATR OF\", binary"),
+    ("both-of", "\"BOTH OF\", binary"),
+    ("either-of", "\"EITHER OF\", binary"),
+    ("won-of", "\"WON OF\", binary"),
+    (
+        "wun-of",
+        "\"WUN OF\", expression, \"DEN\", expression, \"ELSS\", expression",
+    ),
+    ("lengthz", "\"LENGTHZ OF\", expression"),
+    ("codez", "\"CODEZ OF\", expression"),
+    ("lettar", "\"LETTAR OF\", expression"),
+    ("splitz", "\"SPLITZ\", expression, \"ON\", expression"),
+    ("joinz", "\"JOINZ\", expression, \"WIF\", expression"),
+    ("matchez", "\"MATCHEZ\", expression, \"AGENST\", expression"),
+    ("kopy", "\"KOPY OF\", expression"),
+    ("not", "\"NOT\", expression"),
+    ("all-of", "\"ALL OF\", operands"),
+    ("any-of", "\"ANY OF\", operands"),
+    ("both-saem", "\"BOTH SAEM\", binary"),
+    ("diffrint", "\"DIFFRINT\", binary"),
+    ("smoosh", "\"SMOOSH\", operands"),
+    ("maek", "\"MAEK\", expression, \"A\", ( type | \"NOOB\" )"),
+    ("it", "\"IT\""),
+    (
+        "call",
+        "\"I IZ\", identifier, [ \"YR\", expression, { \"AN\", \"YR\", expression } ], \
+         ( \"MKAY\" | ? end of line ? )",
+    ),
+];
+
+/// The rules the statements and expressions are made of
+const SHARED: &[(&str, &str)] = &[
+    (
+        "program",
+        "\"HAI\", \"1.2\", end, { statement, end }, \"KTHXBYE\", [ end ]",
+    ),
+    ("block", "{ statement, end }"),
+    ("end", "\",\" | newline"),
+    ("binary", "expression, \"AN\", expression"),
+    (
+        "operands",
+        "expression, { \"AN\", expression }, ( \"MKAY\" | ? end of line ? )",
+    ),
+    ("type", "\"NUMBER\" | \"NUMBAR\" | \"YARN\" | \"TROOF\""),
+    (
+        "identifier",
+        "? letters, digits and underscores not starting with a digit, or any name in backticks ?",
+    ),
+    ("newline", "? a line break ?"),
+];
+
+/// The grammar the parser accepts as ISO EBNF, one rule a line
+pub fn ebnf() -> String {
+    let alternatives = |names: Vec<&str>| names.join("\n    | ");
+    let mut out =
+        String::from("(* The LOLCODE the parser accepts, from src/parser/grammar.rs *)\n\n");
+    out.push_str(&format!("{} = {} ;\n", SHARED[0].0, SHARED[0].1));
+    out.push_str(&format!(
+        "statement = {} ;\n",
+        alternatives(PRODUCTIONS.iter().map(|p| p.rule().0).collect())
+    ));
+    for (name, rule) in PRODUCTIONS.iter().map(|p| p.rule()) {
+        out.push_str(&format!("{} = {} ;\n", name, rule));
+    }
+    out.push_str(&format!(
+        "expression = {} ;\n",
+        alternatives(EXPRESSIONS.iter().map(|(name, _)| *name).collect())
+    ));
+    for (name, rule) in EXPRESSIONS.iter().chain(&SHARED[1..]) {
+        out.push_str(&format!("{} = {} ;\n", name, rule));
+    }
+    out
+}
+
+/// The statements that start with a keyword, by the name of its token. Only
+/// the two loops share one, IM IN YR, told apart by whether WATCHIN follows
+/// the label; otherwise the keyword alone picks the production.
+pub const KEYWORD_STATEMENTS: &[(&str, Production)] = &[
+    ("Word_I HAS A", Production::VariableDeclaration),
+    ("Word_KTHXBYE", Production::Kthxbye),
+    ("Word_VISIBLE", Production::Visible),
+    ("Word_GIMMEH", Production::Gimmeh),
+    ("Word_ASSURE", Production::Assure),
+    ("Word_DUMPZ", Production::Dumpz),
+    ("Word_O RLY", Production::If),
+    ("Word_WTF", Production::Switch),
+    ("Word_GTFO", Production::Gtfo),
+    ("Word_FLUSHZ", Production::Flushz),
+    ("Word_IM IN YR", Production::Loop),
+    ("Word_FOUND YR", Production::Return),
+    ("Word_HOW IZ I", Production::FunctionDefinition),
+];
+
+/// The production the statement at the start of `tokens` has to be parsed
+/// with. A statement starting with an identifier is a slot declaration or
+/// an assignment by the token after the identifier and its slot path, and
+/// an expression otherwise, as is any statement not starting with a keyword
+/// of KEYWORD_STATEMENTS.
+pub fn statement_production(tokens: &[LexedToken]) -> Production {
+    let first = match tokens.first() {
+        Some(first) => &first.token,
+        None => return Production::Expression,
+    };
+
+    if first.has_name("Identifier") {
+        let mut i = 1;
+        while tokens
+            .get(i)
+            .is_some_and(|token| token.token.has_name("Word_'Z"))
+            && tokens
+                .get(i + 1)
+                .is_some_and(|token| token.token.has_name("Identifier"))
+        {
+            i += 2;
+        }
+
+        return match tokens.get(i) {
+            Some(token) if token.token.has_name("Word_HAS A") => Production::BukkitSlotDeclaration,
+            Some(token) if token.token.has_name("Word_R") => Production::VariableAssignment,
+            _ => Production::Expression,
+        };
+    }
+
+    if first.has_name("Word_IM IN YR")
+        && tokens
+            .get(2)
+            .is_some_and(|token| token.token.has_name("Word_WATCHIN"))
+    {
+        return Production::WatchinLoop;
+    }
+
+    KEYWORD_STATEMENTS
+        .iter()
+        .find(|(name, _)| first.has_name(name))
+        .map_or(Production::Expression, |(_, production)| *production)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::ast::StatementNodeValueOption;
+    use crate::parser::parser::{Parser, ParserReturn};
+
+    /// A statement of each production and the variant it parses to, which
+    /// a new production has to be given
+    fn example(production: Production) -> (&'static str, &'static str) {
+        match production {
+            Production::VariableDeclaration => {
+                ("I HAS A x ITZ NUMBER", "VariableDeclarationStatement")
+            }
+            Production::BukkitSlotDeclaration => {
+                ("b'Z c HAS A k ITZ 1", "BukkitSlotDeclarationStatement")
+            }
+            Production::VariableAssignment => ("x R 1", "VariableAssignmentStatement"),
+            Production::Kthxbye => ("KTHXBYE", "KTHXBYEStatement"),
+            Production::Visible => ("VISIBLE \"a\" AN 1!", "VisibleStatement"),
+            Production::Gimmeh => ("GIMMEH LINE x", "GimmehStatement"),
+            Production::Assure => ("ASSURE WIN MKAY SAYIN \"no\"", "AssureStatement"),
+            Production::Dumpz => ("DUMPZ 1", "DumpzStatement"),
+            Production::If => (
+                "O RLY?\nYA RLY\nVISIBLE 1\nMEBBE WIN\nVISIBLE 2\nNO WAI\nVISIBLE 3\nOIC",
+                "IfStatement",
+            ),
+            Production::Switch => (
+                "WTF?\nOMG 1\nGTFO\nOMGWTF\nVISIBLE 1\nOIC",
+                "SwitchStatement",
+            ),
+            Production::Gtfo => ("GTFO", "GTFOStatement"),
+            Production::Flushz => ("FLUSHZ", "FLUSHZStatement"),
+            Production::WatchinLoop => (
+                "IM IN YR l WATCHIN YR v IN b\nVISIBLE v\nIM OUTTA YR l",
+                "WatchinLoopStatement",
+            ),
+            Production::Loop => (
+                "IM IN YR l NERFIN YR i WILE DIFFRINT i AN -3\nVISIBLE i\nIM OUTTA YR l",
+                "LoopStatement",
+            ),
+            Production::Return => ("FOUND YR 1", "ReturnStatement"),
+            Production::FunctionDefinition => (
+                "HOW IZ I f ITZ NUMBER YR a ITZ NUMBER AN YR b ITZ YARN\nFOUND YR a\nIF U SAY SO",
+                "FunctionDefinitionStatement",
+            ),
+            Production::Expression => ("SUM OF 1 AN 2", "Expression"),
+        }
+    }
+
+    /// An expression of each rule of EXPRESSIONS and the variant it parses to
+    const EXPRESSION_EXAMPLES: &[(&str, &str, &str)] = &[
+        ("number", "1", "NumberValue"),
+        ("numbar", "1.5", "NumbarValue"),
+        ("yarn", "\"a\"", "YarnValue"),
+        ("troof", "WIN", "TroofValue"),
+        ("slot", "b'Z c'Z k", "BukkitSlot"),
+        ("variable", "x", "VariableReference"),
+        ("sum", "SUM OF 1 AN 2", "SumExpression"),
+        ("diff", "DIFF OF 1 AN 2", "DiffExpression"),
+        ("produkt", "PRODUKT OF 1 AN 2", "ProduktExpression"),
+        ("quoshunt", "QUOSHUNT OF 1 AN 2", "QuoshuntExpression"),
+        ("mod", "MOD OF 1 AN 2", "ModExpression"),
+        ("biggr", "BIGGR OF 1 AN 2", "BiggrExpression"),
+        ("smallr", "SMALLR OF 1 AN 2", "SmallrExpression"),
+        ("furst", "FURST OF 1 AN 2", "FurstExpression"),
+        ("latr", "LATR OF 1 AN 2", "LatrExpression"),
+        ("both-of", "BOTH OF WIN AN FAIL", "BothOfExpression"),
+        ("either-of", "EITHER OF WIN AN FAIL", "EitherOfExpression"),
+        ("won-of", "WON OF WIN AN FAIL", "WonOfExpression"),
+        ("wun-of", "WUN OF WIN DEN 1 ELSS 2", "WunOfExpression"),
+        ("lengthz", "LENGTHZ OF \"a\"", "LengthzExpression"),
+        ("codez", "CODEZ OF \"a\"", "CodezExpression"),
+        ("lettar", "LETTAR OF 65", "LettarExpression"),
+        ("splitz", "SPLITZ \"a b\" ON \" \"", "SplitzExpression"),
+        ("joinz", "JOINZ b WIF \",\"", "JoinzExpression"),
+        ("matchez", "MATCHEZ \"a\" AGENST \"a\"", "MatchezExpression"),
+        ("kopy", "KOPY OF b", "KopyExpression"),
+        ("not", "NOT WIN", "NotExpression"),
+        ("all-of", "ALL OF WIN AN FAIL MKAY", "AllOfExpression"),
+        ("any-of", "ANY OF WIN AN FAIL", "AnyOfExpression"),
+        ("both-saem", "BOTH SAEM 1 AN 2", "BothSaemExpression"),
+        ("diffrint", "DIFFRINT 1 AN 2", "DiffrintExpression"),
+        ("smoosh", "SMOOSH \"a\" AN 1 MKAY", "SmooshExpression"),
+        ("maek", "MAEK 1 A YARN", "MaekExpression"),
+        ("it", "IT", "ItReference"),
+        ("call", "I IZ f YR 1 AN YR 2 MKAY", "FunctionCallExpression"),
+    ];
+
+    fn parse(statement: &str) -> ParserReturn<'static> {
+        let text = format!("HAI 1.2\n{}\nKTHXBYE\n", statement);
+        Parser::parse(Lexer::init(&text).get_tokens())
+    }
+
+    /// The name of the variant a node was printed with by Debug
+    fn variant(debug: String) -> String {
+        debug.split('(').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn every_production_is_picked_for_and_parses_its_example() {
+        for production in PRODUCTIONS {
+            let (text, expected) = example(*production);
+            let tokens = Lexer::init(text).get_tokens();
+            assert_eq!(statement_production(&tokens), *production, "{}", text);
+
+            let parsed = parse(text);
+            assert!(parsed.errors.is_empty(), "{}", text);
+            let first = format!("{:?}", parsed.ast.statements[0].value);
+            assert_eq!(variant(first), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn every_expression_rule_parses_its_example() {
+        let names = EXPRESSIONS
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        let examples = EXPRESSION_EXAMPLES
+            .iter()
+            .map(|(name, _, _)| *name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, examples);
+
+        for (_, text, expected) in EXPRESSION_EXAMPLES {
+            let parsed = parse(text);
+            assert!(parsed.errors.is_empty(), "{}", text);
+            match &parsed.ast.statements[0].value {
+                StatementNodeValueOption::Expression(expression) => {
+                    let value = format!("{:?}", expression.value);
+                    assert_eq!(variant(value), *expected, "{}", text);
+                }
+                other => panic!("{} parsed to {:?}", text, other),
+            }
+        }
+    }
+
+    #[test]
+    fn every_keyword_the_parser_accepts_is_in_the_grammar() {
+        let grammar = ebnf();
+        let parser = include_str!("parser.rs");
+        let keywords = parser
+            .split("\"Word_")
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap())
+            .chain(
+                KEYWORD_STATEMENTS
+                    .iter()
+                    .map(|(name, _)| name.strip_prefix("Word_").unwrap()),
+            );
+        for keyword in keywords {
+            assert!(
+                grammar.contains(&format!("\"{}\"", keyword)),
+                "{} is not in the grammar",
+                keyword
+            );
+        }
+    }
+}
@@ -1,2 +1,6 @@
 pub mod ast;
+pub mod desugar;
+pub mod grammar;
 pub mod parser;
+pub mod printer;
+pub mod syntax;
@@ -1,6 +1,14 @@
+use std::collections::HashMap;
+
+use crate::ice;
 use crate::lexer::lexer;
 use crate::lexer::tokens;
 use crate::parser::ast;
+use crate::parser::grammar::{self, Production};
+
+/// How many expressions may be nested in each other, past which parsing
+/// them could overflow the stack
+pub const MAX_EXPRESSION_DEPTH: usize = 100;
 
 #[derive(Debug, Clone)]
 pub struct ParserError<'a> {
@@ -14,6 +22,25 @@ pub struct ParserReturn<'a> {
     pub errors: Vec<ParserError<'a>>,
 }
 
+/// What parsing an expression at a token came to, so parse_expression does
+/// not parse it again
+#[derive(Debug, Clone)]
+pub enum ExpressionMemo<'a> {
+    Parsed,
+    /// the errors it made, with their levels relative to the level it was
+    /// parsed at
+    Failed(Vec<(ParserError<'a>, isize)>),
+}
+
+/// Where the parser was, for rewinding to after a failed attempt
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    current: usize,
+    level: usize,
+    mkay_depth: usize,
+    errors: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Parser<'a> {
     pub tokens: Vec<lexer::LexedToken>,
@@ -21,7 +48,17 @@ pub struct Parser<'a> {
     pub errors: Vec<ParserError<'a>>,
     pub levels: Vec<usize>,
     pub level: usize,
+    /// How many MKAY terminated expressions enclose the current token
+    pub mkay_depth: usize,
+    /// How many expressions enclose the current token
+    pub expression_depth: usize,
+    /// The keyword of the last ALL OF, ANY OF or SMOOSH, the token after
+    /// it and whether a MKAY closed it rather than the end of its line
+    pub last_list: Option<(lexer::LexedToken, usize, bool)>,
     pub stmts: Vec<ast::StatementNode>,
+    /// parse_expression's outcomes by token and mkay_depth, which together
+    /// decide how an expression parses
+    pub expression_memo: HashMap<(usize, usize), ExpressionMemo<'a>>,
 }
 
 impl<'a> Parser<'a> {
@@ -33,34 +70,18 @@ impl<'a> Parser<'a> {
             errors: Vec::new(),
             levels: Vec::new(),
             level: 0,
+            mkay_depth: 0,
+            expression_depth: 0,
+            last_list: None,
             stmts: Vec::new(),
+            expression_memo: HashMap::new(),
         };
 
         let program = p.parse_program();
 
-        let mut filtered_errors: Vec<ParserError<'a>> = Vec::new();
-        for (i, error) in p.errors.iter().enumerate() {
-            let mut found_match = false;
-            for (j, error2) in p.errors.iter().enumerate() {
-                if i == j {
-                    continue;
-                }
-
-                if (error2.token.index >= error.token.index && p.levels[j] == p.levels[i])
-                    || p.current > error.token.index
-                {
-                    found_match = true;
-                    break;
-                }
-            }
-            if !found_match {
-                filtered_errors.push(error.clone());
-            }
-        }
-
         ParserReturn {
             ast: program,
-            errors: filtered_errors,
+            errors: p.errors,
         }
     }
 
@@ -80,10 +101,22 @@ impl<'a> Parser<'a> {
 
 impl<'a> Parser<'a> {
     // Parser Functions
+    /// Records why the parse at the current level failed, unless a deeper
+    /// parse it called has already said why. Failed attempts are rewound
+    /// with their errors (see try_parse), so the errors left are the chain
+    /// of the one parse that failed.
     pub fn create_error(&mut self, parser_error: ParserError<'a>) {
+        if self.levels.last().is_none_or(|level| *level <= self.level) {
+            self.push_error(parser_error);
+        }
+        self.prev_level();
+    }
+
+    /// Records an error even if a deeper parse has already said why, for
+    /// the program's errors, which say which part of it failed
+    pub fn push_error(&mut self, parser_error: ParserError<'a>) {
         self.errors.push(parser_error);
         self.levels.push(self.level);
-        self.prev_level();
     }
 
     pub fn check(&mut self, token: tokens::Token) -> bool {
@@ -98,14 +131,14 @@ impl<'a> Parser<'a> {
     }
 
     pub fn special_check(&self, name: &str) -> bool {
-        if self.peek().token.to_name() == name.to_string() {
+        if self.peek().token.has_name(name) {
             return true;
         }
         false
     }
 
     pub fn special_check_amount(&self, name: &str, amount: usize) -> bool {
-        if self.peek_amount(amount).token.to_name() == name.to_string() {
+        if self.peek_amount(amount).token.has_name(name) {
             return true;
         }
         false
@@ -130,6 +163,36 @@ impl<'a> Parser<'a> {
         self.current = num;
     }
 
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            current: self.current,
+            level: self.level,
+            mkay_depth: self.mkay_depth,
+            errors: self.errors.len(),
+        }
+    }
+
+    /// Puts the parser back where it was at `snapshot`, dropping the errors
+    /// made since
+    pub fn rewind(&mut self, snapshot: Snapshot) {
+        self.current = snapshot.current;
+        self.level = snapshot.level;
+        self.mkay_depth = snapshot.mkay_depth;
+        self.errors.truncate(snapshot.errors);
+        self.levels.truncate(snapshot.errors);
+    }
+
+    /// Runs `parse` as an attempt that leaves no trace if it fails: the
+    /// parser is rewound to where it started and the errors it made dropped
+    pub fn try_parse<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let snapshot = self.snapshot();
+        let parsed = parse(self);
+        if parsed.is_none() {
+            self.rewind(snapshot);
+        }
+        parsed
+    }
+
     pub fn consume(&mut self, token: tokens::Token) -> Option<ast::TokenNode> {
         if self.check(token) {
             self.advance();
@@ -171,6 +234,8 @@ impl<'a> Parser<'a> {
     pub fn advance(&mut self) -> Option<lexer::LexedToken> {
         if !self.is_at_end() {
             self.current += 1;
+            let token = &self.tokens[self.current];
+            ice::set_token(Some((token.index, token.span())));
             return Some(self.peek());
         }
         None
@@ -199,10 +264,11 @@ impl<'a> Parser<'a> {
 
         let version = self.parse_numbar_value();
         if let None = version {
-            self.create_error(ParserError {
+            self.push_error(ParserError {
                 message: "Expected valid version numbar",
                 token: self.peek(),
             });
+            self.prev_level();
             return ast::ProgramNode {
                 statements: self.stmts.clone(),
             };
@@ -233,10 +299,11 @@ impl<'a> Parser<'a> {
         while !self.is_at_end() {
             let parsed_statement = self.parse_statement();
             if let None = parsed_statement {
-                self.create_error(ParserError {
+                self.push_error(ParserError {
                     message: "Expected valid statement line",
                     token: self.peek(),
                 });
+                self.prev_level();
                 return ast::ProgramNode {
                     statements: self.stmts.clone(),
                 };
@@ -272,231 +339,138 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the statement starting at the current token with the one
+    /// production its first tokens pick (see grammar::statement_production),
+    /// so a statement is parsed once and its errors come from that production
     pub fn parse_statement(&mut self) -> Option<ast::StatementNode> {
         self.next_level();
 
-        let variable_declaration_statement = self.parse_variable_declaration_statement();
-        if let Some(variable_declaration_statement) = variable_declaration_statement {
-            if !self.check_ending() && !self.special_check("Word_R") {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::VariableDeclarationStatement(
-                    variable_declaration_statement,
-                ),
-            });
-        }
+        use ast::StatementNodeValueOption as Statement;
+        let production = grammar::statement_production(&self.tokens[self.current..]);
+        let value = match production {
+            Production::VariableDeclaration => self.parse_declaration_statement(),
+            Production::BukkitSlotDeclaration => self
+                .parse_bukkit_slot_declaration_statement()
+                .map(Statement::BukkitSlotDeclarationStatement),
+            Production::VariableAssignment => self
+                .parse_variable_assignment_statement()
+                .map(Statement::VariableAssignmentStatement),
+            Production::Kthxbye => self
+                .special_consume("Word_KTHXBYE")
+                .map(Statement::KTHXBYEStatement),
+            Production::Visible => self
+                .parse_visible_statement()
+                .map(Statement::VisibleStatement),
+            Production::Gimmeh => self
+                .parse_gimmeh_statement()
+                .map(Statement::GimmehStatement),
+            Production::Assure => self
+                .parse_assure_statement()
+                .map(Statement::AssureStatement),
+            Production::Dumpz => self.parse_dumpz_statement().map(Statement::DumpzStatement),
+            Production::If => self.parse_if_statement().map(Statement::IfStatement),
+            Production::Switch => self
+                .parse_switch_statement()
+                .map(Statement::SwitchStatement),
+            Production::Gtfo => self
+                .special_consume("Word_GTFO")
+                .map(Statement::GTFOStatement),
+            Production::Flushz => self
+                .special_consume("Word_FLUSHZ")
+                .map(Statement::FLUSHZStatement),
+            Production::WatchinLoop => self
+                .parse_watchin_loop_statement()
+                .map(Statement::WatchinLoopStatement),
+            Production::Loop => self.parse_loop_statement().map(Statement::LoopStatement),
+            Production::Return => self
+                .parse_return_statement()
+                .map(Statement::ReturnStatement),
+            Production::FunctionDefinition => self
+                .parse_function_definition_statement()
+                .map(Statement::FunctionDefinitionStatement),
+            Production::Expression => self.parse_expression().map(Statement::Expression),
+        };
 
-        let variable_assignment_statement = self.parse_variable_assignment_statement();
-        if let Some(variable_assignment_statement) = variable_assignment_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
+        let value = match value {
+            Some(value) => value,
+            // a statement starting with a keyword is committed to its
+            // production, whose own errors say what went wrong
+            None if production != Production::Expression => {
                 self.prev_level();
                 return None;
             }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::VariableAssignmentStatement(
-                    variable_assignment_statement,
-                ),
-            });
-        }
-
-        let kthxbye_statement = self.special_consume("Word_KTHXBYE");
-        if let Some(kthxbye_statement) = kthxbye_statement {
-            if !self.check_ending() && !self.is_at_end() {
-                self.next_level();
+            None => {
                 self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
+                    message: "Expected valid statement or expression",
                     token: self.peek(),
                 });
-                self.prev_level();
                 return None;
             }
+        };
 
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::KTHXBYEStatement(kthxbye_statement),
-            });
-        }
-
-        let visible_statement = self.parse_visible_statement();
-        if let Some(visible_statement) = visible_statement {
+        let ended = match production {
             // visible checks for ending itself
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::VisibleStatement(visible_statement),
-            });
-        }
-
-        let gimmeh_statement = self.parse_gimmeh_statement();
-        if let Some(gimmeh_statement) = gimmeh_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::GimmehStatement(gimmeh_statement),
-            });
-        }
-
-        let if_statement = self.parse_if_statement();
-        if let Some(if_statement) = if_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::IfStatement(if_statement),
-            });
-        }
-
-        let switch_statement = self.parse_switch_statement();
-        if let Some(switch_statement) = switch_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::SwitchStatement(switch_statement),
-            });
-        }
-
-        let gtfo_statement = self.special_consume("Word_GTFO");
-        if let Some(gtfo_statement) = gtfo_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::GTFOStatement(gtfo_statement),
+            Production::Visible => true,
+            Production::Kthxbye => self.check_ending() || self.is_at_end(),
+            _ => self.check_ending(),
+        };
+        if !ended {
+            self.next_level();
+            self.create_error(ParserError {
+                message: "Expected comma or newline to end statement",
+                token: self.peek(),
             });
-        }
-
-        let loop_statement = self.parse_loop_statement();
-        if let Some(loop_statement) = loop_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
             self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::LoopStatement(loop_statement),
-            });
+            return None;
         }
 
-        let return_statement = self.parse_return_statement();
-        if let Some(return_statement) = return_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::ReturnStatement(return_statement),
-            });
-        }
+        self.prev_level();
+        Some(ast::StatementNode { value })
+    }
 
-        let function_definition_statement = self.parse_function_definition_statement();
-        if let Some(function_definition_statement) = function_definition_statement {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
+    /// Parses the expression at the current token. A failure is remembered
+    /// with its errors, which are made again without parsing when the same
+    /// tokens are tried as an expression again, e.g. after an enclosing
+    /// expression backtracks, so nested expressions are not parsed over and
+    /// over.
+    pub fn parse_expression(&mut self) -> Option<ast::ExpressionNode> {
+        let key = (self.current, self.mkay_depth);
+        if let Some(ExpressionMemo::Failed(errors)) = self.expression_memo.get(&key) {
+            for (error, level) in errors.clone() {
+                self.errors.push(error);
+                self.levels.push((self.level as isize + level) as usize);
             }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::FunctionDefinitionStatement(
-                    function_definition_statement,
-                ),
-            });
+            return None;
         }
 
-        let expression = self.parse_expression();
-        if let Some(expression) = expression {
-            if !self.check_ending() {
-                self.next_level();
-                self.create_error(ParserError {
-                    message: "Expected comma or newline to end statement",
-                    token: self.peek(),
-                });
-                self.prev_level();
-                return None;
-            }
-
-            self.prev_level();
-            return Some(ast::StatementNode {
-                value: ast::StatementNodeValueOption::Expression(expression),
+        if self.expression_depth == MAX_EXPRESSION_DEPTH {
+            self.next_level();
+            self.create_error(ParserError {
+                message: "Expression nested too deeply, split it up with variables",
+                token: self.peek(),
             });
+            return None;
         }
 
-        self.create_error(ParserError {
-            message: "Expected valid statement or expression",
-            token: self.peek(),
-        });
-        None
+        let (errors, level) = (self.errors.len(), self.level as isize);
+        self.expression_depth += 1;
+        let expression = self.parse_expression_alternatives();
+        self.expression_depth -= 1;
+        let memo = match expression {
+            Some(_) => ExpressionMemo::Parsed,
+            None => ExpressionMemo::Failed(
+                self.errors[errors..]
+                    .iter()
+                    .cloned()
+                    .zip(self.levels[errors..].iter().map(|l| *l as isize - level))
+                    .collect(),
+            ),
+        };
+        self.expression_memo.insert(key, memo);
+        expression
     }
 
-    pub fn parse_expression(&mut self) -> Option<ast::ExpressionNode> {
+    fn parse_expression_alternatives(&mut self) -> Option<ast::ExpressionNode> {
         if self.special_check("NumberValue") {
             if let Some(number_value) = self.parse_number_value() {
                 return Some(ast::ExpressionNode {
@@ -529,6 +503,15 @@ impl<'a> Parser<'a> {
             }
         }
 
+        if self.special_check("Identifier") && self.special_check_amount("Word_'Z", 1) {
+            // an identifier with a broken slot path is still a variable
+            if let Some(bukkit_slot) = self.try_parse(|p| p.parse_bukkit_slot_expression()) {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::BukkitSlot(bukkit_slot),
+                });
+            }
+        }
+
         if self.special_check("Identifier") {
             if let Some(variable_reference) = self.parse_variable_reference_expression() {
                 return Some(ast::ExpressionNode {
@@ -537,7 +520,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_SUM") {
+        if self.special_check("Word_SUM OF") {
             if let Some(sum_expression) = self.parse_sum_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::SumExpression(sum_expression),
@@ -545,7 +528,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_DIFF") {
+        if self.special_check("Word_DIFF OF") {
             if let Some(diff_expression) = self.parse_diff_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::DiffExpression(diff_expression),
@@ -553,7 +536,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_PRODUKT") {
+        if self.special_check("Word_PRODUKT OF") {
             if let Some(produkt_expression) = self.parse_produkt_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::ProduktExpression(produkt_expression),
@@ -561,7 +544,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_QUOSHUNT") {
+        if self.special_check("Word_QUOSHUNT OF") {
             if let Some(quoshunt_expression) = self.parse_quoshunt_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::QuoshuntExpression(quoshunt_expression),
@@ -569,7 +552,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_MOD") {
+        if self.special_check("Word_MOD OF") {
             if let Some(mod_expression) = self.parse_mod_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::ModExpression(mod_expression),
@@ -577,7 +560,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_BIGGR") {
+        if self.special_check("Word_BIGGR OF") {
             if let Some(biggr_expression) = self.parse_biggr_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::BiggrExpression(biggr_expression),
@@ -585,7 +568,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_SMALLR") {
+        if self.special_check("Word_SMALLR OF") {
             if let Some(smallr_expression) = self.parse_smallr_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::SmallrExpression(smallr_expression),
@@ -593,7 +576,23 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_BOTH") && self.special_check_amount("Word_OF", 1) {
+        if self.special_check("Word_FURST OF") {
+            if let Some(furst_expression) = self.parse_furst_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::FurstExpression(furst_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_LATR OF") {
+            if let Some(latr_expression) = self.parse_latr_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::LatrExpression(latr_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_BOTH OF") {
             if let Some(both_of_expression) = self.parse_both_of_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::BothOfExpression(both_of_expression),
@@ -601,7 +600,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_EITHER") {
+        if self.special_check("Word_EITHER OF") {
             if let Some(either_expression) = self.parse_either_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::EitherOfExpression(either_expression),
@@ -609,7 +608,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_WON") {
+        if self.special_check("Word_WON OF") {
             if let Some(won_expression) = self.parse_won_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::WonOfExpression(won_expression),
@@ -617,6 +616,70 @@ impl<'a> Parser<'a> {
             }
         }
 
+        if self.special_check("Word_WUN OF") {
+            if let Some(wun_of_expression) = self.parse_wun_of_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::WunOfExpression(wun_of_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_LENGTHZ OF") {
+            if let Some(lengthz_expression) = self.parse_lengthz_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::LengthzExpression(lengthz_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_CODEZ OF") {
+            if let Some(codez_expression) = self.parse_codez_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::CodezExpression(codez_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_LETTAR OF") {
+            if let Some(lettar_expression) = self.parse_lettar_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::LettarExpression(lettar_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_SPLITZ") {
+            if let Some(splitz_expression) = self.parse_splitz_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::SplitzExpression(splitz_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_JOINZ") {
+            if let Some(joinz_expression) = self.parse_joinz_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::JoinzExpression(joinz_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_MATCHEZ") {
+            if let Some(matchez_expression) = self.parse_matchez_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::MatchezExpression(matchez_expression),
+                });
+            }
+        }
+
+        if self.special_check("Word_KOPY OF") {
+            if let Some(kopy_expression) = self.parse_kopy_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::KopyExpression(kopy_expression),
+                });
+            }
+        }
+
         if self.special_check("Word_NOT") {
             if let Some(not_expression) = self.parse_not_expression() {
                 return Some(ast::ExpressionNode {
@@ -625,7 +688,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_ALL") {
+        if self.special_check("Word_ALL OF") {
             if let Some(all_of_expression) = self.parse_all_of_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::AllOfExpression(all_of_expression),
@@ -633,7 +696,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_ANY") {
+        if self.special_check("Word_ANY OF") {
             if let Some(any_of_expression) = self.parse_any_of_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::AnyOfExpression(any_of_expression),
@@ -641,7 +704,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.special_check("Word_BOTH") && self.special_check_amount("Word_SAEM", 1) {
+        if self.special_check("Word_BOTH SAEM") {
             if let Some(both_saem_expression) = self.parse_both_saem_expression() {
                 return Some(ast::ExpressionNode {
                     value: ast::ExpressionNodeValueOption::BothSaemExpression(both_saem_expression),
@@ -681,11 +744,15 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.create_error(ParserError {
-            message: "Expected valid expression",
-            token: self.peek(),
-        });
-        self.next_level(); // prevent level from changing
+        if self.special_check("Word_I IZ") {
+            if let Some(function_call) = self.parse_function_call_expression() {
+                return Some(ast::ExpressionNode {
+                    value: ast::ExpressionNodeValueOption::FunctionCallExpression(function_call),
+                });
+            }
+        }
+
+        // the caller says what it expected the expression for
         None
     }
 
@@ -769,107 +836,120 @@ impl<'a> Parser<'a> {
         None
     }
 
-    pub fn parse_sum_expression(&mut self) -> Option<ast::SumExpressionNode> {
+    pub fn parse_bukkit_slot_expression(&mut self) -> Option<ast::BukkitSlotNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_SUM") {
-            self.create_error(ParserError {
-                message: "Expected SUM keyword for sum expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        let bukkit = self.special_consume("Identifier");
+        if bukkit.is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for sum expression",
+                message: "Expected identifier for bukkit",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        if !self.special_check("Word_'Z") {
             self.create_error(ParserError {
-                message: "Expected valid expression for sum expression",
+                message: "Expected 'Z to access bukkit slot",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for sum expression",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
+        let mut path = match self.parse_slot_path() {
+            Some(path) => path,
+            None => {
+                self.reset(start);
+                return None;
+            }
+        };
+        let slot = path.pop().unwrap();
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
-            self.create_error(ParserError {
-                message: "Expected valid expression for sum expression",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
+        self.prev_level();
+        Some(ast::BukkitSlotNode {
+            bukkit: bukkit.unwrap(),
+            path,
+            slot,
+        })
+    }
+
+    /// Consumes every `'Z slot` that follows, returning the slots
+    pub fn parse_slot_path(&mut self) -> Option<Vec<ast::TokenNode>> {
+        self.next_level();
+        let mut path = vec![];
+
+        while self.special_consume("Word_'Z").is_some() {
+            let slot = self.special_consume("Identifier");
+            if slot.is_none() {
+                self.create_error(ParserError {
+                    message: "Expected identifier for bukkit slot",
+                    token: self.peek(),
+                });
+                return None;
+            }
+            path.push(slot.unwrap());
         }
 
         self.prev_level();
-        Some(ast::SumExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
-        })
+        Some(path)
     }
 
-    pub fn parse_diff_expression(&mut self) -> Option<ast::DiffExpressionNode> {
+    pub fn parse_wun_of_expression(&mut self) -> Option<ast::WunOfExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_DIFF") {
+        if self.special_consume("Word_WUN OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected DIFF keyword for diff expression",
+                message: "Expected WUN OF keyword for wun of expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let condition = self.parse_expression();
+        if condition.is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for diff expression",
+                message: "Expected valid condition for wun of expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        if self.special_consume("Word_DEN").is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for diff expression",
+                message: "Expected DEN keyword for wun of expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
+        let then = self.parse_expression();
+        if then.is_none() {
             self.create_error(ParserError {
-                message: "Expected AN keyword for diff expression",
+                message: "Expected valid expression after DEN",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
+        if self.special_consume("Word_ELSS").is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for diff expression",
+                message: "Expected ELSS keyword for wun of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        let else_ = self.parse_expression();
+        if else_.is_none() {
+            self.create_error(ParserError {
+                message: "Expected valid expression after ELSS",
                 token: self.peek(),
             });
             self.reset(start);
@@ -877,56 +957,57 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::DiffExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
+        Some(ast::WunOfExpressionNode {
+            condition: Box::new(condition.unwrap()),
+            then: Box::new(then.unwrap()),
+            else_: Box::new(else_.unwrap()),
         })
     }
 
-    pub fn parse_produkt_expression(&mut self) -> Option<ast::ProduktExpressionNode> {
+    pub fn parse_lengthz_expression(&mut self) -> Option<ast::LengthzExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_PRODUKT") {
+        if self.special_consume("Word_LENGTHZ OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected PRODUKT keyword for product expression",
+                message: "Expected LENGTHZ OF keyword for lengthz expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let expression = self.parse_expression();
+        if expression.is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for product expression",
+                message: "Expected valid expression for lengthz expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
-            self.create_error(ParserError {
-                message: "Expected valid expression for product expression",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
+        self.prev_level();
+        Some(ast::LengthzExpressionNode {
+            expression: Box::new(expression.unwrap()),
+        })
+    }
 
-        if let None = self.special_consume("Word_AN") {
+    pub fn parse_codez_expression(&mut self) -> Option<ast::CodezExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_CODEZ OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected AN keyword for product expression",
+                message: "Expected CODEZ OF keyword for codez expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
+        let expression = self.parse_expression();
+        if expression.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for product expression",
+                message: "Expected valid expression for codez expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -934,56 +1015,74 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::ProduktExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
+        Some(ast::CodezExpressionNode {
+            expression: Box::new(expression.unwrap()),
         })
     }
 
-    pub fn parse_quoshunt_expression(&mut self) -> Option<ast::QuoshuntExpressionNode> {
+    pub fn parse_lettar_expression(&mut self) -> Option<ast::LettarExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_QUOSHUNT") {
+        if self.special_consume("Word_LETTAR OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected QUOSHUNT keyword for quotient expression",
+                message: "Expected LETTAR OF keyword for lettar expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let expression = self.parse_expression();
+        if expression.is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for quotient expression",
+                message: "Expected valid expression for lettar expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        self.prev_level();
+        Some(ast::LettarExpressionNode {
+            expression: Box::new(expression.unwrap()),
+        })
+    }
+
+    pub fn parse_splitz_expression(&mut self) -> Option<ast::SplitzExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_SPLITZ").is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for quotient expression",
+                message: "Expected SPLITZ keyword for splitz expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let yarn = self.parse_expression();
+        if yarn.is_none() {
+            self.create_error(ParserError {
+                message: "Expected valid YARN for splitz expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
+        if self.special_consume("Word_ON").is_none() {
             self.create_error(ParserError {
-                message: "Expected AN keyword for quotient expression",
+                message: "Expected ON keyword for splitz expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
+        let separator = self.parse_expression();
+        if separator.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for quotient expression",
+                message: "Expected valid separator after ON",
                 token: self.peek(),
             });
             self.reset(start);
@@ -991,56 +1090,47 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::QuoshuntExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
+        Some(ast::SplitzExpressionNode {
+            yarn: Box::new(yarn.unwrap()),
+            separator: Box::new(separator.unwrap()),
         })
     }
 
-    pub fn parse_mod_expression(&mut self) -> Option<ast::ModExpressionNode> {
+    pub fn parse_joinz_expression(&mut self) -> Option<ast::JoinzExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_MOD") {
-            self.create_error(ParserError {
-                message: "Expected MOD keyword for modulo expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        if self.special_consume("Word_JOINZ").is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for modulo expression",
+                message: "Expected JOINZ keyword for joinz expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        let bukkit = self.parse_expression();
+        if bukkit.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for modulo expression",
+                message: "Expected valid BUKKIT for joinz expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
+        if self.special_consume("Word_WIF").is_none() {
             self.create_error(ParserError {
-                message: "Expected AN keyword for modulo expression",
+                message: "Expected WIF keyword for joinz expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
+        let separator = self.parse_expression();
+        if separator.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for modulo expression",
+                message: "Expected valid separator after WIF",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1048,56 +1138,47 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::ModExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
+        Some(ast::JoinzExpressionNode {
+            bukkit: Box::new(bukkit.unwrap()),
+            separator: Box::new(separator.unwrap()),
         })
     }
 
-    pub fn parse_biggr_expression(&mut self) -> Option<ast::BiggrExpressionNode> {
+    pub fn parse_matchez_expression(&mut self) -> Option<ast::MatchezExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_BIGGR") {
-            self.create_error(ParserError {
-                message: "Expected BIGGR keyword for greater expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        if self.special_consume("Word_MATCHEZ").is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for greater expression",
+                message: "Expected MATCHEZ keyword for matchez expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        let yarn = self.parse_expression();
+        if yarn.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for greater expression",
+                message: "Expected valid YARN for matchez expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
+        if self.special_consume("Word_AGENST").is_none() {
             self.create_error(ParserError {
-                message: "Expected AN keyword for greater expression",
+                message: "Expected AGENST keyword for matchez expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression2 = self.parse_expression();
-        if let None = expression2 {
+        let pattern = self.parse_expression();
+        if pattern.is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for greater expression",
+                message: "Expected valid pattern after AGENST",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1105,56 +1186,71 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::BiggrExpressionNode {
-            left: Box::new(expression1.unwrap()),
-            right: Box::new(expression2.unwrap()),
+        Some(ast::MatchezExpressionNode {
+            yarn: Box::new(yarn.unwrap()),
+            pattern: Box::new(pattern.unwrap()),
         })
     }
 
-    pub fn parse_smallr_expression(&mut self) -> Option<ast::SmallrExpressionNode> {
+    pub fn parse_kopy_expression(&mut self) -> Option<ast::KopyExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_SMALLR") {
+        if self.special_consume("Word_KOPY OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected SMALLR keyword for lesser expression",
+                message: "Expected KOPY OF keyword for kopy expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let expression = self.parse_expression();
+        if expression.is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for lesser expression",
+                message: "Expected valid expression for kopy expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let expression1 = self.parse_expression();
-        if let None = expression1 {
+        self.prev_level();
+        Some(ast::KopyExpressionNode {
+            expression: Box::new(expression.unwrap()),
+        })
+    }
+
+    pub fn parse_sum_expression(&mut self) -> Option<ast::SumExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_SUM OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected valid expression for lesser expression",
+                message: "Expected SUM OF keyword for sum expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected AN keyword for lesser expression",
+                message: "Expected valid expression for sum expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
+        if !self.consume_an("Expected AN keyword for sum expression") {
+            self.reset(start);
+            return None;
+        }
+
         let expression2 = self.parse_expression();
         if let None = expression2 {
             self.create_error(ParserError {
-                message: "Expected valid expression for lesser expression",
+                message: "Expected valid expression for sum expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1162,48 +1258,35 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::SmallrExpressionNode {
+        Some(ast::SumExpressionNode {
             left: Box::new(expression1.unwrap()),
             right: Box::new(expression2.unwrap()),
         })
     }
 
-    pub fn parse_both_of_expression(&mut self) -> Option<ast::BothOfExpressionNode> {
+    pub fn parse_diff_expression(&mut self) -> Option<ast::DiffExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_BOTH") {
-            self.create_error(ParserError {
-                message: "Expected BOTH keyword for both of expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        if self.special_consume("Word_DIFF OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for both of expression",
+                message: "Expected DIFF OF keyword for diff expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
         let expression1 = self.parse_expression();
         if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected valid expression for both of expression",
+                message: "Expected valid expression for diff expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for both of expression",
-                token: self.peek(),
-            });
+        if !self.consume_an("Expected AN keyword for diff expression") {
             self.reset(start);
             return None;
         }
@@ -1211,7 +1294,7 @@ impl<'a> Parser<'a> {
         let expression2 = self.parse_expression();
         if let None = expression2 {
             self.create_error(ParserError {
-                message: "Expected valid expression for both of expression",
+                message: "Expected valid expression for diff expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1219,48 +1302,35 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::BothOfExpressionNode {
+        Some(ast::DiffExpressionNode {
             left: Box::new(expression1.unwrap()),
             right: Box::new(expression2.unwrap()),
         })
     }
 
-    pub fn parse_either_expression(&mut self) -> Option<ast::EitherOfExpressionNode> {
+    pub fn parse_produkt_expression(&mut self) -> Option<ast::ProduktExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_EITHER") {
-            self.create_error(ParserError {
-                message: "Expected EITHER keyword for either of expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        if self.special_consume("Word_PRODUKT OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for either of expression",
+                message: "Expected PRODUKT OF keyword for product expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
         let expression1 = self.parse_expression();
         if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected valid expression for either of expression",
+                message: "Expected valid expression for product expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for either of expression",
-                token: self.peek(),
-            });
+        if !self.consume_an("Expected AN keyword for product expression") {
             self.reset(start);
             return None;
         }
@@ -1268,7 +1338,7 @@ impl<'a> Parser<'a> {
         let expression2 = self.parse_expression();
         if let None = expression2 {
             self.create_error(ParserError {
-                message: "Expected valid expression for either of expression",
+                message: "Expected valid expression for product expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1276,48 +1346,35 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::EitherOfExpressionNode {
+        Some(ast::ProduktExpressionNode {
             left: Box::new(expression1.unwrap()),
             right: Box::new(expression2.unwrap()),
         })
     }
 
-    pub fn parse_won_expression(&mut self) -> Option<ast::WonOfExpressionNode> {
+    pub fn parse_quoshunt_expression(&mut self) -> Option<ast::QuoshuntExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_WON") {
-            self.create_error(ParserError {
-                message: "Expected WON keyword for won of expression",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OF") {
+        if self.special_consume("Word_QUOSHUNT OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected OF keyword for won of expression",
+                message: "Expected QUOSHUNT OF keyword for quotient expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
         let expression1 = self.parse_expression();
         if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected valid expression for won of expression",
+                message: "Expected valid expression for quotient expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for won of expression",
-                token: self.peek(),
-            });
+        if !self.consume_an("Expected AN keyword for quotient expression") {
             self.reset(start);
             return None;
         }
@@ -1325,7 +1382,7 @@ impl<'a> Parser<'a> {
         let expression2 = self.parse_expression();
         if let None = expression2 {
             self.create_error(ParserError {
-                message: "Expected valid expression for won of expression",
+                message: "Expected valid expression for quotient expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1333,28 +1390,43 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::WonOfExpressionNode {
+        Some(ast::QuoshuntExpressionNode {
             left: Box::new(expression1.unwrap()),
             right: Box::new(expression2.unwrap()),
         })
     }
 
-    pub fn parse_not_expression(&mut self) -> Option<ast::NotExpressionNode> {
+    pub fn parse_mod_expression(&mut self) -> Option<ast::ModExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_NOT") {
+        if self.special_consume("Word_MOD OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected NOT keyword for not expression",
+                message: "Expected MOD OF keyword for modulo expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        let expression = self.parse_expression();
-        if let None = expression {
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected valid expression for not expression",
+                message: "Expected valid expression for modulo expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for modulo expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for modulo expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1362,55 +1434,87 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::NotExpressionNode {
-            expression: Box::new(expression.unwrap()),
+        Some(ast::ModExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
         })
     }
 
-    pub fn parse_all_of_expression(&mut self) -> Option<ast::AllOfExpressionNode> {
+    pub fn parse_biggr_expression(&mut self) -> Option<ast::BiggrExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_ALL") {
+        if self.special_consume("Word_BIGGR OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected ALL keyword for all of expression",
+                message: "Expected BIGGR OF keyword for greater expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
             self.create_error(ParserError {
-                message: "Expected OF keyword for all of expression",
+                message: "Expected valid expression for greater expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let mut expressions = Vec::new();
-        while !self.is_at_end() {
-            let expression = self.parse_expression();
-            if let None = expression {
-                self.create_error(ParserError {
-                    message: "Expected valid expression for all of expression",
-                    token: self.peek(),
-                });
-                self.reset(start);
-                return None;
-            }
-            expressions.push(expression.unwrap());
+        if !self.consume_an("Expected AN keyword for greater expression") {
+            self.reset(start);
+            return None;
+        }
 
-            if self.special_check("Word_AN") {
-                self.special_consume("Word_AN");
-            } else {
-                break;
-            }
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for greater expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
         }
 
-        if let None = self.special_consume("Word_MKAY") {
+        self.prev_level();
+        Some(ast::BiggrExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_smallr_expression(&mut self) -> Option<ast::SmallrExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_SMALLR OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected MKAY keyword for all of expression",
+                message: "Expected SMALLR OF keyword for lesser expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for lesser expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for lesser expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for lesser expression",
                 token: self.peek(),
             });
             self.reset(start);
@@ -1418,81 +1522,455 @@ impl<'a> Parser<'a> {
         }
 
         self.prev_level();
-        Some(ast::AllOfExpressionNode { expressions })
+        Some(ast::SmallrExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
     }
 
-    pub fn parse_any_of_expression(&mut self) -> Option<ast::AnyOfExpressionNode> {
+    pub fn parse_furst_expression(&mut self) -> Option<ast::FurstExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_FURST OF").is_none() {
+            self.create_error(ParserError {
+                message: "Expected FURST OF keyword for before expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for before expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for before expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for before expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::FurstExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_latr_expression(&mut self) -> Option<ast::LatrExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_LATR OF").is_none() {
+            self.create_error(ParserError {
+                message: "Expected LATR OF keyword for after expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for after expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for after expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for after expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::LatrExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_both_of_expression(&mut self) -> Option<ast::BothOfExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_BOTH OF").is_none() {
+            self.create_error(ParserError {
+                message: "Expected BOTH OF keyword for both of expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for both of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for both of expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for both of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::BothOfExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_either_expression(&mut self) -> Option<ast::EitherOfExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_EITHER OF").is_none() {
+            self.create_error(ParserError {
+                message: "Expected EITHER OF keyword for either of expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for either of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for either of expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for either of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::EitherOfExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_won_expression(&mut self) -> Option<ast::WonOfExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_WON OF").is_none() {
+            self.create_error(ParserError {
+                message: "Expected WON OF keyword for won of expression",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression1 = self.parse_expression();
+        if let None = expression1 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for won of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if !self.consume_an("Expected AN keyword for won of expression") {
+            self.reset(start);
+            return None;
+        }
+
+        let expression2 = self.parse_expression();
+        if let None = expression2 {
+            self.create_error(ParserError {
+                message: "Expected valid expression for won of expression",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::WonOfExpressionNode {
+            left: Box::new(expression1.unwrap()),
+            right: Box::new(expression2.unwrap()),
+        })
+    }
+
+    pub fn parse_not_expression(&mut self) -> Option<ast::NotExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_ANY") {
+        if let None = self.special_consume("Word_NOT") {
             self.create_error(ParserError {
-                message: "Expected ANY keyword for any of expression",
+                message: "Expected NOT keyword for not expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_OF") {
+        let expression = self.parse_expression();
+        if let None = expression {
             self.create_error(ParserError {
-                message: "Expected OF keyword for any of expression",
+                message: "Expected valid expression for not expression",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
+        self.prev_level();
+        Some(ast::NotExpressionNode {
+            expression: Box::new(expression.unwrap()),
+        })
+    }
+
+    /// Parses the AN separated operands of ALL OF, ANY OF or SMOOSH and the
+    /// MKAY closing them. A MKAY always closes the innermost expression still
+    /// open, so `ALL OF ANY OF a AN b MKAY AN c MKAY` is ALL OF (ANY OF a, b)
+    /// and c. At the end of a line the MKAYs of every expression still open
+    /// may be left out, which makes `ALL OF ANY OF a AN b AN c` ANY OF a, b
+    /// and c.
+    pub fn parse_variadic_operands(
+        &mut self,
+        operand_message: &'a str,
+        mkay_message: &'a str,
+    ) -> Option<Vec<ast::ExpressionNode>> {
+        self.mkay_depth += 1;
+        let keyword = self.previous();
+
         let mut expressions = Vec::new();
-        while !self.is_at_end() {
+        let closed = loop {
             let expression = self.parse_expression();
-            if let None = expression {
+            if expression.is_none() {
                 self.create_error(ParserError {
-                    message: "Expected valid expression for any of expression",
+                    message: operand_message,
                     token: self.peek(),
                 });
-                self.reset(start);
-                return None;
+                break false;
             }
             expressions.push(expression.unwrap());
 
-            if self.special_check("Word_AN") {
-                self.special_consume("Word_AN");
-            } else {
-                break;
+            if self.special_consume("Word_AN").is_none() {
+                break self.close_variadic(keyword, mkay_message);
             }
+        };
+
+        self.mkay_depth -= 1;
+        if !closed {
+            return None;
+        }
+        Some(expressions)
+    }
+
+    fn close_variadic(&mut self, keyword: lexer::LexedToken, mkay_message: &'a str) -> bool {
+        if self.special_consume("Word_MKAY").is_some() {
+            // with nothing left to close, another MKAY can only be a mistake
+            if self.mkay_depth == 1 && self.special_check("Word_MKAY") {
+                self.create_error(ParserError {
+                    message: "Unmatched MKAY, every expression before it is already closed",
+                    token: self.peek(),
+                });
+                return false;
+            }
+            self.last_list = Some((keyword, self.current, true));
+            return true;
         }
 
-        if let None = self.special_consume("Word_MKAY") {
+        if self.at_statement_end() {
+            self.last_list = Some((keyword, self.current, false));
+            return true;
+        }
+
+        self.consume_an(mkay_message);
+        false
+    }
+
+    /// Whether the current token ends the statement, where every list and
+    /// call still open may be closed without its MKAY
+    fn at_statement_end(&mut self) -> bool {
+        self.check_newline()
+            || self.check(tokens::Token::Comma)
+            || self.check(tokens::Token::ExclamationMark)
+            || self.is_at_end()
+    }
+
+    /// The keyword of the ALL OF, ANY OF or SMOOSH ending at the current
+    /// token, if it ended by a MKAY when `by_mkay` and by the end of the
+    /// line otherwise
+    fn list_ending_here(&self, by_mkay: bool) -> Option<lexer::LexedToken> {
+        match &self.last_list {
+            Some((keyword, end, mkay)) if *end == self.current && *mkay == by_mkay => {
+                Some(keyword.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the AN between two operands. When it is left out but another
+    /// operand follows, the error points at the gap between the two operands
+    /// rather than reporting `message` at the second one. When the operand
+    /// was a list left open to the end of the line, which took the operands
+    /// meant to follow it, the error points at the list instead.
+    pub fn consume_an(&mut self, message: &'a str) -> bool {
+        if self.special_consume("Word_AN").is_some() {
+            return true;
+        }
+
+        let next = self.peek();
+        if let Some(keyword) = self.list_ending_here(false) {
+            self.create_error(ParserError {
+                message: "Expected MKAY to close this list, which took every operand up to the end of the line",
+                token: keyword,
+            });
+        } else if self.starts_expression() {
+            let start = self.previous().end;
+            self.create_error(ParserError {
+                message: "Missing AN between operands",
+                token: lexer::LexedToken {
+                    start,
+                    end: next.start.max(start + 1),
+                    ..next
+                },
+            });
+        } else {
+            self.create_error(ParserError {
+                message,
+                token: next,
+            });
+        }
+        false
+    }
+
+    /// Whether an expression could be parsed at the current token, leaving
+    /// the parser as it was
+    pub fn starts_expression(&mut self) -> bool {
+        match self.expression_memo.get(&(self.current, self.mkay_depth)) {
+            Some(ExpressionMemo::Parsed) => return true,
+            Some(ExpressionMemo::Failed(_)) => return false,
+            None => {}
+        }
+
+        let snapshot = self.snapshot();
+        let parsed = self.parse_expression().is_some();
+        self.rewind(snapshot);
+        parsed
+    }
+
+    pub fn parse_all_of_expression(&mut self) -> Option<ast::AllOfExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_ALL OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected MKAY keyword for any of expression",
+                message: "Expected ALL OF keyword for all of expression",
                 token: self.peek(),
             });
+            return None;
+        }
+
+        let expressions = self.parse_variadic_operands(
+            "Expected valid expression for all of expression",
+            "Expected MKAY keyword for all of expression",
+        );
+        if expressions.is_none() {
             self.reset(start);
             return None;
         }
 
         self.prev_level();
-        Some(ast::AnyOfExpressionNode { expressions })
+        Some(ast::AllOfExpressionNode {
+            expressions: expressions.unwrap(),
+        })
     }
 
-    pub fn parse_both_saem_expression(&mut self) -> Option<ast::BothSaemExpressionNode> {
+    pub fn parse_any_of_expression(&mut self) -> Option<ast::AnyOfExpressionNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_BOTH") {
+        if self.special_consume("Word_ANY OF").is_none() {
             self.create_error(ParserError {
-                message: "Expected BOTH keyword for both saem expression",
+                message: "Expected ANY OF keyword for any of expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_SAEM") {
+        let expressions = self.parse_variadic_operands(
+            "Expected valid expression for any of expression",
+            "Expected MKAY keyword for any of expression",
+        );
+        if expressions.is_none() {
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::AnyOfExpressionNode {
+            expressions: expressions.unwrap(),
+        })
+    }
+
+    pub fn parse_both_saem_expression(&mut self) -> Option<ast::BothSaemExpressionNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_BOTH SAEM").is_none() {
             self.create_error(ParserError {
-                message: "Expected SAEM keyword for both saem expression",
+                message: "Expected BOTH SAEM keyword for both saem expression",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
@@ -1506,11 +1984,7 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for both saem expression",
-                token: self.peek(),
-            });
+        if !self.consume_an("Expected AN keyword for both saem expression") {
             self.reset(start);
             return None;
         }
@@ -1554,11 +2028,7 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        if let None = self.special_consume("Word_AN") {
-            self.create_error(ParserError {
-                message: "Expected AN keyword for different expression",
-                token: self.peek(),
-            });
+        if !self.consume_an("Expected AN keyword for different expression") {
             self.reset(start);
             return None;
         }
@@ -1592,37 +2062,19 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        let mut expressions = Vec::new();
-        while !self.is_at_end() {
-            let expression = self.parse_expression();
-            if let None = expression {
-                self.create_error(ParserError {
-                    message: "Expected valid expression for smoosh expression",
-                    token: self.peek(),
-                });
-                self.reset(start);
-                return None;
-            }
-            expressions.push(expression.unwrap());
-
-            if self.special_check("Word_AN") {
-                self.special_consume("Word_AN");
-            } else {
-                break;
-            }
-        }
-
-        if let None = self.special_consume("Word_MKAY") {
-            self.create_error(ParserError {
-                message: "Expected MKAY keyword for smoosh expression",
-                token: self.peek(),
-            });
+        let expressions = self.parse_variadic_operands(
+            "Expected valid expression for smoosh expression",
+            "Expected MKAY keyword for smoosh expression",
+        );
+        if expressions.is_none() {
             self.reset(start);
             return None;
         }
 
         self.prev_level();
-        Some(ast::SmooshExpressionNode { expressions })
+        Some(ast::SmooshExpressionNode {
+            expressions: expressions.unwrap(),
+        })
     }
 
     pub fn parse_maek_expression(&mut self) -> Option<ast::MaekExpressionNode> {
@@ -1647,14 +2099,8 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        if let None = self.special_consume("Word_A") {
-            self.create_error(ParserError {
-                message: "Expected A keyword for type conversion expression",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
+        // the A is optional, as in the spec
+        self.special_consume("Word_A");
 
         if let Some(type_) = self.special_consume("Word_NUMBER") {
             self.prev_level();
@@ -1688,6 +2134,14 @@ impl<'a> Parser<'a> {
             });
         }
 
+        if let Some(type_) = self.special_consume("Word_NOOB") {
+            self.prev_level();
+            return Some(ast::MaekExpressionNode {
+                expression: Box::new(expression.unwrap()),
+                type_,
+            });
+        }
+
         self.create_error(ParserError {
             message: "Expected valid type for type conversion expression",
             token: self.peek(),
@@ -1718,23 +2172,14 @@ impl<'a> Parser<'a> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_I") {
+        if self.special_consume("Word_I IZ").is_none() {
             self.create_error(ParserError {
-                message: "Expected I keyword for function call expression",
+                message: "Expected I IZ keyword for function call expression",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_IZ") {
-            self.create_error(ParserError {
-                message: "Expected IZ keyword for function call expression",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
         let identifier = self.special_consume("Identifier");
         if let None = identifier {
             self.create_error(ParserError {
@@ -1747,6 +2192,7 @@ impl<'a> Parser<'a> {
 
         let mut arguments = Vec::new();
         let mut has_args = false;
+        self.mkay_depth += 1;
         while !self.is_at_end() {
             if let None = self.special_consume("Word_YR") {
                 if !has_args {
@@ -1756,6 +2202,7 @@ impl<'a> Parser<'a> {
                     message: "Expected YR keyword for function call expression",
                     token: self.peek(),
                 });
+                self.mkay_depth -= 1;
                 self.reset(start);
                 return None;
             }
@@ -1768,6 +2215,7 @@ impl<'a> Parser<'a> {
                     message: "Expected valid expression for function call expression",
                     token: self.peek(),
                 });
+                self.mkay_depth -= 1;
                 self.reset(start);
                 return None;
             }
@@ -1779,12 +2227,25 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
-
-        if let None = self.special_consume("Word_MKAY") {
-            self.create_error(ParserError {
-                message: "Expected MKAY keyword for function call expression",
-                token: self.peek(),
-            });
+        self.mkay_depth -= 1;
+
+        // like a list, a call ending the statement needs no MKAY
+        if self.at_statement_end() {
+            let keyword = self.tokens[start].clone();
+            self.last_list = Some((keyword, self.current, false));
+        } else if let None = self.special_consume("Word_MKAY") {
+            let error = match self.list_ending_here(true) {
+                Some(keyword) => ParserError {
+                    message:
+                        "Expected another MKAY for the function call, the last one closed this list",
+                    token: keyword,
+                },
+                None => ParserError {
+                    message: "Expected MKAY keyword for function call expression",
+                    token: self.peek(),
+                },
+            };
+            self.create_error(error);
             self.reset(start);
             return None;
         }
@@ -1795,35 +2256,52 @@ impl<'a> Parser<'a> {
         })
     }
 
-    pub fn parse_variable_declaration_statement(
-        &mut self,
-    ) -> Option<ast::VariableDeclarationStatementNode> {
+    /// Parses a declaration and the `R <expression>` that may follow it,
+    /// which makes it an assignment to the variable it declares
+    fn parse_declaration_statement(&mut self) -> Option<ast::StatementNodeValueOption> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_I") {
-            self.create_error(ParserError {
-                message: "Expected I keyword to declare variable",
-                token: self.peek(),
-            });
-            return None;
+        let var_dec = self.parse_variable_declaration_statement()?;
+        if self.special_consume("Word_R").is_none() {
+            self.prev_level();
+            return Some(ast::StatementNodeValueOption::VariableDeclarationStatement(
+                var_dec,
+            ));
         }
 
-        if let None = self.special_consume("Word_HAS") {
+        let expression = self.parse_expression();
+        if let None = expression {
             self.create_error(ParserError {
-                message: "Expected HAS keyword to declare variable",
+                message: "Expected valid expression for variable assignment",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_A") {
+        self.prev_level();
+        Some(ast::StatementNodeValueOption::VariableAssignmentStatement(
+            ast::VariableAssignmentStatementNode {
+                variable: ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(
+                    var_dec,
+                ),
+                expression: expression.unwrap(),
+            },
+        ))
+    }
+
+    pub fn parse_variable_declaration_statement(
+        &mut self,
+    ) -> Option<ast::VariableDeclarationStatementNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_I HAS A").is_none() {
             self.create_error(ParserError {
-                message: "Expected A keyword to declare variable",
+                message: "Expected I HAS A keyword to declare variable",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
@@ -1878,6 +2356,14 @@ impl<'a> Parser<'a> {
             });
         }
 
+        if let Some(type_) = self.special_consume("Word_BUKKIT") {
+            self.prev_level();
+            return Some(ast::VariableDeclarationStatementNode {
+                identifier: identifier.unwrap(),
+                type_,
+            });
+        }
+
         self.create_error(ParserError {
             message: "Expected valid type for variable declaration",
             token: self.peek(),
@@ -1886,6 +2372,78 @@ impl<'a> Parser<'a> {
         None
     }
 
+    pub fn parse_bukkit_slot_declaration_statement(
+        &mut self,
+    ) -> Option<ast::BukkitSlotDeclarationStatementNode> {
+        self.next_level();
+        let start = self.current;
+
+        let bukkit = self.special_consume("Identifier");
+        if bukkit.is_none() {
+            self.create_error(ParserError {
+                message: "Expected identifier for bukkit",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let path = match self.parse_slot_path() {
+            Some(path) => path,
+            None => {
+                self.reset(start);
+                return None;
+            }
+        };
+
+        if self.special_consume("Word_HAS A").is_none() {
+            self.create_error(ParserError {
+                message: "Expected HAS A keyword to declare bukkit slot",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        let slot = self.special_consume("Identifier");
+        if slot.is_none() {
+            self.create_error(ParserError {
+                message: "Expected identifier for bukkit slot declaration",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        if self.special_consume("Word_ITZ").is_none() {
+            self.create_error(ParserError {
+                message: "Expected ITZ keyword to declare bukkit slot",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        let expression = self.parse_expression();
+        if expression.is_none() {
+            self.create_error(ParserError {
+                message: "Expected valid expression for bukkit slot declaration",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::BukkitSlotDeclarationStatementNode {
+            slot: ast::BukkitSlotNode {
+                bukkit: bukkit.unwrap(),
+                path,
+                slot: slot.unwrap(),
+            },
+            expression: expression.unwrap(),
+        })
+    }
+
     pub fn parse_variable_assignment_statement(
         &mut self,
     ) -> Option<ast::VariableAssignmentStatementNode> {
@@ -1893,29 +2451,30 @@ impl<'a> Parser<'a> {
         let start = self.current;
 
         let identifier = self.special_consume("Identifier");
-        let mut var_dec: Option<ast::StatementNode> = None;
-
         if let None = identifier {
-            if self.stmts.len() > 0 {
-                match self.stmts[self.stmts.len() - 1].value {
-                    ast::StatementNodeValueOption::VariableDeclarationStatement(_) => {
-                        var_dec = Some(self.stmts.pop().unwrap());
-                    }
-                    _ => {
-                        self.create_error(ParserError {
-                            message: "Expected identifier or variable declaration for variable assignment",
-                            token: self.peek(),
-                        });
-                        return None;
-                    }
+            self.create_error(ParserError {
+                message: "Expected identifier for variable assignment",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let mut bukkit_slot: Option<ast::BukkitSlotNode> = None;
+        if identifier.is_some() && self.special_check("Word_'Z") {
+            let mut path = match self.parse_slot_path() {
+                Some(path) => path,
+                None => {
+                    self.reset(start);
+                    return None;
                 }
-            } else {
-                self.create_error(ParserError {
-                    message: "Expected identifier or variable declaration for variable assignment",
-                    token: self.peek(),
-                });
-                return None;
-            }
+            };
+            let slot = path.pop().unwrap();
+
+            bukkit_slot = Some(ast::BukkitSlotNode {
+                bukkit: identifier.clone().unwrap(),
+                path,
+                slot,
+            });
         }
 
         if let None = self.special_consume("Word_R") {
@@ -1923,9 +2482,6 @@ impl<'a> Parser<'a> {
                 message: "Expected R keyword to assign variable",
                 token: self.peek(),
             });
-            if let Some(dec) = var_dec {
-                self.stmts.push(dec);
-            }
             self.reset(start);
             return None;
         }
@@ -1936,27 +2492,16 @@ impl<'a> Parser<'a> {
                 message: "Expected valid expression for variable assignment",
                 token: self.peek(),
             });
-            if let Some(dec) = var_dec {
-                self.stmts.push(dec);
-            }
             self.reset(start);
             return None;
         }
 
-        if let Some(dec) = var_dec {
+        if let Some(bukkit_slot) = bukkit_slot {
             self.prev_level();
-            match dec.value {
-                ast::StatementNodeValueOption::VariableDeclarationStatement(node) => {
-                    return Some(ast::VariableAssignmentStatementNode {
-                        variable:
-                            ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(
-                                node,
-                            ),
-                        expression: expression.unwrap(),
-                    });
-                }
-                _ => {}
-            }
+            return Some(ast::VariableAssignmentStatementNode {
+                variable: ast::VariableAssignmentNodeVariableOption::BukkitSlot(bukkit_slot),
+                expression: expression.unwrap(),
+            });
         }
 
         self.prev_level();
@@ -1992,6 +2537,19 @@ impl<'a> Parser<'a> {
 
             expressions.push(expression.unwrap());
 
+            // operands are separated by AN or by nothing at all
+            if self.special_consume("Word_AN").is_some() {
+                if self.is_at_end() {
+                    self.create_error(ParserError {
+                        message: "Expected expression after AN in VISIBLE statement",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+                continue;
+            }
+
             if self.check_ending() || self.check(tokens::Token::ExclamationMark) {
                 break;
             }
@@ -2034,6 +2592,11 @@ impl<'a> Parser<'a> {
             return None;
         }
 
+        let mode = match self.special_consume("Word_LETTAR") {
+            Some(mode) => Some(mode),
+            None => self.special_consume("Word_LINE"),
+        };
+
         let identifier = self.special_consume("Identifier");
         if let None = identifier {
             self.create_error(ParserError {
@@ -2046,52 +2609,129 @@ impl<'a> Parser<'a> {
 
         self.prev_level();
         return Some(ast::GimmehStatementNode {
+            mode,
             identifier: identifier.unwrap(),
         });
     }
 
-    pub fn parse_if_statement(&mut self) -> Option<ast::IfStatementNode> {
+    pub fn parse_assure_statement(&mut self) -> Option<ast::AssureStatementNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_O") {
+        let token = self.special_consume("Word_ASSURE");
+        if let None = token {
             self.create_error(ParserError {
-                message: "Expected O keyword to start if statement",
+                message: "Expected ASSURE keyword to start assertion",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_RLY") {
+        let expression = self.parse_expression();
+        if let None = expression {
             self.create_error(ParserError {
-                message: "Expected RLY keyword to start if statement",
+                message: "Expected expression for ASSURE statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.consume(tokens::Token::QuestionMark) {
+        // the MKAY may already have closed an ALL OF, ANY OF or SMOOSH
+        let mkay = self.special_consume("Word_MKAY");
+        let message = match self.special_consume("Word_SAYIN") {
+            Some(_) => {
+                let message = self.parse_expression();
+                if let None = message {
+                    self.create_error(ParserError {
+                        message: "Expected message after SAYIN",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+                message
+            }
+            None => {
+                if let Some(mkay) = mkay {
+                    self.create_error(ParserError {
+                        message: "Expected SAYIN and a message after MKAY in ASSURE statement",
+                        token: mkay.token,
+                    });
+                    self.reset(start);
+                    return None;
+                }
+                None
+            }
+        };
+
+        self.prev_level();
+        Some(ast::AssureStatementNode {
+            token: token.unwrap(),
+            expression: expression.unwrap(),
+            message,
+        })
+    }
+
+    pub fn parse_dumpz_statement(&mut self) -> Option<ast::DumpzStatementNode> {
+        self.next_level();
+        let start = self.current;
+
+        let token = self.special_consume("Word_DUMPZ");
+        if let None = token {
             self.create_error(ParserError {
-                message: "Expected ? to start if statement",
+                message: "Expected DUMPZ keyword to start dump statement",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let expression = self.parse_expression();
+        if let None = expression {
+            self.create_error(ParserError {
+                message: "Expected expression for DUMPZ statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_YA") {
+        self.prev_level();
+        Some(ast::DumpzStatementNode {
+            token: token.unwrap(),
+            expression: expression.unwrap(),
+        })
+    }
+
+    pub fn parse_if_statement(&mut self) -> Option<ast::IfStatementNode> {
+        self.next_level();
+        let start = self.current;
+
+        let token = match self.special_consume("Word_O RLY") {
+            Some(token) => token,
+            None => {
+                self.create_error(ParserError {
+                    message: "Expected O RLY keyword to start if statement",
+                    token: self.peek(),
+                });
+                return None;
+            }
+        };
+
+        if let None = self.consume(tokens::Token::QuestionMark) {
             self.create_error(ParserError {
-                message: "Expected YA keyword to start if statement",
+                message: "Expected ? to start if statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
+        // YA RLY is usually on a line of its own
+        self.check_ending();
 
-        if let None = self.special_consume("Word_RLY") {
+        if self.special_consume("Word_YA RLY").is_none() {
             self.create_error(ParserError {
-                message: "Expected RLY keyword to start if statement",
+                message: "Expected YA RLY keyword to start if statement",
                 token: self.peek(),
             });
             self.reset(start);
@@ -2122,7 +2762,7 @@ impl<'a> Parser<'a> {
             statements.push(statement.unwrap());
 
             if self.special_check("Word_OIC")
-                || (self.special_check("Word_NO") && self.special_check_amount("Word_WAI", 1))
+                || self.special_check("Word_NO WAI")
                 || self.special_check("Word_MEBBE")
             {
                 break;
@@ -2131,15 +2771,12 @@ impl<'a> Parser<'a> {
 
         let mut else_if_nodes: Vec<ast::ElseIfStatementNode> = Vec::new();
         while !self.is_at_end() {
-            if self.special_check("Word_OIC")
-                || (self.special_check("Word_NO") && self.special_check_amount("Word_WAI", 1))
-            {
+            if self.special_check("Word_OIC") || self.special_check("Word_NO WAI") {
                 break;
             }
 
-            let statement = self.parse_statement();
-            if let Some(s) = statement {
-                if else_if_nodes.len() == 0 {
+            if !self.special_check("Word_MEBBE") {
+                if else_if_nodes.is_empty() {
                     self.create_error(ParserError {
                         message: "Expected MEBBE keyword to start else if statement",
                         token: self.peek(),
@@ -2148,57 +2785,49 @@ impl<'a> Parser<'a> {
                     return None;
                 }
 
+                let statement = self.parse_statement();
+                if let None = statement {
+                    self.create_error(ParserError {
+                        message: "Expected valid statement for else if statement",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+
                 let last = else_if_nodes.len() - 1;
-                else_if_nodes[last].statements.push(s);
+                else_if_nodes[last].statements.push(statement.unwrap());
                 continue;
-            } else if else_if_nodes.len() > 0 {
+            }
+
+            self.special_consume("Word_MEBBE");
+            let expression = self.parse_expression();
+            if let None = expression {
                 self.create_error(ParserError {
-                    message: "Expected valid statement for else if statement",
+                    message: "Expected valid expression for else if statement",
                     token: self.peek(),
                 });
                 self.reset(start);
                 return None;
             }
 
-            if let None = self.special_consume("Word_MEBBE") {
+            else_if_nodes.push(ast::ElseIfStatementNode {
+                expression: expression.unwrap(),
+                statements: Vec::new(),
+            });
+
+            if !self.check_ending() {
                 self.create_error(ParserError {
-                    message: "Expected MEBBE keyword to start else if statement",
+                    message: "Expected newline or comma to end else if statement",
                     token: self.peek(),
                 });
                 self.reset(start);
                 return None;
-            } else {
-                let expression = self.parse_expression();
-                if let None = expression {
-                    self.create_error(ParserError {
-                        message: "Expected valid expression for else if statement",
-                        token: self.peek(),
-                    });
-                    self.reset(start);
-                    return None;
-                }
-
-                else_if_nodes.push(ast::ElseIfStatementNode {
-                    expression: expression.unwrap(),
-                    statements: Vec::new(),
-                });
-
-                if !self.check_ending() {
-                    self.create_error(ParserError {
-                        message: "Expected newline or comma to end else if statement",
-                        token: self.peek(),
-                    });
-                    self.reset(start);
-                    return None;
-                }
             }
         }
 
         let mut else_statements = Vec::new();
-        if self.special_check("Word_NO") && self.special_check_amount("Word_WAI", 1) {
-            self.special_consume("Word_NO");
-            self.special_consume("Word_WAI");
-
+        if self.special_consume("Word_NO WAI").is_some() {
             if !self.check_ending() {
                 self.create_error(ParserError {
                     message: "Expected newline or comma to end else statement",
@@ -2239,12 +2868,14 @@ impl<'a> Parser<'a> {
         self.prev_level();
         if else_statements.len() > 0 {
             return Some(ast::IfStatementNode {
+                token,
                 statements,
                 else_ifs: else_if_nodes,
                 else_: Some(else_statements),
             });
         }
         Some(ast::IfStatementNode {
+            token,
             statements,
             else_ifs: else_if_nodes,
             else_: None,
@@ -2255,88 +2886,201 @@ impl<'a> Parser<'a> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_WTF") {
+        let token = match self.special_consume("Word_WTF") {
+            Some(token) => token,
+            None => {
+                self.create_error(ParserError {
+                    message: "Expected WTF keyword to start switch statement",
+                    token: self.peek(),
+                });
+                return None;
+            }
+        };
+
+        if let None = self.consume(tokens::Token::QuestionMark) {
             self.create_error(ParserError {
-                message: "Expected WTF keyword to start switch statement",
+                message: "Expected ? to start switch statement",
                 token: self.peek(),
             });
+            self.reset(start);
             return None;
         }
 
-        if let None = self.consume(tokens::Token::QuestionMark) {
+        if !self.check_ending() {
             self.create_error(ParserError {
-                message: "Expected ? to start switch statement",
+                message: "Expected newline or comma to end switch statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if !self.check_ending() {
+        let mut cases: Vec<ast::SwitchCaseStatementNode> = Vec::new();
+
+        while !self.is_at_end() {
+            if self.special_check("Word_OIC") || self.special_check("Word_OMGWTF") {
+                break;
+            }
+
+            if !self.special_check("Word_OMG") {
+                if cases.is_empty() {
+                    self.create_error(ParserError {
+                        message: "Expected OMG keyword to start case statement",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+
+                let statement = self.parse_statement();
+                if let None = statement {
+                    self.create_error(ParserError {
+                        message: "Expected valid statement for case statement",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+
+                let last = cases.len() - 1;
+                cases[last].statements.push(statement.unwrap());
+                continue;
+            }
+
+            self.special_consume("Word_OMG");
+            let expression = self.parse_expression();
+            if let None = expression {
+                self.create_error(ParserError {
+                    message: "Expected valid expression for case statement",
+                    token: self.peek(),
+                });
+                self.reset(start);
+                return None;
+            }
+
+            cases.push(ast::SwitchCaseStatementNode {
+                expression: expression.unwrap(),
+                statements: Vec::new(),
+            });
+
+            if !self.check_ending() {
+                self.create_error(ParserError {
+                    message: "Expected newline or comma to end case statement",
+                    token: self.peek(),
+                });
+                self.reset(start);
+                return None;
+            }
+        }
+
+        // OMGWTF is left out when nothing is to be done if no case matches
+        let mut default_case = None;
+        if self.special_consume("Word_OMGWTF").is_some() {
+            if !self.check_ending() {
+                self.create_error(ParserError {
+                    message: "Expected newline or comma to end default case statement",
+                    token: self.peek(),
+                });
+                self.reset(start);
+                return None;
+            }
+
+            let mut statements = Vec::new();
+            while !self.is_at_end() && !self.special_check("Word_OIC") {
+                let statement = self.parse_statement();
+                if let None = statement {
+                    self.create_error(ParserError {
+                        message: "Expected valid statement for default case statement",
+                        token: self.peek(),
+                    });
+                    self.reset(start);
+                    return None;
+                }
+                statements.push(statement.unwrap());
+            }
+            default_case = Some(statements);
+        }
+
+        if let None = self.special_consume("Word_OIC") {
             self.create_error(ParserError {
-                message: "Expected newline or comma to end switch statement",
+                message: "Expected OIC keyword to end switch statement",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
+        }
+
+        self.prev_level();
+        Some(ast::SwitchStatementNode {
+            token,
+            cases,
+            default: default_case,
+        })
+    }
+
+    pub fn parse_loop_statement(&mut self) -> Option<ast::LoopStatementNode> {
+        self.next_level();
+        let start = self.current;
+
+        if self.special_consume("Word_IM IN YR").is_none() {
+            self.create_error(ParserError {
+                message: "Expected IM IN YR keyword to start loop statement",
+                token: self.peek(),
+            });
+            return None;
+        }
+
+        let label = self.special_consume("Identifier");
+        if let None = label {
+            self.create_error(ParserError {
+                message: "Expected identifier for loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let mut cases: Vec<ast::SwitchCaseStatementNode> = Vec::new();
-
-        while !self.is_at_end() {
-            if self.special_check("Word_OIC") || self.special_check("Word_OMGWTF") {
-                break;
-            }
-
-            let statement = self.parse_statement();
-            if let Some(s) = statement {
-                if cases.len() == 0 {
-                    self.create_error(ParserError {
-                        message: "Expected OMGWTF keyword to start case statement",
-                        token: self.peek(),
-                    });
-                    self.reset(start);
-                    return None;
-                }
+        // a loop without UPPIN or NERFIN runs until GTFO leaves it
+        let mut counter = None;
+        let mut condition = None;
+        let mut condition_expression = None;
+        if self.special_consume("Word_UPPIN").is_some()
+            || self.special_consume("Word_NERFIN").is_some()
+        {
+            let operation = self.previous();
 
-                let last = cases.len() - 1;
-                cases[last].statements.push(s);
-                continue;
-            } else if cases.len() > 0 {
+            if self.special_consume("Word_YR").is_none() {
                 self.create_error(ParserError {
-                    message: "Expected valid statement for case statement",
+                    message: "Expected YR keyword to start loop statement",
                     token: self.peek(),
                 });
                 self.reset(start);
                 return None;
             }
 
-            if let None = self.special_consume("Word_OMG") {
-                self.create_error(ParserError {
-                    message: "Expected OMG keyword to start case statement",
-                    token: self.peek(),
-                });
-                self.reset(start);
-                return None;
-            } else {
-                let expression = self.parse_expression();
-                if let None = expression {
+            let variable = match self.special_consume("Identifier") {
+                Some(variable) => variable,
+                None => {
                     self.create_error(ParserError {
-                        message: "Expected valid expression for case statement",
+                        message: "Expected identifier for loop statement",
                         token: self.peek(),
                     });
                     self.reset(start);
                     return None;
                 }
+            };
+            counter = Some((ast::TokenNode { token: operation }, variable));
 
-                cases.push(ast::SwitchCaseStatementNode {
-                    expression: expression.unwrap(),
-                    statements: Vec::new(),
-                });
+            if let Some(t) = self
+                .special_consume("Word_TIL")
+                .or_else(|| self.special_consume("Word_WILE"))
+            {
+                condition = Some(t);
 
-                if !self.check_ending() {
+                condition_expression = self.parse_expression();
+                if condition_expression.is_none() {
                     self.create_error(ParserError {
-                        message: "Expected newline or comma to end case statement",
+                        message: "Expected valid expression for loop statement",
                         token: self.peek(),
                     });
                     self.reset(start);
@@ -2345,103 +3089,113 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if let None = self.special_consume("Word_OMGWTF") {
-            self.create_error(ParserError {
-                message: "Expected OMGWTF keyword to start default case statement",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
         if !self.check_ending() {
             self.create_error(ParserError {
-                message: "Expected newline or comma to end default case statement",
+                message: "Expected newline or comma to end loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let mut default_case = Some(Vec::new());
+        let mut statements = Vec::new();
         while !self.is_at_end() {
+            if self.special_check("Word_IM OUTTA YR") && self.special_check_amount("Identifier", 1)
+            {
+                break;
+            }
+
             let statement = self.parse_statement();
             if let None = statement {
                 self.create_error(ParserError {
-                    message: "Expected valid statement for default case statement",
+                    message: "Expected valid statement for loop statement",
                     token: self.peek(),
                 });
                 self.reset(start);
                 return None;
             }
 
-            default_case.as_mut().unwrap().push(statement.unwrap());
+            statements.push(statement.unwrap());
+        }
 
-            if self.special_check("Word_OIC") {
-                break;
-            }
+        if self.special_consume("Word_IM OUTTA YR").is_none() {
+            self.create_error(ParserError {
+                message: "Expected IM OUTTA YR keyword to end loop statement",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
         }
 
-        if let None = self.special_consume("Word_OIC") {
+        let outta_label = self.special_consume("Identifier");
+        if let None = outta_label {
             self.create_error(ParserError {
-                message: "Expected OIC keyword to end switch statement",
+                message: "Expected identifier to end loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
+        match label.clone().unwrap().token.token {
+            tokens::Token::Identifier(label) => match outta_label.unwrap().token.token {
+                tokens::Token::Identifier(outta_label) => {
+                    if label != outta_label {
+                        self.create_error(ParserError {
+                            message: "Expected same label to end loop statement",
+                            token: self.peek(),
+                        });
+                        self.reset(start);
+                        return None;
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
         self.prev_level();
-        Some(ast::SwitchStatementNode {
-            cases,
-            default: default_case,
+        Some(ast::LoopStatementNode {
+            label: label.unwrap(),
+            counter,
+            condition,
+            condition_expression,
+            statements,
         })
     }
 
-    pub fn parse_loop_statement(&mut self) -> Option<ast::LoopStatementNode> {
+    pub fn parse_watchin_loop_statement(&mut self) -> Option<ast::WatchinLoopStatementNode> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_IM") {
+        if self.special_consume("Word_IM IN YR").is_none() {
             self.create_error(ParserError {
-                message: "Expected IM keyword to start loop statement",
+                message: "Expected IM IN YR keyword to start loop statement",
                 token: self.peek(),
             });
             return None;
         }
 
-        if let None = self.special_consume("Word_IN") {
+        let label = self.special_consume("Identifier");
+        if label.is_none() {
             self.create_error(ParserError {
-                message: "Expected IN keyword to start loop statement",
+                message: "Expected identifier for loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let label = self.special_consume("Identifier");
-        if let None = label {
+        if self.special_consume("Word_WATCHIN").is_none() {
             self.create_error(ParserError {
-                message: "Expected identifier for loop statement",
+                message: "Expected WATCHIN keyword to start loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_UPPIN") {
-            if let None = self.special_consume("Word_NERFIN") {
-                self.create_error(ParserError {
-                    message: "Expected UPPIN or NERFIN keyword to start loop statement",
-                    token: self.peek(),
-                });
-                self.reset(start);
-                return None;
-            }
-        }
-        let operation = self.previous();
-
-        if let None = self.special_consume("Word_YR") {
+        if self.special_consume("Word_YR").is_none() {
             self.create_error(ParserError {
                 message: "Expected YR keyword to start loop statement",
                 token: self.peek(),
@@ -2450,46 +3204,33 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        let variable = self.special_consume("Identifier");
-        if let None = variable {
+        let item = self.special_consume("Identifier");
+        if item.is_none() {
             self.create_error(ParserError {
-                message: "Expected identifier for loop statement",
+                message: "Expected identifier for loop item",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let mut condition = None;
-        let mut condition_expression = None;
-        if let None = self.special_consume("Word_TIL") {
-            if let Some(t) = self.special_consume("Word_WILE") {
-                condition = Some(t);
-
-                condition_expression = self.parse_expression();
-                if let None = condition_expression {
-                    self.create_error(ParserError {
-                        message: "Expected valid expression for loop statement",
-                        token: self.peek(),
-                    });
-                    self.reset(start);
-                    return None;
-                }
-            }
-        } else {
-            condition = Some(ast::TokenNode {
-                token: self.previous(),
+        if self.special_consume("Word_IN").is_none() {
+            self.create_error(ParserError {
+                message: "Expected IN keyword before the bukkit to watch",
+                token: self.peek(),
             });
+            self.reset(start);
+            return None;
+        }
 
-            condition_expression = self.parse_expression();
-            if let None = condition_expression {
-                self.create_error(ParserError {
-                    message: "Expected valid expression for loop statement",
-                    token: self.peek(),
-                });
-                self.reset(start);
-                return None;
-            }
+        let bukkit = self.parse_expression();
+        if bukkit.is_none() {
+            self.create_error(ParserError {
+                message: "Expected valid expression for loop statement",
+                token: self.peek(),
+            });
+            self.reset(start);
+            return None;
         }
 
         if !self.check_ending() {
@@ -2503,16 +3244,13 @@ impl<'a> Parser<'a> {
 
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            if self.special_check("Word_IM")
-                && self.special_check_amount("Word_OUTTA", 1)
-                && self.special_check_amount("Word_YR", 2)
-                && self.special_check_amount("Identifier", 3)
+            if self.special_check("Word_IM OUTTA YR") && self.special_check_amount("Identifier", 1)
             {
                 break;
             }
 
             let statement = self.parse_statement();
-            if let None = statement {
+            if statement.is_none() {
                 self.create_error(ParserError {
                     message: "Expected valid statement for loop statement",
                     token: self.peek(),
@@ -2524,67 +3262,39 @@ impl<'a> Parser<'a> {
             statements.push(statement.unwrap());
         }
 
-        if let None = self.special_consume("Word_IM") {
-            self.create_error(ParserError {
-                message: "Expected IM keyword to end loop statement",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
-        if let None = self.special_consume("Word_OUTTA") {
+        if self.special_consume("Word_IM OUTTA YR").is_none() {
             self.create_error(ParserError {
-                message: "Expected OUTTA keyword to end loop statement",
+                message: "Expected IM OUTTA YR to end loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        if let None = self.special_consume("Word_YR") {
+        let outta_label = self.special_consume("Identifier");
+        if outta_label.is_none() {
             self.create_error(ParserError {
-                message: "Expected YR keyword to end loop statement",
+                message: "Expected identifier to end loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        let outta_label = self.special_consume("Identifier");
-        if let None = outta_label {
+        if outta_label.unwrap().value() != label.clone().unwrap().value() {
             self.create_error(ParserError {
-                message: "Expected identifier to end loop statement",
+                message: "Expected same label to end loop statement",
                 token: self.peek(),
             });
             self.reset(start);
             return None;
         }
 
-        match label.clone().unwrap().token.token {
-            tokens::Token::Identifier(label) => match outta_label.unwrap().token.token {
-                tokens::Token::Identifier(outta_label) => {
-                    if label != outta_label {
-                        self.create_error(ParserError {
-                            message: "Expected same label to end loop statement",
-                            token: self.peek(),
-                        });
-                        self.reset(start);
-                        return None;
-                    }
-                }
-                _ => {}
-            },
-            _ => {}
-        }
-
         self.prev_level();
-        Some(ast::LoopStatementNode {
+        Some(ast::WatchinLoopStatementNode {
             label: label.unwrap(),
-            operation: ast::TokenNode { token: operation },
-            variable: variable.unwrap(),
-            condition,
-            condition_expression,
+            item: item.unwrap(),
+            bukkit: bukkit.unwrap(),
             statements,
         })
     }
@@ -2593,20 +3303,11 @@ impl<'a> Parser<'a> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_FOUND") {
-            self.create_error(ParserError {
-                message: "Expected FOUND keyword to start return statement",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_YR") {
+        if self.special_consume("Word_FOUND YR").is_none() {
             self.create_error(ParserError {
-                message: "Expected YR keyword to start return statement",
+                message: "Expected FOUND YR keyword to start return statement",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
@@ -2632,29 +3333,11 @@ impl<'a> Parser<'a> {
         self.next_level();
         let start = self.current;
 
-        if let None = self.special_consume("Word_HOW") {
-            self.create_error(ParserError {
-                message: "Expected HOW keyword to start function definition",
-                token: self.peek(),
-            });
-            return None;
-        }
-
-        if let None = self.special_consume("Word_IZ") {
-            self.create_error(ParserError {
-                message: "Expected IZ keyword to start function definition",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
-        if let None = self.special_consume("Word_I") {
+        if self.special_consume("Word_HOW IZ I").is_none() {
             self.create_error(ParserError {
-                message: "Expected I keyword to start function definition",
+                message: "Expected HOW IZ I keyword to start function definition",
                 token: self.peek(),
             });
-            self.reset(start);
             return None;
         }
 
@@ -2700,6 +3383,10 @@ impl<'a> Parser<'a> {
         let mut arguments = Vec::new();
         while !self.is_at_end() {
             if let None = self.special_consume("Word_YR") {
+                // a function can take no arguments
+                if arguments.is_empty() {
+                    break;
+                }
                 self.create_error(ParserError {
                     message: "Expected YR keyword for function definition",
                     token: self.peek(),
@@ -2765,11 +3452,7 @@ impl<'a> Parser<'a> {
 
         let mut statements = Vec::new();
         while !self.is_at_end() {
-            if self.special_check("Word_IF")
-                && self.special_check_amount("Word_U", 1)
-                && self.special_check_amount("Word_SAY", 2)
-                && self.special_check_amount("Word_SO", 3)
-            {
+            if self.special_check("Word_IF U SAY SO") {
                 break;
             }
 
@@ -2786,36 +3469,9 @@ impl<'a> Parser<'a> {
             statements.push(statement.unwrap());
         }
 
-        if let None = self.special_consume("Word_IF") {
-            self.create_error(ParserError {
-                message: "Expected IF keyword to end function definition",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
-        if let None = self.special_consume("Word_U") {
-            self.create_error(ParserError {
-                message: "Expected U keyword to end function definition",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
-        if let None = self.special_consume("Word_SAY") {
+        if self.special_consume("Word_IF U SAY SO").is_none() {
             self.create_error(ParserError {
-                message: "Expected SAY keyword to end function definition",
-                token: self.peek(),
-            });
-            self.reset(start);
-            return None;
-        }
-
-        if let None = self.special_consume("Word_SO") {
-            self.create_error(ParserError {
-                message: "Expected SO keyword to end function definition",
+                message: "Expected IF U SAY SO keyword to end function definition",
                 token: self.peek(),
             });
             self.reset(start);
@@ -2831,3 +3487,240 @@ impl<'a> Parser<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::parser::printer;
+
+    fn parse(text: &str) -> ParserReturn<'static> {
+        Parser::parse(lexer::Lexer::init(text).get_tokens())
+    }
+
+    #[test]
+    fn hundreds_of_broken_lines_report_the_first_failure_quickly() {
+        let broken = "VISIBLE SUM OF 1 AN\n";
+        let mut text = String::from("HAI 1.2\nVISIBLE \"ok\"\n");
+        let first = text.len()..text.len() + broken.len();
+        for _ in 0..500 {
+            text.push_str(broken);
+        }
+        text.push_str("KTHXBYE\n");
+
+        let start = Instant::now();
+        let parsed = parse(&text);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the chain of the first failure, whatever comes after it
+        assert!(!parsed.errors.is_empty());
+        assert!(parsed.errors.len() < 10);
+        for error in parsed.errors.iter() {
+            assert!(first.contains(&error.token.start), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn the_first_broken_statement_is_the_one_reported() {
+        let mut text = String::from("HAI 1.2\nI HAS A x ITZ\n");
+        for _ in 0..300 {
+            text.push_str("VISIBLE x AN\n");
+        }
+        text.push_str("KTHXBYE\n");
+
+        let parsed = parse(&text);
+        let line = "HAI 1.2\n".len().."HAI 1.2\nI HAS A x ITZ\n".len();
+        assert!(!parsed.errors.is_empty());
+        assert!(line.contains(&parsed.errors[0].token.start));
+    }
+
+    #[test]
+    fn a_loop_needs_no_variable_or_condition() {
+        let parsed = parse("HAI 1.2\nIM IN YR l\nGTFO\nIM OUTTA YR l\nKTHXBYE\n");
+        assert!(parsed.errors.is_empty());
+        match &parsed.ast.statements[0].value {
+            ast::StatementNodeValueOption::LoopStatement(loop_stmt) => {
+                assert!(loop_stmt.counter.is_none());
+                assert!(loop_stmt.condition.is_none());
+                assert_eq!(loop_stmt.statements.len(), 1);
+            }
+            _ => panic!("Expected a loop"),
+        }
+    }
+
+    #[test]
+    fn a_declaration_assigns_with_r_inside_any_block() {
+        let declaration = "I HAS A g ITZ YARN R \"a\"\n";
+        let blocks = [
+            ("IM IN YR l\n", "GTFO\nIM OUTTA YR l\n"),
+            ("WIN, O RLY?\nYA RLY\n", "OIC\n"),
+            ("HOW IZ I f ITZ YARN\n", "FOUND YR g\nIF U SAY SO\n"),
+        ];
+        for (open, close) in blocks {
+            let text = format!("HAI 1.2\n{}{}{}KTHXBYE\n", open, declaration, close);
+            let parsed = parse(&text);
+            assert!(parsed.errors.is_empty(), "{}: {:?}", text, parsed.errors);
+            let statements = match &parsed.ast.statements[0].value {
+                ast::StatementNodeValueOption::LoopStatement(loop_stmt) => &loop_stmt.statements,
+                ast::StatementNodeValueOption::Expression(_) => {
+                    match &parsed.ast.statements[1].value {
+                        ast::StatementNodeValueOption::IfStatement(if_stmt) => &if_stmt.statements,
+                        _ => panic!("Expected O RLY?"),
+                    }
+                }
+                ast::StatementNodeValueOption::FunctionDefinitionStatement(function) => {
+                    &function.statements
+                }
+                _ => panic!("Expected a block"),
+            };
+            assert!(matches!(
+                &statements[0].value,
+                ast::StatementNodeValueOption::VariableAssignmentStatement(assignment)
+                    if matches!(
+                        assignment.variable,
+                        ast::VariableAssignmentNodeVariableOption::VariableDeclerationStatement(_)
+                    )
+            ));
+        }
+    }
+
+    #[test]
+    fn r_only_follows_a_declaration_on_its_line() {
+        let parsed = parse("HAI 1.2\nI HAS A g ITZ YARN\nR \"a\"\nKTHXBYE\n");
+        assert!(!parsed.errors.is_empty());
+    }
+
+    /// The operands of the VISIBLE statement `line`, printed with every MKAY
+    fn visible_operands(line: &str) -> Vec<String> {
+        let parsed = parse(&format!("HAI 1.2\n{}\nKTHXBYE\n", line));
+        assert!(parsed.errors.is_empty(), "{}: {:?}", line, parsed.errors);
+        match &parsed.ast.statements[0].value {
+            ast::StatementNodeValueOption::VisibleStatement(visible) => visible
+                .expressions
+                .iter()
+                .map(printer::print_expression)
+                .collect(),
+            _ => panic!("Expected a VISIBLE statement"),
+        }
+    }
+
+    /// The expression statement `line`, printed with every MKAY
+    fn expression(line: &str) -> String {
+        let parsed = parse(&format!("HAI 1.2\n{}\nKTHXBYE\n", line));
+        assert!(parsed.errors.is_empty(), "{}: {:?}", line, parsed.errors);
+        match &parsed.ast.statements[0].value {
+            ast::StatementNodeValueOption::Expression(expression) => {
+                printer::print_expression(expression)
+            }
+            _ => panic!("Expected an expression statement"),
+        }
+    }
+
+    #[test]
+    fn visible_operands_are_separated_by_an_or_by_nothing() {
+        assert_eq!(
+            visible_operands("VISIBLE \"a\" AN \"b\""),
+            ["\"a\"", "\"b\""]
+        );
+        assert_eq!(
+            visible_operands("VISIBLE \"a\" \"b\" x"),
+            ["\"a\"", "\"b\"", "x"]
+        );
+        assert_eq!(
+            visible_operands("VISIBLE \"a\" AN SUM OF 1 AN 2 AN \"c\"!"),
+            ["\"a\"", "SUM OF 1 AN 2", "\"c\""]
+        );
+    }
+
+    #[test]
+    fn a_list_closed_by_mkay_is_one_visible_operand() {
+        assert_eq!(
+            visible_operands("VISIBLE SMOOSH \"a\" AN \"b\" MKAY AN x"),
+            ["SMOOSH \"a\" AN \"b\" MKAY", "x"]
+        );
+        assert_eq!(
+            visible_operands("VISIBLE SMOOSH \"a\" AN \"b\" MKAY x"),
+            ["SMOOSH \"a\" AN \"b\" MKAY", "x"]
+        );
+        // without MKAY the list takes every operand to the end of the line
+        assert_eq!(
+            visible_operands("VISIBLE SMOOSH \"a\" AN \"b\" AN x"),
+            ["SMOOSH \"a\" AN \"b\" AN x MKAY"]
+        );
+    }
+
+    #[test]
+    fn visible_ending_in_an_is_an_error() {
+        let parsed = parse("HAI 1.2\nVISIBLE \"a\" AN\nKTHXBYE\n");
+        assert!(parsed
+            .errors
+            .iter()
+            .any(|error| error.message == "Expected valid expression for VISIBLE statement"));
+    }
+
+    #[test]
+    fn mkay_closes_the_innermost_open_list() {
+        assert_eq!(
+            expression("ALL OF ANY OF a AN b MKAY AN c MKAY"),
+            "ALL OF ANY OF a AN b MKAY AN c MKAY"
+        );
+        assert_eq!(
+            expression("ANY OF ALL OF a AN b MKAY AN ALL OF c AN d MKAY MKAY"),
+            "ANY OF ALL OF a AN b MKAY AN ALL OF c AN d MKAY MKAY"
+        );
+        assert_eq!(
+            expression("SMOOSH ALL OF a AN b MKAY AN \"c\" MKAY"),
+            "SMOOSH ALL OF a AN b MKAY AN \"c\" MKAY"
+        );
+    }
+
+    #[test]
+    fn the_end_of_the_line_closes_every_open_list() {
+        assert_eq!(
+            expression("ALL OF a AN ANY OF b AN c"),
+            "ALL OF a AN ANY OF b AN c MKAY MKAY"
+        );
+        assert_eq!(
+            expression("ALL OF ANY OF a AN b MKAY AN c"),
+            "ALL OF ANY OF a AN b MKAY AN c MKAY"
+        );
+        // a comma ends the statement as a newline does
+        let parsed = parse("HAI 1.2\nALL OF a AN b, VISIBLE \"c\"\nKTHXBYE\n");
+        assert!(parsed.errors.is_empty());
+        assert!(matches!(
+            parsed.ast.statements[1].value,
+            ast::StatementNodeValueOption::VisibleStatement(_)
+        ));
+    }
+
+    #[test]
+    fn a_call_at_the_end_of_a_statement_needs_no_mkay() {
+        assert_eq!(
+            expression("I IZ f YR 1 AN YR 2"),
+            "I IZ f YR 1 AN YR 2 MKAY"
+        );
+        assert_eq!(expression("I IZ f"), "I IZ f MKAY");
+        assert_eq!(
+            visible_operands("VISIBLE I IZ f YR \"a\""),
+            ["I IZ f YR \"a\" MKAY"]
+        );
+    }
+
+    #[test]
+    fn a_call_inside_an_expression_is_closed_by_mkay() {
+        assert_eq!(
+            expression("I IZ f YR I IZ g YR 1 MKAY AN YR 2"),
+            "I IZ f YR I IZ g YR 1 MKAY AN YR 2 MKAY"
+        );
+        assert_eq!(
+            expression("SMOOSH I IZ f YR 1 MKAY AN \"x\""),
+            "SMOOSH I IZ f YR 1 MKAY AN \"x\" MKAY"
+        );
+        // without it the inner call takes every argument after it
+        assert_eq!(
+            expression("I IZ f YR I IZ g YR 1 AN YR 2"),
+            "I IZ f YR I IZ g YR 1 AN YR 2 MKAY MKAY"
+        );
+    }
+}
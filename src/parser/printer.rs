@@ -0,0 +1,187 @@
+use crate::lexer::tokens;
+use crate::parser::ast;
+
+/// A YARN literal holding `text`
+pub fn print_yarn(text: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str(":\""),
+            ':' => out.push_str("::"),
+            '\n' => out.push_str(":)"),
+            '\t' => out.push_str(":>"),
+            _ => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The source text of a single token, with YARNs and identifiers that need
+/// it escaped again
+pub fn print_token(token: &ast::TokenNode) -> String {
+    print_token_value(token.value())
+}
+
+/// print_token for a token straight from the lexer, which has no source
+/// text for newlines, comments and the end of input
+pub fn print_token_value(token: &tokens::Token) -> String {
+    match token {
+        tokens::Token::Word(text) => text.to_string(),
+        tokens::Token::Identifier(name) => name.escaped(),
+        tokens::Token::NumberValue(text)
+        | tokens::Token::NumbarValue(text)
+        | tokens::Token::TroofValue(text) => text.clone(),
+        tokens::Token::YarnValue(text) => print_yarn(text),
+        tokens::Token::Comma => ",".to_string(),
+        tokens::Token::ExclamationMark => "!".to_string(),
+        tokens::Token::QuestionMark => "?".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn print_binary(keyword: &str, left: &ast::ExpressionNode, right: &ast::ExpressionNode) -> String {
+    format!(
+        "{} {} AN {}",
+        keyword,
+        print_expression(left),
+        print_expression(right)
+    )
+}
+
+fn print_variadic(keyword: &str, expressions: &[ast::ExpressionNode]) -> String {
+    let operands = expressions
+        .iter()
+        .map(print_expression)
+        .collect::<Vec<String>>();
+
+    format!("{} {} MKAY", keyword, operands.join(" AN "))
+}
+
+/// Reconstructs an expression as LOLCODE, normalized to single spaces, an
+/// AN between every pair of operands and an explicit MKAY for every ALL OF,
+/// ANY OF, SMOOSH and I IZ
+pub fn print_expression(expression: &ast::ExpressionNode) -> String {
+    match &expression.value {
+        ast::ExpressionNodeValueOption::NumberValue(number) => print_token(&number.token),
+        ast::ExpressionNodeValueOption::NumbarValue(numbar) => print_token(&numbar.token),
+        ast::ExpressionNodeValueOption::YarnValue(yarn) => print_token(&yarn.token),
+        ast::ExpressionNodeValueOption::TroofValue(troof) => print_token(&troof.token),
+        ast::ExpressionNodeValueOption::VariableReference(var) => print_token(&var.identifier),
+        ast::ExpressionNodeValueOption::ItReference(it) => print_token(&it.token),
+        ast::ExpressionNodeValueOption::SumExpression(e) => {
+            print_binary("SUM OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::DiffExpression(e) => {
+            print_binary("DIFF OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::ProduktExpression(e) => {
+            print_binary("PRODUKT OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::QuoshuntExpression(e) => {
+            print_binary("QUOSHUNT OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::ModExpression(e) => {
+            print_binary("MOD OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::BiggrExpression(e) => {
+            print_binary("BIGGR OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::SmallrExpression(e) => {
+            print_binary("SMALLR OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::FurstExpression(e) => {
+            print_binary("FURST OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::LatrExpression(e) => {
+            print_binary("LATR OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::BothOfExpression(e) => {
+            print_binary("BOTH OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::EitherOfExpression(e) => {
+            print_binary("EITHER OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::WonOfExpression(e) => {
+            print_binary("WON OF", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::BothSaemExpression(e) => {
+            print_binary("BOTH SAEM", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::DiffrintExpression(e) => {
+            print_binary("DIFFRINT", &e.left, &e.right)
+        }
+        ast::ExpressionNodeValueOption::NotExpression(e) => {
+            format!("NOT {}", print_expression(&e.expression))
+        }
+        ast::ExpressionNodeValueOption::AllOfExpression(e) => {
+            print_variadic("ALL OF", &e.expressions)
+        }
+        ast::ExpressionNodeValueOption::AnyOfExpression(e) => {
+            print_variadic("ANY OF", &e.expressions)
+        }
+        ast::ExpressionNodeValueOption::SmooshExpression(e) => {
+            print_variadic("SMOOSH", &e.expressions)
+        }
+        ast::ExpressionNodeValueOption::MaekExpression(e) => format!(
+            "MAEK {} A {}",
+            print_expression(&e.expression),
+            print_token(&e.type_)
+        ),
+        ast::ExpressionNodeValueOption::BukkitSlot(slot) => {
+            let mut out = print_token(&slot.bukkit);
+            for token in slot.path.iter().chain(std::iter::once(&slot.slot)) {
+                out.push_str("'Z ");
+                out.push_str(&print_token(token));
+            }
+            out
+        }
+        ast::ExpressionNodeValueOption::KopyExpression(e) => {
+            format!("KOPY OF {}", print_expression(&e.expression))
+        }
+        ast::ExpressionNodeValueOption::LengthzExpression(e) => {
+            format!("LENGTHZ OF {}", print_expression(&e.expression))
+        }
+        ast::ExpressionNodeValueOption::CodezExpression(e) => {
+            format!("CODEZ OF {}", print_expression(&e.expression))
+        }
+        ast::ExpressionNodeValueOption::LettarExpression(e) => {
+            format!("LETTAR OF {}", print_expression(&e.expression))
+        }
+        ast::ExpressionNodeValueOption::SplitzExpression(e) => format!(
+            "SPLITZ {} ON {}",
+            print_expression(&e.yarn),
+            print_expression(&e.separator)
+        ),
+        ast::ExpressionNodeValueOption::JoinzExpression(e) => format!(
+            "JOINZ {} WIF {}",
+            print_expression(&e.bukkit),
+            print_expression(&e.separator)
+        ),
+        ast::ExpressionNodeValueOption::MatchezExpression(e) => format!(
+            "MATCHEZ {} AGENST {}",
+            print_expression(&e.yarn),
+            print_expression(&e.pattern)
+        ),
+        ast::ExpressionNodeValueOption::WunOfExpression(e) => format!(
+            "WUN OF {} DEN {} ELSS {}",
+            print_expression(&e.condition),
+            print_expression(&e.then),
+            print_expression(&e.else_)
+        ),
+        ast::ExpressionNodeValueOption::FunctionCallExpression(call) => {
+            let mut out = format!("I IZ {}", print_token(&call.identifier));
+            let arguments = call
+                .arguments
+                .iter()
+                .map(|argument| format!("YR {}", print_expression(argument)))
+                .collect::<Vec<String>>();
+            if !arguments.is_empty() {
+                out.push(' ');
+                out.push_str(&arguments.join(" AN "));
+            }
+            out.push_str(" MKAY");
+            out
+        }
+    }
+}
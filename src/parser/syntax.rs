@@ -0,0 +1,422 @@
+//! The lossless surface syntax tree of a program, for the formatter and
+//! editor tools, kept apart from the core AST the compiler lowers. Every
+//! character of the source is in exactly one leaf, tokens, comments and the
+//! trivia between them alike, so the leaves in order give back the source
+//! as written. It is built from the tokens alone, nesting by the keywords
+//! that open and close blocks, so programs that do not parse have one too.
+//!
+//! The two map onto each other by token: a core statement keeps the tokens
+//! it was parsed from, desugared ones included, and belongs to the surface
+//! statement its token is in (see statement_at and core_statements).
+
+use crate::lexer::lexer::{LexedToken, Lexer};
+use crate::lexer::tokens::Token;
+use crate::parser::ast;
+use crate::source::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// the whole source
+    Program,
+    /// a statement up to and with the newline or comma ending it, and for
+    /// one that opens a block its body and the line closing it
+    Statement,
+    /// a branch of O RLY? or WTF?: the line starting it and its body
+    Branch,
+    /// the statements of a body
+    Block,
+    /// a token the parser reads
+    Token,
+    /// a BTW or OBTW comment
+    Comment,
+    /// the spaces and tabs between tokens
+    Trivia,
+}
+
+impl SyntaxKind {
+    fn name(&self) -> &'static str {
+        match self {
+            SyntaxKind::Program => "Program",
+            SyntaxKind::Statement => "Statement",
+            SyntaxKind::Branch => "Branch",
+            SyntaxKind::Block => "Block",
+            SyntaxKind::Token => "Token",
+            SyntaxKind::Comment => "Comment",
+            SyntaxKind::Trivia => "Trivia",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub span: Span,
+    /// for a Token, its index among the parser's tokens, which the TokenNodes
+    /// of the core AST keep too
+    pub token: Option<usize>,
+    pub children: Vec<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    fn leaf(kind: SyntaxKind, span: Span, token: Option<usize>) -> Self {
+        SyntaxNode {
+            kind,
+            span,
+            token,
+            children: vec![],
+        }
+    }
+
+    fn node(kind: SyntaxKind, at: usize) -> Self {
+        SyntaxNode::leaf(kind, Span::new(at, at), None)
+    }
+
+    fn push(&mut self, child: SyntaxNode) {
+        if self.children.is_empty() {
+            self.span.start = child.span.start;
+        }
+        self.span.end = child.span.end;
+        self.children.push(child);
+    }
+
+    /// Every leaf under the node, in source order, with how many blocks and
+    /// branches it is in below the node, which is how deep it is indented
+    pub fn leaves(&self) -> Vec<(&SyntaxNode, usize)> {
+        if !self.is_inner() {
+            return vec![(self, 0)];
+        }
+        let nests = matches!(self.kind, SyntaxKind::Block | SyntaxKind::Branch) as usize;
+        self.children
+            .iter()
+            .flat_map(|child| child.leaves())
+            .map(|(leaf, depth)| (leaf, depth + nests))
+            .collect()
+    }
+
+    fn is_inner(&self) -> bool {
+        !matches!(
+            self.kind,
+            SyntaxKind::Token | SyntaxKind::Comment | SyntaxKind::Trivia
+        )
+    }
+
+    /// The innermost statement holding the token with index `token`, which
+    /// for a core statement is the one its `token()` is in
+    pub fn statement_at(&self, token: usize) -> Option<&SyntaxNode> {
+        if !self.is_inner() {
+            return None;
+        }
+        for child in &self.children {
+            if child.token == Some(token) {
+                return (self.kind == SyntaxKind::Statement).then_some(self);
+            }
+            if let Some(statement) = child.statement_at(token) {
+                return Some(statement);
+            }
+        }
+        None
+    }
+
+    /// The core statements, among `statements` and the blocks in them, that
+    /// were parsed from this statement's own tokens rather than those of the
+    /// statements in its body. A statement desugared into several gives
+    /// them all.
+    pub fn core_statements<'a>(
+        &self,
+        statements: &'a [ast::StatementNode],
+    ) -> Vec<&'a ast::StatementNode> {
+        let own: Vec<usize> = self
+            .children
+            .iter()
+            .filter_map(|child| child.token)
+            .collect();
+        let mut found = vec![];
+        let mut pending: Vec<&ast::StatementNode> = statements.iter().rev().collect();
+        while let Some(statement) = pending.pop() {
+            if statement
+                .token()
+                .is_some_and(|token| own.contains(&token.token.index))
+            {
+                found.push(statement);
+            }
+            pending.extend(blocks(statement).into_iter().rev());
+        }
+        found
+    }
+
+    /// The tree, one node a line indented by its depth, with the text of
+    /// every leaf and after each statement the kinds of the core statements
+    /// among `core` it maps to, for --emit syntax
+    pub fn dump(&self, chars: &[char], core: &[ast::StatementNode]) -> String {
+        let mut out = String::new();
+        self.dump_into(chars, core, 0, &mut out);
+        out
+    }
+
+    fn dump_into(
+        &self,
+        chars: &[char],
+        core: &[ast::StatementNode],
+        depth: usize,
+        out: &mut String,
+    ) {
+        out.push_str(&format!(
+            "{}{}@{}..{}",
+            "  ".repeat(depth),
+            self.kind.name(),
+            self.span.start,
+            self.span.end
+        ));
+        if !self.is_inner() {
+            let text: String = chars[self.span.start..self.span.end].iter().collect();
+            out.push_str(&format!(" {:?}", text));
+        }
+        if self.kind == SyntaxKind::Statement {
+            let kinds: Vec<&str> = self
+                .core_statements(core)
+                .into_iter()
+                .map(core_kind)
+                .collect();
+            if !kinds.is_empty() {
+                out.push_str(&format!(" -> {}", kinds.join(", ")));
+            }
+        }
+        out.push('\n');
+        for child in &self.children {
+            child.dump_into(chars, core, depth + 1, out);
+        }
+    }
+}
+
+/// The kind of a core statement, as --emit syntax names it
+fn core_kind(statement: &ast::StatementNode) -> &'static str {
+    use ast::StatementNodeValueOption as S;
+    match &statement.value {
+        S::Expression(_) => "Expression",
+        S::VariableDeclarationStatement(_) => "VariableDeclaration",
+        S::VariableAssignmentStatement(_) => "VariableAssignment",
+        S::KTHXBYEStatement(_) => "Kthxbye",
+        S::VisibleStatement(_) => "Visible",
+        S::GimmehStatement(_) => "Gimmeh",
+        S::IfStatement(_) => "If",
+        S::SwitchStatement(_) => "Switch",
+        S::GTFOStatement(_) => "Gtfo",
+        S::FLUSHZStatement(_) => "Flushz",
+        S::AssureStatement(_) => "Assure",
+        S::DumpzStatement(_) => "Dumpz",
+        S::BukkitSlotDeclarationStatement(_) => "BukkitSlotDeclaration",
+        S::WatchinLoopStatement(_) => "WatchinLoop",
+        S::LoopStatement(_) => "Loop",
+        S::ReturnStatement(_) => "Return",
+        S::FunctionDefinitionStatement(_) => "FunctionDefinition",
+    }
+}
+
+/// The statements in the blocks of a core statement, in source order
+fn blocks(statement: &ast::StatementNode) -> Vec<&ast::StatementNode> {
+    use ast::StatementNodeValueOption as S;
+    match &statement.value {
+        S::IfStatement(if_stmt) => {
+            let mut statements: Vec<&ast::StatementNode> = if_stmt.statements.iter().collect();
+            for else_if in &if_stmt.else_ifs {
+                statements.extend(&else_if.statements);
+            }
+            statements.extend(if_stmt.else_.iter().flatten());
+            statements
+        }
+        S::SwitchStatement(switch) => switch
+            .cases
+            .iter()
+            .flat_map(|case| &case.statements)
+            .chain(switch.default.iter().flatten())
+            .collect(),
+        S::WatchinLoopStatement(loop_stmt) => loop_stmt.statements.iter().collect(),
+        S::LoopStatement(loop_stmt) => loop_stmt.statements.iter().collect(),
+        S::FunctionDefinitionStatement(function) => function.statements.iter().collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether a statement starting with `token` opens a block, and if so
+/// whether it is one of O RLY? or WTF?, whose block is made of branches,
+/// and the keyword starting the line that closes it
+fn opener(token: &Token) -> Option<(bool, &'static str)> {
+    if token.has_name("Word_HAI") {
+        Some((false, "Word_KTHXBYE"))
+    } else if token.has_name("Word_O RLY") || token.has_name("Word_WTF") {
+        Some((true, "Word_OIC"))
+    } else if token.has_name("Word_IM IN YR") {
+        Some((false, "Word_IM OUTTA YR"))
+    } else if token.has_name("Word_HOW IZ I") {
+        Some((false, "Word_IF U SAY SO"))
+    } else {
+        None
+    }
+}
+
+const BRANCHES: &[&str] = &[
+    "Word_YA RLY",
+    "Word_MEBBE",
+    "Word_NO WAI",
+    "Word_OMG",
+    "Word_OMGWTF",
+];
+
+/// The syntax tree of `text`, which is lexed for it
+pub fn parse(text: &str) -> SyntaxNode {
+    let mut lexer = Lexer::init(text);
+    let tokens = lexer.get_tokens();
+    build(&tokens, &lexer.comments, text.chars().count())
+}
+
+/// The syntax tree of a source of `length` characters, from its tokens and
+/// the comments the lexer kept apart from them
+pub fn build(tokens: &[LexedToken], comments: &[LexedToken], length: usize) -> SyntaxNode {
+    let mut leaves: Vec<(SyntaxNode, Option<&LexedToken>)> = tokens
+        .iter()
+        .filter(|token| token.token != Token::EOF)
+        .map(|token| {
+            let leaf = SyntaxNode::leaf(SyntaxKind::Token, token.span(), Some(token.index));
+            (leaf, Some(token))
+        })
+        .chain(comments.iter().map(|comment| {
+            (
+                SyntaxNode::leaf(SyntaxKind::Comment, comment.span(), None),
+                None,
+            )
+        }))
+        .collect();
+    leaves.sort_by_key(|(leaf, _)| leaf.span.start);
+
+    let mut builder = Builder {
+        stack: vec![SyntaxNode::node(SyntaxKind::Program, 0)],
+        open: vec![],
+        unit: vec![],
+    };
+    let mut at = 0;
+    for (leaf, token) in leaves {
+        if at < leaf.span.start {
+            builder.add(
+                SyntaxNode::leaf(SyntaxKind::Trivia, Span::new(at, leaf.span.start), None),
+                None,
+            );
+        }
+        at = leaf.span.end;
+        builder.add(leaf, token);
+    }
+    if at < length {
+        builder.add(
+            SyntaxNode::leaf(SyntaxKind::Trivia, Span::new(at, length), None),
+            None,
+        );
+    }
+    builder.finish(length)
+}
+
+struct Builder<'t> {
+    /// the nodes being filled, the program first and the innermost last
+    stack: Vec<SyntaxNode>,
+    /// the statements opening a block still open, by their place on the
+    /// stack, with whether their block is made of branches and the keyword
+    /// closing them
+    open: Vec<(usize, bool, &'static str)>,
+    /// the leaves of the statement being read, with the first token's
+    unit: Vec<(SyntaxNode, Option<&'t LexedToken>)>,
+}
+
+impl<'t> Builder<'t> {
+    /// Adds a leaf, ending the statement being read at a newline or comma,
+    /// or at the ? of O RLY? and WTF?, which YA RLY and OMG may follow on
+    /// the same line. Trivia and comments between statements go to the
+    /// node around them.
+    fn add(&mut self, leaf: SyntaxNode, token: Option<&'t LexedToken>) {
+        let end = token.is_some_and(|token| matches!(token.token, Token::Newline | Token::Comma));
+        if self.unit.is_empty() && (token.is_none() || end) {
+            self.stack.last_mut().unwrap().push(leaf);
+            return;
+        }
+        let question = token.is_some_and(|token| token.token == Token::QuestionMark)
+            && self.unit.len() == 1
+            && opener(&self.unit[0].1.unwrap().token).is_some_and(|(branches, _)| branches);
+        self.unit.push((leaf, token));
+        if end || question {
+            self.statement();
+        }
+    }
+
+    /// Places the statement read, opening, branching or closing a block by
+    /// its first token. A line closing a block closes the innermost one it
+    /// can, with any left open inside it, and is a statement of its own if
+    /// there is none.
+    fn statement(&mut self) {
+        let unit = std::mem::take(&mut self.unit);
+        let first = unit[0].1.unwrap().token.clone();
+        let at = unit[0].0.span.start;
+        let mut statement = SyntaxNode::node(SyntaxKind::Statement, at);
+        for (leaf, _) in unit {
+            statement.push(leaf);
+        }
+
+        let closes = self
+            .open
+            .iter()
+            .rposition(|(_, _, closer)| first.has_name(closer));
+        if let Some(index) = closes {
+            let (depth, _, _) = self.open[index];
+            self.close_to(depth + 1);
+            self.open.truncate(index);
+            self.stack.last_mut().unwrap().push(statement);
+            let opened = self.stack.pop().unwrap();
+            self.stack.last_mut().unwrap().push(opened);
+            return;
+        }
+
+        if let Some((depth, true, _)) = self.open.last().copied() {
+            if BRANCHES.iter().any(|name| first.has_name(name)) {
+                self.close_to(depth + 1);
+                let mut branch = SyntaxNode::node(SyntaxKind::Branch, at);
+                branch.push(statement);
+                self.stack.push(branch);
+                self.stack
+                    .push(SyntaxNode::node(SyntaxKind::Block, self.end()));
+                return;
+            }
+        }
+
+        match opener(&first) {
+            Some((branches, closer)) => {
+                self.open.push((self.stack.len(), branches, closer));
+                let end = statement.span.end;
+                self.stack.push(statement);
+                if !branches {
+                    self.stack.push(SyntaxNode::node(SyntaxKind::Block, end));
+                }
+            }
+            None => self.stack.last_mut().unwrap().push(statement),
+        }
+    }
+
+    /// Where the innermost node being filled ends
+    fn end(&self) -> usize {
+        self.stack.last().unwrap().span.end
+    }
+
+    /// Ends the nodes being filled above the first `depth`, each going into
+    /// the one around it
+    fn close_to(&mut self, depth: usize) {
+        while self.stack.len() > depth {
+            let node = self.stack.pop().unwrap();
+            self.stack.last_mut().unwrap().push(node);
+        }
+    }
+
+    fn finish(mut self, length: usize) -> SyntaxNode {
+        if !self.unit.is_empty() {
+            self.statement();
+        }
+        self.close_to(1);
+        let mut program = self.stack.pop().unwrap();
+        program.span = Span::new(0, length);
+        program
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::compiler::target::vm::VM;
+
+const INDEX_TEMPLATE: &str = include_str!("playground/index.html");
+const GLUE: &str = include_str!("playground/lolcat.js");
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes a directory that can be served as is to run a program in the
+/// browser: the program compiled to program.wasm, the WASI glue in
+/// lolcat.js and an index.html showing `source` with an input box and an
+/// output pane
+pub fn write(dir: &Path, source: &str, code: String, target: &VM) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    target.compile_wasm(code, &dir.join("program.wasm").to_string_lossy())?;
+
+    let title = match dir.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => "program".to_string(),
+    };
+    let index = INDEX_TEMPLATE
+        .replace("{{TITLE}}", &escape_html(&title))
+        .replace("{{SOURCE}}", &escape_html(source));
+    fs::write(dir.join("index.html"), index)?;
+    fs::write(dir.join("lolcat.js"), GLUE)?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+const MAIN_TEMPLATE: &str = "HAI 1.2
+VISIBLE \"HAI WORLD!\"
+KTHXBYE
+";
+
+const TEST_TEMPLATE: &str = "HAI 1.2
+I HAS A greeting ITZ YARN R \"HAI\"
+VISIBLE greeting \" WORLD!\"
+KTHXBYE
+";
+
+const TEST_EXPECTED: &str = "HAI WORLD!\n";
+
+fn manifest(name: &str) -> String {
+    format!(
+        "[project]\nname = \"{}\"\nentry = \"main.lol\"\n\n[tests]\n# every tests/<name>.lol is run and its stdout compared with tests/<name>.out\ndir = \"tests\"\n",
+        name
+    )
+}
+
+/// Creates a new project at `path` containing a hello world program, a
+/// lolcat.toml manifest and a tests directory with one expected-output test
+pub fn new_project(path: &Path) -> Result<()> {
+    if path.exists() && fs::read_dir(path)?.next().is_some() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "destination exists and is not empty",
+        ));
+    }
+
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "destination has no directory name",
+            ))
+        }
+    };
+
+    fs::create_dir_all(path.join("tests"))?;
+    fs::write(path.join("lolcat.toml"), manifest(&name))?;
+    fs::write(path.join("main.lol"), MAIN_TEMPLATE)?;
+    fs::write(path.join("tests").join("hello.lol"), TEST_TEMPLATE)?;
+    fs::write(path.join("tests").join("hello.out"), TEST_EXPECTED)?;
+
+    Ok(())
+}
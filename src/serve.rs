@@ -0,0 +1,216 @@
+use std::io::{BufRead, Write};
+
+use crate::analysis::{format, symbols};
+use crate::diagnostics::{self as diag, Diagnostic};
+use crate::json::Json;
+use crate::source::{self, SourceMap};
+
+/// JSON-RPC's error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const METHODS: &[&str] = &["check", "compile", "format", "symbols"];
+
+/// What a program was built into and its warnings, or every diagnostic
+pub type CompileResult = Result<(String, Vec<Diagnostic>), Vec<Diagnostic>>;
+
+/// Runs the front end and backend for a request, given the name diagnostics
+/// call the program by and its source
+pub struct Compiler<'f> {
+    /// every diagnostic of the front end
+    pub check: &'f dyn Fn(&str, &str) -> Vec<Diagnostic>,
+    /// the generated C, or with an output path the path of the executable
+    /// built there, and the warnings; Err with the diagnostics
+    pub compile: &'f dyn Fn(&str, &str, Option<&str>) -> CompileResult,
+}
+
+/// The diagnostics up to the error limit, with the note of how many more
+/// there were
+fn diagnostics_json(diagnostics: &[Diagnostic]) -> Json {
+    Json::from(
+        diag::limit_errors(diagnostics.to_vec())
+            .iter()
+            .map(|diagnostic| {
+                Json::object(vec![
+                    (
+                        "severity",
+                        Json::from(diagnostic.severity.to_string().to_lowercase()),
+                    ),
+                    ("message", Json::from(diagnostic.to_string())),
+                ])
+            })
+            .collect::<Vec<Json>>(),
+    )
+}
+
+/// How many errors and warnings there were, for clients that only show a count
+fn summary_json(diagnostics: &[Diagnostic]) -> Json {
+    let (errors, warnings) = diag::count(diagnostics);
+    Json::object(vec![
+        ("errors", Json::from(errors)),
+        ("warnings", Json::from(warnings)),
+    ])
+}
+
+fn position(map: &SourceMap, offset: usize) -> Json {
+    let (line, column) = map.line_col(offset);
+    Json::object(vec![
+        ("line", Json::from(line)),
+        ("column", Json::from(column)),
+    ])
+}
+
+fn symbols_json(text: &str) -> Json {
+    let text = source::normalize_newlines(text);
+    let map = SourceMap::single("", &text);
+    let table = symbols::analyze(&text);
+
+    let symbols = table
+        .symbols
+        .iter()
+        .map(|symbol| {
+            Json::object(vec![
+                ("name", Json::from(symbol.name.as_str())),
+                ("kind", Json::from(symbol.kind.to_string())),
+                ("type", symbol.type_.clone().map_or(Json::Null, Json::from)),
+                ("scope", Json::from(symbol.scope.as_str())),
+                ("declaration", position(&map, symbol.start)),
+                (
+                    "references",
+                    Json::from(
+                        symbol
+                            .references
+                            .iter()
+                            .map(|reference| position(&map, reference.start))
+                            .collect::<Vec<Json>>(),
+                    ),
+                ),
+            ])
+        })
+        .collect::<Vec<Json>>();
+
+    Json::object(vec![
+        ("symbols", Json::from(symbols)),
+        ("complete", Json::from(table.complete)),
+    ])
+}
+
+/// The result of a request, Err with a JSON-RPC error code and message
+fn call(method: &str, params: &Json, compiler: &Compiler) -> Result<Json, (i64, String)> {
+    if !METHODS.contains(&method) {
+        return Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method)));
+    }
+    let text = match params.get("source").and_then(|source| source.as_str()) {
+        Some(text) => text,
+        None => {
+            return Err((
+                INVALID_PARAMS,
+                "params.source must be the program's text".to_string(),
+            ))
+        }
+    };
+    let name = params
+        .get("name")
+        .and_then(|name| name.as_str())
+        .unwrap_or("<request>");
+
+    match method {
+        "check" => {
+            let diagnostics = (compiler.check)(name, text);
+            Ok(Json::object(vec![
+                ("ok", Json::from(!diagnostics.iter().any(|d| d.is_error()))),
+                ("diagnostics", diagnostics_json(&diagnostics)),
+                ("summary", summary_json(&diagnostics)),
+            ]))
+        }
+        "compile" => {
+            let output = params.get("output").and_then(|output| output.as_str());
+            match (compiler.compile)(name, text, output) {
+                Ok((built, warnings)) => Ok(Json::object(vec![
+                    ("ok", Json::from(true)),
+                    ("diagnostics", diagnostics_json(&warnings)),
+                    ("summary", summary_json(&warnings)),
+                    (
+                        if output.is_some() { "output" } else { "c" },
+                        Json::from(built),
+                    ),
+                ])),
+                Err(diagnostics) => Ok(Json::object(vec![
+                    ("ok", Json::from(false)),
+                    ("diagnostics", diagnostics_json(&diagnostics)),
+                    ("summary", summary_json(&diagnostics)),
+                ])),
+            }
+        }
+        "format" => Ok(Json::object(vec![(
+            "source",
+            Json::from(format::format(text)),
+        )])),
+        _ => Ok(symbols_json(text)),
+    }
+}
+
+fn respond(request: &Json, compiler: &Compiler) -> Json {
+    let id = request.get("id").cloned().unwrap_or(Json::Null);
+    let outcome = match request.get("method").and_then(|method| method.as_str()) {
+        Some(method) => call(
+            method,
+            request.get("params").unwrap_or(&Json::Null),
+            compiler,
+        ),
+        None => Err((INVALID_REQUEST, "A request needs a method".to_string())),
+    };
+
+    let (key, value) = match outcome {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            Json::object(vec![
+                ("code", Json::from(code)),
+                ("message", Json::from(message)),
+            ]),
+        ),
+    };
+    Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        (key, value),
+    ])
+}
+
+/// Answers JSON-RPC 2.0 requests, one to a line of `input`, with one
+/// response to a line of `output`, until the input ends. The methods are
+/// check, compile, format and symbols, each taking the program as
+/// params.source and, for diagnostics, its file name as params.name.
+pub fn serve(input: &mut dyn BufRead, output: &mut dyn Write, compiler: &Compiler) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match Json::parse(line.trim()) {
+            Some(request) => respond(&request, compiler),
+            None => Json::object(vec![
+                ("jsonrpc", Json::from("2.0")),
+                ("id", Json::Null),
+                (
+                    "error",
+                    Json::object(vec![
+                        ("code", Json::from(PARSE_ERROR)),
+                        ("message", Json::from("The request is not valid JSON")),
+                    ]),
+                ),
+            ]),
+        };
+        let _ = writeln!(output, "{}", response);
+        let _ = output.flush();
+    }
+}
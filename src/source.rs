@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use crate::diagnostics::Severity;
+
+/// `text` with every \r\n and lone \r made into \n, the only line ending the
+/// lexer and SourceMap see, so a file written on Windows lexes and reports
+/// positions like its \n twin
+pub fn normalize_newlines(text: &str) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// A range of character offsets into a SourceMap, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// One file of a SourceMap
+pub struct SourceFile {
+    /// the path diagnostics name the file by
+    pub name: String,
+    /// the offset of the file's first character in the map
+    base: usize,
+    lines: Vec<String>,
+    /// the offset, within the file, every line starts at
+    starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(name: &str, base: usize, contents: &str) -> SourceFile {
+        let contents = normalize_newlines(contents);
+        let mut starts = vec![0];
+        for (i, ch) in contents.chars().enumerate() {
+            if ch == '\n' {
+                starts.push(i + 1);
+            }
+        }
+
+        SourceFile {
+            name: name.to_string(),
+            base,
+            lines: contents.split('\n').map(|line| line.to_string()).collect(),
+            starts,
+        }
+    }
+
+    /// The offset just past the file's last character in the map
+    fn end(&self) -> usize {
+        self.base
+            + self.starts[self.starts.len() - 1]
+            + self.lines[self.lines.len() - 1].chars().count()
+    }
+
+    /// The line, counting from 0, of an offset in the map
+    fn line_index(&self, offset: usize) -> usize {
+        self.starts
+            .partition_point(|start| *start <= offset - self.base)
+            - 1
+    }
+
+    /// The text of line `line`, counting from 1, without its newline
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        self.lines
+            .get(line.checked_sub(1)?)
+            .map(|text| text.as_str())
+    }
+}
+
+/// Every source a compilation reads, with the offset of each line worked out
+/// once so positions can be turned into files, lines and columns without
+/// rescanning the text. Files are laid out one after another, so an offset
+/// alone says which file it is in.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// A map of just one file, whose offsets start at 0
+    pub fn single(name: &str, contents: &str) -> SourceMap {
+        let mut map = SourceMap::new();
+        map.add_file(name, contents);
+        map
+    }
+
+    /// Adds a file after the others, returning the offset its first character
+    /// has in the map. Positions in the file must be moved by that much.
+    pub fn add_file(&mut self, name: &str, contents: &str) -> usize {
+        // one past the end of the last file, which is where errors at the
+        // end of input point
+        let base = match self.files.last() {
+            Some(last) => last.end() + 1,
+            None => 0,
+        };
+        self.files.push(SourceFile::new(name, base, contents));
+        base
+    }
+
+    /// The file `offset` is in
+    pub fn file(&self, offset: usize) -> &SourceFile {
+        let index = self.files.partition_point(|file| file.base <= offset);
+        &self.files[index.max(1) - 1]
+    }
+
+    /// The line and column, both counting from 1, of `offset` in its file
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let file = self.file(offset);
+        let line = file.line_index(offset);
+        (line + 1, offset - file.base - file.starts[line] + 1)
+    }
+
+    /// The line, counting from 1, that `offset` is on in its file
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_col(offset).0
+    }
+
+    /// The text of the line `offset` is on, without its newline
+    pub fn line_text(&self, offset: usize) -> &str {
+        let file = self.file(offset);
+        &file.lines[file.line_index(offset)]
+    }
+
+    /// `path:line:col` of `offset`, the form editors jump to
+    pub fn location(&self, offset: usize) -> String {
+        let (line, column) = self.line_col(offset);
+        format!("{}:{}:{}", self.file(offset).name, line, column)
+    }
+
+    pub fn format_error(&self, span: Span, message: &str) -> String {
+        self.format_diagnostic(span, Severity::Error, message)
+    }
+
+    /// Names where `span` starts and shows the line it is on with the span
+    /// underlined
+    pub fn format_diagnostic(&self, span: Span, severity: Severity, message: &str) -> String {
+        let (_, column) = self.line_col(span.start);
+
+        format!(
+            "{}: {}: {}\n{}\n{}{}",
+            self.location(span.start),
+            severity,
+            message,
+            self.line_text(span.start),
+            " ".repeat(column - 1),
+            "^".repeat(span.end.saturating_sub(span.start))
+        )
+    }
+}
@@ -1,13 +1,126 @@
-pub fn get_line(lines: &Vec<&str>, start: usize) -> (usize, usize) {
-    let mut line = 0;
-    let mut count = 0;
-    for (i, l) in lines.iter().enumerate() {
-        if start - count < l.len() {
-            line = i;
-            break;
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecodeError {
+    InvalidUtf8 { offset: usize, byte: u8 },
+    Utf16,
+}
+
+impl std::error::Error for DecodeError {}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::InvalidUtf8 { offset, byte } => write!(
+                f,
+                "invalid UTF-8 byte 0x{:02X} at byte offset {} (use --latin1 to decode as Latin-1)",
+                byte, offset
+            ),
+            DecodeError::Utf16 => write!(f, "UTF-16 encoded files are not supported"),
         }
-        count += l.len() + 1;
     }
+}
+
+pub struct DecodedSource {
+    pub contents: String,
+    pub warnings: Vec<String>,
+}
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+pub fn decode_source(bytes: &[u8], latin1: bool) -> Result<DecodedSource, DecodeError> {
+    let mut warnings = Vec::new();
+
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return Err(DecodeError::Utf16);
+    }
+
+    // offsets are reported into the file, BOM included
+    let (bom, bytes) = match bytes.strip_prefix(&UTF8_BOM) {
+        Some(rest) => (UTF8_BOM.len(), rest),
+        None => (0, bytes),
+    };
 
-    (line, count)
+    match std::str::from_utf8(bytes) {
+        Ok(contents) => Ok(DecodedSource {
+            contents: contents.to_string(),
+            warnings,
+        }),
+        Err(e) => {
+            let byte = bytes[e.valid_up_to()];
+            let offset = bom + e.valid_up_to();
+
+            if !latin1 {
+                return Err(DecodeError::InvalidUtf8 { offset, byte });
+            }
+
+            // every byte maps directly onto the first 256 unicode code points
+            warnings.push(format!(
+                "input is not valid UTF-8 (byte 0x{:02X} at byte offset {}), decoded as Latin-1",
+                byte, offset
+            ));
+            Ok(DecodedSource {
+                contents: bytes.iter().map(|b| *b as char).collect(),
+                warnings,
+            })
+        }
+    }
+}
+
+/// What MAEK makes of the letters of a YARN: -?[0-9]+ for a NUMBER, which
+/// for a NUMBAR may also have one . among its digits. None for any other
+/// text, which the runtimes panic on. std.c's parse_loaded_number reads
+/// numbers the same way so both runtimes round alike.
+pub fn parse_number(text: &str, numbar: bool) -> Option<f32> {
+    let (is_negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+
+    let mut integer_part: i32 = 0;
+    let mut fraction_part: f32 = 0.0;
+    let mut divisor_for_fraction: f32 = 1.0;
+    let mut found_decimal_point = false;
+    let mut found_digit = false;
+    for ch in digits.chars() {
+        match ch {
+            '.' if numbar && !found_decimal_point => found_decimal_point = true,
+            '0'..='9' => {
+                let digit = ch as i32 - '0' as i32;
+                found_digit = true;
+                if !found_decimal_point {
+                    integer_part = integer_part.wrapping_mul(10).wrapping_add(digit);
+                } else {
+                    divisor_for_fraction *= 10.0;
+                    fraction_part += digit as f32 / divisor_for_fraction;
+                }
+            }
+            _ => return None,
+        }
+    }
+    if !found_digit {
+        return None;
+    }
+
+    let result = integer_part as f32 + fraction_part;
+    Some(if is_negative { -result } else { result })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_invalid_byte_after_a_bom_is_reported_at_its_offset_in_the_file() {
+        let bytes = [&UTF8_BOM[..], b"HAI\xE9"].concat();
+        assert_eq!(
+            decode_source(&bytes, false).err(),
+            Some(DecodeError::InvalidUtf8 {
+                offset: 6,
+                byte: 0xE9
+            })
+        );
+
+        let decoded = decode_source(&bytes, true).ok().unwrap();
+        assert_eq!(decoded.contents, "HAI\u{E9}");
+        assert!(decoded.warnings[0].contains("at byte offset 6"));
+    }
 }